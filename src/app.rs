@@ -0,0 +1,3860 @@
+use crate::{
+    cache::{CacheConfig, DirectoryCache, FileMetadataCache},
+    checksum::Checksums,
+    chords::{self, Chord, Lookup, CHORD_TIMEOUT, CTRL_W},
+    cli::StartupOptions,
+    clipboard,
+    columns::ColumnRegistry,
+    command::{
+        complete_command, expand_path, parse_command, CommandAction, CommandHistory, Prompt,
+    },
+    config::{DirSettings, Opener, OpenerConfig},
+    custom::CustomCommands,
+    devices::{self, Devices},
+    diff::DiffView,
+    find::{Query, SavedSearch, SavedSearches, SearchAction},
+    follow::{follows_automatically, Follow},
+    fs_ops::{
+        convert_text_file, free_space, list_files, mount_notice, search_files, Entry, LinkKind,
+        MountRules, MountStrategy, Preserve, SortMode,
+    },
+    git::{self, GitAction},
+    hooks::{HookEvent, Hooks},
+    icons::IconStyle,
+    ignored::IgnoreRules,
+    ipc::{IpcServer, Reply},
+    jobs::{
+        load_filing_rules, plan_filing, BackgroundLoader, ConflictChoice, FilingRule, Journal,
+        JournalOperation, LoadUpdate, Merge,
+    },
+    macros::{Macros, MAX_MACRO_DEPTH},
+    notes::Notes,
+    opener::{open_file, open_targets, reveal, run_opener, show_folder},
+    preview::{preview_file, Pager, Preview, PreviewWorker},
+    previewers::PreviewerRegistry,
+    register::{Paste, PasteChoice, Register, RegisterOperation},
+    repeat::{Change, RenamePattern},
+    script::{ScriptAction, ScriptContext, ScriptHook, Scripts, MAX_DEPTH},
+    select::NamePattern,
+    shell::{ShellRun, OUTPUT_LINES},
+    sync::DirSync,
+    tags::Tags,
+    terminal::{with_suspended_tui, EventSource},
+    theme::Theme,
+    todo::{
+        add_todo, archive_path, archive_todos, global_todo_path, insert_todo, load_todos,
+        move_todo, project_todo_path, save_todos, Todo, TODO_FILE,
+    },
+    tree::DirTree,
+    tutorial::{Tutorial, TutorialView},
+    ui::{Backend, ListState, Rect, Terminal, PREVIEW_STEP, PREVIEW_WIDTHS},
+    vfs::{Direction, Location, Remote, S3Config, Transfer, PREVIEW_LIMIT},
+};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+impl Level {
+    // How long a notification stays on screen
+    fn timeout(self) -> Duration {
+        match self {
+            Level::Info => Duration::from_secs(4),
+            Level::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+pub struct Notification {
+    pub level: Level,
+    pub message: String,
+    pub created: Instant,
+    pub timestamp: chrono::DateTime<chrono::Local>,
+}
+
+// Messages shown transiently in the status bar and kept in a log for `:messages`
+#[derive(Default)]
+pub struct Notifications {
+    pub log: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub const MAX_LOG: usize = 200;
+
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        if self.log.len() == Self::MAX_LOG {
+            self.log.pop_front();
+        }
+        self.log.push_back(Notification {
+            level,
+            message: message.into(),
+            created: Instant::now(),
+            timestamp: chrono::Local::now(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message);
+    }
+
+    // Errors stay on screen longer than confirmations
+    pub fn current(&self) -> Option<&Notification> {
+        self.log
+            .back()
+            .filter(|n| n.created.elapsed() < n.level.timeout())
+    }
+
+    // When the notification on screen goes away
+    pub fn expires(&self) -> Option<Instant> {
+        self.current().map(|n| n.created + n.level.timeout())
+    }
+}
+
+// The panels keys can go to, moved between with Ctrl+w and h/j/k/l like
+// Vim's windows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Panel {
+    Files,
+    Preview,
+    Todos,
+}
+
+impl Panel {
+    // The panel in the direction of `key`, with `w` the next one round
+    pub fn towards(self, key: char) -> Panel {
+        match (self, key) {
+            (Panel::Files, 'l' | 'w') => Panel::Preview,
+            (Panel::Preview | Panel::Todos, 'h') => Panel::Files,
+            (Panel::Preview, 'j' | 'w') => Panel::Todos,
+            (Panel::Todos, 'k') => Panel::Preview,
+            (Panel::Todos, 'w') => Panel::Files,
+            (panel, _) => panel,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Files => "file list",
+            Panel::Preview => "preview",
+            Panel::Todos => "to-do list",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SplitDirection {
+    // Panes side by side
+    #[value(alias = "v")]
+    Vertical,
+    // Panes stacked
+    #[value(alias = "h")]
+    Horizontal,
+}
+
+// A file list that is not focused; the focused one lives in the main loop
+pub struct Pane {
+    pub dir: PathBuf,
+    pub cursor: usize,
+    pub files: Vec<Entry>,
+    // The remote directory listed in place of `dir`, its mirror
+    pub remote: Option<Remote>,
+}
+
+// Visual mode: the entries from `anchor` to the cursor are marked, on top
+// of the ones marked before it started
+pub struct Visual {
+    pub anchor: usize,
+    pub dir: PathBuf,
+    pub marked_before: HashSet<PathBuf>,
+}
+
+pub struct Split {
+    pub direction: SplitDirection,
+    pub other: Pane,
+    // Whether the focused pane is the right (or bottom) one
+    pub focus_second: bool,
+}
+
+pub struct AppState {
+    pub files: Vec<Entry>,
+    pub loading: bool,
+    pub last_load_time: Instant,
+    pub sort_mode: SortMode,
+    pub marked: HashSet<PathBuf>,
+    pub free_space: Option<u64>,
+    pub notifications: Notifications,
+    pub prompt: Option<Prompt>,
+    pub command_history: CommandHistory,
+    pub detailed_view: bool,
+    pub show_properties: bool,
+    pub show_messages: bool,
+    pub show_cache_stats: bool,
+    // What a `[commands]` entry with `output = true` or a `!` command
+    // printed, under its name
+    pub command_output: Option<(String, Vec<String>)>,
+    // First line of the output shown, None to follow its end
+    pub output_scroll: Option<usize>,
+    // Preview kept on screen next to the live one for comparison
+    pub pinned_preview: Option<(PathBuf, Preview)>,
+    // Markdown previews as source instead of rendered
+    pub markdown_source: bool,
+    // The end of large files is previewed instead of their start
+    pub preview_tail: bool,
+    // The selection's preview over the whole terminal
+    pub pager: Option<Pager>,
+    // The preview column, with the to-do list below it, gives its room to
+    // the file list
+    pub preview_hidden: bool,
+    // Percent of the width for the preview column, None for the view's default
+    pub preview_width: Option<u16>,
+    // The panel keys go to first
+    pub focus: Panel,
+    // Rows of the preview scrolled past while it had the focus, for the file
+    // they were scrolled on
+    pub preview_scroll: Option<(PathBuf, usize)>,
+    pub open_with: Option<OpenWithMenu>,
+    // The devices view
+    pub devices: Option<Devices>,
+    pub split: Option<Split>,
+    pub merge: Option<Merge>,
+    // A sync plan under review, or running
+    pub sync: Option<DirSync>,
+    // A running `:get` or `:put`
+    pub transfer: Option<Transfer>,
+    // Paste stopped at a file whose name is taken
+    pub paste: Option<Paste>,
+    // Operation interrupted by a crash, waiting for the user's decision
+    pub recovery: Option<Journal>,
+    // Moves planned by `:organize`, waiting for confirmation
+    pub filing_plan: Option<Vec<(PathBuf, PathBuf)>>,
+    // Files whose changes `:git discard` throws away once confirmed
+    pub pending_discard: Option<Vec<PathBuf>>,
+    // Entries `:delete` removes once confirmed
+    pub pending_delete: Option<Vec<PathBuf>>,
+    pub checksums: Option<Checksums>,
+    pub diff: Option<DiffView>,
+    // The start of a chord such as `gg`, waiting for the rest since
+    // `pending_since`
+    pub pending_keys: String,
+    pub pending_since: Instant,
+    // A count typed before a motion, e.g. the 5 of `5j`
+    pub count: Option<usize>,
+    pub mount_rules: MountRules,
+    pub mount: MountStrategy,
+    // The directory tree panel, when shown
+    pub tree: Option<DirTree>,
+}
+
+impl AppState {
+    // Lists `dir`, or what `query` finds below it
+    pub fn start_loading(
+        &mut self,
+        dir: &Path,
+        show_hidden: bool,
+        query: Option<Query>,
+    ) -> BackgroundLoader {
+        self.loading = true;
+        self.last_load_time = Instant::now();
+        self.files = Vec::new();
+
+        let mount = self.mount_rules.strategy_for(dir);
+        if mount != self.mount && mount.is_special() {
+            self.notifications.info(mount_notice(&mount));
+        }
+        self.mount = mount;
+
+        let mut loader = BackgroundLoader::new(
+            dir.to_path_buf(),
+            show_hidden,
+            self.sort_mode,
+            self.mount.stat_entries,
+        );
+        match query {
+            Some(query) => loader.start_find(query),
+            None => loader.start(),
+        }
+        loader
+    }
+
+    // Merges until the first conflict. Returns true if the merge is already
+    // done and the listing needs a reload.
+    pub fn run_merge(&mut self, mut merge: Merge) -> bool {
+        if merge.advance() {
+            merge.finish(&mut self.notifications);
+            true
+        } else {
+            self.merge = Some(merge);
+            false
+        }
+    }
+}
+
+// Status bar label of the flattened view, a search for every file
+const FLAT_LABEL: &str = "flattened";
+
+// "Open with" popup: every configured opener followed by a free-form entry
+pub struct OpenWithMenu {
+    pub file: PathBuf,
+    pub choices: Vec<Opener>,
+    pub state: ListState,
+}
+
+impl OpenWithMenu {
+    pub fn new(file: PathBuf, choices: Vec<Opener>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            file,
+            choices,
+            state,
+        }
+    }
+
+    // The extra last row is "Other command…"
+    fn len(&self) -> usize {
+        self.choices.len() + 1
+    }
+
+    pub fn selected(&self) -> usize {
+        self.state.selected().unwrap_or(0)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.state.select(Some(index.min(self.len() - 1)));
+    }
+}
+
+// Directories given on the command line must exist, a typo should not
+// silently start somewhere else
+pub fn startup_dir(path: &Path) -> Result<PathBuf, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    let dir = expand_path(&path.to_string_lossy(), &cwd);
+    match fs::canonicalize(&dir) {
+        Ok(dir) if dir.is_dir() => Ok(dir),
+        _ => Err(format!("Not a directory: {}", path.display())),
+    }
+}
+
+// Rows a key scrolls a panel `page` rows high by, isize::MIN and isize::MAX
+// for its ends. None for keys that don't scroll.
+fn scroll_rows(code: KeyCode, modifiers: KeyModifiers, page: usize) -> Option<isize> {
+    let page = page.max(1) as isize;
+    match (code, modifiers) {
+        (KeyCode::Down | KeyCode::Char('j'), _) => Some(1),
+        (KeyCode::Up | KeyCode::Char('k'), _) => Some(-1),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => Some((page / 2).max(1)),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => Some(-(page / 2).max(1)),
+        (KeyCode::PageDown, _) => Some(page),
+        (KeyCode::PageUp, _) => Some(-page),
+        (KeyCode::Home | KeyCode::Char('g'), _) => Some(isize::MIN),
+        (KeyCode::End | KeyCode::Char('G'), _) => Some(isize::MAX),
+        _ => None,
+    }
+}
+
+// `--select` must name an existing entry. Only its directory is resolved, so a
+// symlink is selected itself rather than its target.
+pub fn startup_selection(path: &Path) -> Result<PathBuf, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    let path = expand_path(&path.to_string_lossy(), &cwd);
+    let not_found = || format!("No such file or directory: {}", path.display());
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if fs::symlink_metadata(&path).is_ok() => {
+            fs::canonicalize(parent)
+                .map(|parent| parent.join(name))
+                .map_err(|_| not_found())
+        }
+        _ => Err(not_found()),
+    }
+}
+
+// Everything the file manager shows and edits. The binary drives it from the
+// terminal; `tick`, `draw` and `handle_event` can be driven from tests as well.
+pub struct App {
+    pub state: AppState,
+    pub current_dir: PathBuf,
+    pub cursor_position: usize,
+    // First row of the file list on screen
+    pub list_offset: usize,
+    // Rows of the file list on screen, for paging
+    pub list_height: usize,
+    pub visual: Option<Visual>,
+    // Where each breadcrumb of the path bar was drawn, for mouse clicks
+    pub breadcrumbs: Vec<(Rect, PathBuf)>,
+    // Entry to put the cursor on once the next listing is in (`--select`)
+    pub select: Option<String>,
+    pub last_dir: PathBuf,
+    pub show_hidden: bool,
+    // Sort order and hidden files outside of trees with a `.termfm.toml`
+    // setting them
+    pub default_sort: SortMode,
+    pub default_hidden: bool,
+    pub dir_settings: DirSettings,
+    pub background_loader: Option<BackgroundLoader>,
+    pub preview_cache: Option<(PathBuf, Preview)>,
+    // The selected file while it is followed like `tail -f`
+    pub follow: Option<Follow>,
+    pub macros: Macros,
+    // What `.` repeats
+    pub last_change: Option<Change>,
+    pub last_selected_file_path: Option<PathBuf>,
+    pub preview_worker: PreviewWorker,
+    pub search_query: String,
+    // The listing holds only the matches of `search_query`, until it is
+    // listed again
+    pub search_filtered: bool,
+    // `n` or `N` on a filtered listing, carried out once it is listed in full
+    pending_match: Option<isize>,
+    pub todos: Vec<Todo>,
+    pub todo_list_state: ListState,
+    // The file the to-do list shown is kept in, the global one or a project's
+    pub todo_path: Option<PathBuf>,
+    // Show the project's list inside a project rather than the global one
+    pub project_todos: bool,
+    pub dir_cache: DirectoryCache,
+    pub metadata_cache: FileMetadataCache,
+    pub columns: ColumnRegistry,
+    // What pasted copies keep of the originals, `[copy]` in the config
+    pub preserve: Preserve,
+    // Endpoint and credentials for `s3://` buckets
+    pub s3: S3Config,
+    // `[commands]` of the config, run as `:name` or with their keys
+    pub custom_commands: CustomCommands,
+    pub hooks: Hooks,
+    // The directory the `enter_dir` and `on_dir_change` hooks last ran for
+    pub hooked_dir: PathBuf,
+    // The `!` command whose output is being read into `command_output`
+    pub shell: Option<ShellRun>,
+    // Rows of the output panel on screen, for paging
+    pub output_rows: usize,
+    pub scripts: Scripts,
+    // The entry `on_select` last ran for
+    pub scripted_selection: Option<PathBuf>,
+    // How deep scripts are running commands that run scripts
+    script_depth: usize,
+    // What the `status` functions of the script returned last
+    pub script_segments: Vec<String>,
+    pub theme: Theme,
+    pub icons: IconStyle,
+    // `.gitignore`, `.ignore` and `[ignore]` patterns of the current directory
+    pub ignore: IgnoreRules,
+    pub tags: Tags,
+    // Only entries with this tag are listed, in every directory
+    pub tag_filter: Option<String>,
+    pub notes: Notes,
+    pub searches: SavedSearches,
+    // The `:find` or saved search listed in place of its root directory, and
+    // its label for the status bar
+    pub virtual_dir: Option<(String, SavedSearch)>,
+    // The `sftp://` directory listed in place of the current one, which is
+    // then its local mirror
+    pub remote: Option<Remote>,
+    // Where `termfm --remote` reaches this instance, set up by the binary
+    pub ipc: Option<IpcServer>,
+    // opener.toml, and with the current tree's `.termfm.toml` on top
+    pub global_opener_config: Arc<OpenerConfig>,
+    pub opener_config: Arc<OpenerConfig>,
+    pub filing_rules: Vec<FilingRule>,
+    pub tutorial: Option<Tutorial>,
+    // Print the chosen files instead of opening them
+    pub pick: bool,
+    pub quit: bool,
+    // Files chosen in `--pick` mode or with `:choose`, None when cancelled
+    pub picked: Option<Vec<PathBuf>>,
+    // Requests for whoever owns the terminal: repaint everything after a
+    // program ran in it, or stop the process (Ctrl+Z)
+    pub clear_screen: bool,
+    pub suspend: bool,
+}
+
+impl App {
+    pub fn new(
+        options: &StartupOptions,
+        config_path: &Path,
+        opener_config: Arc<OpenerConfig>,
+        tutorial: Option<Tutorial>,
+    ) -> io::Result<Self> {
+        let mut notifications = Notifications::default();
+        // The tutorial sandbox wins, then an explicit --left, then the
+        // directory remembered in the cwd file
+        let current_dir = match (tutorial.as_ref(), &options.left, &options.cwd_file) {
+            (Some(tutorial), _, _) => tutorial.root.clone(),
+            (None, Some(dir), _) => dir.clone(),
+            (None, None, Some(path)) if path.exists() => match fs::read_to_string(path) {
+                Ok(content) => {
+                    let dir = PathBuf::from(content.trim());
+                    if dir.is_dir() {
+                        dir
+                    } else {
+                        notifications.error(
+                            "Path in cwd file is not a directory. Falling back to current directory.",
+                        );
+                        env::current_dir()?
+                    }
+                }
+                Err(e) => {
+                    notifications.error(format!(
+                        "Failed to read cwd file: {}. Falling back to current directory.",
+                        e
+                    ));
+                    env::current_dir()?
+                }
+            },
+            (None, None, _) => env::current_dir()?,
+        };
+
+        let mount_rules = MountRules::from_config(config_path);
+        let mount = mount_rules.strategy_for(&current_dir);
+        if mount.is_special() {
+            notifications.info(mount_notice(&mount));
+        }
+
+        let theme = Theme::from_config(config_path, opener_config.color_depth, &mut notifications);
+        let icons = IconStyle::from_config(config_path, &mut notifications);
+        let ignore = IgnoreRules::from_config(config_path, &mut notifications);
+        let mut previewers = PreviewerRegistry::from_config(config_path, &mut notifications);
+        let scripts = Scripts::load(config_path, &mut notifications);
+        scripts.register_previewers(&mut previewers);
+        let tags = Tags::from_config(config_path, &mut notifications);
+        let notes = Notes::from_config(config_path, &mut notifications);
+        let searches = SavedSearches::from_config(config_path, &mut notifications);
+        let preserve = Preserve::from_config(config_path, &mut notifications);
+        let s3 = S3Config::from_config(config_path, &mut notifications);
+        let custom_commands = CustomCommands::from_config(config_path, &mut notifications);
+        let hooks = Hooks::from_config(config_path, &mut notifications);
+        let show_hidden = options.show_hidden;
+        let cache_config = CacheConfig::from_config(config_path);
+
+        let mut state = AppState {
+            files: vec![Entry::placeholder("<Loading...>")],
+            loading: true,
+            last_load_time: Instant::now(),
+            sort_mode: SortMode::Name,
+            marked: HashSet::new(),
+            free_space: free_space(&current_dir),
+            notifications,
+            prompt: None,
+            command_history: CommandHistory::load(),
+            detailed_view: false,
+            show_properties: false,
+            show_messages: false,
+            show_cache_stats: false,
+            command_output: None,
+            output_scroll: None,
+            pinned_preview: None,
+            markdown_source: false,
+            pager: None,
+            preview_hidden: false,
+            preview_width: None,
+            focus: Panel::Files,
+            preview_scroll: None,
+            preview_tail: false,
+            open_with: None,
+            devices: None,
+            merge: None,
+            sync: None,
+            transfer: None,
+            paste: None,
+            recovery: None,
+            filing_plan: None,
+            pending_discard: None,
+            pending_delete: None,
+            checksums: None,
+            diff: None,
+            pending_keys: String::new(),
+            pending_since: Instant::now(),
+            count: None,
+            split: options.split.map(|direction| {
+                let dir = options.right.clone().unwrap_or_else(|| current_dir.clone());
+                Split {
+                    direction,
+                    other: Pane {
+                        files: list_files(&dir, show_hidden, SortMode::Name, mount.stat_entries)
+                            .unwrap_or_default(),
+                        dir,
+                        cursor: 0,
+                        remote: None,
+                    },
+                    focus_second: false,
+                }
+            }),
+            mount_rules,
+            mount,
+            tree: None,
+        };
+
+        if let Some(journal) = Journal::load() {
+            state
+                .notifications
+                .error("A file operation was interrupted, see the recovery dialog");
+            state.recovery = Some(journal);
+        }
+
+        let mut app = Self {
+            state,
+            last_dir: current_dir.clone(),
+            current_dir,
+            cursor_position: 0,
+            list_offset: 0,
+            list_height: 0,
+            visual: None,
+            breadcrumbs: Vec::new(),
+            select: options
+                .select
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned()),
+            show_hidden,
+            default_sort: SortMode::Name,
+            default_hidden: show_hidden,
+            dir_settings: DirSettings::default(),
+            background_loader: None,
+            preview_cache: None,
+            follow: None,
+            macros: Macros::default(),
+            last_change: None,
+            last_selected_file_path: None,
+            preview_worker: PreviewWorker::new(Arc::new(previewers)),
+            search_query: String::new(),
+            search_filtered: false,
+            pending_match: None,
+            todos: Vec::new(),
+            todo_list_state: ListState::default(),
+            todo_path: None,
+            project_todos: true,
+            dir_cache: DirectoryCache::new(cache_config.directories),
+            metadata_cache: FileMetadataCache::new(cache_config.metadata),
+            columns: ColumnRegistry::from_config(config_path, &opener_config.programs),
+            preserve,
+            s3,
+            custom_commands,
+            hooks,
+            hooked_dir: PathBuf::new(),
+            shell: None,
+            output_rows: 0,
+            scripts,
+            scripted_selection: None,
+            script_depth: 0,
+            script_segments: Vec::new(),
+            theme,
+            icons,
+            ignore,
+            tags,
+            tag_filter: None,
+            notes,
+            searches,
+            virtual_dir: None,
+            remote: None,
+            ipc: None,
+            global_opener_config: Arc::clone(&opener_config),
+            opener_config,
+            filing_rules: load_filing_rules(config_path),
+            tutorial,
+            pick: options.pick,
+            quit: false,
+            picked: None,
+            clear_screen: false,
+            suspend: false,
+        };
+        app.reload();
+        app.fire_script(ScriptHook::Startup, None);
+        Ok(app)
+    }
+
+    // Lists the current directory again, under the settings that apply to it
+    pub fn reload(&mut self) {
+        self.search_filtered = false;
+        self.sync_todos();
+        if let Some(remote) = &self.remote {
+            self.state.files = remote.files(self.show_hidden, self.state.sort_mode);
+            self.state.loading = false;
+            self.background_loader = None;
+            self.cursor_position = self
+                .cursor_position
+                .min(self.state.files.len().saturating_sub(1));
+            return;
+        }
+        self.apply_dir_settings();
+        self.ignore.update(&self.current_dir);
+        // A search stays open until another directory is listed
+        let query = match self.virtual_dir.take() {
+            Some((label, search)) if search.root == self.current_dir => {
+                match Query::parse(&search.query) {
+                    Ok(query) => {
+                        self.virtual_dir = Some((label, search));
+                        Some(query)
+                    }
+                    Err(e) => {
+                        self.state.notifications.error(e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        self.background_loader = Some(self.state.start_loading(
+            &self.current_dir,
+            self.show_hidden,
+            query,
+        ));
+        if let Some(tree) = self.state.tree.as_mut() {
+            tree.sync(&self.current_dir, self.show_hidden);
+        }
+    }
+
+    // Switches to the `.termfm.toml` settings of the current directory's tree
+    // when they differ from the last directory's, falling back to the user's
+    // own choices for everything they leave out
+    fn apply_dir_settings(&mut self) {
+        let settings = DirSettings::discover(&self.current_dir);
+        for error in &settings.errors {
+            self.state.notifications.error(error.clone());
+        }
+        if settings.sources == self.dir_settings.sources {
+            return;
+        }
+        let sort = settings.sort.unwrap_or(self.default_sort);
+        let hidden = settings.hidden.unwrap_or(self.default_hidden);
+        if sort != self.state.sort_mode || hidden != self.show_hidden {
+            self.state.sort_mode = sort;
+            self.show_hidden = hidden;
+            self.dir_cache.clear();
+        }
+        self.opener_config = if settings.sources.is_empty() {
+            Arc::clone(&self.global_opener_config)
+        } else {
+            Arc::new(settings.openers(&self.global_opener_config))
+        };
+        self.dir_settings = settings;
+    }
+
+    // Switches to the to-do list of the project the current directory is in,
+    // or back to the global list outside of one and on remote panes, saving
+    // the list left behind
+    fn sync_todos(&mut self) {
+        let path = Some(&self.current_dir)
+            .filter(|_| self.project_todos && self.remote.is_none())
+            .and_then(|dir| project_todo_path(dir))
+            .or_else(global_todo_path);
+        if path == self.todo_path {
+            return;
+        }
+        self.save_todo_list();
+        self.todos = path.as_deref().map(load_todos).unwrap_or_default();
+        self.todo_list_state
+            .select((!self.todos.is_empty()).then_some(0));
+        self.todo_path = path;
+    }
+
+    // Called after every change to the list, so that nothing is lost however
+    // termfm ends
+    pub fn save_todo_list(&mut self) {
+        let Some(path) = &self.todo_path else {
+            return;
+        };
+        if let Err(e) = save_todos(path, &self.todos) {
+            self.state
+                .notifications
+                .error(format!("Failed to save {}: {}", path.display(), e));
+        }
+    }
+
+    // The top directory of the project whose to-do list is shown
+    pub fn todo_project(&self) -> Option<&Path> {
+        self.todo_path
+            .as_deref()
+            .filter(|path| Some(*path) != global_todo_path().as_deref())
+            .and_then(Path::parent)
+    }
+
+    // Between the project's list and the global one, starting a list in the
+    // current directory when it isn't in a project yet
+    fn toggle_todos(&mut self) {
+        if self.todo_project().is_some() {
+            self.project_todos = false;
+            self.sync_todos();
+            self.state
+                .notifications
+                .info("Showing the global to-do list");
+            return;
+        }
+        if self.remote.is_some() {
+            self.state
+                .notifications
+                .error("To-do lists can only be kept in local directories");
+            return;
+        }
+        if project_todo_path(&self.current_dir).is_none() {
+            let path = self.current_dir.join(TODO_FILE);
+            if Some(&path) == global_todo_path().as_ref() {
+                self.state
+                    .notifications
+                    .error("The home directory's to-do list is the global one");
+                return;
+            }
+            if let Err(e) = save_todos(&path, &[]) {
+                self.state
+                    .notifications
+                    .error(format!("Failed to start a to-do list: {}", e));
+                return;
+            }
+        }
+        self.project_todos = true;
+        self.sync_todos();
+        if let Some(project) = self.todo_project() {
+            let message = format!("Showing the to-do list of {}", project.display());
+            self.state.notifications.info(message);
+        }
+    }
+
+    // Writes the tags and notes after a change, complaining when that fails
+    fn save_annotations(&mut self) {
+        if let Err(e) = self.tags.save() {
+            self.state
+                .notifications
+                .error(format!("Failed to save tags: {}", e));
+        }
+        if let Err(e) = self.notes.save() {
+            self.state
+                .notifications
+                .error(format!("Failed to save notes: {}", e));
+        }
+    }
+
+    pub fn selected_file(&self) -> Option<String> {
+        self.state
+            .files
+            .get(self.cursor_position)
+            .map(|entry| entry.name.clone())
+    }
+
+    // Picks up finished background work: directory listings, previews, `!`
+    // command output and tutorial progress
+    pub fn tick(&mut self) {
+        if !self.state.pending_keys.is_empty() && self.state.pending_since.elapsed() > CHORD_TIMEOUT
+        {
+            self.state.pending_keys.clear();
+            self.state.count = None;
+        }
+        if let Some(loader) = &self.background_loader {
+            let mut finished = None;
+            for update in loader.updates() {
+                match update {
+                    LoadUpdate::Batch(mut entries) => {
+                        self.ignore.filter(&self.current_dir, &mut entries);
+                        self.tags.read_finder(&self.current_dir, &entries);
+                        self.tags.filter(
+                            &self.current_dir,
+                            self.tag_filter.as_deref(),
+                            &mut entries,
+                        );
+                        self.state.files.extend(entries);
+                    }
+                    LoadUpdate::Done(result) => finished = Some(result),
+                }
+            }
+            if let Some(result) = finished {
+                // Stay on the entry the user moved to while the listing streamed in
+                let selected = self
+                    .state
+                    .files
+                    .get(self.cursor_position)
+                    .filter(|_| self.cursor_position > 0)
+                    .map(|entry| entry.name.clone());
+                self.state.files = match result {
+                    Ok(mut files) => {
+                        self.ignore.filter(&self.current_dir, &mut files);
+                        self.tags.read_finder(&self.current_dir, &files);
+                        self.tags
+                            .filter(&self.current_dir, self.tag_filter.as_deref(), &mut files);
+                        files
+                    }
+                    Err(e) => {
+                        self.state.notifications.error(e);
+                        vec![Entry::placeholder("<Error loading directory>")]
+                    }
+                };
+                if let Some(selected) = selected.or(self.select.take()) {
+                    if let Some(index) = self.state.files.iter().position(|e| e.name == selected) {
+                        self.cursor_position = index;
+                    }
+                }
+                self.state.loading = false;
+                self.state.free_space = free_space(&self.current_dir);
+                if self.state.detailed_view {
+                    self.columns.refresh(&self.current_dir);
+                }
+                self.background_loader = None;
+
+                if self.cursor_position >= self.state.files.len() && !self.state.files.is_empty() {
+                    self.cursor_position = self.state.files.len() - 1;
+                }
+                if let Some(steps) = self.pending_match.take() {
+                    self.next_match(steps);
+                }
+            }
+        }
+
+        // Also for the directory started in
+        if self.current_dir != self.hooked_dir && self.remote.is_none() {
+            self.hooked_dir = self.current_dir.clone();
+            let dir = self.current_dir.to_string_lossy().into_owned();
+            self.fire_hook(HookEvent::EnterDir, &[("TERMFM_DIR", &dir)]);
+            self.fire_script(ScriptHook::DirChange, Some(&self.current_dir.clone()));
+        }
+
+        self.poll_shell();
+
+        if self.reload_due().is_some_and(|due| Instant::now() >= due) {
+            self.last_dir = self.current_dir.clone();
+            self.reload();
+            self.cursor_position = 0;
+        }
+
+        let requests = self.ipc.as_ref().map(IpcServer::requests);
+        for (command, reply) in requests.unwrap_or_default() {
+            let _ = reply.send(self.answer_remote(&command));
+        }
+
+        if let Some(checksums) = &mut self.state.checksums {
+            checksums.poll();
+        }
+        if self.state.sync.as_mut().is_some_and(|sync| sync.poll()) {
+            self.finish_sync();
+        }
+        if let Some(transfer) = self.state.transfer.as_mut() {
+            if transfer.poll() {
+                self.finish_transfer();
+            }
+        }
+
+        if let Some(tutorial) = &mut self.tutorial {
+            let view = TutorialView {
+                dir: &self.current_dir,
+                marked: &self.state.marked,
+                search_query: &self.search_query,
+            };
+            for title in tutorial.update(&view) {
+                self.state
+                    .notifications
+                    .info(format!("Tutorial: {} done", title));
+            }
+        }
+
+        let selected_file = self.selected_file();
+
+        // Remote files are previewed from the mirror, downloaded by the
+        // preview worker
+        if let Some((remote, file_name)) = self.remote.as_ref().zip(selected_file.as_ref()) {
+            let full_path = self.current_dir.join(file_name);
+            if self.last_selected_file_path.as_ref() != Some(&full_path) {
+                let message = match remote.entry(file_name) {
+                    Some(entry) if entry.is_dir => Some("Remote directory".to_string()),
+                    Some(entry) if entry.size > PREVIEW_LIMIT => {
+                        Some("Too large to preview remotely".to_string())
+                    }
+                    _ => match remote.fetch(file_name) {
+                        Ok((local, fetch)) => {
+                            self.metadata_cache.metadata.remove(&local);
+                            self.preview_worker.request_fetched(local, Some(fetch));
+                            None
+                        }
+                        Err(e) => Some(e),
+                    },
+                };
+                if let Some(message) = message {
+                    self.preview_cache = Some((full_path.clone(), Preview::message(&message)));
+                }
+                self.last_selected_file_path = Some(full_path);
+            }
+        }
+
+        if let Some(file_name) = &selected_file {
+            let full_path = self.current_dir.join(file_name);
+            if self.metadata_cache.is_file(&full_path)
+                && self.last_selected_file_path.as_ref() != Some(&full_path)
+            {
+                self.preview_worker.request(full_path.clone());
+                self.follow = follows_automatically(&full_path)
+                    .then(|| Follow::open(&full_path).ok())
+                    .flatten();
+                self.last_selected_file_path = Some(full_path);
+            }
+        }
+        if let Some(preview) = self.preview_worker.poll(&self.opener_config) {
+            self.preview_cache = Some(preview);
+        }
+        // Following ends when the selection moves on
+        let selected_path = selected_file.map(|file| self.current_dir.join(file));
+        if self.follow.as_ref().map(|follow| &follow.path) != selected_path.as_ref() {
+            self.follow = None;
+        }
+        if !self.state.loading && self.scripted_selection != selected_path {
+            self.scripted_selection = selected_path.clone();
+            if let Some(path) = &selected_path {
+                self.fire_script(ScriptHook::Select, Some(path));
+            }
+        }
+        if self.scripts.has_segments() {
+            let selected = self.script_selection();
+            let context = ScriptContext {
+                dir: &self.current_dir,
+                file: selected_path.as_deref(),
+                selected: &selected,
+            };
+            self.script_segments = self
+                .scripts
+                .segments(&context, &mut self.state.notifications);
+        }
+        // Previewers and status segments may have asked for something too
+        self.run_script_actions();
+        if let Some(follow) = self.follow.as_mut().filter(|follow| follow.is_due()) {
+            if let Err(e) = follow.poll() {
+                self.state
+                    .notifications
+                    .error(format!("Stopped following: {}", e));
+                self.follow = None;
+            }
+        }
+    }
+
+    // When the directory moved to gets listed, if it hasn't been yet
+    fn reload_due(&self) -> Option<Instant> {
+        let debounce_time = if self.state.loading {
+            Duration::from_millis(100) // Shorter debounce when already loading
+        } else {
+            Duration::from_millis(300) // Normal debounce
+        };
+        (self.current_dir != self.last_dir).then(|| self.state.last_load_time + debounce_time)
+    }
+
+    // The next time `tick` has something to do that no event or worker
+    // wakes it for: a chord or notification timing out, a listing or
+    // preview whose debounce ends, a followed file to look at again or a
+    // shell command whose exit is waited for
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let chord =
+            (!self.state.pending_keys.is_empty()).then(|| self.state.pending_since + CHORD_TIMEOUT);
+        [
+            chord,
+            self.reload_due(),
+            self.preview_worker.deadline(),
+            self.follow.as_ref().map(Follow::due),
+            self.state.notifications.expires(),
+            self.shell.as_ref().and_then(ShellRun::deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    // One turn of the main loop: pick up background work, draw, then wait
+    // for the next event, piece of background work or deadline and handle
+    // the event if it was one
+    pub fn step<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+    ) -> io::Result<()> {
+        self.tick();
+        terminal
+            .draw(|f| self.draw(f))
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        if let Some(event) = events.next_event(self.next_deadline())? {
+            self.handle_event(event);
+        }
+        Ok(())
+    }
+
+    // The next draw lays everything out for the new size. What was drawn
+    // before may be left over where the old size had other cells.
+    fn resized(&mut self) {
+        self.clear_screen = true;
+        self.list_offset = self
+            .list_offset
+            .min(self.state.files.len().saturating_sub(1));
+        if let Some(view) = self.state.diff.as_mut() {
+            view.scroll = view.scroll.min(view.line_count().saturating_sub(1));
+        }
+    }
+
+    pub fn handle_event(&mut self, event: Event) {
+        // Not part of a macro, the next one may run in a different size
+        if let Event::Resize(..) = event {
+            self.resized();
+            return;
+        }
+        self.macros.record(&event);
+        if let Event::Mouse(mouse) = event {
+            self.handle_mouse(mouse);
+        } else if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        {
+            let mut pending_action: Option<CommandAction> = None;
+
+            if self.state.recovery.is_some() {
+                match code {
+                    KeyCode::Char('r') => self.resume_recovery(),
+                    KeyCode::Char('c') => self.discard_recovery(),
+                    KeyCode::Esc => self.state.recovery = None,
+                    _ => {}
+                }
+            } else if self.state.filing_plan.is_some() {
+                match code {
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let plan = self.state.filing_plan.take().unwrap();
+                        let total = plan.len();
+                        let mut journal = Journal::begin(JournalOperation::Moves(plan));
+                        let (moved, errors) = journal.apply_moves();
+                        journal.finish();
+                        for error in errors {
+                            self.state.notifications.error(error);
+                        }
+                        self.state
+                            .notifications
+                            .info(format!("Filed {} of {} file(s)", moved, total));
+                        self.reload();
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                        self.state.filing_plan = None;
+                    }
+                    _ => {}
+                }
+            } else if self.state.pending_discard.is_some() {
+                match code {
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let paths = self.state.pending_discard.take().unwrap();
+                        match git::discard(&self.current_dir, &paths, &self.opener_config.programs)
+                        {
+                            Ok(()) => self
+                                .state
+                                .notifications
+                                .info(format!("Discarded changes to {} file(s)", paths.len())),
+                            Err(e) => self.state.notifications.error(e),
+                        }
+                        self.last_selected_file_path = None;
+                        self.reload();
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                        self.state.pending_discard = None;
+                    }
+                    _ => {}
+                }
+            } else if self.state.pending_delete.is_some() {
+                match code {
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let targets = self.state.pending_delete.take().unwrap();
+                        self.delete_paths(&targets);
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                        self.state.pending_delete = None;
+                    }
+                    _ => {}
+                }
+            } else if let Some(sync) = self.state.sync.as_mut() {
+                match code {
+                    KeyCode::Enter | KeyCode::Char('y') if sync.done.is_none() => {
+                        sync.start(self.preserve)
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => sync.move_by(1),
+                    KeyCode::Up | KeyCode::Char('k') => sync.move_by(-1),
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                        if sync.done.is_some() {
+                            sync.cancel();
+                            self.finish_sync();
+                        } else {
+                            self.state.sync = None;
+                        }
+                    }
+                    _ => {}
+                }
+            } else if self.state.transfer.is_some() {
+                if matches!(code, KeyCode::Esc | KeyCode::Char('q')) {
+                    self.finish_transfer();
+                }
+            } else if let Some(merge) = self.state.merge.as_mut() {
+                let choice = match code {
+                    KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                        'n' => Some(ConflictChoice::KeepNewest),
+                        'l' => Some(ConflictChoice::KeepLarger),
+                        'b' => Some(ConflictChoice::KeepBoth),
+                        's' => Some(ConflictChoice::Skip),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let remember = modifiers.contains(KeyModifiers::SHIFT)
+                    || matches!(code, KeyCode::Char(c) if c.is_ascii_uppercase());
+                let finished = match (code, choice) {
+                    (KeyCode::Esc, _) => true,
+                    (_, Some(choice)) => {
+                        merge.resolve(choice, remember);
+                        merge.advance()
+                    }
+                    _ => false,
+                };
+                if finished {
+                    let merge = self.state.merge.take().unwrap();
+                    merge.finish(&mut self.state.notifications);
+                    self.job_done("merge");
+                    self.reload();
+                }
+            } else if let Some(paste) = self.state.paste.as_mut() {
+                let choice = match code {
+                    KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                        'o' => Some(PasteChoice::Overwrite),
+                        'n' => Some(PasteChoice::OverwriteIfNewer),
+                        'r' => Some(PasteChoice::Rename),
+                        's' => Some(PasteChoice::Skip),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let remember = modifiers.contains(KeyModifiers::SHIFT)
+                    || matches!(code, KeyCode::Char(c) if c.is_ascii_uppercase());
+                match (code, choice) {
+                    (KeyCode::Esc, _) => {
+                        self.state.paste = None;
+                        self.state.notifications.info("Paste cancelled");
+                    }
+                    (_, Some(choice)) => {
+                        paste.resolve(choice, remember);
+                        if paste.advance() {
+                            let paste = self.state.paste.take().unwrap();
+                            self.finish_paste(paste);
+                        }
+                    }
+                    _ => {}
+                }
+            } else if !self.state.pending_keys.is_empty() {
+                let count = self.state.count.take();
+                let mut keys = std::mem::take(&mut self.state.pending_keys);
+                if let KeyCode::Char(c) = code {
+                    keys.push(c);
+                    match chords::lookup(&keys) {
+                        Lookup::Found(chord) => pending_action = self.run_chord(chord, count),
+                        Lookup::Pending => {
+                            self.state.pending_keys = keys;
+                            self.state.count = count;
+                        }
+                        Lookup::Unbound => {}
+                    }
+                }
+            } else if let Some(pager) = self.state.pager.as_mut() {
+                let page = pager.height.max(1) as isize;
+                match (code, modifiers) {
+                    (KeyCode::Esc | KeyCode::Char('q'), _) => self.state.pager = None,
+                    (KeyCode::Down | KeyCode::Char('j'), _) => pager.scroll_by(1),
+                    (KeyCode::Up | KeyCode::Char('k'), _) => pager.scroll_by(-1),
+                    (KeyCode::Char('d'), KeyModifiers::CONTROL) => pager.scroll_by(page / 2),
+                    (KeyCode::Char('u'), KeyModifiers::CONTROL) => pager.scroll_by(-page / 2),
+                    (KeyCode::PageDown | KeyCode::Char(' '), _)
+                    | (KeyCode::Char('f'), KeyModifiers::CONTROL) => pager.scroll_by(page),
+                    (KeyCode::PageUp, _) | (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
+                        pager.scroll_by(-page)
+                    }
+                    (KeyCode::Char('g') | KeyCode::Home, _) => pager.scroll = 0,
+                    (KeyCode::Char('G') | KeyCode::End, _) => pager.scroll_by(isize::MAX),
+                    _ => {}
+                }
+            } else if let Some(view) = self.state.diff.as_mut() {
+                let last = view.line_count().saturating_sub(1);
+                match code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.state.diff = None,
+                    KeyCode::Char('s') => {
+                        view.side_by_side = !view.side_by_side;
+                        view.scroll = 0;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => view.scroll = (view.scroll + 1).min(last),
+                    KeyCode::Up | KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+                    KeyCode::PageDown | KeyCode::Char(' ') => {
+                        view.scroll = (view.scroll + 20).min(last)
+                    }
+                    KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+                    KeyCode::Char('g') => view.scroll = 0,
+                    KeyCode::Char('G') => view.scroll = last,
+                    _ => {}
+                }
+            } else if let Some(checksums) = self.state.checksums.as_mut() {
+                match code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.state.checksums = None,
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        checksums.select(checksums.selected() + 1)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        checksums.select(checksums.selected().saturating_sub(1))
+                    }
+                    KeyCode::Char('y') => match checksums.selected_digest() {
+                        Some(digest) => match clipboard::copy(digest, &self.opener_config.programs)
+                        {
+                            Ok(_) => self.state.notifications.info(format!("Copied {}", digest)),
+                            Err(e) => self.state.notifications.error(e),
+                        },
+                        None => self.state.notifications.error("No checksum to copy yet"),
+                    },
+                    KeyCode::Char('w') if checksums.source.is_none() => match checksums.write() {
+                        Ok(path) => {
+                            self.state
+                                .notifications
+                                .info(format!("Wrote {}", path.display()));
+                            self.state.checksums = None;
+                            self.reload();
+                        }
+                        Err(e) => self
+                            .state
+                            .notifications
+                            .error(format!("Failed to write checksums: {}", e)),
+                    },
+                    _ => {}
+                }
+            } else if let Some(menu) = self.state.open_with.as_mut() {
+                match code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.state.open_with = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select(menu.selected() + 1),
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        menu.select(menu.selected().saturating_sub(1))
+                    }
+                    KeyCode::Enter => {
+                        let mut menu = self.state.open_with.take().unwrap();
+                        let index = menu.selected();
+                        if index < menu.choices.len() {
+                            pending_action =
+                                Some(CommandAction::OpenWith(menu.choices.swap_remove(index)));
+                        } else {
+                            let mut prompt = Prompt::new(':');
+                            prompt.input = "open ".to_string();
+                            self.state.prompt = Some(prompt);
+                        }
+                    }
+                    _ => {}
+                }
+            } else if let Some(devices) = self.state.devices.as_mut() {
+                let device = devices
+                    .selected()
+                    .map(|device| (device.path.clone(), device.mount_point.clone()));
+                match (code, device) {
+                    (KeyCode::Esc | KeyCode::Char('q'), _) => self.state.devices = None,
+                    (KeyCode::Down | KeyCode::Char('j'), _) => devices.move_by(1),
+                    (KeyCode::Up | KeyCode::Char('k'), _) => devices.move_by(-1),
+                    (KeyCode::Char('r'), _) => devices.refresh(),
+                    // Opens the mount point, mounting the device first if needed
+                    (KeyCode::Enter | KeyCode::Char('l'), Some((_, Some(mount_point)))) => {
+                        self.state.devices = None;
+                        pending_action = Some(CommandAction::Cd(mount_point));
+                    }
+                    (KeyCode::Enter | KeyCode::Char('l' | 'm'), Some((path, None))) => {
+                        match devices::mount(&path, &self.opener_config.programs) {
+                            Ok(mount_point) => {
+                                self.state.notifications.info(format!(
+                                    "Mounted {} at {}",
+                                    path.display(),
+                                    mount_point.display()
+                                ));
+                                devices.refresh();
+                                if code != KeyCode::Char('m') {
+                                    self.state.devices = None;
+                                    pending_action = Some(CommandAction::Cd(mount_point));
+                                }
+                            }
+                            Err(e) => self.state.notifications.error(e),
+                        }
+                    }
+                    (KeyCode::Char('u'), Some((path, Some(mount_point)))) => {
+                        // Out of the way first, or it would stay busy
+                        if self.current_dir.starts_with(&mount_point) {
+                            let parent = mount_point.parent().unwrap_or(Path::new("/"));
+                            self.change_dir(parent.to_path_buf());
+                        }
+                        match devices::unmount(&path, &self.opener_config.programs) {
+                            Ok(()) => {
+                                self.state
+                                    .notifications
+                                    .info(format!("Unmounted {}", path.display()));
+                                if let Some(devices) = self.state.devices.as_mut() {
+                                    devices.refresh();
+                                }
+                            }
+                            Err(e) => self.state.notifications.error(e),
+                        }
+                    }
+                    _ => {}
+                }
+            } else if let Some(prompt) = self.state.prompt.as_mut() {
+                if code != KeyCode::Tab {
+                    prompt.completions.clear();
+                }
+                match code {
+                    KeyCode::Esc => self.state.prompt = None,
+                    KeyCode::Backspace => {
+                        if prompt.input.is_empty() {
+                            self.state.prompt = None;
+                        } else {
+                            prompt.input.pop();
+                        }
+                    }
+                    KeyCode::Char(c) => prompt.input.push(c),
+                    // Completion and history are those of `:` commands
+                    KeyCode::Tab if prompt.prefix == ':' => {
+                        if prompt.completions.is_empty() {
+                            prompt.completions = complete_command(&prompt.input, &self.current_dir);
+                            // Commands of the config complete like the built-in ones
+                            if !prompt.input.contains(' ') {
+                                prompt.completions.extend(
+                                    self.custom_commands
+                                        .names()
+                                        .chain(self.scripts.command_names())
+                                        .filter(|name| name.starts_with(&prompt.input))
+                                        .map(|name| format!("{} ", name)),
+                                );
+                                prompt.completions.sort();
+                            }
+                            prompt.completion_index = 0;
+                        } else {
+                            prompt.completion_index =
+                                (prompt.completion_index + 1) % prompt.completions.len();
+                        }
+                        if let Some(completion) = prompt.completions.get(prompt.completion_index) {
+                            prompt.input = completion.clone();
+                        }
+                    }
+                    KeyCode::Up | KeyCode::Down if prompt.prefix == ':' => {
+                        let history = &self.state.command_history.entries;
+                        let index = match (code, prompt.history_index) {
+                            (KeyCode::Up, None) => history.len().checked_sub(1),
+                            (KeyCode::Up, Some(i)) => Some(i.saturating_sub(1)),
+                            (_, Some(i)) if i + 1 < history.len() => Some(i + 1),
+                            _ => None,
+                        };
+                        prompt.history_index = index;
+                        prompt.input = index.map(|i| history[i].clone()).unwrap_or_default();
+                    }
+                    KeyCode::Enter if prompt.prefix == '!' => {
+                        let input = self.state.prompt.take().unwrap().input;
+                        pending_action = Some(CommandAction::Shell(input));
+                    }
+                    KeyCode::Enter => {
+                        let input = self.state.prompt.take().unwrap().input;
+                        self.state.command_history.push(input.trim());
+                        match self.parse_input(&input) {
+                            Ok(action) => pending_action = Some(action),
+                            Err(e) => self.state.notifications.error(e),
+                        }
+                    }
+                    _ => {}
+                }
+            } else if self.state.command_output.is_some() && code == KeyCode::Esc {
+                self.close_output();
+            } else if let Some(rows) = self
+                .state
+                .command_output
+                .as_ref()
+                .and_then(|_| scroll_rows(code, modifiers, self.output_rows))
+            {
+                self.scroll_output(rows);
+            } else if (code, modifiers) == (KeyCode::Char('w'), KeyModifiers::CONTROL) {
+                // The start of a `Ctrl+w` chord, which moves the focus
+                self.state.pending_keys.push(CTRL_W);
+                self.state.pending_since = Instant::now();
+            } else if self.state.focus != Panel::Files && code == KeyCode::Esc {
+                self.state.focus = Panel::Files;
+            } else if self.state.focus == Panel::Todos && code == KeyCode::Enter {
+                self.go_to_todo_path();
+            } else if let Some(rows) = scroll_rows(code, modifiers, self.list_height)
+                .filter(|_| self.state.focus != Panel::Files)
+            {
+                self.scroll_panel(rows);
+            } else if let Some(tree) = self.state.tree.as_mut().filter(|tree| tree.focused) {
+                match code {
+                    KeyCode::Down | KeyCode::Char('j') => tree.move_by(1),
+                    KeyCode::Up | KeyCode::Char('k') => tree.move_by(-1),
+                    KeyCode::Right | KeyCode::Char('l') => tree.expand(),
+                    KeyCode::Left | KeyCode::Char('h') => tree.collapse(),
+                    KeyCode::Char(' ') => tree.toggle(),
+                    KeyCode::Enter => {
+                        if let Some(dir) = tree.selected() {
+                            pending_action = Some(CommandAction::Cd(dir.to_path_buf()));
+                        }
+                        tree.focused = false;
+                    }
+                    KeyCode::Esc => tree.focused = false,
+                    KeyCode::Char('Z') => self.state.tree = None,
+                    KeyCode::Char('q') => pending_action = Some(CommandAction::Quit),
+                    _ => {}
+                }
+            } else if let Some(remote) = &self.remote {
+                // Only browsing and copying work on remote directories
+                let last = self.state.files.len().saturating_sub(1);
+                let selected = self.selected_file();
+                match code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        self.cursor_position = (self.cursor_position + 1).min(last)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.cursor_position = self.cursor_position.saturating_sub(1)
+                    }
+                    KeyCode::Home | KeyCode::Char('g') => self.cursor_position = 0,
+                    KeyCode::End | KeyCode::Char('G') => self.cursor_position = last,
+                    KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => {
+                        // Links may point at directories, which only listing them tells
+                        if let Some(entry) = selected
+                            .as_deref()
+                            .and_then(|name| remote.entry(name))
+                            .filter(|entry| entry.is_dir || entry.is_symlink)
+                        {
+                            pending_action =
+                                Some(CommandAction::Remote(remote.location.join(&entry.name)));
+                        }
+                    }
+                    KeyCode::Left | KeyCode::Char('h') | KeyCode::Backspace => {
+                        if let Some(parent) = remote.location.parent() {
+                            pending_action = Some(CommandAction::Remote(parent));
+                        }
+                    }
+                    KeyCode::Char('m') | KeyCode::Char(' ') => {
+                        if let Some(name) = selected {
+                            let full_path = self.current_dir.join(name);
+                            if !self.state.marked.remove(&full_path) {
+                                self.state.marked.insert(full_path);
+                            }
+                            self.cursor_position = (self.cursor_position + 1).min(last);
+                        }
+                    }
+                    KeyCode::Char('r') => {
+                        if let Err(e) = self.remote.as_mut().unwrap().refresh() {
+                            self.state.notifications.error(e);
+                        }
+                        self.reload();
+                    }
+                    KeyCode::Char(':') => self.state.prompt = Some(Prompt::new(':')),
+                    KeyCode::Tab => self.switch_pane(),
+                    KeyCode::Esc => {
+                        pending_action = Some(CommandAction::Cd(remote.local_dir.clone()))
+                    }
+                    KeyCode::Char('q') => pending_action = Some(CommandAction::Quit),
+                    _ => {}
+                }
+            } else {
+                let count = self.state.count.take();
+                let custom = self
+                    .custom_commands
+                    .for_key(code, modifiers)
+                    .map(|command| command.name.clone());
+                let bound = self
+                    .scripts
+                    .for_key(code, modifiers)
+                    .map(|line| line.trim_start_matches(':').to_string());
+                match (code, modifiers) {
+                    (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE)
+                        if c != '0' || count.is_some() =>
+                    {
+                        let digit = c as usize - '0' as usize;
+                        self.state.count =
+                            Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    }
+                    (KeyCode::Char('Q'), _) if self.macros.recording.is_some() => {
+                        if let Some((register, keys)) = self.macros.stop() {
+                            self.state
+                                .notifications
+                                .info(format!("Recorded {} key(s) into @{}", keys, register));
+                        }
+                    }
+                    // Keys of `[commands]` take over the built-in ones
+                    _ if custom.is_some() => pending_action = custom.map(CommandAction::Custom),
+                    // So do the keys bound by the script
+                    _ if bound.is_some() => {
+                        match self.parse_input(bound.as_deref().unwrap_or_default()) {
+                            Ok(action) => pending_action = Some(action),
+                            Err(e) => self.state.notifications.error(e),
+                        }
+                    }
+                    (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                        if chords::lookup(&c.to_string()) == Lookup::Pending =>
+                    {
+                        self.state.pending_keys.push(c);
+                        self.state.pending_since = Instant::now();
+                        self.state.count = count;
+                    }
+                    (KeyCode::Char('q'), _) => {
+                        pending_action = Some(CommandAction::Quit);
+                    }
+                    // Alt+1 goes to the parent, Alt+2 to its parent and so on,
+                    // as numbered in the path bar
+                    (KeyCode::Char(c @ '1'..='9'), KeyModifiers::ALT) => {
+                        let levels = c as usize - '0' as usize;
+                        if let Some(dir) = self.current_dir.ancestors().nth(levels) {
+                            pending_action = Some(CommandAction::Cd(dir.to_path_buf()));
+                        }
+                    }
+                    (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                        self.clear_screen = true;
+                    }
+                    (KeyCode::Char('z'), KeyModifiers::CONTROL) => {
+                        self.suspend = true;
+                        self.clear_screen = true;
+                    }
+                    (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
+                        self.scroll_by((self.list_height / 2).max(1) as isize);
+                    }
+                    (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+                        self.scroll_by(-((self.list_height / 2).max(1) as isize));
+                    }
+                    (KeyCode::PageDown, _) | (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                        self.scroll_by(self.list_height.max(1) as isize);
+                    }
+                    (KeyCode::PageUp, _) | (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
+                        self.scroll_by(-(self.list_height.max(1) as isize));
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('j'), _) => {
+                        self.jump_to(self.cursor_position.saturating_add(count.unwrap_or(1)));
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('k'), _) => {
+                        self.jump_to(self.cursor_position.saturating_sub(count.unwrap_or(1)));
+                    }
+                    (KeyCode::Home, _) => self.jump_to(0),
+                    (KeyCode::End, _) => self.jump_to(usize::MAX),
+                    // The last entry, or the count's like Vim's `:<count>`
+                    (KeyCode::Char('G'), _) => {
+                        self.jump_to(count.map_or(usize::MAX, |count| count - 1));
+                    }
+                    // The top, middle and bottom rows on screen; a count puts
+                    // the cursor that many rows from the top or bottom
+                    (KeyCode::Char('H'), _) => {
+                        self.jump_to(self.list_offset + count.unwrap_or(1) - 1);
+                    }
+                    (KeyCode::Char('M'), _) => {
+                        let rows = self
+                            .list_height
+                            .min(self.state.files.len().saturating_sub(self.list_offset));
+                        self.jump_to(self.list_offset + rows.saturating_sub(1) / 2);
+                    }
+                    (KeyCode::Char('L'), _) => {
+                        let bottom = (self.list_offset + self.list_height).saturating_sub(1);
+                        self.jump_to(bottom.saturating_sub(count.unwrap_or(1) - 1));
+                    }
+                    (KeyCode::Right, _) | (KeyCode::Char('l'), _) => {
+                        if let Some(selected_file) = self
+                            .state
+                            .files
+                            .get(self.cursor_position)
+                            .map(|entry| &entry.name)
+                        {
+                            let full_path = self.current_dir.join(selected_file);
+                            if self.metadata_cache.is_dir(&full_path) {
+                                self.current_dir = full_path;
+                                self.search_query.clear();
+                                self.last_dir = self.current_dir.clone();
+                                self.reload();
+                                self.cursor_position = 0;
+                            }
+                        }
+                    }
+                    (KeyCode::Left, _) | (KeyCode::Char('h'), _) => {
+                        if let Some(parent) = self.current_dir.parent() {
+                            self.current_dir = parent.to_path_buf();
+                            self.search_query.clear();
+                            self.last_dir = self.current_dir.clone();
+                            self.reload();
+                            self.cursor_position = 0;
+                        }
+                    }
+                    (KeyCode::Enter, _) => {
+                        if let Some(selected_file) = self
+                            .state
+                            .files
+                            .get(self.cursor_position)
+                            .map(|entry| &entry.name)
+                        {
+                            let full_path = self.current_dir.join(selected_file);
+                            if self.metadata_cache.is_file(&full_path) && self.pick {
+                                self.picked = Some(open_targets(&self.state.marked, &full_path));
+                                self.quit = true;
+                            } else if self.metadata_cache.is_file(&full_path) {
+                                let selected = open_targets(&self.state.marked, &full_path);
+                                match open_file(&full_path, &selected, &self.opener_config) {
+                                    Ok(message) => {
+                                        self.state.notifications.info(message);
+                                        self.opened(&full_path);
+                                    }
+                                    Err(e) => self.state.notifications.error(e),
+                                }
+                                // Terminal openers leave the screen in an unknown state
+                                self.clear_screen = true;
+                            }
+                        }
+                    }
+                    (KeyCode::Char('.'), _) => {
+                        pending_action = self.repeat_change();
+                    }
+                    (KeyCode::Char('I'), _) => {
+                        self.ignore.mode = self.ignore.mode.next();
+                        self.state
+                            .notifications
+                            .info(format!("Ignored files {}", self.ignore.mode.label()));
+                        self.reload();
+                    }
+                    (KeyCode::Char('/'), _) => {
+                        let search_input = with_suspended_tui(|| {
+                            print!("Search: ");
+                            let _ = io::stdout().flush();
+
+                            let mut search_input = String::new();
+                            io::stdin()
+                                .read_line(&mut search_input)
+                                .ok()
+                                .map(|_| search_input)
+                        });
+                        self.clear_screen = true;
+
+                        if let Some(search_input) = search_input {
+                            self.search_query = search_input.trim().to_string();
+
+                            if !self.search_query.is_empty() {
+                                match search_files(&self.current_dir, &self.search_query) {
+                                    Ok(search_results) => {
+                                        self.state.files = search_results
+                                            .into_iter()
+                                            .map(|path| {
+                                                let name = path
+                                                    .file_name()
+                                                    .unwrap()
+                                                    .to_string_lossy()
+                                                    .into_owned();
+                                                Entry::load(&self.current_dir, name)
+                                            })
+                                            .collect();
+                                        self.search_filtered = true;
+                                    }
+                                    Err(e) => {
+                                        self.state.files =
+                                            vec![Entry::placeholder("<Search error>")];
+                                        self.state
+                                            .notifications
+                                            .error(format!("Search failed: {}", e));
+                                    }
+                                }
+                            } else {
+                                // Reset to normal listing if search is empty
+                                self.reload();
+                            }
+                        }
+                        self.cursor_position = 0;
+                    }
+                    (KeyCode::Char('n'), _) => {
+                        self.next_match(count.unwrap_or(1) as isize);
+                    }
+                    (KeyCode::Char('N'), _) => {
+                        self.next_match(-(count.unwrap_or(1) as isize));
+                    }
+                    (KeyCode::Char('m'), _) => {
+                        if let Some(selected_file) = self
+                            .state
+                            .files
+                            .get(self.cursor_position)
+                            .map(|entry| &entry.name)
+                        {
+                            let full_path = self.current_dir.join(selected_file);
+                            if !self.state.marked.remove(&full_path) {
+                                self.state.marked.insert(full_path);
+                            }
+                            self.cursor_position = (self.cursor_position + 1)
+                                .min(self.state.files.len().saturating_sub(1));
+                        }
+                    }
+                    (KeyCode::Char('s'), _) => {
+                        pending_action = Some(CommandAction::Sort(self.state.sort_mode.next()));
+                    }
+                    (KeyCode::Char('w'), _) => {
+                        pending_action = Some(CommandAction::ToggleDetailed);
+                    }
+                    (KeyCode::Char('v'), _) => match self.selected_file() {
+                        Some(file) => {
+                            self.state.pager = Some(Pager::new(self.current_dir.join(file)))
+                        }
+                        None => self.state.notifications.error("Nothing selected"),
+                    },
+                    (KeyCode::Char(c @ ('<' | '>')), _) => {
+                        let step = PREVIEW_STEP * count.unwrap_or(1).min(100) as i32;
+                        pending_action = Some(CommandAction::ResizePreview(if c == '<' {
+                            -step
+                        } else {
+                            step
+                        }));
+                    }
+                    (KeyCode::Char('p'), _) => {
+                        if self.state.pinned_preview.take().is_some() {
+                            self.state.notifications.info("Unpinned preview");
+                        } else if let Some(selected_file) = self
+                            .state
+                            .files
+                            .get(self.cursor_position)
+                            .map(|entry| &entry.name)
+                        {
+                            let full_path = self.current_dir.join(selected_file);
+                            if self.metadata_cache.is_file(&full_path) {
+                                let preview = match &self.preview_cache {
+                                    Some((path, preview)) if path == &full_path => preview.clone(),
+                                    _ => preview_file(
+                                        &full_path,
+                                        &self.opener_config,
+                                        &self.preview_worker.previewers,
+                                        &AtomicBool::new(false),
+                                    ),
+                                };
+                                self.state
+                                    .notifications
+                                    .info(format!("Pinned {}", selected_file));
+                                self.state.pinned_preview = Some((full_path, preview));
+                            } else {
+                                self.state.notifications.error("Only files can be pinned");
+                            }
+                        }
+                    }
+                    (KeyCode::Tab, _) => self.switch_pane(),
+                    (KeyCode::Char('e'), _) => {
+                        // Marked files are all opened together, as `%s` does for openers
+                        let editor = env::var("VISUAL")
+                            .or_else(|_| env::var("EDITOR"))
+                            .unwrap_or_else(|_| "vi".to_string());
+                        pending_action = Some(CommandAction::OpenWith(Opener::new(
+                            format!("{} %s", editor),
+                            true,
+                        )));
+                    }
+                    (KeyCode::Char('S'), _) => {
+                        let (variable, default) = if cfg!(windows) {
+                            ("COMSPEC", "cmd")
+                        } else {
+                            ("SHELL", "sh")
+                        };
+                        let shell = env::var(variable).unwrap_or_else(|_| default.to_string());
+                        let status = with_suspended_tui(|| {
+                            Command::new(&shell).current_dir(&self.current_dir).status()
+                        });
+                        if let Err(e) = status {
+                            self.state
+                                .notifications
+                                .error(format!("Failed to run {}: {}", shell, e));
+                        }
+                        self.clear_screen = true;
+                        // Anything may have changed while the shell was running
+                        self.dir_cache.clear();
+                        self.last_selected_file_path = None;
+                        self.reload();
+                    }
+                    (KeyCode::Char('o'), _) => {
+                        pending_action = Some(CommandAction::OpenWithMenu);
+                    }
+                    (KeyCode::Char('T'), _) => {
+                        self.state.preview_tail = !self.state.preview_tail;
+                        self.state.notifications.info(if self.state.preview_tail {
+                            "Large files previewed from the end"
+                        } else {
+                            "Large files previewed from the start"
+                        });
+                    }
+                    (KeyCode::Char('x'), _) => {
+                        pending_action = Some(CommandAction::Yank(RegisterOperation::Cut));
+                    }
+                    (KeyCode::Char('P'), _) => {
+                        pending_action = Some(CommandAction::Paste);
+                    }
+                    (KeyCode::Char('R'), _) => {
+                        pending_action = Some(CommandAction::Flatten);
+                    }
+                    (KeyCode::Char('Z'), _) => match self.state.tree.as_mut() {
+                        Some(tree) => tree.focused = true,
+                        None => {
+                            self.state.tree =
+                                Some(DirTree::new(&self.current_dir, self.show_hidden))
+                        }
+                    },
+                    (KeyCode::Char('D'), _) => {
+                        pending_action = Some(CommandAction::Diff);
+                    }
+                    (KeyCode::Char('F'), _) => {
+                        if let Some(follow) = self.follow.take() {
+                            self.state
+                                .notifications
+                                .info(format!("Stopped following {}", follow.path.display()));
+                        } else if let Some(path) = self
+                            .selected_file()
+                            .map(|file| self.current_dir.join(file))
+                            .filter(|path| self.metadata_cache.is_file(path))
+                        {
+                            match Follow::open(&path) {
+                                Ok(follow) => {
+                                    self.state
+                                        .notifications
+                                        .info(format!("Following {}", path.display()));
+                                    self.follow = Some(follow);
+                                }
+                                Err(e) => self.state.notifications.error(format!(
+                                    "Cannot follow {}: {}",
+                                    path.display(),
+                                    e
+                                )),
+                            }
+                        }
+                    }
+                    (KeyCode::Char('A'), _) => {
+                        pending_action = Some(CommandAction::Git(GitAction::Stage));
+                    }
+                    (KeyCode::Char('U'), _) => {
+                        pending_action = Some(CommandAction::Git(GitAction::Unstage));
+                    }
+                    (KeyCode::Char('X'), _) => {
+                        pending_action = Some(CommandAction::Git(GitAction::Discard));
+                    }
+                    (KeyCode::Char('C'), _) => {
+                        let mut prompt = Prompt::new(':');
+                        prompt.input = "git commit ".to_string();
+                        self.state.prompt = Some(prompt);
+                    }
+                    (KeyCode::Char('t'), _) => {
+                        let mut prompt = Prompt::new(':');
+                        prompt.input = "tag ".to_string();
+                        self.state.prompt = Some(prompt);
+                    }
+                    (KeyCode::Char('*'), _) => {
+                        let mut prompt = Prompt::new(':');
+                        prompt.input = "select ".to_string();
+                        self.state.prompt = Some(prompt);
+                    }
+                    (KeyCode::Char('i'), _) => {
+                        self.state.show_properties = !self.state.show_properties;
+                    }
+                    // Starts visual mode at the cursor, or ends it keeping the
+                    // range marked
+                    (KeyCode::Char('V'), _) => match self.visual.take() {
+                        Some(_) => {}
+                        None if self.state.files.is_empty() => {}
+                        None => {
+                            self.visual = Some(Visual {
+                                anchor: self.cursor_position,
+                                dir: self.current_dir.clone(),
+                                marked_before: self.state.marked.clone(),
+                            })
+                        }
+                    },
+                    (KeyCode::Esc, _) => {
+                        // Cancels visual mode, the range is unmarked again
+                        if let Some(visual) = self.visual.take() {
+                            self.state.marked = visual.marked_before;
+                        }
+                        self.state.show_properties = false;
+                        self.state.show_messages = false;
+                        self.state.show_cache_stats = false;
+                        self.state.command_output = None;
+                    }
+                    (KeyCode::Char(':'), _) => {
+                        self.state.prompt = Some(Prompt::new(':'));
+                    }
+                    (KeyCode::Char('!'), _) => {
+                        self.state.prompt = Some(Prompt::new('!'));
+                    }
+                    (KeyCode::Char('a'), _) => {
+                        // With Alt the to-do is about the selection
+                        let selection = self
+                            .selected_file()
+                            .filter(|_| modifiers == KeyModifiers::ALT && self.remote.is_none())
+                            .map(|name| self.current_dir.join(name));
+                        match add_todo() {
+                            Some(Ok(mut new_todo)) => {
+                                new_todo.path = match new_todo.path {
+                                    Some(path) => Some(expand_path(
+                                        &path.to_string_lossy(),
+                                        &self.current_dir,
+                                    )),
+                                    None => selection,
+                                };
+                                let index = insert_todo(&mut self.todos, new_todo);
+                                self.todo_list_state.select(Some(index));
+                                self.save_todo_list();
+                            }
+                            Some(Err(e)) => self.state.notifications.error(e),
+                            None => {}
+                        }
+                        self.clear_screen = true;
+                    }
+                    (KeyCode::Char(' '), _) => {
+                        if let Some(selected_index) = self.todo_list_state.selected() {
+                            if let Some(todo) = self.todos.get_mut(selected_index) {
+                                todo.completed = !todo.completed;
+                                self.save_todo_list();
+                            }
+                        }
+                    }
+                    (KeyCode::Char('J'), _) | (KeyCode::Char('K'), _) => {
+                        if let Some(selected_index) = self
+                            .todo_list_state
+                            .selected()
+                            .filter(|_| !self.todos.is_empty())
+                        {
+                            let places = count.unwrap_or(1) as isize;
+                            let offset = if code == KeyCode::Char('J') {
+                                places
+                            } else {
+                                -places
+                            };
+                            let index = move_todo(&mut self.todos, selected_index, offset);
+                            self.todo_list_state.select(Some(index));
+                            self.save_todo_list();
+                        }
+                    }
+                    (KeyCode::Char('+'), _) if !self.todos.is_empty() => {
+                        let mut selected_index = self.todo_list_state.selected().unwrap_or(0);
+                        if selected_index < self.todos.len() - 1 {
+                            selected_index += 1;
+                            self.todo_list_state.select(Some(selected_index));
+                        }
+                    }
+                    (KeyCode::Char('-'), _) if !self.todos.is_empty() => {
+                        let mut selected_index = self.todo_list_state.selected().unwrap_or(0);
+                        if selected_index > 0 {
+                            selected_index -= 1;
+                            self.todo_list_state.select(Some(selected_index));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            self.run_action(pending_action);
+            self.update_visual();
+        }
+    }
+
+    // `:` commands of the config first, then the built-in ones
+    fn parse_input(&self, input: &str) -> Result<CommandAction, String> {
+        let name = input.split_whitespace().next().unwrap_or_default();
+        if self.custom_commands.get(name).is_some() {
+            return Ok(CommandAction::Custom(name.to_string()));
+        }
+        if self.scripts.has_command(name) {
+            return Ok(CommandAction::Script(input.trim().to_string()));
+        }
+        parse_command(input, &self.current_dir)
+    }
+
+    // Runs a `[commands]` entry on the marked entries or the selection, or
+    // the current directory when it's empty
+    fn run_custom(&mut self, name: &str, selected: Option<PathBuf>) {
+        let Some(command) = self.custom_commands.get(name).cloned() else {
+            return;
+        };
+        if self.remote.is_some() {
+            self.state
+                .notifications
+                .error(format!(":{} only runs in local directories", name));
+            return;
+        }
+        let file = selected.unwrap_or_else(|| self.current_dir.clone());
+        let targets = open_targets(&self.state.marked, &file);
+        match command.run(&file, &targets, &self.current_dir) {
+            Ok(Some(lines)) => self.state.command_output = Some((command.name.clone(), lines)),
+            Ok(None) => self
+                .state
+                .notifications
+                .info(format!(":{} finished", command.name)),
+            Err(e) => self.state.notifications.error(e),
+        }
+        // The command had the terminal
+        if !command.output {
+            self.clear_screen = true;
+        }
+        if command.refresh {
+            self.dir_cache.clear();
+            self.last_selected_file_path = None;
+            self.reload();
+        }
+    }
+
+    // Runs a command sent with `termfm --remote` as if it was typed at the
+    // prompt, and answers with what it reported. `cwd` and `selection` only
+    // ask for the directory and selection that come with every answer.
+    fn answer_remote(&mut self, command: &str) -> Reply {
+        let command = command.trim().trim_start_matches(':');
+        let start = Instant::now();
+        if !matches!(command, "cwd" | "selection") {
+            match self.parse_input(command) {
+                Ok(action) => self.run_action(Some(action)),
+                Err(e) => self.state.notifications.error(e),
+            }
+        }
+        let reported: Vec<_> = self
+            .state
+            .notifications
+            .log
+            .iter()
+            .filter(|notification| notification.created >= start)
+            .collect();
+        let failed = reported.iter().any(|n| n.level == Level::Error);
+        let message = reported
+            .iter()
+            .filter(|n| !failed || n.level == Level::Error)
+            .map(|n| n.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut selection: Vec<PathBuf> = self.state.marked.iter().cloned().collect();
+        selection.sort();
+        if selection.is_empty() {
+            selection.extend(
+                self.state
+                    .files
+                    .get(self.cursor_position)
+                    .filter(|_| !self.state.loading)
+                    .map(|entry| self.current_dir.join(&entry.name)),
+            );
+        }
+        Reply {
+            status: if failed { "error" } else { "ok" }.to_string(),
+            message,
+            cwd: self.current_dir.clone(),
+            selection,
+        }
+    }
+
+    // Carries out a command, whether it came from a key, the prompt or a macro
+    fn run_action(&mut self, pending_action: Option<CommandAction>) {
+        let selected_path = self
+            .state
+            .files
+            .get(self.cursor_position)
+            .map(|entry| self.current_dir.join(&entry.name));
+        if let Some(change) = pending_action.as_ref().and_then(Change::from_action) {
+            self.last_change = Some(change);
+        }
+        // Anything done with the range ends visual mode
+        if pending_action.is_some() {
+            self.visual = None;
+        }
+        match pending_action {
+            Some(CommandAction::Messages) => self.state.show_messages = true,
+            Some(CommandAction::Mounts) => self.state.devices = Some(Devices::load()),
+            Some(CommandAction::CacheStats) => self.state.show_cache_stats = true,
+            Some(CommandAction::Cd(path)) => match fs::canonicalize(&path) {
+                Ok(dir) if dir.is_dir() => {
+                    self.remote = None;
+                    self.change_dir(dir)
+                }
+                _ => self
+                    .state
+                    .notifications
+                    .error(format!("Not a directory: {}", path.display())),
+            },
+            Some(CommandAction::Remote(location)) => self.open_remote(location),
+            Some(CommandAction::Download) => self.download(),
+            Some(CommandAction::Upload) => self.upload(),
+            Some(CommandAction::Mkdir(name)) => {
+                match fs::create_dir_all(self.current_dir.join(&name)) {
+                    Ok(()) => {
+                        self.state.notifications.info(format!("Created {}", name));
+                        self.reload();
+                    }
+                    Err(e) => self
+                        .state
+                        .notifications
+                        .error(format!("Failed to create {}: {}", name, e)),
+                }
+            }
+            Some(CommandAction::Rename(new_name)) => match selected_path {
+                Some(old_path) => {
+                    if self.rename_entry(&old_path, &new_name) {
+                        let old_name = old_path.file_name().unwrap_or_default();
+                        self.last_change = Some(Change::Rename(RenamePattern::new(
+                            &old_name.to_string_lossy(),
+                            &new_name,
+                        )));
+                        self.reload();
+                    }
+                }
+                None => self.state.notifications.error("Nothing selected"),
+            },
+            Some(CommandAction::Delete) => {
+                // Marked files take precedence over the cursor, and are
+                // listed for confirmation, wherever they were marked
+                let mut targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.iter().cloned().collect()
+                };
+                targets.sort();
+                if targets.is_empty() {
+                    self.state.notifications.error("Nothing selected");
+                } else {
+                    self.state.pending_delete = Some(targets);
+                }
+            }
+            Some(CommandAction::Git(GitAction::Commit(message))) => {
+                match git::commit(&self.current_dir, &message, &self.opener_config.programs) {
+                    Ok(summary) => self
+                        .state
+                        .notifications
+                        .info(format!("Committed {}", summary)),
+                    Err(e) => self.state.notifications.error(e),
+                }
+                self.reload();
+            }
+            Some(CommandAction::Git(action)) => {
+                // Marked files take precedence over the cursor
+                let targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.drain().collect()
+                };
+                if targets.is_empty() {
+                    self.state.notifications.error("Nothing selected");
+                } else if action == GitAction::Discard {
+                    self.state.pending_discard = Some(targets);
+                } else {
+                    let result = if action == GitAction::Stage {
+                        git::stage(&self.current_dir, &targets, &self.opener_config.programs)
+                            .map(|()| format!("Staged {} item(s)", targets.len()))
+                    } else {
+                        git::unstage(&self.current_dir, &targets, &self.opener_config.programs)
+                            .map(|()| format!("Unstaged {} item(s)", targets.len()))
+                    };
+                    match result {
+                        Ok(message) => self.state.notifications.info(message),
+                        Err(e) => self.state.notifications.error(e),
+                    }
+                    self.reload();
+                }
+            }
+            Some(CommandAction::Checksum(algorithm)) => {
+                let mut targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.drain().collect()
+                };
+                targets.retain(|path| self.metadata_cache.is_file(path));
+                targets.sort();
+                if targets.is_empty() {
+                    self.state.notifications.error("No files selected");
+                } else {
+                    self.state.checksums =
+                        Some(Checksums::compute(algorithm, &self.current_dir, &targets));
+                }
+            }
+            Some(CommandAction::Copy(target)) => {
+                let mut paths: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.iter().cloned().collect()
+                };
+                paths.sort();
+                if paths.is_empty() {
+                    self.state.notifications.error("Nothing selected");
+                } else {
+                    match target.copy(&paths, &self.opener_config.programs) {
+                        Ok(how) => self.state.notifications.info(format!(
+                            "Copied {} {}(s) ({})",
+                            paths.len(),
+                            target.label(),
+                            how
+                        )),
+                        Err(e) => self.state.notifications.error(e),
+                    }
+                }
+            }
+            Some(CommandAction::Yank(operation)) => {
+                let mut paths: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.drain().collect()
+                };
+                paths.sort();
+                if paths.is_empty() {
+                    self.state.notifications.error("Nothing selected");
+                } else {
+                    let count = paths.len();
+                    match (Register { operation, paths }).save() {
+                        Ok(()) => self.state.notifications.info(match operation {
+                            RegisterOperation::Copy => format!("Yanked {} item(s)", count),
+                            RegisterOperation::Cut => {
+                                format!("Cut {} item(s), P moves them here", count)
+                            }
+                        }),
+                        Err(e) => self
+                            .state
+                            .notifications
+                            .error(format!("Failed to save the register: {}", e)),
+                    }
+                }
+            }
+            Some(CommandAction::Paste) => match Register::load() {
+                Some(register) => self.start_paste(register.plan(&self.current_dir)),
+                None => self
+                    .state
+                    .notifications
+                    .error("Nothing to paste, yank files with yy or cut them with dd"),
+            },
+            Some(CommandAction::Tag(tag)) | Some(CommandAction::Untag(Some(tag)))
+                if tag.contains(char::is_whitespace) =>
+            {
+                self.state
+                    .notifications
+                    .error("Tag names cannot contain spaces");
+            }
+            Some(action @ (CommandAction::Tag(_) | CommandAction::Untag(_))) => {
+                let targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.drain().collect()
+                };
+                if targets.is_empty() {
+                    self.state.notifications.error("Nothing selected");
+                } else {
+                    let message = match action {
+                        CommandAction::Tag(tag) => {
+                            self.tags.add(&targets, &tag);
+                            format!("Tagged {} item(s) {}", targets.len(), tag)
+                        }
+                        CommandAction::Untag(tag) => {
+                            self.tags.remove(&targets, tag.as_deref());
+                            format!("Untagged {} item(s)", targets.len())
+                        }
+                        _ => unreachable!(),
+                    };
+                    self.state.notifications.info(message);
+                    self.save_annotations();
+                }
+            }
+            Some(CommandAction::Tagged(None)) => {
+                if self.tag_filter.take().is_some() {
+                    self.reload();
+                }
+                let names = self.tags.names();
+                if names.is_empty() {
+                    self.state
+                        .notifications
+                        .info("No tags yet, :tag <name> adds one");
+                } else {
+                    let names: Vec<String> = names
+                        .iter()
+                        .map(|(name, count)| format!("{} ({})", name, count))
+                        .collect();
+                    self.state
+                        .notifications
+                        .info(format!("Tags: {}", names.join(", ")));
+                }
+            }
+            Some(CommandAction::Tagged(Some(tag))) => {
+                self.tag_filter = Some(tag);
+                self.reload();
+                self.cursor_position = 0;
+            }
+            Some(CommandAction::Note(None)) => match selected_path {
+                Some(path) => {
+                    let mut prompt = Prompt::new(':');
+                    prompt.input = format!("note {}", self.notes.get(&path).unwrap_or(""));
+                    self.state.prompt = Some(prompt);
+                }
+                None => self.state.notifications.error("Nothing selected"),
+            },
+            Some(action @ (CommandAction::Note(Some(_)) | CommandAction::Unnote)) => {
+                match selected_path {
+                    Some(path) => {
+                        let note = match &action {
+                            CommandAction::Note(note) => note.as_deref(),
+                            _ => None,
+                        };
+                        self.notes.set(&path, note);
+                        self.state.notifications.info(if note.is_some() {
+                            "Note saved"
+                        } else {
+                            "Note removed"
+                        });
+                        self.save_annotations();
+                    }
+                    None => self.state.notifications.error("Nothing selected"),
+                }
+            }
+            Some(CommandAction::Select { pattern, invert }) => match NamePattern::parse(&pattern) {
+                Ok(pattern) => {
+                    let matching: Vec<PathBuf> = self
+                        .state
+                        .files
+                        .iter()
+                        .filter(|entry| pattern.matches(&entry.name) != invert)
+                        .map(|entry| self.current_dir.join(&entry.name))
+                        .collect();
+                    self.state
+                        .notifications
+                        .info(format!("Marked {} item(s)", matching.len()));
+                    self.state.marked.extend(matching);
+                }
+                Err(e) => self.state.notifications.error(e),
+            },
+            Some(CommandAction::Link { kind, name }) => {
+                let mut sources: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.iter().cloned().collect()
+                };
+                sources.sort();
+                match (sources.as_slice(), name) {
+                    ([], _) => self.state.notifications.error("Nothing selected"),
+                    // Asks for the name, the file's own to start with
+                    ([source], None) => {
+                        let mut prompt = Prompt::new(':');
+                        prompt.input = format!(
+                            "{} {}",
+                            kind.command(),
+                            source.file_name().unwrap_or_default().to_string_lossy()
+                        );
+                        self.state.prompt = Some(prompt);
+                    }
+                    ([_, _, ..], Some(_)) => self
+                        .state
+                        .notifications
+                        .error("A link name only works for a single file"),
+                    (sources, name) => self.create_links(kind, sources, name),
+                }
+            }
+            Some(CommandAction::ToggleNotes) => {
+                self.notes.in_listing = !self.notes.in_listing;
+            }
+            Some(CommandAction::TogglePreview) => {
+                self.state.preview_hidden = !self.state.preview_hidden;
+                if self.state.preview_hidden {
+                    self.state.focus = Panel::Files;
+                }
+            }
+            Some(CommandAction::ResizePreview(change)) => {
+                self.state.preview_hidden = false;
+                let width = (100 - self.list_width()) as i32 + change;
+                let width =
+                    width.clamp(*PREVIEW_WIDTHS.start() as i32, *PREVIEW_WIDTHS.end() as i32);
+                self.state.preview_width = Some(width as u16);
+                self.state
+                    .notifications
+                    .info(format!("Preview at {}% of the width", width));
+            }
+            Some(CommandAction::ToggleMarkdown) => {
+                self.state.markdown_source = !self.state.markdown_source;
+                self.state
+                    .notifications
+                    .info(if self.state.markdown_source {
+                        "Markdown shown as source"
+                    } else {
+                        "Markdown shown rendered"
+                    });
+            }
+            Some(CommandAction::Search(SearchAction::List)) => {
+                if self.searches.searches.is_empty() {
+                    self.state
+                        .notifications
+                        .info("No saved searches, :search save <name> <query> adds one");
+                } else {
+                    let names: Vec<&str> =
+                        self.searches.searches.keys().map(|n| n.as_str()).collect();
+                    self.state
+                        .notifications
+                        .info(format!("Saved searches: {}", names.join(", ")));
+                }
+            }
+            Some(CommandAction::Search(SearchAction::Save { name, query })) => {
+                let search = SavedSearch {
+                    root: self.current_dir.clone(),
+                    query,
+                };
+                self.searches.searches.insert(name.clone(), search);
+                match self.searches.save() {
+                    Ok(()) => self
+                        .state
+                        .notifications
+                        .info(format!("Saved search {}, :search {} opens it", name, name)),
+                    Err(e) => self
+                        .state
+                        .notifications
+                        .error(format!("Failed to save searches: {}", e)),
+                }
+            }
+            Some(CommandAction::Search(SearchAction::Delete(name))) => {
+                if self.searches.searches.remove(&name).is_none() {
+                    self.state
+                        .notifications
+                        .error(format!("No saved search {}", name));
+                } else if let Err(e) = self.searches.save() {
+                    self.state
+                        .notifications
+                        .error(format!("Failed to save searches: {}", e));
+                } else {
+                    self.state
+                        .notifications
+                        .info(format!("Deleted search {}", name));
+                }
+            }
+            Some(CommandAction::Search(SearchAction::Open(name))) => {
+                match self.searches.searches.get(&name).cloned() {
+                    Some(search) if search.root.is_dir() => {
+                        self.current_dir = search.root.clone();
+                        self.last_dir = self.current_dir.clone();
+                        self.search_query.clear();
+                        self.virtual_dir = Some((format!("search: {}", name), search));
+                        self.cursor_position = 0;
+                        self.reload();
+                    }
+                    Some(search) => self
+                        .state
+                        .notifications
+                        .error(format!("Not a directory: {}", search.root.display())),
+                    None => self
+                        .state
+                        .notifications
+                        .error(format!("No saved search {}", name)),
+                }
+            }
+            Some(CommandAction::Find(query)) => {
+                let search = SavedSearch {
+                    root: self.current_dir.clone(),
+                    query: query.clone(),
+                };
+                self.virtual_dir = Some((format!("find: {}", query), search));
+                self.search_query.clear();
+                self.cursor_position = 0;
+                self.reload();
+            }
+            Some(CommandAction::Flatten) => {
+                if self
+                    .virtual_dir
+                    .as_ref()
+                    .is_some_and(|(label, _)| label == FLAT_LABEL)
+                {
+                    self.virtual_dir = None;
+                } else {
+                    let search = SavedSearch {
+                        root: self.current_dir.clone(),
+                        query: "type:file".to_string(),
+                    };
+                    self.virtual_dir = Some((FLAT_LABEL.to_string(), search));
+                }
+                self.search_query.clear();
+                self.cursor_position = 0;
+                self.reload();
+            }
+            Some(CommandAction::Search(SearchAction::Refresh)) => {
+                if self.virtual_dir.is_some() {
+                    self.reload();
+                } else {
+                    self.state.notifications.error("No saved search is open");
+                }
+            }
+            Some(CommandAction::Diff) => {
+                let mut marked: Vec<PathBuf> = self.state.marked.iter().cloned().collect();
+                marked.sort();
+                let pair = match (marked.as_slice(), selected_path) {
+                    ([left, right], _) => Some((left.clone(), right.clone())),
+                    ([left], Some(right)) if *left != right => Some((left.clone(), right)),
+                    _ => None,
+                };
+                match pair {
+                    Some((left, right)) => match DiffView::new(left, right) {
+                        Ok(view) => self.state.diff = Some(view),
+                        Err(e) => self.state.notifications.error(e),
+                    },
+                    None => self
+                        .state
+                        .notifications
+                        .error("Mark two files or directories to compare"),
+                }
+            }
+            Some(CommandAction::VerifyChecksums) => {
+                match selected_path
+                    .ok_or_else(|| "Select a checksum file".to_string())
+                    .and_then(|path| Checksums::verify(&path))
+                {
+                    Ok(checksums) => self.state.checksums = Some(checksums),
+                    Err(e) => self.state.notifications.error(e),
+                }
+            }
+            Some(CommandAction::Sync(dest)) => {
+                let other = self.state.split.as_ref().map(|split| &split.other);
+                let dest = dest.or_else(|| other.map(|other| other.dir.clone()));
+                let sync = match dest {
+                    _ if self.remote.is_some() || other.is_some_and(|o| o.remote.is_some()) => {
+                        Err("Only local directories can be synced".to_string())
+                    }
+                    None => Err("Usage: :sync <target directory>".to_string()),
+                    // A new directory is created
+                    Some(dest) => DirSync::new(
+                        self.current_dir.clone(),
+                        fs::canonicalize(&dest).unwrap_or(dest),
+                    ),
+                };
+                match sync {
+                    Ok(sync) if sync.steps.is_empty() => {
+                        self.state.notifications.info("Already in sync")
+                    }
+                    Ok(sync) => self.state.sync = Some(sync),
+                    Err(e) => self.state.notifications.error(e),
+                }
+            }
+            Some(CommandAction::Merge(dest)) => {
+                let dest = dest.or_else(|| {
+                    self.state
+                        .split
+                        .as_ref()
+                        .map(|split| split.other.dir.clone())
+                });
+                let source = selected_path.filter(|path| self.metadata_cache.is_dir(path));
+                let merge = match (source, dest.map(fs::canonicalize)) {
+                    (None, _) => Err("Select the directory to merge".to_string()),
+                    (_, None) => Err("Usage: :merge <target directory>".to_string()),
+                    (_, Some(Err(e))) => Err(format!("Invalid target: {}", e)),
+                    (Some(source), Some(Ok(dest))) if dest.starts_with(&source) => {
+                        Err("Cannot merge a directory into itself".to_string())
+                    }
+                    (Some(_), Some(Ok(dest))) if !dest.is_dir() => {
+                        Err(format!("Not a directory: {}", dest.display()))
+                    }
+                    (Some(source), Some(Ok(dest))) => {
+                        Merge::new(source, dest).map_err(|e| e.to_string())
+                    }
+                };
+                match merge {
+                    Ok(merge) => {
+                        if self.state.run_merge(merge) {
+                            self.job_done("merge");
+                            self.reload();
+                        }
+                    }
+                    Err(e) => self.state.notifications.error(e),
+                }
+            }
+            Some(CommandAction::Organize) if self.filing_rules.is_empty() => {
+                self.state
+                    .notifications
+                    .error("No [[filing]] rules configured in opener.toml");
+            }
+            Some(CommandAction::Organize) => {
+                match plan_filing(&self.current_dir, &self.filing_rules, self.show_hidden) {
+                    Ok(plan) if plan.is_empty() => {
+                        self.state.notifications.info("Nothing to organize")
+                    }
+                    Ok(plan) => self.state.filing_plan = Some(plan),
+                    Err(e) => self
+                        .state
+                        .notifications
+                        .error(format!("Failed to read directory: {}", e)),
+                }
+            }
+            Some(CommandAction::Convert(conversion)) => {
+                let targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+                    selected_path.into_iter().collect()
+                } else {
+                    self.state.marked.iter().cloned().collect()
+                };
+                let mut converted = 0;
+                for target in &targets {
+                    match convert_text_file(target, conversion) {
+                        Ok(true) => converted += 1,
+                        Ok(false) => {}
+                        Err(e) => self.state.notifications.error(format!(
+                            "Failed to convert {}: {}",
+                            target.display(),
+                            e
+                        )),
+                    }
+                }
+                self.state.notifications.info(format!(
+                    "Converted {} of {} file(s)",
+                    converted,
+                    targets.len()
+                ));
+                // Force the preview and its format indicator to refresh
+                self.last_selected_file_path = None;
+            }
+            Some(CommandAction::Custom(name)) => self.run_custom(&name, selected_path),
+            Some(CommandAction::Script(line)) => self.run_script_command(&line, selected_path),
+            Some(CommandAction::Reveal(path)) => {
+                let programs = &self.opener_config.programs;
+                let result = match (path, selected_path) {
+                    _ if self.remote.is_some() => {
+                        Err("Remote files cannot be shown in the file manager".to_string())
+                    }
+                    (Some(dir), _) if dir.is_dir() => show_folder(&dir, programs),
+                    (Some(path), _) if path.exists() => reveal(&path, programs),
+                    (Some(path), _) => Err(format!("Not found: {}", path.display())),
+                    (None, Some(path)) => reveal(&path, programs),
+                    (None, None) => show_folder(&self.current_dir, programs),
+                };
+                match result {
+                    Ok(message) => self.state.notifications.info(message),
+                    Err(e) => self.state.notifications.error(e),
+                }
+            }
+            Some(CommandAction::OpenWithMenu) => match selected_path {
+                Some(path) if self.metadata_cache.is_file(&path) => {
+                    self.state.open_with =
+                        Some(OpenWithMenu::new(path, self.opener_config.choices()));
+                }
+                _ => self.state.notifications.error("Only files can be opened"),
+            },
+            Some(CommandAction::OpenWith(opener)) => match selected_path {
+                Some(path) if self.metadata_cache.is_file(&path) => {
+                    let selected = open_targets(&self.state.marked, &path);
+                    match run_opener(&opener, &path, &selected, &self.opener_config.programs) {
+                        Ok(message) => {
+                            self.state.notifications.info(message);
+                            self.opened(&path);
+                        }
+                        Err(e) => self.state.notifications.error(e),
+                    }
+                    self.clear_screen = true;
+                }
+                _ => self.state.notifications.error("Only files can be opened"),
+            },
+            Some(CommandAction::Shell(line)) => self.run_shell(&line, selected_path),
+            Some(CommandAction::Sort(mode))
+                if mode != SortMode::Name && !self.state.mount.stat_entries =>
+            {
+                self.state.notifications.error(format!(
+                    "Sorting by {} needs per-file stats, disabled on {} mounts",
+                    mode.label(),
+                    self.state.mount.fs_type
+                ));
+            }
+            Some(CommandAction::Sort(mode)) => {
+                if mode != SortMode::Name && self.state.mount.network {
+                    self.state.notifications.info(format!(
+                        "Sorting by {} stats every entry on this {} mount and may be slow",
+                        mode.label(),
+                        self.state.mount.fs_type
+                    ));
+                }
+                self.state.sort_mode = mode;
+                if self.dir_settings.sort.is_none() {
+                    self.default_sort = mode;
+                }
+                self.search_query.clear();
+                self.dir_cache.clear();
+                self.reload();
+                self.cursor_position = 0;
+                self.state
+                    .notifications
+                    .info(format!("Sorting by {}", mode.label()));
+            }
+            Some(CommandAction::SetHidden(value)) => {
+                self.show_hidden = value.unwrap_or(!self.show_hidden);
+                if self.dir_settings.hidden.is_none() {
+                    self.default_hidden = self.show_hidden;
+                }
+                self.search_query.clear();
+                self.dir_cache.clear();
+                self.reload();
+                self.cursor_position = 0;
+            }
+            Some(CommandAction::ToggleDetailed)
+                if !self.state.detailed_view && !self.state.mount.stat_entries =>
+            {
+                self.state.notifications.error(format!(
+                    "Detailed view needs per-file stats, disabled on {} mounts",
+                    self.state.mount.fs_type
+                ));
+            }
+            Some(CommandAction::ToggleDetailed) => {
+                self.state.detailed_view = !self.state.detailed_view;
+                if self.state.detailed_view {
+                    self.columns.refresh(&self.current_dir);
+                }
+            }
+            Some(CommandAction::ArchiveTodos) => {
+                let Some(path) = self.todo_path.clone() else {
+                    self.state
+                        .notifications
+                        .error("There is no to-do list to archive from");
+                    return;
+                };
+                match archive_todos(&path, &mut self.todos) {
+                    Ok(0) => self
+                        .state
+                        .notifications
+                        .info("No completed to-dos to archive"),
+                    Ok(count) => {
+                        self.todo_list_state
+                            .select((!self.todos.is_empty()).then_some(0));
+                        self.save_todo_list();
+                        self.state.notifications.info(format!(
+                            "Archived {} to-do(s), :todo archived shows them",
+                            count
+                        ));
+                    }
+                    Err(e) => self
+                        .state
+                        .notifications
+                        .error(format!("Failed to archive the to-dos: {}", e)),
+                }
+            }
+            Some(CommandAction::ArchivedTodos) => {
+                let archived = self
+                    .todo_path
+                    .as_deref()
+                    .map(|path| load_todos(&archive_path(path)))
+                    .unwrap_or_default();
+                if archived.is_empty() {
+                    self.state.notifications.info("No archived to-dos");
+                } else {
+                    let lines = archived.iter().map(Todo::label).collect();
+                    self.state.command_output = Some(("todo archived".to_string(), lines));
+                }
+            }
+            Some(CommandAction::Quit) => {
+                self.quit = true;
+            }
+            Some(CommandAction::Choose) => {
+                if self.remote.is_some() {
+                    self.state
+                        .notifications
+                        .error("Remote entries can't be chosen".to_string());
+                } else if let Some(entry) = self.state.files.get(self.cursor_position) {
+                    let path = self.current_dir.join(&entry.name);
+                    self.picked = Some(open_targets(&self.state.marked, &path));
+                    self.quit = true;
+                } else if !self.state.marked.is_empty() {
+                    self.picked = Some(open_targets(&self.state.marked, &self.current_dir));
+                    self.quit = true;
+                } else {
+                    self.state
+                        .notifications
+                        .error("Nothing to choose".to_string());
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Marks the range between the anchor and the cursor. Leaving the
+    // directory ends visual mode.
+    fn update_visual(&mut self) {
+        let Some(visual) = &self.visual else {
+            return;
+        };
+        if visual.dir != self.current_dir {
+            self.visual = None;
+            return;
+        }
+        let start = visual.anchor.min(self.cursor_position);
+        let end = visual.anchor.max(self.cursor_position);
+        let mut marked = visual.marked_before.clone();
+        marked.extend(
+            self.state
+                .files
+                .iter()
+                .take(end + 1)
+                .skip(start)
+                .map(|entry| self.current_dir.join(&entry.name)),
+        );
+        self.state.marked = marked;
+    }
+
+    // Starts a `!` command on the marked entries or the selection, its output
+    // read into the panel as it is printed
+    fn run_shell(&mut self, line: &str, selected: Option<PathBuf>) {
+        if line.trim().is_empty() {
+            return;
+        }
+        if self.remote.is_some() {
+            self.state
+                .notifications
+                .error("Shell commands only run in local directories");
+            return;
+        }
+        let file = selected.unwrap_or_else(|| self.current_dir.clone());
+        let targets = open_targets(&self.state.marked, &file);
+        match ShellRun::start(line, &file, &targets, &self.current_dir) {
+            Ok(run) => {
+                self.shell = Some(run);
+                self.state.command_output = Some((format!("!{}", line), Vec::new()));
+                self.state.output_scroll = None;
+            }
+            Err(e) => self.state.notifications.error(e),
+        }
+    }
+
+    // Reads what the `!` command printed since the last tick into the panel
+    fn poll_shell(&mut self) {
+        let Some(run) = self.shell.as_mut() else {
+            return;
+        };
+        // Closed, or replaced by other output: nobody reads it anymore
+        let Some((_, output)) = self
+            .state
+            .command_output
+            .as_mut()
+            .filter(|(name, _)| name.strip_prefix('!') == Some(run.line.as_str()))
+        else {
+            self.shell = None;
+            return;
+        };
+        let (lines, status) = run.poll();
+        output.extend(lines);
+        if let Some(status) = status {
+            if !status.success() {
+                output.push(format!("{} exited with {}", run.line, status));
+            }
+            self.shell = None;
+        }
+        if output.len() > OUTPUT_LINES {
+            let dropped = output.len() - OUTPUT_LINES;
+            output.drain(..dropped);
+            if let Some(scroll) = self.state.output_scroll.as_mut() {
+                *scroll = scroll.saturating_sub(dropped);
+            }
+        }
+    }
+
+    // `r` in the recovery dialog: finishes the interrupted operation
+    fn resume_recovery(&mut self) {
+        if !self.adopt_recovery() {
+            return;
+        }
+        let mut journal = self.state.recovery.take().unwrap();
+        if let Err(e) = journal.resume_in_flight() {
+            self.state
+                .notifications
+                .error(format!("Failed to resume: {}", e));
+        }
+        match journal.operation.clone() {
+            JournalOperation::Moves(_) => {
+                let (moved, errors) = journal.apply_moves();
+                journal.finish();
+                for error in errors {
+                    self.state.notifications.error(error);
+                }
+                self.state
+                    .notifications
+                    .info(format!("Resumed: moved {} remaining file(s)", moved));
+            }
+            JournalOperation::Merge { source, dest } => {
+                journal.finish();
+                match Merge::new(source, dest) {
+                    Ok(merge) => {
+                        self.state.run_merge(merge);
+                    }
+                    Err(e) => self
+                        .state
+                        .notifications
+                        .error(format!("Failed to resume merge: {}", e)),
+                }
+            }
+        }
+        self.reload();
+    }
+
+    // `c` in the recovery dialog: removes the partial copy and forgets the
+    // operation
+    fn discard_recovery(&mut self) {
+        if !self.adopt_recovery() {
+            return;
+        }
+        let journal = self.state.recovery.take().unwrap();
+        if let Some(target) = journal.partial_target() {
+            match fs::remove_file(target) {
+                Ok(()) => self
+                    .state
+                    .notifications
+                    .info(format!("Removed {}", target.display())),
+                Err(e) => self.state.notifications.error(format!(
+                    "Failed to remove {}: {}",
+                    target.display(),
+                    e
+                )),
+            }
+        }
+        journal.finish();
+        self.reload();
+    }
+
+    // Takes over the journal in the recovery dialog, closing the dialog if
+    // another instance already did
+    fn adopt_recovery(&mut self) -> bool {
+        let Some(journal) = self.state.recovery.as_mut() else {
+            return false;
+        };
+        if let Err(e) = journal.adopt() {
+            self.state.recovery = None;
+            self.state.notifications.error(format!(
+                "Failed to take over the interrupted operation, another instance may have: {}",
+                e
+            ));
+            return false;
+        }
+        true
+    }
+
+    // Esc stops a `!` command that is still running, then closes the panel
+    fn close_output(&mut self) {
+        match self.shell.take() {
+            Some(mut run) => {
+                run.stop();
+                let (lines, _) = run.poll();
+                if let Some((_, output)) = self.state.command_output.as_mut() {
+                    output.extend(lines);
+                    output.push(format!("{} stopped", run.line));
+                }
+            }
+            None => {
+                self.state.command_output = None;
+                self.state.output_scroll = None;
+            }
+        }
+    }
+
+    fn scroll_output(&mut self, rows: isize) {
+        let Some((_, output)) = &self.state.command_output else {
+            return;
+        };
+        // Back at the end the panel follows new output again
+        let last = output.len().saturating_sub(self.output_rows);
+        let scroll = self
+            .state
+            .output_scroll
+            .unwrap_or(last)
+            .saturating_add_signed(rows)
+            .min(last);
+        self.state.output_scroll = (scroll < last).then_some(scroll);
+    }
+
+    // A click on a breadcrumb opens that directory
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return;
+        }
+        let clicked = self.breadcrumbs.iter().find(|(area, _)| {
+            (area.left()..area.right()).contains(&mouse.column)
+                && (area.top()..area.bottom()).contains(&mouse.row)
+        });
+        if let Some((_, dir)) = clicked {
+            self.change_dir(dir.clone());
+        }
+    }
+
+    // What a chord does; the ones that don't act right away return the action
+    fn run_chord(&mut self, chord: Chord, count: Option<usize>) -> Option<CommandAction> {
+        match chord {
+            // `5gg` goes to the fifth entry like `5G`
+            Chord::Top => self.jump_to(count.map_or(0, |count| count - 1)),
+            Chord::Home => return dirs::home_dir().map(CommandAction::Cd),
+            // The drive's root on Windows
+            Chord::Root => {
+                let root = self
+                    .current_dir
+                    .ancestors()
+                    .last()
+                    .unwrap_or(Path::new("/"));
+                return Some(CommandAction::Cd(root.to_path_buf()));
+            }
+            Chord::Mounts => return Some(CommandAction::Mounts),
+            Chord::FileManager => {
+                return Some(CommandAction::Reveal(Some(self.current_dir.clone())))
+            }
+            Chord::ToggleHidden => return Some(CommandAction::SetHidden(None)),
+            Chord::Choose => return Some(CommandAction::Choose),
+            Chord::TogglePreview => return Some(CommandAction::TogglePreview),
+            Chord::ToggleTodos => self.toggle_todos(),
+            Chord::Focus(key) => {
+                let panel = self.state.focus.towards(key);
+                if panel != Panel::Files && self.state.preview_hidden {
+                    self.state
+                        .notifications
+                        .error("The preview column is hidden, zp shows it");
+                } else if panel == Panel::Todos && self.tutorial.is_some() {
+                    self.state
+                        .notifications
+                        .error("The tutorial takes the place of the to-do list");
+                } else {
+                    if let Some(tree) = self.state.tree.as_mut() {
+                        tree.focused = false;
+                    }
+                    self.state.focus = panel;
+                }
+            }
+            Chord::Yank(operation) => return Some(CommandAction::Yank(operation)),
+            Chord::Copy(target) => return Some(CommandAction::Copy(target)),
+            Chord::Record(register) => self.macros.start(register),
+            Chord::Replay(register) => self.replay_macro(register, count.unwrap_or(1)),
+            Chord::DeleteTodo => {
+                if let Some(selected_index) = self.todo_list_state.selected() {
+                    if selected_index < self.todos.len() {
+                        self.todos.remove(selected_index);
+                        if !self.todos.is_empty() && selected_index >= self.todos.len() {
+                            self.todo_list_state.select(Some(self.todos.len() - 1));
+                        }
+                        self.save_todo_list();
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Types the keys of a macro `times` times over, `@` being the last one
+    // replayed
+    fn replay_macro(&mut self, register: char, times: usize) {
+        let register = match register {
+            '@' => match self.macros.last {
+                Some(last) => last,
+                None => {
+                    self.state.notifications.error("No macro replayed yet");
+                    return;
+                }
+            },
+            register => register,
+        };
+        let Some(keys) = self.macros.get(register).map(<[Event]>::to_vec) else {
+            self.state
+                .notifications
+                .error(format!("Nothing recorded in @{}", register));
+            return;
+        };
+        if self.macros.depth >= MAX_MACRO_DEPTH {
+            self.state
+                .notifications
+                .error(format!("@{} replays macros too deep", register));
+            return;
+        }
+        self.macros.last = Some(register);
+        self.macros.depth += 1;
+        'replay: for _ in 0..times {
+            for event in &keys {
+                self.handle_event(event.clone());
+                self.finish_loading();
+                if self.quit {
+                    break 'replay;
+                }
+            }
+        }
+        self.macros.depth -= 1;
+    }
+
+    // Waits for the listing of the current directory, so that every replayed
+    // key sees it complete
+    fn finish_loading(&mut self) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.background_loader.is_some() && Instant::now() < deadline {
+            self.tick();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // Removes the confirmed entries, directories with everything in them,
+    // along with their marks, tags and notes
+    fn delete_paths(&mut self, targets: &[PathBuf]) {
+        let mut failures = 0;
+        for target in targets {
+            let result = match fs::symlink_metadata(target) {
+                Ok(meta) if meta.is_dir() => fs::remove_dir_all(target),
+                Ok(_) => fs::remove_file(target),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    self.state.marked.remove(target);
+                    self.tags.forget(target);
+                    self.notes.forget(target);
+                }
+                Err(e) => {
+                    failures += 1;
+                    self.state.notifications.error(format!(
+                        "Failed to delete {}: {}",
+                        target.display(),
+                        e
+                    ));
+                }
+            }
+        }
+        self.save_annotations();
+        if failures == 0 {
+            self.state
+                .notifications
+                .info(format!("Deleted {} item(s)", targets.len()));
+        }
+        self.reload();
+    }
+
+    // Renames an entry within its own directory, which in the flat view and
+    // search results isn't the current one, keeping its tags and notes
+    fn rename_entry(&mut self, old_path: &Path, new_name: &str) -> bool {
+        let dir = old_path.parent().unwrap_or(&self.current_dir);
+        let new_path = dir.join(new_name);
+        if new_path.exists() {
+            self.state
+                .notifications
+                .error(format!("{} already exists", new_name));
+            return false;
+        }
+        match fs::rename(old_path, &new_path) {
+            Ok(()) => {
+                self.state
+                    .notifications
+                    .info(format!("Renamed to {}", new_name));
+                self.tags.rename(old_path, &new_path);
+                self.notes.rename(old_path, &new_path);
+                self.save_annotations();
+                true
+            }
+            Err(e) => {
+                self.state
+                    .notifications
+                    .error(format!("Failed to rename: {}", e));
+                false
+            }
+        }
+    }
+
+    // Pastes right away, or once the names already taken in `dir` are
+    // decided on
+    fn start_paste(&mut self, mut paste: Paste) {
+        if paste.advance() {
+            self.finish_paste(paste);
+        } else {
+            self.state.paste = Some(paste);
+        }
+    }
+
+    // Copies or moves the files and reports how that went. Cut files take
+    // their tags and notes along and leave the register.
+    fn finish_paste(&mut self, paste: Paste) {
+        let (operation, skipped, dir) = (paste.operation, paste.skipped, paste.dir.clone());
+        let (pasted, errors, strategy) = paste.run(self.preserve);
+        for error in errors {
+            self.state.notifications.error(error);
+        }
+        if operation == RegisterOperation::Cut && !pasted.is_empty() {
+            for (from, to) in &pasted {
+                self.tags.rename(from, to);
+                self.notes.rename(from, to);
+            }
+            self.save_annotations();
+            // Cut files can only be pasted once
+            if let Some(mut register) =
+                Register::load().filter(|r| r.operation == RegisterOperation::Cut)
+            {
+                register
+                    .paths
+                    .retain(|path| !pasted.iter().any(|(from, _)| from == path));
+                if register.paths.is_empty() {
+                    Register::clear();
+                } else if let Err(e) = register.save() {
+                    self.state
+                        .notifications
+                        .error(format!("Failed to save the register: {}", e));
+                }
+            }
+        }
+        if !pasted.is_empty() || skipped > 0 {
+            let mut message = format!(
+                "{} {} item(s)",
+                match operation {
+                    RegisterOperation::Copy => "Pasted",
+                    RegisterOperation::Cut => "Moved",
+                },
+                pasted.len()
+            );
+            if let Some(strategy) = strategy {
+                message.push_str(&format!(" by {}", strategy.label()));
+            }
+            if skipped > 0 {
+                message.push_str(&format!(", skipped {}", skipped));
+            }
+            self.state.notifications.info(message);
+            self.job_done("paste");
+        }
+        if !pasted.is_empty() {
+            self.last_change = Some(Change::Paste(operation, dir));
+        }
+        self.reload();
+    }
+
+    // Links `sources` into the other pane's directory when the layout is
+    // split, into the current one otherwise; under their own names unless
+    // `name` gives one
+    fn create_links(&mut self, kind: LinkKind, sources: &[PathBuf], name: Option<String>) {
+        let dir = match &self.state.split {
+            Some(split) => split.other.dir.clone(),
+            None => self.current_dir.clone(),
+        };
+        let mut created = 0;
+        for source in sources {
+            let link = match &name {
+                Some(name) => dir.join(name),
+                None => dir.join(source.file_name().unwrap_or_default()),
+            };
+            let result = if fs::symlink_metadata(&link).is_ok() {
+                Err(format!("{} already exists", link.display()))
+            } else if kind == LinkKind::Hard && self.metadata_cache.is_dir(source) {
+                Err(format!("Cannot hard link directory {}", source.display()))
+            } else {
+                kind.create(source, &link)
+                    .map_err(|e| format!("Failed to link {}: {}", source.display(), e))
+            };
+            match result {
+                Ok(()) => created += 1,
+                Err(e) => self.state.notifications.error(e),
+            }
+        }
+        if created > 0 {
+            self.state.notifications.info(format!(
+                "Created {} {}(s) in {}",
+                created,
+                kind.label(),
+                dir.display()
+            ));
+        }
+        if let Some(split) = self.state.split.as_mut() {
+            split.other.files = list_files(
+                &split.other.dir,
+                self.show_hidden,
+                self.state.sort_mode,
+                self.state.mount.stat_entries,
+            )
+            .unwrap_or_default();
+        }
+        self.reload();
+    }
+
+    // Applies the last change to the marked files or the selection. Commands
+    // are handed back to run again.
+    fn repeat_change(&mut self) -> Option<CommandAction> {
+        let change = match &self.last_change {
+            Some(Change::Action(action)) => return Some(action.clone()),
+            Some(change) => change.clone(),
+            None => {
+                self.state.notifications.error("Nothing to repeat");
+                return None;
+            }
+        };
+        // Marked files take precedence over the cursor
+        let mut targets: Vec<PathBuf> = if self.state.marked.is_empty() {
+            self.selected_file()
+                .map(|file| self.current_dir.join(file))
+                .into_iter()
+                .collect()
+        } else {
+            self.state.marked.drain().collect()
+        };
+        targets.sort();
+        if targets.is_empty() {
+            self.state.notifications.error("Nothing selected");
+            return None;
+        }
+        match change {
+            Change::Rename(pattern) => {
+                for path in targets {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    match pattern.apply(&name) {
+                        Some(new_name) => {
+                            self.rename_entry(&path, &new_name);
+                        }
+                        None => self
+                            .state
+                            .notifications
+                            .error(format!("The last rename doesn't fit {}", name)),
+                    }
+                }
+            }
+            Change::Paste(operation, dir) => {
+                let register = Register {
+                    operation,
+                    paths: targets,
+                };
+                self.start_paste(register.plan(&dir));
+                return None;
+            }
+            Change::Action(_) => unreachable!(),
+        }
+        self.reload();
+        None
+    }
+
+    // Puts the cursor on entry `index`, or the last one when there are fewer
+    fn jump_to(&mut self, index: usize) {
+        self.cursor_position = index.min(self.state.files.len().saturating_sub(1));
+    }
+
+    // Moves the cursor `steps` matches of the last search down, or up when
+    // negative, wrapping around at the ends. A listing filtered by the search
+    // is listed in full first so the matches are seen among the other entries.
+    fn next_match(&mut self, steps: isize) {
+        if self.search_query.is_empty() {
+            self.state
+                .notifications
+                .error("No search yet, / starts one");
+            return;
+        }
+        if self.search_filtered {
+            self.select = self.selected_file();
+            self.cursor_position = 0;
+            self.reload();
+            self.pending_match = Some(steps);
+            return;
+        }
+        let matches: Vec<usize> = self
+            .state
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.name.contains(&self.search_query))
+            .map(|(index, _)| index)
+            .collect();
+        let (Some(&first), Some(&last)) = (matches.first(), matches.last()) else {
+            self.state
+                .notifications
+                .error(format!("No match for {}", self.search_query));
+            return;
+        };
+        let mut wrapped = false;
+        for _ in 0..steps.unsigned_abs() {
+            let next = if steps > 0 {
+                matches.iter().find(|&&index| index > self.cursor_position)
+            } else {
+                matches
+                    .iter()
+                    .rev()
+                    .find(|&&index| index < self.cursor_position)
+            };
+            self.cursor_position = match next {
+                Some(&index) => index,
+                None => {
+                    wrapped = true;
+                    if steps > 0 {
+                        first
+                    } else {
+                        last
+                    }
+                }
+            };
+        }
+        let position = matches
+            .iter()
+            .position(|&index| index == self.cursor_position)
+            .unwrap_or(0);
+        let mut message = format!(
+            "Match {} of {} for {}",
+            position + 1,
+            matches.len(),
+            self.search_query
+        );
+        if wrapped {
+            message.push_str(if steps > 0 {
+                ", continued at the top"
+            } else {
+                ", continued at the bottom"
+            });
+        }
+        self.state.notifications.info(message);
+    }
+
+    // Moves through the focused panel when it isn't the file list, as j/k
+    // and the like move through the files
+    fn scroll_panel(&mut self, rows: isize) {
+        let rows = match self.state.count.take() {
+            Some(count) if rows.abs() == 1 => rows * count.min(isize::MAX as usize) as isize,
+            _ => rows,
+        };
+        match self.state.focus {
+            Panel::Files => {}
+            Panel::Preview => {
+                let Some(path) = self.selected_file().map(|name| self.current_dir.join(name))
+                else {
+                    return;
+                };
+                let scroll = match &self.state.preview_scroll {
+                    Some((scrolled, scroll)) if *scrolled == path => *scroll,
+                    _ => 0,
+                };
+                // Past the end is taken back when it is drawn
+                self.state.preview_scroll = Some((path, scroll.saturating_add_signed(rows)));
+            }
+            Panel::Todos if !self.todos.is_empty() => {
+                let selected = self.todo_list_state.selected().unwrap_or(0);
+                let index = selected
+                    .saturating_add_signed(rows)
+                    .min(self.todos.len() - 1);
+                self.todo_list_state.select(Some(index));
+            }
+            Panel::Todos => {}
+        }
+    }
+
+    // Shows what the selected to-do is linked to: enters the directory, or
+    // the file's directory with the file selected
+    fn go_to_todo_path(&mut self) {
+        let Some(path) = self
+            .todo_list_state
+            .selected()
+            .and_then(|index| self.todos.get(index))
+            .and_then(|todo| todo.path.clone())
+        else {
+            self.state
+                .notifications
+                .error("The to-do isn't linked to a file");
+            return;
+        };
+        if !path.exists() {
+            self.state
+                .notifications
+                .error(format!("{} no longer exists", path.display()));
+            return;
+        }
+        self.remote = None;
+        self.state.focus = Panel::Files;
+        match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) if !path.is_dir() => {
+                self.select = Some(name.to_string_lossy().into_owned());
+                self.change_dir(dir.to_path_buf());
+            }
+            _ => self.change_dir(path),
+        }
+    }
+
+    // Moves the view and the cursor by `rows` like Vim's Ctrl+D and Ctrl+U,
+    // the cursor stays where it was on screen
+    fn scroll_by(&mut self, rows: isize) {
+        let last = self.state.files.len().saturating_sub(1);
+        let max_offset = self.state.files.len().saturating_sub(self.list_height);
+        self.list_offset = self.list_offset.saturating_add_signed(rows).min(max_offset);
+        self.jump_to(self.cursor_position.saturating_add_signed(rows).min(last));
+    }
+
+    // Lists `dir` with the cursor on its first entry
+    fn switch_pane(&mut self) {
+        if let Some(split) = self.state.split.as_mut() {
+            let focused = Pane {
+                dir: self.current_dir.clone(),
+                cursor: self.cursor_position,
+                files: self.state.files.clone(),
+                remote: self.remote.take(),
+            };
+            let other = std::mem::replace(&mut split.other, focused);
+            split.focus_second = !split.focus_second;
+            self.current_dir = other.dir;
+            self.cursor_position = other.cursor;
+            self.remote = other.remote;
+            self.search_query.clear();
+            self.last_dir = self.current_dir.clone();
+            self.reload();
+        }
+    }
+
+    // Lists a remote directory, over the open connection when it is on the
+    // same server
+    fn open_remote(&mut self, location: Location) {
+        let previous = self.remote.as_ref().map(|remote| remote.location.clone());
+        let result = match self
+            .remote
+            .as_mut()
+            .filter(|remote| remote.location.same_server(&location))
+        {
+            Some(remote) => remote.cd(location),
+            None => {
+                let local_dir = match &self.remote {
+                    Some(remote) => remote.local_dir.clone(),
+                    None => self.current_dir.clone(),
+                };
+                Remote::open(location, local_dir, &self.s3, &self.opener_config.programs)
+                    .map(|remote| self.remote = Some(remote))
+            }
+        };
+        if let Err(e) = result {
+            self.state.notifications.error(e);
+            return;
+        }
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let mirror = remote.location.mirror();
+        if let Err(e) = fs::create_dir_all(&mirror) {
+            self.state
+                .notifications
+                .error(format!("Failed to create {}: {}", mirror.display(), e));
+        }
+        // Coming back up, the cursor stays on the directory just left
+        let child = previous
+            .filter(|previous| previous.parent().as_ref() == Some(&remote.location))
+            .and_then(|previous| previous.name().map(str::to_string));
+        self.change_dir(mirror);
+        if let Some(index) = child.and_then(|child| {
+            self.state
+                .files
+                .iter()
+                .position(|entry| entry.name == child)
+        }) {
+            self.cursor_position = index;
+        }
+    }
+
+    // The marked entries, or the selection
+    fn sources(&self) -> Vec<PathBuf> {
+        let mut sources: Vec<PathBuf> = if self.state.marked.is_empty() {
+            self.selected_file()
+                .map(|file| self.current_dir.join(file))
+                .into_iter()
+                .collect()
+        } else {
+            self.state.marked.iter().cloned().collect()
+        };
+        sources.sort();
+        sources
+    }
+
+    // Downloads the marked remote entries or the selection into the other
+    // pane's directory, or the local directory the remote one was opened from
+    fn download(&mut self) {
+        let Some(remote) = &self.remote else {
+            self.state
+                .notifications
+                .error(":get copies from a remote directory");
+            return;
+        };
+        let dir = match self
+            .state
+            .split
+            .as_ref()
+            .filter(|s| s.other.remote.is_none())
+        {
+            Some(split) => split.other.dir.clone(),
+            None => remote.local_dir.clone(),
+        };
+        let names: Vec<String> = self
+            .sources()
+            .iter()
+            .map(|source| {
+                source
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        self.state.transfer = Some(remote.download(&names, &dir));
+    }
+
+    // Uploads the marked entries or the selection into the remote directory
+    // open in the other pane
+    fn upload(&mut self) {
+        let Some(remote) = self
+            .state
+            .split
+            .as_ref()
+            .and_then(|split| split.other.remote.as_ref())
+        else {
+            self.state
+                .notifications
+                .error(":put needs a remote directory in the other pane");
+            return;
+        };
+        if self.remote.is_some() {
+            self.state
+                .notifications
+                .error(":put copies from a local directory");
+            return;
+        }
+        self.state.transfer = Some(remote.upload(&self.sources()));
+    }
+
+    // Reports a sync that is done or was stopped, and lists both sides again
+    fn finish_sync(&mut self) {
+        let Some(sync) = self.state.sync.take() else {
+            return;
+        };
+        sync.cancel();
+        let done = sync.done.unwrap_or(0);
+        for error in &sync.errors {
+            self.state.notifications.error(error.clone());
+        }
+        if sync.is_done() {
+            self.state.notifications.info(format!(
+                "Synced {} into {}",
+                sync.source.display(),
+                sync.dest.display()
+            ));
+        } else {
+            self.state.notifications.info(format!(
+                "Sync stopped after {} of {} step(s)",
+                done,
+                sync.steps.len()
+            ));
+        }
+        self.job_done("sync");
+        if let Some(split) = self.state.split.as_mut() {
+            split.other.files = list_files(
+                &split.other.dir,
+                self.show_hidden,
+                self.state.sort_mode,
+                self.state.mount.stat_entries,
+            )
+            .unwrap_or_default();
+        }
+        self.reload();
+    }
+
+    // Reports a transfer that is done or was stopped, and lists the other
+    // pane again
+    fn finish_transfer(&mut self) {
+        let Some(transfer) = self.state.transfer.take() else {
+            return;
+        };
+        transfer.cancel();
+        for e in &transfer.errors {
+            self.state.notifications.error(e.clone());
+        }
+        let (verb, job) = match transfer.direction {
+            Direction::Download => ("Downloaded", "download"),
+            Direction::Upload => ("Uploaded", "upload"),
+        };
+        let copied = transfer.copied();
+        if !transfer.is_done() {
+            self.state.notifications.info(format!(
+                "Stopped after {} of {} item(s)",
+                transfer.done,
+                transfer.names.len()
+            ));
+        } else if copied > 0 {
+            self.state
+                .notifications
+                .info(format!("{} {} item(s) to {}", verb, copied, transfer.to));
+            self.job_done(job);
+        }
+        self.state.marked.clear();
+        if let Some(split) = self.state.split.as_mut() {
+            let other = &mut split.other;
+            other.files = match other.remote.as_mut() {
+                Some(remote) => {
+                    if let Err(e) = remote.refresh() {
+                        self.state.notifications.error(e);
+                    }
+                    remote.files(self.show_hidden, self.state.sort_mode)
+                }
+                None => list_files(
+                    &other.dir,
+                    self.show_hidden,
+                    self.state.sort_mode,
+                    self.state.mount.stat_entries,
+                )
+                .unwrap_or_default(),
+            };
+        }
+    }
+
+    // Runs the `[hooks]` command of the event, complaining when it can't start
+    fn fire_hook(&mut self, event: HookEvent, vars: &[(&str, &str)]) {
+        if let Err(e) = self.hooks.fire(event, vars) {
+            self.state.notifications.error(e);
+        }
+    }
+
+    fn fire_script(&mut self, hook: ScriptHook, path: Option<&Path>) {
+        if let Err(e) = self.scripts.fire(hook, path) {
+            self.state.notifications.error(e);
+        }
+        self.run_script_actions();
+    }
+
+    // The marked entries, sorted, as scripts get them
+    fn script_selection(&self) -> Vec<PathBuf> {
+        let mut selected: Vec<PathBuf> = self.state.marked.iter().cloned().collect();
+        selected.sort();
+        selected
+    }
+
+    fn run_script_command(&mut self, line: &str, selected: Option<PathBuf>) {
+        let marked = self.script_selection();
+        let context = ScriptContext {
+            dir: &self.current_dir,
+            file: selected.as_deref(),
+            selected: &marked,
+        };
+        if let Err(e) = self.scripts.run_command(line, &context) {
+            self.state.notifications.error(e);
+        }
+        self.run_script_actions();
+    }
+
+    // Carries out what the scripts asked for. The commands they run may run
+    // scripts in turn, up to `MAX_DEPTH` deep.
+    fn run_script_actions(&mut self) {
+        let actions = self.scripts.take_actions();
+        if actions.is_empty() {
+            return;
+        }
+        if self.script_depth == MAX_DEPTH {
+            self.state
+                .notifications
+                .error(format!("Scripts run commands more than {} deep", MAX_DEPTH));
+            return;
+        }
+        self.script_depth += 1;
+        for action in actions {
+            match action {
+                ScriptAction::Notify(message) => self.state.notifications.info(message),
+                ScriptAction::Error(message) => self.state.notifications.error(message),
+                ScriptAction::Run(line) => match self.parse_input(line.trim_start_matches(':')) {
+                    Ok(action) => self.run_action(Some(action)),
+                    Err(e) => self.state.notifications.error(e),
+                },
+            }
+        }
+        self.script_depth -= 1;
+    }
+
+    fn opened(&mut self, file: &Path) {
+        self.fire_script(ScriptHook::Open, Some(file));
+        let file = file.to_string_lossy().into_owned();
+        let dir = self.current_dir.to_string_lossy().into_owned();
+        self.fire_hook(
+            HookEvent::Open,
+            &[("TERMFM_FILE", &file), ("TERMFM_DIR", &dir)],
+        );
+    }
+
+    // After a paste, merge, sync or transfer, with the message it ended with
+    fn job_done(&mut self, job: &str) {
+        let message = self
+            .state
+            .notifications
+            .log
+            .back()
+            .map(|n| n.message.clone())
+            .unwrap_or_default();
+        let dir = self.current_dir.to_string_lossy().into_owned();
+        self.fire_hook(
+            HookEvent::JobDone,
+            &[
+                ("TERMFM_JOB", job),
+                ("TERMFM_MESSAGE", &message),
+                ("TERMFM_DIR", &dir),
+            ],
+        );
+    }
+
+    fn change_dir(&mut self, dir: PathBuf) {
+        self.current_dir = dir;
+        self.search_query.clear();
+        self.last_dir = self.current_dir.clone();
+        self.reload();
+        self.cursor_position = 0;
+    }
+}
@@ -0,0 +1,278 @@
+use crate::fs_ops::{format_size, list_files, Entry, SortMode};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::Value;
+
+#[derive(Clone, Copy)]
+pub struct CacheLimits {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+// Limits of the directory and metadata caches from the `[cache]` section of
+// opener.toml; the memory limit applies to each cache on its own
+pub struct CacheConfig {
+    pub directories: CacheLimits,
+    pub metadata: CacheLimits,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            directories: CacheLimits {
+                entries: 64,
+                bytes: 64 << 20,
+            },
+            metadata: CacheLimits {
+                entries: 4096,
+                bytes: 64 << 20,
+            },
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn from_config(config_path: &Path) -> Self {
+        let mut config = Self::default();
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("cache").cloned());
+        let Some(Value::Table(section)) = section else {
+            return config;
+        };
+
+        let limit = |key: &str| {
+            section
+                .get(key)
+                .and_then(|v| v.as_integer())
+                .and_then(|v| usize::try_from(v).ok())
+        };
+        if let Some(entries) = limit("directories") {
+            config.directories.entries = entries;
+        }
+        if let Some(entries) = limit("metadata") {
+            config.metadata.entries = entries;
+        }
+        if let Some(megabytes) = limit("memory_mb") {
+            config.directories.bytes = megabytes << 20;
+            config.metadata.bytes = megabytes << 20;
+        }
+        config
+    }
+}
+
+// Path-keyed cache that evicts the least recently used entries once it holds
+// more than its limits. Sizes are estimates given by the caller.
+pub struct LruCache<V> {
+    pub entries: HashMap<PathBuf, (V, usize, u64)>,
+    pub limits: CacheLimits,
+    // Bumped on every access, the entry with the oldest tick goes first
+    pub clock: u64,
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl<V> LruCache<V> {
+    pub fn new(limits: CacheLimits) -> Self {
+        Self {
+            entries: HashMap::new(),
+            limits,
+            clock: 0,
+            bytes: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &Path) -> Option<&mut V> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some((value, _, last_used)) => {
+                self.hits += 1;
+                *last_used = self.clock;
+                Some(value)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: PathBuf, value: V, size: usize) {
+        self.remove(&key);
+        self.clock += 1;
+        self.bytes += size;
+        self.entries.insert(key, (value, size, self.clock));
+        while self.entries.len() > self.limits.entries.max(1)
+            || (self.bytes > self.limits.bytes && self.entries.len() > 1)
+        {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, _, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.remove(&oldest);
+                self.evictions += 1;
+            }
+        }
+    }
+
+    // Updates the size estimate after a value was changed in place
+    pub fn resize(&mut self, key: &Path, size: usize) {
+        if let Some((_, old_size, _)) = self.entries.get_mut(key) {
+            self.bytes = self.bytes - *old_size + size;
+            *old_size = size;
+        }
+    }
+
+    pub fn remove(&mut self, key: &Path) {
+        if let Some((_, size, _)) = self.entries.remove(key) {
+            self.bytes -= size;
+        }
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        let bytes = &mut self.bytes;
+        self.entries.retain(|_, (value, size, _)| {
+            let kept = keep(value);
+            if !kept {
+                *bytes -= *size;
+            }
+            kept
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    pub fn stats(&self, name: &str) -> String {
+        let lookups = self.hits + self.misses;
+        let hit_rate = if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 * 100.0 / lookups as f64
+        };
+        format!(
+            "{}: {}/{} entries, {}/{}, {:.0}% hits ({} of {}), {} evicted",
+            name,
+            self.entries.len(),
+            self.limits.entries,
+            format_size(self.bytes as u64),
+            format_size(self.limits.bytes as u64),
+            hit_rate,
+            self.hits,
+            lookups,
+            self.evictions
+        )
+    }
+}
+
+pub struct FileMetadataCache {
+    pub metadata: LruCache<(std::fs::Metadata, std::time::SystemTime)>,
+}
+
+impl FileMetadataCache {
+    pub fn new(limits: CacheLimits) -> Self {
+        Self {
+            metadata: LruCache::new(limits),
+        }
+    }
+
+    pub fn get_metadata(&mut self, path: &Path) -> Option<&std::fs::Metadata> {
+        let current_time = std::time::SystemTime::now();
+
+        // Clean old entries first
+        self.clean_old_entries(current_time);
+
+        // Check if we already have the metadata
+        if self.metadata.get_mut(path).is_none() {
+            // If not, get it from the filesystem
+            let meta = std::fs::metadata(path).ok()?;
+            let size = std::mem::size_of::<std::fs::Metadata>() + path.as_os_str().len();
+            self.metadata
+                .insert(path.to_path_buf(), (meta, current_time), size);
+        }
+        self.metadata
+            .entries
+            .get(path)
+            .map(|((meta, _), _, _)| meta)
+    }
+
+    pub fn clean_old_entries(&mut self, current_time: std::time::SystemTime) {
+        self.metadata.retain(|(_, time)| {
+            current_time.duration_since(*time).unwrap_or_default() <= Duration::from_secs(5)
+        });
+    }
+
+    pub fn is_dir(&mut self, path: &Path) -> bool {
+        self.get_metadata(path).map(|m| m.is_dir()).unwrap_or(false)
+    }
+
+    pub fn is_file(&mut self, path: &Path) -> bool {
+        self.get_metadata(path)
+            .map(|m| m.is_file())
+            .unwrap_or(false)
+    }
+}
+
+pub struct DirectoryCache {
+    pub entries: LruCache<(Vec<Entry>, std::time::SystemTime)>,
+}
+
+impl DirectoryCache {
+    pub fn new(limits: CacheLimits) -> Self {
+        Self {
+            entries: LruCache::new(limits),
+        }
+    }
+
+    pub fn get_entries(
+        &mut self,
+        path: &Path,
+        show_hidden: bool,
+        sort_mode: SortMode,
+        stat_entries: bool,
+    ) -> io::Result<&Vec<Entry>> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+
+        if let Some((entries, last_modified)) = self.entries.get_mut(path) {
+            if modified > *last_modified {
+                *entries = list_files(path, show_hidden, sort_mode, stat_entries)?;
+                *last_modified = modified;
+                let size = Self::size_of(entries);
+                self.entries.resize(path, size);
+            }
+        } else {
+            let entries = list_files(path, show_hidden, sort_mode, stat_entries)?;
+            let size = Self::size_of(&entries);
+            self.entries
+                .insert(path.to_path_buf(), (entries, modified), size);
+        }
+
+        Ok(&self.entries.entries[path].0 .0)
+    }
+
+    // Rough heap footprint of a listing
+    pub fn size_of(entries: &[Entry]) -> usize {
+        entries
+            .iter()
+            .map(|entry| std::mem::size_of::<Entry>() + entry.name.capacity())
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
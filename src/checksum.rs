@@ -0,0 +1,320 @@
+// File checksums, computed on a background thread, and verification against
+// checksum files in the format of `sha256sum` and friends
+use crate::{terminal::wake, ui::ListState};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    pub const NAMES: [&str; 3] = ["md5", "sha1", "sha256"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "md5" => Some(Algorithm::Md5),
+            "sha1" => Some(Algorithm::Sha1),
+            "sha256" => Some(Algorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+        }
+    }
+
+    // From the name of a checksum file ("SHA256SUMS", "x.md5sum"), else from
+    // the length of the digests in it
+    pub fn detect(file_name: &str, digest: &str) -> Option<Self> {
+        let name = file_name.to_lowercase();
+        Self::NAMES
+            .iter()
+            .find(|algorithm| name.contains(*algorithm))
+            .and_then(|algorithm| Self::parse(algorithm))
+            .or(match digest.len() {
+                32 => Some(Algorithm::Md5),
+                40 => Some(Algorithm::Sha1),
+                64 => Some(Algorithm::Sha256),
+                _ => None,
+            })
+    }
+
+    pub fn hash_file(self, path: &Path, cancel: &AtomicBool) -> io::Result<String> {
+        match self {
+            Algorithm::Md5 => hash::<Md5>(path, cancel),
+            Algorithm::Sha1 => hash::<Sha1>(path, cancel),
+            Algorithm::Sha256 => hash::<Sha256>(path, cancel),
+        }
+    }
+}
+
+fn hash<D: Digest>(path: &Path, cancel: &AtomicBool) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+pub enum ChecksumState {
+    Pending,
+    Done(String),
+    Failed(String),
+}
+
+pub struct ChecksumEntry {
+    // Relative to the directory of the checksums
+    pub name: String,
+    // The digest listed in the checksum file being verified
+    pub expected: Option<String>,
+    pub state: ChecksumState,
+}
+
+impl ChecksumEntry {
+    // `sha256sum` output when computing, `sha256sum -c` output when verifying
+    pub fn line(&self) -> String {
+        match (&self.state, &self.expected) {
+            (ChecksumState::Pending, _) => format!("{}: …", self.name),
+            (ChecksumState::Failed(e), _) => format!("{}: {}", self.name, e),
+            (ChecksumState::Done(digest), None) => format!("{}  {}", digest, self.name),
+            (ChecksumState::Done(digest), Some(expected)) if digest == expected => {
+                format!("{}: OK", self.name)
+            }
+            (ChecksumState::Done(_), Some(_)) => format!("{}: FAILED", self.name),
+        }
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        match (&self.state, &self.expected) {
+            (ChecksumState::Done(digest), Some(expected)) => digest != expected,
+            (ChecksumState::Failed(_), Some(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+// The checksum popup. Hashing runs on a thread that stops when the popup is
+// closed.
+pub struct Checksums {
+    pub algorithm: Algorithm,
+    pub dir: PathBuf,
+    pub entries: Vec<ChecksumEntry>,
+    pub state: ListState,
+    // Set when verifying, the checksum file that was read
+    pub source: Option<PathBuf>,
+    receiver: mpsc::Receiver<(usize, Result<String, String>)>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Checksums {
+    pub fn compute(algorithm: Algorithm, dir: &Path, paths: &[PathBuf]) -> Self {
+        let entries = paths
+            .iter()
+            .map(|path| ChecksumEntry {
+                name: path
+                    .strip_prefix(dir)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .into_owned(),
+                expected: None,
+                state: ChecksumState::Pending,
+            })
+            .collect();
+        Self::start(algorithm, dir, entries, None)
+    }
+
+    // Reads a checksum file and hashes the files it lists, relative to the
+    // directory it is in
+    pub fn verify(checksum_file: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(checksum_file)
+            .map_err(|e| format!("Failed to read {}: {}", checksum_file.display(), e))?;
+        let entries: Vec<ChecksumEntry> = contents
+            .lines()
+            .filter_map(|line| {
+                // "<digest>  <name>", or "<digest> *<name>" for binary mode
+                let (digest, name) = line.trim_end().split_once(' ')?;
+                let name = name.strip_prefix(['*', ' ']).unwrap_or(name);
+                (!digest.is_empty() && digest.chars().all(|c| c.is_ascii_hexdigit())).then(|| {
+                    ChecksumEntry {
+                        name: name.to_string(),
+                        expected: Some(digest.to_lowercase()),
+                        state: ChecksumState::Pending,
+                    }
+                })
+            })
+            .collect();
+        let file_name = checksum_file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let algorithm = entries
+            .first()
+            .and_then(|entry| Algorithm::detect(&file_name, entry.expected.as_deref()?))
+            .ok_or_else(|| format!("Not a checksum file: {}", file_name))?;
+        let dir = checksum_file.parent().unwrap_or(Path::new("."));
+        Ok(Self::start(
+            algorithm,
+            dir,
+            entries,
+            Some(checksum_file.to_path_buf()),
+        ))
+    }
+
+    fn start(
+        algorithm: Algorithm,
+        dir: &Path,
+        entries: Vec<ChecksumEntry>,
+        source: Option<PathBuf>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let paths: Vec<PathBuf> = entries.iter().map(|entry| dir.join(&entry.name)).collect();
+        let thread_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            for (index, path) in paths.iter().enumerate() {
+                let result = algorithm
+                    .hash_file(path, &thread_cancel)
+                    .map_err(|e| e.to_string());
+                if thread_cancel.load(Ordering::SeqCst) || sender.send((index, result)).is_err() {
+                    return;
+                }
+                wake();
+            }
+        });
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            algorithm,
+            dir: dir.to_path_buf(),
+            entries,
+            state,
+            source,
+            receiver,
+            cancel,
+        }
+    }
+
+    // Picks up finished digests, returns whether all of them are done
+    pub fn poll(&mut self) -> bool {
+        for (index, result) in self.receiver.try_iter() {
+            if let Some(entry) = self.entries.get_mut(index) {
+                entry.state = match result {
+                    Ok(digest) => ChecksumState::Done(digest),
+                    Err(e) => ChecksumState::Failed(e),
+                };
+            }
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        !self
+            .entries
+            .iter()
+            .any(|entry| matches!(entry.state, ChecksumState::Pending))
+    }
+
+    pub fn selected(&self) -> usize {
+        self.state.selected().unwrap_or(0)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.state
+            .select(Some(index.min(self.entries.len().saturating_sub(1))));
+    }
+
+    pub fn selected_digest(&self) -> Option<&str> {
+        match &self.entries.get(self.selected())?.state {
+            ChecksumState::Done(digest) => Some(digest),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let done = self.entries.len()
+            - self
+                .entries
+                .iter()
+                .filter(|entry| matches!(entry.state, ChecksumState::Pending))
+                .count();
+        let progress = if self.is_done() {
+            String::new()
+        } else {
+            format!(" ({}/{})", done, self.entries.len())
+        };
+        match &self.source {
+            Some(source) => {
+                let failed = self.entries.iter().filter(|e| e.is_mismatch()).count();
+                format!(
+                    "Verify {}{}: {} failed, Esc to close",
+                    source.file_name().unwrap_or_default().to_string_lossy(),
+                    progress,
+                    failed
+                )
+            }
+            None => format!(
+                "{}{}: y copies, w writes .{}sum, Esc to close",
+                self.algorithm.name(),
+                progress,
+                self.algorithm.name()
+            ),
+        }
+    }
+
+    // Writes the digests as `<file>.sha256sum` next to a single file, or as
+    // `checksums.sha256sum` for several
+    pub fn write(&self) -> io::Result<PathBuf> {
+        if !self.is_done() {
+            return Err(io::Error::other("checksums are still being computed"));
+        }
+        let base = match self.entries.as_slice() {
+            [entry] => entry.name.clone(),
+            _ => "checksums".to_string(),
+        };
+        let path = self
+            .dir
+            .join(format!("{}.{}sum", base, self.algorithm.name()));
+        let mut contents = String::new();
+        for entry in &self.entries {
+            if let ChecksumState::Done(digest) = &entry.state {
+                contents.push_str(&format!("{}  {}\n", digest, entry.name));
+            }
+        }
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+impl Drop for Checksums {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
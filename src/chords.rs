@@ -0,0 +1,121 @@
+// Bindings of more than one key, such as `gg` or `Yn`. The first key waits
+// for the rest; a key that doesn't continue any of them, or a pause longer
+// than `CHORD_TIMEOUT`, drops what was typed so far.
+use crate::{clipboard::CopyTarget, register::RegisterOperation};
+use std::time::Duration;
+
+pub const CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+// Ctrl+w, which starts the chords moving the focus between panels
+pub const CTRL_W: char = '\u{17}';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chord {
+    // The first entry, or the count's
+    Top,
+    Home,
+    Root,
+    Mounts,
+    // The current directory in the desktop file manager
+    FileManager,
+    Yank(RegisterOperation),
+    Copy(CopyTarget),
+    DeleteTodo,
+    ToggleHidden,
+    // Quit with the selection as the choice (`:choose`)
+    Choose,
+    TogglePreview,
+    // The project's to-do list or the global one
+    ToggleTodos,
+    // The panel in the direction of h/j/k/l, or the next one with w
+    Focus(char),
+    // Keyboard macros, by register
+    Record(char),
+    Replay(char),
+}
+
+// The keys, what they do and how the status bar names it while they are typed
+pub const CHORDS: [(&str, Chord, &str); 21] = [
+    ("gg", Chord::Top, "top"),
+    ("gh", Chord::Home, "home"),
+    ("g/", Chord::Root, "root"),
+    ("gm", Chord::Mounts, "mounts"),
+    ("gO", Chord::FileManager, "file manager"),
+    ("yy", Chord::Yank(RegisterOperation::Copy), "yank"),
+    ("dd", Chord::Yank(RegisterOperation::Cut), "cut"),
+    ("dt", Chord::DeleteTodo, "delete to-do"),
+    ("zh", Chord::ToggleHidden, "hidden files"),
+    ("zq", Chord::Choose, "quit and choose"),
+    ("zp", Chord::TogglePreview, "preview"),
+    ("zt", Chord::ToggleTodos, "project to-dos"),
+    ("Yn", Chord::Copy(CopyTarget::Name), "name"),
+    ("Yp", Chord::Copy(CopyTarget::Path), "path"),
+    ("Yd", Chord::Copy(CopyTarget::Directory), "directory"),
+    ("Yf", Chord::Copy(CopyTarget::Files), "files"),
+    ("\u{17}h", Chord::Focus('h'), "left"),
+    ("\u{17}j", Chord::Focus('j'), "down"),
+    ("\u{17}k", Chord::Focus('k'), "up"),
+    ("\u{17}l", Chord::Focus('l'), "right"),
+    ("\u{17}w", Chord::Focus('w'), "next panel"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Found(Chord),
+    // The start of one or more chords
+    Pending,
+    Unbound,
+}
+
+// `Q` and `@` take a register, `a` to `z`, as their second key; `@@`
+// replays the last macro again
+fn register_chord(keys: &str) -> Option<Lookup> {
+    let mut chars = keys.chars();
+    let first = chars.next()?;
+    let lookup = match (first, chars.next(), chars.next()) {
+        ('Q' | '@', None, _) => Lookup::Pending,
+        ('Q', Some(register @ 'a'..='z'), None) => Lookup::Found(Chord::Record(register)),
+        ('@', Some(register @ ('a'..='z' | '@')), None) => Lookup::Found(Chord::Replay(register)),
+        ('Q' | '@', ..) => Lookup::Unbound,
+        _ => return None,
+    };
+    Some(lookup)
+}
+
+pub fn lookup(keys: &str) -> Lookup {
+    if let Some(lookup) = register_chord(keys) {
+        lookup
+    } else if let Some((_, chord, _)) = CHORDS.iter().find(|(chord_keys, ..)| *chord_keys == keys) {
+        Lookup::Found(*chord)
+    } else if CHORDS
+        .iter()
+        .any(|(chord_keys, ..)| chord_keys.starts_with(keys))
+    {
+        Lookup::Pending
+    } else {
+        Lookup::Unbound
+    }
+}
+
+// `keys` as the status bar shows them
+pub fn display(keys: &str) -> String {
+    keys.replace(CTRL_W, "Ctrl+w ")
+}
+
+// What may follow `keys`, e.g. `n name, p path` after `Y`
+pub fn continuations(keys: &str) -> String {
+    match keys {
+        "Q" => return "a-z register to record into".to_string(),
+        "@" => return "a-z register to replay, @ the last one".to_string(),
+        _ => {}
+    }
+    CHORDS
+        .iter()
+        .filter_map(|(chord_keys, _, description)| {
+            let rest = chord_keys
+                .strip_prefix(keys)
+                .filter(|rest| !rest.is_empty())?;
+            Some(format!("{} {}", rest, description))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,392 @@
+use crate::{
+    app::SplitDirection,
+    command::{parse_command, CommandAction},
+    ipc::{send, target_socket},
+    jobs::{plan_filing, FilingRule, Journal, JournalOperation},
+    opener::posix_quote,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Exit codes of the scripted modes (`--pick`, `--script`, `--headless`)
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_CANCELLED: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Clone, PartialEq, Subcommand)]
+pub enum StartupCommand {
+    #[command(about = "Print a shell function that runs termfm and changes to its last directory")]
+    Init {
+        #[arg(value_enum)]
+        shell: Shell,
+        #[arg(long, default_value = "tfm", help = "Name of the function")]
+        name: String,
+    },
+}
+
+#[derive(Default, Parser)]
+#[command(name = "termfm", version, about = "A terminal file manager")]
+pub struct StartupOptions {
+    #[command(subcommand)]
+    pub command: Option<StartupCommand>,
+    // Same as --left, or --select when it names a file
+    #[arg(
+        value_name = "PATH",
+        conflicts_with = "left",
+        help = "Directory to start in, or a file to start next to with it highlighted"
+    )]
+    pub dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Remember the last directory in FILE and start there"
+    )]
+    pub cwd_file: Option<PathBuf>,
+    // Written on exit with what `:choose` chose, emptied on a plain quit
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["pick", "choose_files"],
+        help = "Write the paths chosen with `zq` (`:choose`) to FILE on exit"
+    )]
+    pub chooser_file: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Read opener.toml from FILE instead of ./src/opener.toml"
+    )]
+    pub config: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["dir", "left"],
+        help = "Start in the directory containing PATH with it highlighted"
+    )]
+    pub select: Option<PathBuf>,
+    #[arg(long, value_enum, help = "Show two panes side by side or stacked")]
+    pub split: Option<SplitDirection>,
+    #[arg(long, value_name = "DIR", help = "Directory of the first pane")]
+    pub left: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Open a second pane in DIR (implies --split vertical)"
+    )]
+    pub right: Option<PathBuf>,
+    #[arg(long, help = "Show hidden files from the start")]
+    pub show_hidden: bool,
+    // Print the chosen files instead of opening them
+    #[arg(
+        long,
+        help = "Print the chosen files to stdout and exit instead of opening them"
+    )]
+    pub pick: bool,
+    // `--pick` writing to a file instead, `-` is stdout
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Like --pick, but write the chosen files to FILE (`-` for stdout)"
+    )]
+    pub choose_files: Option<PathBuf>,
+    // Run `:` commands from a file (`-` for stdin) without the TUI
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["pick", "choose_files"],
+        help = "Run `:` commands from FILE (`-` for stdin) without the interface"
+    )]
+    pub script: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with_all = ["pick", "choose_files"],
+        help = "Run `:` commands from stdin without the interface"
+    )]
+    pub headless: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        help = "Format of results and errors in the scripted modes"
+    )]
+    pub output: OutputFormat,
+    // Guided walkthrough in a throwaway directory
+    #[arg(
+        long,
+        conflicts_with_all = ["pick", "choose_files", "script", "headless"],
+        help = "Start the guided tutorial in a sandbox directory"
+    )]
+    pub tutorial: bool,
+    // Listing server for the other instances, see `daemon`
+    #[arg(
+        long,
+        conflicts_with_all = ["pick", "choose_files", "script", "headless", "tutorial"],
+        help = "Keep directory listings in memory for other instances until killed"
+    )]
+    pub daemon: bool,
+    // Sent to a running instance, see `ipc`
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with_all = ["pick", "choose_files", "script", "headless", "tutorial", "daemon"],
+        help = "Run a `:` command in the running instance (`cwd` and `selection` print them)"
+    )]
+    pub remote: Option<String>,
+}
+
+pub fn parse_args<I, T>(args: I) -> Result<StartupOptions, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut options = StartupOptions::try_parse_from(args)?;
+    // `termfm some/file.txt` is what other tools run to reveal a file
+    if let Some(dir) = options.dir.take() {
+        if fs::metadata(&dir).is_ok_and(|meta| !meta.is_dir()) {
+            options.select = Some(dir);
+        } else {
+            options.left = Some(dir);
+        }
+    }
+    // A second directory implies a split
+    if options.right.is_some() && options.split.is_none() {
+        options.split = Some(SplitDirection::Vertical);
+    }
+    if options.choose_files.is_some() {
+        options.pick = true;
+    }
+    // A script never runs inside the TUI
+    if options.script.is_some() {
+        options.headless = true;
+    }
+    Ok(options)
+}
+
+// The function behind `termfm init`: runs the file manager with a cwd file in
+// a fresh temporary directory, so it starts where the shell is, and changes
+// to the directory it was left in. `--config` is passed on.
+pub fn shell_init(shell: Shell, name: &str, config: Option<&Path>) -> String {
+    let config = config
+        .map(|path| format!(" --config {}", posix_quote(&path.to_string_lossy())))
+        .unwrap_or_default();
+    match shell {
+        Shell::Bash | Shell::Zsh => format!(
+            "{name}() {{\n\
+             \x20   local tmp dir\n\
+             \x20   tmp=\"$(mktemp -d)\" || return\n\
+             \x20   command termfm{config} --cwd-file=\"$tmp/cwd\" \"$@\"\n\
+             \x20   dir=\"$(cat -- \"$tmp/cwd\" 2>/dev/null)\"\n\
+             \x20   rm -rf -- \"$tmp\"\n\
+             \x20   if [ -n \"$dir\" ] && [ \"$dir\" != \"$PWD\" ]; then\n\
+             \x20       cd -- \"$dir\"\n\
+             \x20   fi\n\
+             }}\n"
+        ),
+        Shell::Fish => format!(
+            "function {name}\n\
+             \x20   set -l tmp (mktemp -d); or return\n\
+             \x20   command termfm{config} --cwd-file=\"$tmp/cwd\" $argv\n\
+             \x20   set -l dir (cat -- \"$tmp/cwd\" 2>/dev/null)\n\
+             \x20   rm -rf -- \"$tmp\"\n\
+             \x20   if test -n \"$dir\"; and test \"$dir\" != \"$PWD\"\n\
+             \x20       cd -- \"$dir\"\n\
+             \x20   end\n\
+             end\n"
+        ),
+    }
+}
+
+// Looked up on its own so that even invalid arguments are reported in JSON
+pub fn requested_output(args: &[String]) -> OutputFormat {
+    let json = args.iter().any(|arg| arg == "--output=json")
+        || args
+            .windows(2)
+            .any(|w| w[0] == "--output" && w[1] == "json");
+    if json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    }
+}
+
+// Reports a startup failure in the requested format and exits
+pub fn fail(output: OutputFormat, message: &str) -> ! {
+    match output {
+        OutputFormat::Text => eprintln!("{}", message),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::json!({ "status": "error", "error": message })
+        ),
+    }
+    std::process::exit(EXIT_ERROR);
+}
+
+// Writes the outcome of `--pick`: one path per line, or a JSON object
+pub fn print_picked(
+    out: &mut impl Write,
+    output: OutputFormat,
+    picked: Option<&[PathBuf]>,
+) -> io::Result<()> {
+    match output {
+        OutputFormat::Text => {
+            for path in picked.unwrap_or_default() {
+                writeln!(out, "{}", path.display())?;
+            }
+        }
+        OutputFormat::Json => {
+            let selected: Vec<String> = picked
+                .unwrap_or_default()
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            let status = if picked.is_some() {
+                "success"
+            } else {
+                "cancelled"
+            };
+            writeln!(
+                out,
+                "{}",
+                serde_json::json!({ "status": status, "selected": selected })
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Sends `command` to the instance of `$TERMFM_SOCKET` or the one started
+// last, prints its answer and returns the exit code
+pub fn run_remote(command: &str, output: OutputFormat) -> i32 {
+    let Some(socket) = target_socket() else {
+        fail(output, "No running instance found");
+    };
+    let reply = match send(&socket, command) {
+        Ok(reply) => reply,
+        Err(e) => fail(
+            output,
+            &format!("Failed to reach {}: {}", socket.display(), e),
+        ),
+    };
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::json!(reply)),
+        OutputFormat::Text if reply.status != "ok" => eprintln!("{}", reply.message),
+        OutputFormat::Text => match command.trim().trim_start_matches(':') {
+            "cwd" => println!("{}", reply.cwd.display()),
+            "selection" => {
+                for path in &reply.selection {
+                    println!("{}", path.display());
+                }
+            }
+            _ if reply.message.is_empty() => {}
+            _ => println!("{}", reply.message),
+        },
+    }
+    if reply.status == "ok" {
+        EXIT_SUCCESS
+    } else {
+        EXIT_ERROR
+    }
+}
+
+// Runs `:` commands without a terminal. Only commands that need neither a
+// selection nor the screen are available. Stops at the first failure and
+// returns the exit code.
+pub fn run_headless(
+    script: &str,
+    mut dir: PathBuf,
+    filing_rules: &[FilingRule],
+    mut show_hidden: bool,
+    output: OutputFormat,
+) -> i32 {
+    let mut results = Vec::new();
+    let mut failed = false;
+    for line in script.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let command = line.strip_prefix(':').unwrap_or(line);
+        let result = match parse_command(command, &dir) {
+            Ok(CommandAction::Cd(path)) => match fs::canonicalize(&path) {
+                Ok(target) if target.is_dir() => {
+                    dir = target;
+                    Ok(format!("Changed directory to {}", dir.display()))
+                }
+                _ => Err(format!("Not a directory: {}", path.display())),
+            },
+            Ok(CommandAction::Mkdir(name)) => fs::create_dir_all(dir.join(&name))
+                .map(|_| format!("Created {}", name))
+                .map_err(|e| format!("Failed to create {}: {}", name, e)),
+            Ok(CommandAction::Organize) => plan_filing(&dir, filing_rules, show_hidden)
+                .map_err(|e| format!("Failed to read directory: {}", e))
+                .and_then(|plan| {
+                    let mut journal = Journal::begin(JournalOperation::Moves(plan));
+                    let (moved, errors) = journal.apply_moves();
+                    journal.finish();
+                    if errors.is_empty() {
+                        Ok(format!("Filed {} file(s)", moved))
+                    } else {
+                        Err(errors.join("; "))
+                    }
+                }),
+            Ok(CommandAction::SetHidden(value)) => {
+                show_hidden = value.unwrap_or(!show_hidden);
+                Ok(format!(
+                    "Hidden files {}",
+                    if show_hidden { "shown" } else { "hidden" }
+                ))
+            }
+            Ok(CommandAction::Quit) => break,
+            Ok(_) => Err(format!("Not available in headless mode: {}", command)),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = &result {
+            eprintln!("{}: {}", command, e);
+            failed = true;
+        } else if output == OutputFormat::Text {
+            println!("{}", result.as_ref().unwrap());
+        }
+        results.push(match result {
+            Ok(message) => {
+                serde_json::json!({ "command": command, "status": "ok", "message": message })
+            }
+            Err(e) => serde_json::json!({ "command": command, "status": "error", "message": e }),
+        });
+        if failed {
+            break;
+        }
+    }
+
+    if output == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "status": if failed { "error" } else { "success" },
+                "cwd": dir.to_string_lossy(),
+                "results": results,
+            })
+        );
+    }
+    if failed {
+        EXIT_ERROR
+    } else {
+        EXIT_SUCCESS
+    }
+}
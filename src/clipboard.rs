@@ -0,0 +1,181 @@
+// Copies text to the system clipboard: through the terminal with OSC 52, which
+// also works over SSH, or through whichever clipboard tool is installed
+use crate::config::Programs;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+// Tried in order, the first one found is used. The last field is the option
+// that sets the MIME type, for tools that have one.
+const TOOLS: [(&str, &[&str], Option<&str>); 4] = [
+    ("wl-copy", &[], Some("--type")),
+    ("xclip", &["-selection", "clipboard"], Some("-t")),
+    ("xsel", &["--clipboard", "--input"], None),
+    ("pbcopy", &[], None),
+];
+
+// What `Y` and `:copy` put on the clipboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyTarget {
+    Name,
+    Path,
+    Directory,
+    // The files themselves, as a `text/uri-list` file managers can paste
+    Files,
+}
+
+impl CopyTarget {
+    pub const NAMES: [&str; 4] = ["name", "path", "dir", "files"];
+
+    pub fn parse(argument: &str) -> Option<Self> {
+        match argument {
+            "name" => Some(CopyTarget::Name),
+            "path" => Some(CopyTarget::Path),
+            "dir" | "directory" => Some(CopyTarget::Directory),
+            "files" => Some(CopyTarget::Files),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyTarget::Name => "name",
+            CopyTarget::Path => "path",
+            CopyTarget::Directory => "directory",
+            CopyTarget::Files => "file",
+        }
+    }
+
+    // Copies `paths` and returns how
+    pub fn copy(self, paths: &[PathBuf], programs: &Programs) -> Result<&'static str, String> {
+        let lines: Vec<String> = match self {
+            CopyTarget::Files => return copy_as(&uri_list(paths), Some("text/uri-list"), programs),
+            CopyTarget::Name => paths
+                .iter()
+                .map(|path| {
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect(),
+            CopyTarget::Path => paths.iter().map(|p| p.display().to_string()).collect(),
+            CopyTarget::Directory => {
+                let mut dirs: Vec<String> = paths
+                    .iter()
+                    .filter_map(|path| path.parent())
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                dirs.dedup();
+                dirs
+            }
+        };
+        copy(&lines.join("\n"), programs)
+    }
+}
+
+pub fn copy(text: &str, programs: &Programs) -> Result<&'static str, String> {
+    copy_as(text, None, programs)
+}
+
+// Over SSH the clipboard that matters is the one of the local terminal, so
+// OSC 52 goes first there; elsewhere the tools are more reliable, as not every
+// terminal supports it. Typed content such as a URI list needs a tool.
+pub fn copy_as(
+    text: &str,
+    mime: Option<&str>,
+    programs: &Programs,
+) -> Result<&'static str, String> {
+    let remote = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+    if remote && mime.is_none() && write_osc52(text).is_ok() {
+        return Ok("OSC 52");
+    }
+    match copy_with_tool(text, mime, programs) {
+        Ok(tool) => Ok(tool),
+        Err(Some(e)) => Err(e),
+        Err(None) if mime.is_some() => Err("Copying files needs wl-copy or xclip".to_string()),
+        Err(None) => write_osc52(text)
+            .map(|()| "OSC 52")
+            .map_err(|_| "No clipboard found, install wl-copy, xclip or xsel".to_string()),
+    }
+}
+
+// Err(None) when no suitable tool is installed
+fn copy_with_tool(
+    text: &str,
+    mime: Option<&str>,
+    programs: &Programs,
+) -> Result<&'static str, Option<String>> {
+    for (program, args, mime_option) in TOOLS {
+        let mut command = programs.command(program);
+        command.args(args);
+        match (mime, mime_option) {
+            (Some(mime), Some(option)) => {
+                command.args([option, mime]);
+            }
+            (Some(_), None) => continue,
+            _ => {}
+        }
+        let child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Some(format!("Failed to run {}: {}", program, e))),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| Some(format!("Failed to write to {}: {}", program, e)))?;
+        }
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(program),
+            Ok(_) => Err(Some(format!("{} could not reach the clipboard", program))),
+            Err(e) => Err(Some(format!("Failed to run {}: {}", program, e))),
+        };
+    }
+    Err(None)
+}
+
+// The escape sequence asking the terminal to set its clipboard
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+fn write_osc52(text: &str) -> io::Result<()> {
+    // Without a terminal the sequence would end up in a pipe
+    let mut output: Box<dyn Write> = if io::stdout().is_terminal() {
+        Box::new(io::stdout())
+    } else {
+        Box::new(fs::OpenOptions::new().write(true).open("/dev/tty")?)
+    };
+    output.write_all(osc52(text).as_bytes())?;
+    output.flush()
+}
+
+// `file://` URIs, one per line as text/uri-list wants them
+pub fn uri_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("{}\r\n", file_uri(path)))
+        .collect()
+}
+
+fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    for byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(*byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,381 @@
+use crate::{
+    config::Programs,
+    fs_ops::{format_size, format_time, group_name, mode, owner_ids, user_name},
+    opener::{shell_command, shell_quote},
+    ui::{Color as TuiColor, Modifier, Span, Style},
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A column of the detailed view. Built-in columns implement this trait and
+// plugins can register their own through `ColumnRegistry::register`.
+pub trait ColumnProvider {
+    fn id(&self) -> &str;
+
+    fn width(&self) -> usize;
+
+    // Called once per directory listing so expensive lookups can be batched
+    fn refresh(&mut self, _dir: &Path) {}
+
+    fn render(&mut self, path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>>;
+}
+
+pub struct PermissionsColumn;
+
+impl ColumnProvider for PermissionsColumn {
+    fn id(&self) -> &str {
+        "permissions"
+    }
+
+    fn width(&self) -> usize {
+        15
+    }
+
+    fn render(&mut self, _path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        permission_spans(metadata)
+    }
+}
+
+pub struct SizeColumn;
+
+impl ColumnProvider for SizeColumn {
+    fn id(&self) -> &str {
+        "size"
+    }
+
+    fn width(&self) -> usize {
+        7
+    }
+
+    fn render(&mut self, _path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        vec![Span::raw(format!("{:>7}", format_size(metadata.len())))]
+    }
+}
+
+pub struct MtimeColumn;
+
+impl ColumnProvider for MtimeColumn {
+    fn id(&self) -> &str {
+        "mtime"
+    }
+
+    fn width(&self) -> usize {
+        16
+    }
+
+    fn render(&mut self, _path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        vec![Span::raw(format_time(metadata.modified().ok()))]
+    }
+}
+
+#[derive(Default)]
+pub struct OwnerColumn {
+    pub names: HashMap<(u32, u32), String>,
+}
+
+impl ColumnProvider for OwnerColumn {
+    fn id(&self) -> &str {
+        "owner"
+    }
+
+    fn width(&self) -> usize {
+        16
+    }
+
+    fn render(&mut self, _path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        let Some((uid, gid)) = owner_ids(metadata) else {
+            return vec![Span::raw(format!("{:<16}", "-"))];
+        };
+        let owner = self
+            .names
+            .entry((uid, gid))
+            .or_insert_with(|| format!("{}:{}", user_name(uid), group_name(gid)));
+        vec![Span::raw(format!("{:<16.16}", owner))]
+    }
+}
+
+#[derive(Default)]
+pub struct GitStatusColumn {
+    pub statuses: HashMap<PathBuf, String>,
+    pub programs: Programs,
+}
+
+impl ColumnProvider for GitStatusColumn {
+    fn id(&self) -> &str {
+        "git"
+    }
+
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn refresh(&mut self, dir: &Path) {
+        self.statuses.clear();
+        let root = match self
+            .programs
+            .command("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+            }
+            _ => return,
+        };
+        let Ok(output) = self
+            .programs
+            .command("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["status", "--porcelain", "--", "."])
+            .output()
+        else {
+            return;
+        };
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if line.len() < 4 {
+                continue;
+            }
+            let (status, file) = line.split_at(3);
+            // Renames are reported as `old -> new`
+            let file = file.rsplit(" -> ").next().unwrap_or(file);
+            self.statuses.insert(
+                root.join(file.trim_end_matches('/')),
+                status.trim_end().to_string(),
+            );
+        }
+    }
+
+    fn render(&mut self, path: &Path, _metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        let status = match self.statuses.get(path) {
+            Some(status) => status.clone(),
+            // Directories with changes somewhere below them
+            None if self
+                .statuses
+                .keys()
+                .any(|changed| changed.starts_with(path)) =>
+            {
+                " ~".to_string()
+            }
+            None => "  ".to_string(),
+        };
+        let color = match status.trim() {
+            "??" => TuiColor::Red,
+            "" => TuiColor::White,
+            s if s.starts_with('M') || s.starts_with('A') => TuiColor::Green,
+            _ => TuiColor::Yellow,
+        };
+        vec![Span::styled(
+            format!("{:>2}", status),
+            Style::default().fg(color),
+        )]
+    }
+}
+
+// Output of a user command, e.g. `wc -l < %f`, cached per file modification time
+pub struct ScriptColumn {
+    pub id: String,
+    pub command: String,
+    pub width: usize,
+    pub cache: HashMap<PathBuf, (Option<std::time::SystemTime>, String)>,
+}
+
+impl ColumnProvider for ScriptColumn {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn render(&mut self, path: &Path, metadata: &fs::Metadata) -> Vec<Span<'static>> {
+        let modified = metadata.modified().ok();
+        let cached = self
+            .cache
+            .get(path)
+            .filter(|(time, _)| *time == modified)
+            .map(|(_, value)| value.clone());
+        let value = cached.unwrap_or_else(|| {
+            let command = self
+                .command
+                .replace("%f", &shell_quote(&path.to_string_lossy()));
+            let value = shell_command(&command)
+                .output()
+                .map(|output| {
+                    String::from_utf8_lossy(&output.stdout)
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                })
+                .unwrap_or_default();
+            self.cache
+                .insert(path.to_path_buf(), (modified, value.clone()));
+            value
+        });
+        vec![Span::raw(format!(
+            "{:>width$.width$}",
+            value,
+            width = self.width
+        ))]
+    }
+}
+
+pub struct ColumnRegistry {
+    pub providers: Vec<Box<dyn ColumnProvider>>,
+    // Ids of the columns shown, in display order
+    pub enabled: Vec<String>,
+}
+
+impl ColumnRegistry {
+    pub const DEFAULT_COLUMNS: [&'static str; 3] = ["permissions", "size", "mtime"];
+
+    pub fn from_config(config_path: &Path, programs: &Programs) -> Self {
+        let mut registry = Self {
+            providers: Vec::new(),
+            enabled: Self::DEFAULT_COLUMNS
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        };
+        registry.register(Box::new(PermissionsColumn));
+        registry.register(Box::new(SizeColumn));
+        registry.register(Box::new(MtimeColumn));
+        registry.register(Box::new(OwnerColumn::default()));
+        registry.register(Box::new(GitStatusColumn {
+            programs: programs.clone(),
+            ..GitStatusColumn::default()
+        }));
+
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("detailed_view").cloned());
+        let Some(section) = section else {
+            return registry;
+        };
+
+        if let Some(custom) = section.get("custom").and_then(|v| v.as_table()) {
+            for (id, column) in custom {
+                let Some(command) = column.get("command").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let width = column
+                    .get("width")
+                    .and_then(|v| v.as_integer())
+                    .unwrap_or(8)
+                    .max(1) as usize;
+                registry.register(Box::new(ScriptColumn {
+                    id: id.clone(),
+                    command: command.to_string(),
+                    width,
+                    cache: HashMap::new(),
+                }));
+            }
+        }
+        if let Some(columns) = section.get("columns").and_then(|v| v.as_array()) {
+            registry.enabled = columns
+                .iter()
+                .filter_map(|c| c.as_str())
+                .map(|c| c.to_string())
+                .collect();
+        }
+        registry
+    }
+
+    // Later registrations replace built-in columns with the same id
+    pub fn register(&mut self, provider: Box<dyn ColumnProvider>) {
+        self.providers.retain(|p| p.id() != provider.id());
+        self.providers.push(provider);
+    }
+
+    pub fn refresh(&mut self, dir: &Path) {
+        for provider in &mut self.providers {
+            if self.enabled.iter().any(|id| id == provider.id()) {
+                provider.refresh(dir);
+            }
+        }
+    }
+
+    pub fn render(&mut self, path: &Path, metadata: Option<&fs::Metadata>) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for id in &self.enabled {
+            let Some(provider) = self.providers.iter_mut().find(|p| p.id() == id) else {
+                continue;
+            };
+            match metadata {
+                Some(metadata) => spans.extend(provider.render(path, metadata)),
+                None => spans.push(Span::raw(format!("{:<1$}", "?", provider.width()))),
+            }
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::raw(" "));
+        spans
+    }
+}
+
+// Renders an `ls -l` style permission string followed by its octal value.
+// Setuid/setgid bits and world-writable entries are highlighted as unsafe.
+pub fn permission_spans(metadata: &fs::Metadata) -> Vec<Span<'static>> {
+    let mode = mode(metadata);
+    let file_type = if metadata.file_type().is_symlink() {
+        "l"
+    } else if metadata.is_dir() {
+        "d"
+    } else {
+        "-"
+    };
+    let warning = Style::default()
+        .fg(TuiColor::White)
+        .bg(TuiColor::Red)
+        .add_modifier(Modifier::BOLD);
+
+    let mut spans = vec![Span::styled(file_type, Style::default().fg(TuiColor::Blue))];
+    // (shift, class color, special bit, special char when executable)
+    let classes = [
+        (6, TuiColor::Green, 0o4000, 's'),
+        (3, TuiColor::Yellow, 0o2000, 's'),
+        (0, TuiColor::Magenta, 0o1000, 't'),
+    ];
+    for (shift, color, special_bit, special_char) in classes {
+        let bits = (mode >> shift) & 0o7;
+        let class_style = Style::default().fg(color);
+        let read = if bits & 0o4 != 0 { "r" } else { "-" };
+        let write = if bits & 0o2 != 0 { "w" } else { "-" };
+        spans.push(Span::styled(read, class_style));
+        // Anyone may write: make it stand out
+        if shift == 0 && bits & 0o2 != 0 {
+            spans.push(Span::styled(write, warning));
+        } else {
+            spans.push(Span::styled(write, class_style));
+        }
+        if mode & special_bit != 0 {
+            let exec = if bits & 0o1 != 0 {
+                special_char
+            } else {
+                special_char.to_ascii_uppercase()
+            };
+            // The sticky bit is harmless, setuid/setgid are not
+            let style = if special_char == 't' {
+                class_style.add_modifier(Modifier::BOLD)
+            } else {
+                warning
+            };
+            spans.push(Span::styled(exec.to_string(), style));
+        } else {
+            let exec = if bits & 0o1 != 0 { "x" } else { "-" };
+            spans.push(Span::styled(exec, class_style));
+        }
+    }
+    spans.push(Span::styled(
+        format!(" {:04o}", mode & 0o7777),
+        Style::default().fg(TuiColor::DarkGray),
+    ));
+    spans
+}
@@ -0,0 +1,414 @@
+use crate::{
+    checksum::Algorithm,
+    clipboard::CopyTarget,
+    config::Opener,
+    find::{Query, SearchAction},
+    fs_ops::{Conversion, LinkKind, SortMode},
+    git::GitAction,
+    register::RegisterOperation,
+    select::NamePattern,
+    vfs::Location,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Single-line input rendered in place of the status bar
+pub struct Prompt {
+    pub prefix: char,
+    pub input: String,
+    // Full-line candidates cycled through with Tab
+    pub completions: Vec<String>,
+    pub completion_index: usize,
+    pub history_index: Option<usize>,
+}
+
+impl Prompt {
+    pub fn new(prefix: char) -> Self {
+        Self {
+            prefix,
+            input: String::new(),
+            completions: Vec::new(),
+            completion_index: 0,
+            history_index: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CommandHistory {
+    pub entries: Vec<String>,
+}
+
+impl CommandHistory {
+    pub const MAX_ENTRIES: usize = 500;
+
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".termfm_history"))
+    }
+
+    pub fn load() -> Self {
+        let entries = Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(|line| line.to_string()).collect())
+            .unwrap_or_default();
+        Self { entries }
+    }
+
+    pub fn push(&mut self, command: &str) {
+        if command.is_empty() || self.entries.last().map(|c| c.as_str()) == Some(command) {
+            return;
+        }
+        self.entries.push(command.to_string());
+        if self.entries.len() > Self::MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        if let Some(path) = Self::path() {
+            let _ = fs::write(path, self.entries.join("\n") + "\n");
+        }
+    }
+}
+
+pub const COMMANDS: [&str; 38] = [
+    "alink", "cache", "cd", "checksum", "choose", "convert", "copy", "delete", "diff", "find",
+    "flatten", "get", "git", "link", "merge", "messages", "mkdir", "mounts", "note", "open",
+    "organize", "put", "q", "quit", "rename", "reveal", "rlink", "search", "select", "select!",
+    "set", "sort", "sync", "tag", "tagged", "todo", "unnote", "untag",
+];
+pub const SET_OPTIONS: [&str; 7] = [
+    "hidden",
+    "nohidden",
+    "hidden!",
+    "detailed!",
+    "notes!",
+    "markdown!",
+    "preview!",
+];
+
+#[derive(Clone)]
+pub enum CommandAction {
+    Messages,
+    CacheStats,
+    // Block devices and their mount points
+    Mounts,
+    Cd(PathBuf),
+    // List a remote directory in place of the current one
+    Remote(Location),
+    // Copy the marked remote entries or the selection to the local pane, or
+    // the local ones to the remote pane
+    Download,
+    Upload,
+    Mkdir(String),
+    Rename(String),
+    Delete,
+    // Merge the selected directory into the given one, or the other pane
+    Merge(Option<PathBuf>),
+    // Make the given directory, or the other pane's, a copy of this one
+    Sync(Option<PathBuf>),
+    // Preview where the filing rules would move the files of this directory
+    Organize,
+    Convert(Conversion),
+    Sort(SortMode),
+    // Some(value) sets the option, None toggles it
+    SetHidden(Option<bool>),
+    ToggleDetailed,
+    // Show notes after the names in the listing, or stop
+    ToggleNotes,
+    // Preview Markdown as source instead of rendered, or back
+    ToggleMarkdown,
+    // Hide or show the preview column, or make it wider by the percentage
+    TogglePreview,
+    ResizePreview(i32),
+    // A `[commands]` entry of the config, by name
+    Custom(String),
+    // A command of `init.rhai`, with the arguments it was given
+    Script(String),
+    // Show the given directory in the desktop file manager, or the selection
+    // in its directory
+    Reveal(Option<PathBuf>),
+    // Pick an opener from a menu, or use the given one
+    OpenWithMenu,
+    OpenWith(Opener),
+    // A command line typed at the `!` prompt
+    Shell(String),
+    Git(GitAction),
+    // Hash the marked files or the selection, or check the selected checksum file
+    Checksum(Algorithm),
+    VerifyChecksums,
+    // Compare the two marked entries, or the marked one with the selection
+    Diff,
+    // Put names, paths or the files of the marked entries or the selection on
+    // the system clipboard
+    Copy(CopyTarget),
+    // Put the marked entries or the selection in the shared yank register
+    Yank(RegisterOperation),
+    // Copy or move what the register holds into the current directory
+    Paste,
+    // Tag the marked entries or the selection, or take one or all tags off
+    Tag(String),
+    Untag(Option<String>),
+    // Show only the entries with the tag, or list the tags in use
+    Tagged(Option<String>),
+    // Note on the selection; None edits the current one
+    Note(Option<String>),
+    Unnote,
+    // Search the current directory's tree and list the results
+    Find(String),
+    // List every file below the current directory, or go back
+    Flatten,
+    // Mark the entries whose names match, or with `invert` the ones that don't
+    Select {
+        pattern: String,
+        invert: bool,
+    },
+    // Link the marked entries or the selection into the other pane's directory,
+    // or the current one; None asks for the name of a single link
+    Link {
+        kind: LinkKind,
+        name: Option<String>,
+    },
+    Search(SearchAction),
+    // Move the completed to-dos into the archive, or show what is in it
+    ArchiveTodos,
+    ArchivedTodos,
+    Quit,
+    // Quit with the marked entries or the selection, files or directories, as
+    // the choice for `--chooser-file` and the pickers
+    Choose,
+}
+
+pub fn parse_command(input: &str, current_dir: &Path) -> Result<CommandAction, String> {
+    let input = input.trim();
+    let (command, argument) = match input.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (input, ""),
+    };
+    let require_argument = |usage: &str| {
+        if argument.is_empty() {
+            Err(format!("Usage: :{}", usage))
+        } else {
+            Ok(argument.to_string())
+        }
+    };
+
+    match command {
+        "messages" | "mes" => Ok(CommandAction::Messages),
+        "mounts" => Ok(CommandAction::Mounts),
+        "cache" => match argument {
+            "stats" => Ok(CommandAction::CacheStats),
+            _ => Err("Usage: :cache stats".to_string()),
+        },
+        "cd" if Location::is_url(argument) => Location::parse(argument).map(CommandAction::Remote),
+        "get" => Ok(CommandAction::Download),
+        "put" => Ok(CommandAction::Upload),
+        "cd" => {
+            let target = if argument.is_empty() { "~" } else { argument };
+            Ok(CommandAction::Cd(expand_path(target, current_dir)))
+        }
+        "mkdir" => require_argument("mkdir <name>").map(CommandAction::Mkdir),
+        "rename" => require_argument("rename <new name>").map(CommandAction::Rename),
+        "delete" => Ok(CommandAction::Delete),
+        "organize" => Ok(CommandAction::Organize),
+        "diff" => Ok(CommandAction::Diff),
+        "copy" => CopyTarget::parse(argument)
+            .map(CommandAction::Copy)
+            .ok_or_else(|| "Usage: :copy name|path|dir|files".to_string()),
+        "sync" if argument.is_empty() => Ok(CommandAction::Sync(None)),
+        "sync" => Ok(CommandAction::Sync(Some(expand_path(
+            argument,
+            current_dir,
+        )))),
+        "merge" if argument.is_empty() => Ok(CommandAction::Merge(None)),
+        "merge" => Ok(CommandAction::Merge(Some(expand_path(
+            argument,
+            current_dir,
+        )))),
+        "reveal" if argument.is_empty() => Ok(CommandAction::Reveal(None)),
+        "reveal" => Ok(CommandAction::Reveal(Some(expand_path(
+            argument,
+            current_dir,
+        )))),
+        "open" if argument.is_empty() => Ok(CommandAction::OpenWithMenu),
+        // Ad-hoc commands may need the terminal, so they are run in the foreground
+        "open" => Ok(CommandAction::OpenWith(Opener::new(
+            argument.to_string(),
+            true,
+        ))),
+        "convert" => match argument {
+            "lf" | "unix" => Ok(CommandAction::Convert(Conversion::Lf)),
+            "crlf" | "dos" => Ok(CommandAction::Convert(Conversion::Crlf)),
+            "nobom" => Ok(CommandAction::Convert(Conversion::StripBom)),
+            _ => Err("Usage: :convert lf|crlf|nobom".to_string()),
+        },
+        "sort" => SortMode::parse(argument)
+            .map(CommandAction::Sort)
+            .ok_or_else(|| "Usage: :sort name|size|mtime".to_string()),
+        "git" => GitAction::parse(argument).map(CommandAction::Git),
+        "checksum" => match argument {
+            "" => Ok(CommandAction::Checksum(Algorithm::Sha256)),
+            "verify" | "check" => Ok(CommandAction::VerifyChecksums),
+            other => Algorithm::parse(other)
+                .map(CommandAction::Checksum)
+                .ok_or_else(|| "Usage: :checksum [md5|sha1|sha256|verify]".to_string()),
+        },
+        "set" => match argument {
+            "hidden" => Ok(CommandAction::SetHidden(Some(true))),
+            "nohidden" => Ok(CommandAction::SetHidden(Some(false))),
+            "hidden!" => Ok(CommandAction::SetHidden(None)),
+            "detailed!" => Ok(CommandAction::ToggleDetailed),
+            "notes!" => Ok(CommandAction::ToggleNotes),
+            "markdown!" => Ok(CommandAction::ToggleMarkdown),
+            "preview!" => Ok(CommandAction::TogglePreview),
+            "" => Err("Usage: :set <option>".to_string()),
+            other => Err(format!("Unknown option: {}", other)),
+        },
+        "tag" => require_argument("tag <name>").map(CommandAction::Tag),
+        "untag" if argument.is_empty() => Ok(CommandAction::Untag(None)),
+        "untag" => Ok(CommandAction::Untag(Some(argument.to_string()))),
+        "tagged" if argument.is_empty() => Ok(CommandAction::Tagged(None)),
+        "tagged" => Ok(CommandAction::Tagged(Some(argument.to_string()))),
+        "note" if argument.is_empty() => Ok(CommandAction::Note(None)),
+        "note" => Ok(CommandAction::Note(Some(argument.to_string()))),
+        "unnote" => Ok(CommandAction::Unnote),
+        "search" => SearchAction::parse(argument).map(CommandAction::Search),
+        "todo" => match argument {
+            "archive" => Ok(CommandAction::ArchiveTodos),
+            "archived" => Ok(CommandAction::ArchivedTodos),
+            _ => Err("Usage: :todo archive|archived".to_string()),
+        },
+        "flatten" | "flat" => Ok(CommandAction::Flatten),
+        "select" | "select!" if argument.is_empty() => {
+            Err(format!("Usage: :{} <glob>|/<regex>/", command))
+        }
+        "select" | "select!" => NamePattern::parse(argument).map(|_| CommandAction::Select {
+            pattern: argument.to_string(),
+            invert: command == "select!",
+        }),
+        "link" | "alink" | "rlink" => Ok(CommandAction::Link {
+            kind: LinkKind::parse(command).unwrap(),
+            name: Some(argument.to_string()).filter(|name| !name.is_empty()),
+        }),
+        "find" if argument.is_empty() => Err("Usage: :find <query>".to_string()),
+        "find" => Query::parse(argument).map(|_| CommandAction::Find(argument.to_string())),
+        "q" | "quit" => Ok(CommandAction::Quit),
+        "choose" => Ok(CommandAction::Choose),
+        "" => Err("No command given".to_string()),
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+// Resolves `~` and relative paths against the current directory
+pub fn expand_path(input: &str, current_dir: &Path) -> PathBuf {
+    if input == "~" {
+        return dirs::home_dir().unwrap_or_else(|| current_dir.to_path_buf());
+    }
+    if let Some(rest) = input.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    // A bare `D:` would be the drive's remembered directory, which we don't keep
+    if cfg!(windows) && is_drive(input) {
+        return PathBuf::from(format!("{}\\", input));
+    }
+    current_dir.join(input)
+}
+
+// `C:`, `d:`
+pub fn is_drive(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+pub fn complete_command(input: &str, current_dir: &Path) -> Vec<String> {
+    let Some((command, argument)) = input.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .filter(|c| c.starts_with(input))
+            .map(|c| format!("{} ", c))
+            .collect();
+    };
+
+    let arguments: Vec<String> = match command {
+        "sort" => ["name", "size", "mtime"]
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "convert" => ["lf", "crlf", "nobom"]
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "copy" => CopyTarget::NAMES
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "checksum" => Algorithm::NAMES
+            .iter()
+            .chain(["verify"].iter())
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "todo" => ["archive", "archived"]
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "search" => SearchAction::NAMES
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "git" => GitAction::NAMES
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "set" => SET_OPTIONS
+            .iter()
+            .filter(|a| a.starts_with(argument))
+            .map(|a| a.to_string())
+            .collect(),
+        "cd" | "mkdir" | "rename" => complete_path(argument, current_dir, command == "cd"),
+        _ => Vec::new(),
+    };
+    arguments
+        .into_iter()
+        .map(|argument| format!("{} {}", command, argument))
+        .collect()
+}
+
+pub fn complete_path(partial: &str, current_dir: &Path, dirs_only: bool) -> Vec<String> {
+    let (parent, prefix) = match partial.rfind('/') {
+        Some(index) => (&partial[..=index], &partial[index + 1..]),
+        None => ("", partial),
+    };
+    let search_dir = if parent.is_empty() {
+        current_dir.to_path_buf()
+    } else {
+        expand_path(parent, current_dir)
+    };
+    let Ok(entries) = fs::read_dir(&search_dir) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden entries only complete when explicitly asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            if dirs_only && !is_dir {
+                return None;
+            }
+            let suffix = if is_dir { "/" } else { "" };
+            Some(format!("{}{}{}", parent, name, suffix))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,546 @@
+use crate::{fs_ops::SortMode, ui::Color as TuiColor};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use toml::Value;
+
+#[derive(Clone)]
+pub struct Opener {
+    // Command template, see `expand_template` for the placeholders
+    pub command: String,
+    pub color: String,
+    // Terminal programs take over the screen until they exit,
+    // everything else runs detached
+    pub terminal: bool,
+    // Template whose output replaces the built-in preview
+    pub preview: Option<String>,
+}
+
+impl Opener {
+    // An opener that only runs a command, e.g. one typed by the user
+    pub fn new(command: String, terminal: bool) -> Self {
+        Self {
+            command,
+            color: String::new(),
+            terminal,
+            preview: None,
+        }
+    }
+}
+
+// Openers are matched by extension first, then by glob patterns on the file
+// name and finally by the MIME type detected from the file contents. Files
+// matching none of them go to the desktop's default application unless
+// `system_fallback` is turned off.
+#[derive(Clone)]
+pub struct OpenerConfig {
+    pub extensions: HashMap<String, Opener>,
+    pub patterns: Vec<(String, Opener)>,
+    pub mime_types: Vec<(String, Opener)>,
+    pub system_fallback: bool,
+    pub color_depth: ColorDepth,
+    pub programs: Programs,
+}
+
+impl Default for OpenerConfig {
+    fn default() -> Self {
+        Self {
+            extensions: HashMap::new(),
+            patterns: Vec::new(),
+            mime_types: Vec::new(),
+            system_fallback: true,
+            color_depth: ColorDepth::detect(),
+            programs: Programs::default(),
+        }
+    }
+}
+
+// Where the external programs termfm runs by name (batcat, udisksctl, curl,
+// ...) live, from the `[programs]` table. Programs not listed there are
+// looked up on PATH.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Programs {
+    paths: HashMap<String, PathBuf>,
+}
+
+impl Programs {
+    pub fn insert(&mut self, name: &str, path: PathBuf) {
+        self.paths.insert(name.to_string(), path);
+    }
+
+    // Adds the programs of `other`, which win over those listed here
+    pub fn merge(&mut self, other: Programs) {
+        self.paths.extend(other.paths);
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.paths
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(name))
+    }
+
+    pub fn command(&self, name: &str) -> Command {
+        Command::new(self.path(name))
+    }
+
+    // The same command with its program looked up here, for commands built
+    // from a template
+    pub fn resolve(&self, command: Command) -> Command {
+        let Some(path) = command
+            .get_program()
+            .to_str()
+            .and_then(|p| self.paths.get(p))
+        else {
+            return command;
+        };
+        let mut resolved = Command::new(path);
+        resolved.args(command.get_args());
+        if let Some(dir) = command.get_current_dir() {
+            resolved.current_dir(dir);
+        }
+        resolved
+    }
+
+    fn parse(table: &toml::Table) -> Self {
+        let paths = table
+            .iter()
+            .filter_map(|(name, path)| {
+                let path = path.as_str()?;
+                let path = match path.strip_prefix("~/") {
+                    Some(rest) => env::var_os("HOME")
+                        .map_or(PathBuf::from(path), |home| PathBuf::from(home).join(rest)),
+                    None => PathBuf::from(path),
+                };
+                Some((name.clone(), path))
+            })
+            .collect();
+        Self { paths }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    // Terminals announce truecolor through COLORTERM; TERM only tells 256
+    // colors apart from the basic palette
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::detect()),
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+
+    // Maps RGB colors to the nearest color the terminal can show
+    pub fn adapt(self, color: TuiColor) -> TuiColor {
+        let TuiColor::Rgb(r, g, b) = color else {
+            return color;
+        };
+        match self {
+            ColorDepth::TrueColor => color,
+            ColorDepth::Ansi256 => TuiColor::Indexed(nearest_ansi256(r, g, b)),
+            ColorDepth::Ansi16 => nearest_ansi16(r, g, b),
+        }
+    }
+}
+
+pub fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+// Picks the closer of the 6x6x6 color cube and the grayscale ramp of the
+// xterm 256 color palette
+pub fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = ((average.saturating_sub(8) + 5) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_step;
+    if color_distance((r, g, b), (gray, gray, gray)) < color_distance((r, g, b), cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
+pub fn nearest_ansi16(r: u8, g: u8, b: u8) -> TuiColor {
+    // xterm's default values of the basic palette
+    const PALETTE: [(TuiColor, (u8, u8, u8)); 16] = [
+        (TuiColor::Black, (0, 0, 0)),
+        (TuiColor::Red, (205, 0, 0)),
+        (TuiColor::Green, (0, 205, 0)),
+        (TuiColor::Yellow, (205, 205, 0)),
+        (TuiColor::Blue, (0, 0, 238)),
+        (TuiColor::Magenta, (205, 0, 205)),
+        (TuiColor::Cyan, (0, 205, 205)),
+        (TuiColor::Gray, (229, 229, 229)),
+        (TuiColor::DarkGray, (127, 127, 127)),
+        (TuiColor::LightRed, (255, 0, 0)),
+        (TuiColor::LightGreen, (0, 255, 0)),
+        (TuiColor::LightYellow, (255, 255, 0)),
+        (TuiColor::LightBlue, (92, 92, 255)),
+        (TuiColor::LightMagenta, (255, 0, 255)),
+        (TuiColor::LightCyan, (0, 255, 255)),
+        (TuiColor::White, (255, 255, 255)),
+    ];
+    PALETTE
+        .iter()
+        .min_by_key(|(_, rgb)| color_distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+impl OpenerConfig {
+    // Explorer hands a file to its default application without going
+    // through `cmd`, which would run whatever follows a `&` in the name
+    pub const SYSTEM_OPENER: &'static str = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    pub fn len(&self) -> usize {
+        self.extensions.len() + self.patterns.len() + self.mime_types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Every distinct opener, for picking one by hand
+    pub fn choices(&self) -> Vec<Opener> {
+        let mut choices: Vec<Opener> = self
+            .extensions
+            .values()
+            .chain(self.patterns.iter().map(|(_, opener)| opener))
+            .chain(self.mime_types.iter().map(|(_, opener)| opener))
+            .filter(|opener| !opener.command.is_empty())
+            .cloned()
+            .collect();
+        if self.system_fallback {
+            choices.push(Opener::new(Self::SYSTEM_OPENER.to_string(), false));
+        }
+        choices.sort_by(|a, b| (&a.command, a.terminal).cmp(&(&b.command, b.terminal)));
+        choices.dedup_by(|a, b| a.command == b.command && a.terminal == b.terminal);
+        choices
+    }
+
+    // Name based lookup, cheap enough to run for every listed file
+    pub fn lookup_name(&self, filename: &str) -> Option<&Opener> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        extension
+            .and_then(|ext| self.extensions.get(&ext))
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| glob_match(pattern, filename))
+                    .map(|(_, entry)| entry)
+            })
+    }
+
+    pub fn lookup(&self, path: &Path) -> Option<&Opener> {
+        let filename = path.file_name()?.to_string_lossy();
+        self.lookup_name(&filename).or_else(|| {
+            let mime = detect_mime(path)?;
+            self.mime_types
+                .iter()
+                .find(|(pattern, _)| glob_match(pattern, &mime))
+                .map(|(_, entry)| entry)
+        })
+    }
+}
+
+pub fn load_opener_config(config_path: &Path) -> Result<OpenerConfig, io::Error> {
+    let toml_contents = fs::read_to_string(config_path)?;
+    let value = match toml_contents.parse::<toml::Table>() {
+        Ok(table) => Value::Table(table),
+        Err(e) => {
+            eprintln!("Error parsing opener.toml: {}", e);
+            return Ok(OpenerConfig::default());
+        }
+    };
+
+    let section = |name: &str| value.as_table().map(|table| opener_section(table, name));
+    Ok(OpenerConfig {
+        extensions: section("openers").unwrap_or_default().into_iter().collect(),
+        patterns: section("patterns").unwrap_or_default(),
+        mime_types: section("mime").unwrap_or_default(),
+        system_fallback: value
+            .get("system_fallback")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true),
+        color_depth: value
+            .get("color_depth")
+            .and_then(|v| v.as_str())
+            .and_then(ColorDepth::parse)
+            .unwrap_or_else(ColorDepth::detect),
+        programs: value
+            .get("programs")
+            .and_then(|v| v.as_table())
+            .map(Programs::parse)
+            .unwrap_or_default(),
+    })
+}
+
+// Openers of one table, keyed by extension, glob pattern or MIME type
+pub fn opener_section(table: &toml::Table, name: &str) -> Vec<(String, Opener)> {
+    let Some(section) = table.get(name).and_then(|v| v.as_table()) else {
+        return Vec::new();
+    };
+    section
+        .iter()
+        .map(|(key, val)| {
+            let command = val
+                .get("opener")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let color = val
+                .get("color")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let terminal = val
+                .get("terminal")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let preview = val
+                .get("preview")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            (
+                key.to_lowercase(),
+                Opener {
+                    command,
+                    color,
+                    terminal,
+                    preview,
+                },
+            )
+        })
+        .collect()
+}
+
+// Settings from `.termfm.toml` files in a directory and its ancestors, for
+// project trees that want their own sort order, hidden files or openers. The
+// nearest file wins for every setting and its openers are tried before those
+// of files further up and of opener.toml.
+#[derive(Default)]
+pub struct DirSettings {
+    // Files found, nearest first
+    pub sources: Vec<PathBuf>,
+    pub sort: Option<SortMode>,
+    pub hidden: Option<bool>,
+    pub extensions: HashMap<String, Opener>,
+    pub patterns: Vec<(String, Opener)>,
+    pub mime_types: Vec<(String, Opener)>,
+    // Files that could not be read or parsed
+    pub errors: Vec<String>,
+}
+
+impl DirSettings {
+    pub const FILE_NAME: &str = ".termfm.toml";
+
+    pub fn discover(dir: &Path) -> Self {
+        let mut settings = Self::default();
+        for ancestor in dir.ancestors() {
+            let path = ancestor.join(Self::FILE_NAME);
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    settings
+                        .errors
+                        .push(format!("Failed to read {}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            let table = match contents.parse::<toml::Table>() {
+                Ok(table) => table,
+                Err(e) => {
+                    settings
+                        .errors
+                        .push(format!("Failed to parse {}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            settings.merge(&table, &path);
+            settings.sources.push(path);
+        }
+        settings
+    }
+
+    // Adds a file further up than the ones merged so far
+    fn merge(&mut self, table: &toml::Table, path: &Path) {
+        if self.sort.is_none() {
+            if let Some(sort) = table.get("sort").and_then(|v| v.as_str()) {
+                match SortMode::parse(sort) {
+                    Some(sort) => self.sort = Some(sort),
+                    None => self.errors.push(format!(
+                        "{}: unknown sort order {}, expected name, size or mtime",
+                        path.display(),
+                        sort
+                    )),
+                }
+            }
+        }
+        if self.hidden.is_none() {
+            self.hidden = table.get("hidden").and_then(|v| v.as_bool());
+        }
+        for (extension, opener) in opener_section(table, "openers") {
+            self.extensions.entry(extension).or_insert(opener);
+        }
+        self.patterns.extend(opener_section(table, "patterns"));
+        self.mime_types.extend(opener_section(table, "mime"));
+    }
+
+    // The global openers with these on top
+    pub fn openers(&self, global: &OpenerConfig) -> OpenerConfig {
+        let mut config = global.clone();
+        config.extensions.extend(self.extensions.clone());
+        config.patterns = self
+            .patterns
+            .iter()
+            .chain(&global.patterns)
+            .cloned()
+            .collect();
+        config.mime_types = self
+            .mime_types
+            .iter()
+            .chain(&global.mime_types)
+            .cloned()
+            .collect();
+        config
+    }
+}
+
+// Case-insensitive shell-style wildcard match supporting `*` and `?`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Sniffs the MIME type from the first bytes of the file
+pub fn detect_mime(path: &Path) -> Option<String> {
+    let mut buffer = [0u8; 8192];
+    let mut file = fs::File::open(path).ok()?;
+    let read = io::Read::read(&mut file, &mut buffer).ok()?;
+    let head = &buffer[..read];
+
+    if let Some(kind) = infer::get(head) {
+        return Some(kind.mime_type().to_string());
+    }
+    if head.starts_with(b"#!") {
+        return Some("text/x-script".to_string());
+    }
+    // A multi-byte character cut off at the end of the buffer is still text
+    match std::str::from_utf8(head) {
+        Ok(_) => Some("text/plain".to_string()),
+        Err(e) if e.error_len().is_none() => Some("text/plain".to_string()),
+        Err(_) => Some("application/octet-stream".to_string()),
+    }
+}
+
+pub fn get_file_style(filename: &str, opener_config: &Arc<OpenerConfig>) -> Option<TuiColor> {
+    let opener = opener_config.lookup_name(filename)?;
+    let color = parse_color(&opener.color).unwrap_or(TuiColor::White);
+    Some(opener_config.color_depth.adapt(color))
+}
+
+// Color names, `#rrggbb`, or `reset` for the terminal's own color
+pub fn parse_color(name: &str) -> Option<TuiColor> {
+    if let Some(hex) = name.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        return Some(TuiColor::Rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ));
+    }
+    let color = match name.to_lowercase().as_str() {
+        "reset" | "default" => TuiColor::Reset,
+        "black" => TuiColor::Black,
+        "white" => TuiColor::White,
+        "green" => TuiColor::Green,
+        "blue" => TuiColor::Blue,
+        "red" => TuiColor::Red,
+        "cyan" => TuiColor::Cyan,
+        "magenta" => TuiColor::Magenta,
+        "yellow" => TuiColor::Yellow,
+        "orange" => TuiColor::Rgb(255, 165, 0),
+        "purple" => TuiColor::Rgb(128, 0, 128),
+        "pink" => TuiColor::Rgb(255, 192, 203),
+        "brown" => TuiColor::Rgb(165, 42, 42),
+        "gray" => TuiColor::Gray,
+        "darkgray" => TuiColor::DarkGray,
+        "lightblue" => TuiColor::Rgb(173, 216, 230),
+        "lightgreen" => TuiColor::Rgb(144, 238, 144),
+        "lightred" => TuiColor::Rgb(255, 182, 193),
+        "lightyellow" => TuiColor::Rgb(255, 255, 224),
+        "lightcyan" => TuiColor::Rgb(224, 255, 255),
+        "lightmagenta" => TuiColor::Rgb(255, 224, 255),
+        "lightorange" => TuiColor::Rgb(255, 200, 150),
+        _ => return None,
+    };
+    Some(color)
+}
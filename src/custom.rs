@@ -0,0 +1,152 @@
+// User-defined commands from the `[commands]` section of opener.toml: a shell
+// command template run as `:name` or with a key, with the same placeholders as
+// openers (`%f`, `%d`, `%s`)
+use crate::{
+    app::Notifications, command::COMMANDS, opener::template_command, terminal::with_suspended_tui,
+};
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomCommand {
+    pub name: String,
+    pub template: String,
+    pub key: Option<(KeyCode, KeyModifiers)>,
+    // List the directory again afterwards, for commands that change it
+    pub refresh: bool,
+    // Show what the command prints in a panel instead of handing it the terminal
+    pub output: bool,
+}
+
+impl CustomCommand {
+    pub const KEYS: [&'static str; 4] = ["command", "key", "refresh", "output"];
+
+    // Runs in `dir`. Returns the lines printed when they are captured.
+    pub fn run(
+        &self,
+        file: &Path,
+        selected: &[PathBuf],
+        dir: &Path,
+    ) -> Result<Option<Vec<String>>, String> {
+        let (program, mut command) = template_command(&self.template, file, selected)?;
+        command.current_dir(dir);
+        if self.output {
+            let output = command
+                .output()
+                .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+            let mut lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .chain(String::from_utf8_lossy(&output.stderr).lines())
+                .map(str::to_string)
+                .collect();
+            if !output.status.success() {
+                lines.push(format!("{} exited with {}", program, output.status));
+            }
+            return Ok(Some(lines));
+        }
+        match with_suspended_tui(|| command.status()) {
+            Ok(status) if status.success() => Ok(None),
+            Ok(status) => Err(format!("{} exited with {}", program, status)),
+            Err(e) => Err(format!("Failed to run {}: {}", program, e)),
+        }
+    }
+}
+
+// "x", "X", "ctrl-x" or "alt-x"
+pub fn parse_key(key: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let (modifiers, key) = match key.split_once('-') {
+        Some(("ctrl", key)) => (KeyModifiers::CONTROL, key),
+        Some(("alt", key)) => (KeyModifiers::ALT, key),
+        _ => (KeyModifiers::NONE, key),
+    };
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some((KeyCode::Char(c), modifiers)),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct CustomCommands {
+    pub commands: Vec<CustomCommand>,
+}
+
+impl CustomCommands {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("commands").cloned());
+        let Some(Value::Table(section)) = section else {
+            return Self::default();
+        };
+        let mut commands = Vec::new();
+        for (name, value) in section {
+            let Value::Table(table) = value else {
+                notifications.error(format!("commands.{} must be a table", name));
+                continue;
+            };
+            let mut command = CustomCommand {
+                name: name.clone(),
+                template: String::new(),
+                key: None,
+                refresh: false,
+                output: false,
+            };
+            for (key, value) in table {
+                match (key.as_str(), value) {
+                    ("command", Value::String(template)) => command.template = template,
+                    ("key", Value::String(key)) => match parse_key(&key) {
+                        Some(binding) => command.key = Some(binding),
+                        None => notifications.error(format!(
+                            "commands.{}.key must be a character, ctrl-<c> or alt-<c>",
+                            name
+                        )),
+                    },
+                    ("refresh", Value::Boolean(refresh)) => command.refresh = refresh,
+                    ("output", Value::Boolean(output)) => command.output = output,
+                    (key, _) if CustomCommand::KEYS.contains(&key) => {
+                        notifications.error(format!("commands.{}.{} has the wrong type", name, key))
+                    }
+                    (key, _) => notifications.error(format!(
+                        "Unknown setting commands.{}.{}, expected one of {}",
+                        name,
+                        key,
+                        CustomCommand::KEYS.join(", ")
+                    )),
+                }
+            }
+            if COMMANDS.contains(&name.as_str()) {
+                notifications.error(format!(
+                    "commands.{} is taken by the built-in :{}",
+                    name, name
+                ));
+                continue;
+            }
+            if command.template.is_empty() {
+                notifications.error(format!("commands.{} has no command", name));
+                continue;
+            }
+            commands.push(command);
+        }
+        Self { commands }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CustomCommand> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    // Shift is part of the character already
+    pub fn for_key(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<&CustomCommand> {
+        let modifiers = modifiers - KeyModifiers::SHIFT;
+        self.commands
+            .iter()
+            .find(|command| command.key == Some((code, modifiers)))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().map(|command| command.name.as_str())
+    }
+}
@@ -0,0 +1,201 @@
+// `termfm --daemon` keeps the listings of the directories it was asked about
+// in memory for every instance of the file manager, so giant or slow
+// directories open without being read again. Instances ask it over a unix
+// socket, one JSON request and one JSON answer per line, and read the
+// directory themselves whenever no daemon answers. There is no daemon outside
+// Unix.
+use crate::{cache::CacheLimits, fs_ops::Entry, ipc::socket_dir};
+#[cfg(unix)]
+use crate::{
+    cache::LruCache,
+    ipc::{connect, user_id},
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+// Far more than an instance keeps, the daemon is there to remember
+pub const DAEMON_LIMITS: CacheLimits = CacheLimits {
+    entries: 4096,
+    bytes: 512 << 20,
+};
+// A daemon that takes longer than this is passed over
+#[cfg(unix)]
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// `$TERMFM_DAEMON_SOCKET`, or one socket per user in the runtime directory
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = env::var_os("TERMFM_DAEMON_SOCKET") {
+        return PathBuf::from(path);
+    }
+    #[cfg(unix)]
+    let name = format!("termfm-daemon-{}.sock", user_id());
+    #[cfg(not(unix))]
+    let name = "termfm-daemon.sock".to_string();
+    socket_dir().join(name)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    // Every entry of the directory, hidden ones too
+    List { dir: PathBuf },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Entries(Vec<Name>),
+    Error(String),
+}
+
+// An entry as `read_dir` knows it, without stats: those go stale without the
+// directory changing, so instances take them themselves
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Name {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+}
+
+impl Name {
+    pub fn entry(&self, dir: &Path, stat: bool) -> Entry {
+        if stat {
+            return Entry::load(dir, self.name.clone());
+        }
+        Entry {
+            name: self.name.clone(),
+            is_dir: self.is_dir,
+            is_symlink: self.is_symlink,
+            is_broken: false,
+            metadata: None,
+        }
+    }
+}
+
+// Listings by directory with the modification time they were read at
+#[cfg(unix)]
+type Index = Mutex<LruCache<(SystemTime, Arc<Vec<Name>>)>>;
+
+// Runs the daemon until it is killed. Refuses to take over the socket of a
+// daemon that is still running.
+#[cfg(unix)]
+pub fn serve(path: &Path) -> io::Result<()> {
+    if connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("A daemon is already running on {}", path.display()),
+        ));
+    }
+    // Left behind by a daemon that didn't exit cleanly
+    let _ = fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    let index: Arc<Index> = Arc::new(Mutex::new(LruCache::new(DAEMON_LIMITS)));
+    for stream in listener.incoming().flatten() {
+        let index = Arc::clone(&index);
+        thread::spawn(move || answer(stream, &index));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_path: &Path) -> io::Result<()> {
+    Err(crate::ipc::unsupported())
+}
+
+#[cfg(unix)]
+fn answer(stream: UnixStream, index: &Index) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let response = match serde_json::from_str(&line) {
+            Ok(Request::List { dir }) => match list(index, &dir) {
+                Ok(names) => Response::Entries(names.to_vec()),
+                Err(e) => Response::Error(format!("Failed to load {}: {}", dir.display(), e)),
+            },
+            Err(e) => Response::Error(format!("Invalid request: {}", e)),
+        };
+        let Ok(json) = serde_json::to_string(&response) else {
+            return;
+        };
+        if writeln!(writer, "{}", json).is_err() {
+            return;
+        }
+    }
+}
+
+// The remembered listing while the directory's modification time is the
+// same, read again otherwise. Reading happens outside the lock, so one slow
+// directory doesn't hold up the others.
+#[cfg(unix)]
+fn list(index: &Index, dir: &Path) -> io::Result<Arc<Vec<Name>>> {
+    let modified = fs::metadata(dir)?.modified()?;
+    if let Some((read_at, names)) = index.lock().unwrap().get_mut(dir) {
+        if *read_at == modified {
+            return Ok(Arc::clone(names));
+        }
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type().ok();
+        names.push(Name {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: file_type.is_some_and(|t| t.is_dir()),
+            is_symlink: file_type.is_some_and(|t| t.is_symlink()),
+        });
+    }
+    let size = names
+        .iter()
+        .map(|name| std::mem::size_of::<Name>() + name.name.capacity())
+        .sum();
+    let names = Arc::new(names);
+    index
+        .lock()
+        .unwrap()
+        .insert(dir.to_path_buf(), (modified, Arc::clone(&names)), size);
+    Ok(names)
+}
+
+// Asks the daemon for a listing. None when there is no daemon or it fails,
+// and the caller reads the directory itself.
+#[cfg(unix)]
+pub fn request_listing(dir: &Path) -> Option<Vec<Name>> {
+    let mut stream = connect(&socket_path()).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+    let request = serde_json::to_string(&Request::List {
+        dir: dir.to_path_buf(),
+    })
+    .ok()?;
+    writeln!(stream, "{}", request).ok()?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    match serde_json::from_str(&line).ok()? {
+        Response::Entries(names) => Some(names),
+        Response::Error(_) => None,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request_listing(_dir: &Path) -> Option<Vec<Name>> {
+    None
+}
@@ -0,0 +1,236 @@
+// Block devices and where they are mounted, for the devices view. Mounting and
+// unmounting go through udisks2's `udisksctl`, which lets users mount their
+// removable drives without root.
+use crate::{
+    config::Programs,
+    fs_ops::{format_size, unescape_mount_path},
+    ui::ListState,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct Device {
+    // Such as `/dev/sdb1`
+    pub path: PathBuf,
+    pub label: Option<String>,
+    pub size: Option<u64>,
+    pub mount_point: Option<PathBuf>,
+    pub removable: bool,
+}
+
+impl Device {
+    pub fn line(&self) -> String {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let mount_point = match &self.mount_point {
+            Some(mount_point) => mount_point.display().to_string(),
+            None => "not mounted".to_string(),
+        };
+        format!(
+            "{} {:<10}{:>10}  {:<16}{}",
+            if self.removable { "⏏" } else { " " },
+            name,
+            self.size.map(format_size).unwrap_or_default(),
+            self.label.as_deref().unwrap_or(""),
+            mount_point
+        )
+    }
+}
+
+pub struct Devices {
+    pub devices: Vec<Device>,
+    pub state: ListState,
+}
+
+impl Devices {
+    pub fn new(devices: Vec<Device>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self { devices, state }
+    }
+
+    pub fn load() -> Self {
+        Self::new(list_devices())
+    }
+
+    pub fn selected(&self) -> Option<&Device> {
+        self.devices.get(self.state.selected().unwrap_or(0))
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        let index = self
+            .state
+            .selected()
+            .unwrap_or(0)
+            .saturating_add_signed(delta)
+            .min(self.devices.len().saturating_sub(1));
+        self.state.select(Some(index));
+    }
+
+    // Reads the devices again, keeping the cursor on the same one
+    pub fn refresh(&mut self) {
+        let selected = self.selected().map(|device| device.path.clone());
+        self.devices = list_devices();
+        let index = selected
+            .and_then(|path| self.devices.iter().position(|d| d.path == path))
+            .unwrap_or(0);
+        self.state.select(Some(index));
+    }
+}
+
+// (device, mount point, filesystem type) of every line of /proc/self/mounts
+pub fn parse_mounts(contents: &str) -> Vec<(String, PathBuf, String)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (device, mount_point, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            Some((
+                unescape_mount_path(device).to_string_lossy().into_owned(),
+                unescape_mount_path(mount_point),
+                fs_type.to_string(),
+            ))
+        })
+        .collect()
+}
+
+// The mounted block devices, then the partitions and partitionless disks that
+// aren't mounted; removable ones first
+pub fn list_devices() -> Vec<Device> {
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    let labels = labels();
+    let mut devices: Vec<Device> = Vec::new();
+    for (device, mount_point, _) in parse_mounts(&mounts) {
+        if !device.starts_with("/dev/") {
+            continue;
+        }
+        // `/dev/mapper/x` and `/dev/disk/by-uuid/x` are links to the device
+        let path = fs::canonicalize(&device).unwrap_or_else(|_| PathBuf::from(&device));
+        // Bind mounts show the same device again
+        if devices.iter().any(|d| d.path == path) {
+            continue;
+        }
+        devices.push(Device {
+            label: labels.get(&path).cloned(),
+            size: sys_size(&path),
+            mount_point: Some(mount_point),
+            removable: is_removable(&path),
+            path,
+        });
+    }
+
+    if let Ok(entries) = fs::read_dir("/sys/class/block") {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = Path::new("/dev").join(&name);
+            let sys = entry.path();
+            let is_partition = sys.join("partition").exists();
+            let has_partitions = fs::read_dir(&sys).is_ok_and(|children| {
+                children
+                    .filter_map(|child| child.ok())
+                    .any(|child| child.file_name().to_string_lossy().starts_with(&name))
+            });
+            if ["loop", "ram", "zram"].iter().any(|p| name.starts_with(p))
+                || (!is_partition && has_partitions)
+                || sys_size(&path).unwrap_or(0) == 0
+                || devices.iter().any(|d| d.path == path)
+            {
+                continue;
+            }
+            devices.push(Device {
+                label: labels.get(&path).cloned(),
+                size: sys_size(&path),
+                mount_point: None,
+                removable: is_removable(&path),
+                path,
+            });
+        }
+    }
+    devices.sort_by(|a, b| (!a.removable, &a.path).cmp(&(!b.removable, &b.path)));
+    devices
+}
+
+// Filesystem labels by device, from the links in /dev/disk/by-label
+fn labels() -> HashMap<PathBuf, String> {
+    let Ok(entries) = fs::read_dir("/dev/disk/by-label") else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let device = fs::canonicalize(entry.path()).ok()?;
+            // Characters such as spaces are escaped like `\x20`
+            let label = entry.file_name().to_string_lossy().replace("\\x20", " ");
+            Some((device, label))
+        })
+        .collect()
+}
+
+fn sys_dir(device: &Path) -> PathBuf {
+    Path::new("/sys/class/block").join(device.file_name().unwrap_or_default())
+}
+
+fn sys_size(device: &Path) -> Option<u64> {
+    let sectors = fs::read_to_string(sys_dir(device).join("size")).ok()?;
+    // Always counted in 512-byte sectors
+    sectors
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|sectors| sectors * 512)
+}
+
+// A partition is removable when its disk is, which is its parent in /sys
+fn is_removable(device: &Path) -> bool {
+    let Ok(sys) = fs::canonicalize(sys_dir(device)) else {
+        return false;
+    };
+    [sys.join("removable"), sys.join("../removable")]
+        .iter()
+        .any(|path| fs::read_to_string(path).is_ok_and(|value| value.trim() == "1"))
+}
+
+// Mounts `device` and returns where
+pub fn mount(device: &Path, programs: &Programs) -> Result<PathBuf, String> {
+    let output = udisksctl(&["mount", "--no-user-interaction", "-b"], device, programs)?;
+    // "Mounted /dev/sdb1 at /run/media/user/USB", older versions end with "."
+    output
+        .trim()
+        .split_once(" at ")
+        .map(|(_, mount_point)| PathBuf::from(mount_point.trim_end_matches('.')))
+        .ok_or_else(|| format!("Unexpected output from udisksctl: {}", output.trim()))
+}
+
+pub fn unmount(device: &Path, programs: &Programs) -> Result<(), String> {
+    udisksctl(
+        &["unmount", "--no-user-interaction", "-b"],
+        device,
+        programs,
+    )
+    .map(|_| ())
+}
+
+fn udisksctl(args: &[&str], device: &Path, programs: &Programs) -> Result<String, String> {
+    match programs
+        .command("udisksctl")
+        .args(args)
+        .arg(device)
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(format!(
+                "udisksctl failed: {}",
+                stderr.lines().find(|line| !line.is_empty()).unwrap_or("")
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err("Mounting needs udisksctl from udisks2".to_string())
+        }
+        Err(e) => Err(format!("Failed to run udisksctl: {}", e)),
+    }
+}
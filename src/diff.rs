@@ -0,0 +1,299 @@
+// Compares two files line by line, or two directory trees entry by entry,
+// for the diff popup
+use crate::{
+    preview::{detect_encoding, expand_tabs},
+    ui::{truncate_end, Color as TuiColor, Line, Span, Style},
+};
+use similar::{udiff::UnifiedHunkHeader, ChangeTag, TextDiff};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+
+// Unchanged lines kept around every change
+const CONTEXT_LINES: usize = 3;
+
+pub enum DiffRow {
+    Hunk(String),
+    // Line numbers count from 1
+    Line {
+        tag: ChangeTag,
+        old: Option<usize>,
+        new: Option<usize>,
+        text: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirDifference {
+    OnlyLeft,
+    OnlyRight,
+    Differs,
+}
+
+pub enum DiffContent {
+    // No rows when the files are identical
+    Text(Vec<DiffRow>),
+    // Whether the binary files differ
+    Binary(bool),
+    // Relative paths; directories found on one side only aren't descended into
+    Dirs(Vec<(DirDifference, PathBuf)>),
+}
+
+pub struct DiffView {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub content: DiffContent,
+    pub side_by_side: bool,
+    pub scroll: usize,
+}
+
+impl DiffView {
+    pub fn new(left: PathBuf, right: PathBuf) -> Result<Self, String> {
+        let content = match (left.is_dir(), right.is_dir()) {
+            (true, true) => {
+                let mut differences = Vec::new();
+                compare_dirs(&left, &right, Path::new(""), &mut differences)
+                    .map_err(|e| format!("Failed to compare: {}", e))?;
+                DiffContent::Dirs(differences)
+            }
+            (false, false) => {
+                compare_files(&left, &right).map_err(|e| format!("Failed to compare: {}", e))?
+            }
+            _ => return Err("Cannot compare a file with a directory".to_string()),
+        };
+        Ok(Self {
+            left,
+            right,
+            content,
+            side_by_side: false,
+            scroll: 0,
+        })
+    }
+
+    pub fn title(&self) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+        let mode = match (&self.content, self.side_by_side) {
+            (DiffContent::Text(_), true) => " [side by side]",
+            (DiffContent::Text(_), false) => " [unified]",
+            _ => "",
+        };
+        format!(
+            "{} ↔ {}{}, s switches the layout, Esc closes",
+            name(&self.left),
+            name(&self.right),
+            mode
+        )
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines(0).len()
+    }
+
+    pub fn lines(&self, width: usize) -> Vec<Line<'static>> {
+        let removed = Style::default().fg(TuiColor::Red);
+        let added = Style::default().fg(TuiColor::Green);
+        let hunk = Style::default().fg(TuiColor::Cyan);
+        match &self.content {
+            DiffContent::Binary(true) => vec![Line::from("Binary files differ")],
+            DiffContent::Binary(false) => vec![Line::from("Files are identical")],
+            DiffContent::Text(rows) if rows.is_empty() => {
+                vec![Line::from("Files are identical")]
+            }
+            DiffContent::Dirs(differences) if differences.is_empty() => {
+                vec![Line::from("Directories are identical")]
+            }
+            DiffContent::Dirs(differences) => differences
+                .iter()
+                .map(|(difference, path)| {
+                    let (marker, style, side) = match difference {
+                        DirDifference::OnlyLeft => ("-", removed, "only in left"),
+                        DirDifference::OnlyRight => ("+", added, "only in right"),
+                        DirDifference::Differs => {
+                            ("~", Style::default().fg(TuiColor::Yellow), "differs")
+                        }
+                    };
+                    Line::from(Span::styled(
+                        format!("{} {}  ({})", marker, path.display(), side),
+                        style,
+                    ))
+                })
+                .collect(),
+            DiffContent::Text(rows) if !self.side_by_side => rows
+                .iter()
+                .map(|row| match row {
+                    DiffRow::Hunk(header) => Line::from(Span::styled(header.clone(), hunk)),
+                    DiffRow::Line { tag, text, .. } => match tag {
+                        ChangeTag::Delete => {
+                            Line::from(Span::styled(format!("-{}", text), removed))
+                        }
+                        ChangeTag::Insert => Line::from(Span::styled(format!("+{}", text), added)),
+                        ChangeTag::Equal => Line::from(format!(" {}", text)),
+                    },
+                })
+                .collect(),
+            DiffContent::Text(rows) => {
+                let half = width.saturating_sub(3) / 2;
+                let cell = |number: Option<usize>, text: &str, style: Style| {
+                    let text = match number {
+                        Some(number) => format!("{:>4} {}", number, text),
+                        None => String::new(),
+                    };
+                    let text = truncate_end(&text, half);
+                    let padding = " ".repeat(half.saturating_sub(text.width()));
+                    Span::styled(format!("{}{}", text, padding), style)
+                };
+                let separator = || Span::styled(" │ ", Style::default().fg(TuiColor::DarkGray));
+                let mut lines = Vec::new();
+                // Removed lines are paired up with the lines added in their place
+                let mut deleted: Vec<(usize, &str)> = Vec::new();
+                let mut inserted: Vec<(usize, &str)> = Vec::new();
+                let flush = |deleted: &mut Vec<(usize, &str)>,
+                             inserted: &mut Vec<(usize, &str)>,
+                             lines: &mut Vec<Line<'static>>| {
+                    for i in 0..deleted.len().max(inserted.len()) {
+                        let left = deleted.get(i);
+                        let right = inserted.get(i);
+                        lines.push(Line::from(vec![
+                            cell(left.map(|l| l.0), left.map_or("", |l| l.1), removed),
+                            separator(),
+                            cell(right.map(|r| r.0), right.map_or("", |r| r.1), added),
+                        ]));
+                    }
+                    deleted.clear();
+                    inserted.clear();
+                };
+                for row in rows {
+                    match row {
+                        DiffRow::Line {
+                            tag: ChangeTag::Delete,
+                            old: Some(old),
+                            text,
+                            ..
+                        } => deleted.push((*old, text)),
+                        DiffRow::Line {
+                            tag: ChangeTag::Insert,
+                            new: Some(new),
+                            text,
+                            ..
+                        } => inserted.push((*new, text)),
+                        DiffRow::Line { old, new, text, .. } => {
+                            flush(&mut deleted, &mut inserted, &mut lines);
+                            lines.push(Line::from(vec![
+                                cell(*old, text, Style::default()),
+                                separator(),
+                                cell(*new, text, Style::default()),
+                            ]));
+                        }
+                        DiffRow::Hunk(header) => {
+                            flush(&mut deleted, &mut inserted, &mut lines);
+                            lines.push(Line::from(Span::styled(header.clone(), hunk)));
+                        }
+                    }
+                }
+                flush(&mut deleted, &mut inserted, &mut lines);
+                lines
+            }
+        }
+    }
+}
+
+fn compare_files(left: &Path, right: &Path) -> io::Result<DiffContent> {
+    let left_bytes = fs::read(left)?;
+    let right_bytes = fs::read(right)?;
+    let decode =
+        |bytes: &[u8]| detect_encoding(bytes).map(|encoding| encoding.decode(bytes).0.into_owned());
+    let (Some(old), Some(new)) = (decode(&left_bytes), decode(&right_bytes)) else {
+        return Ok(DiffContent::Binary(left_bytes != right_bytes));
+    };
+
+    let diff = TextDiff::from_lines(&old, &new);
+    let mut rows = Vec::new();
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        rows.push(DiffRow::Hunk(UnifiedHunkHeader::new(&group).to_string()));
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                rows.push(DiffRow::Line {
+                    tag: change.tag(),
+                    old: change.old_index().map(|i| i + 1),
+                    new: change.new_index().map(|i| i + 1),
+                    text: expand_tabs(change.value().trim_end_matches(['\n', '\r'])),
+                });
+            }
+        }
+    }
+    Ok(DiffContent::Text(rows))
+}
+
+fn compare_dirs(
+    left: &Path,
+    right: &Path,
+    relative: &Path,
+    differences: &mut Vec<(DirDifference, PathBuf)>,
+) -> io::Result<()> {
+    let names = |dir: &Path| -> io::Result<BTreeSet<_>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .collect())
+    };
+    let left_dir = left.join(relative);
+    let right_dir = right.join(relative);
+    let left_names = names(&left_dir)?;
+    let right_names = names(&right_dir)?;
+
+    for name in left_names.union(&right_names) {
+        let path = relative.join(name);
+        let (left_path, right_path) = (left_dir.join(name), right_dir.join(name));
+        let (left_meta, right_meta) = match (
+            fs::symlink_metadata(&left_path),
+            fs::symlink_metadata(&right_path),
+        ) {
+            (Ok(l), Ok(r)) => (l, r),
+            (Ok(_), Err(_)) => {
+                differences.push((DirDifference::OnlyLeft, path));
+                continue;
+            }
+            (Err(_), _) => {
+                differences.push((DirDifference::OnlyRight, path));
+                continue;
+            }
+        };
+        let same = if left_meta.is_dir() && right_meta.is_dir() {
+            compare_dirs(left, right, &path, differences)?;
+            true
+        } else if left_meta.file_type().is_symlink() || right_meta.file_type().is_symlink() {
+            fs::read_link(&left_path).ok() == fs::read_link(&right_path).ok()
+        } else if left_meta.is_file() && right_meta.is_file() {
+            left_meta.len() == right_meta.len() && same_contents(&left_path, &right_path)?
+        } else {
+            false
+        };
+        if !same {
+            differences.push((DirDifference::Differs, path));
+        }
+    }
+    Ok(())
+}
+
+fn same_contents(left: &Path, right: &Path) -> io::Result<bool> {
+    let mut left = File::open(left)?;
+    let mut right = File::open(right)?;
+    let mut left_buffer = vec![0u8; 64 * 1024];
+    let mut right_buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = left.read(&mut left_buffer)?;
+        if read == 0 {
+            return Ok(right.read(&mut right_buffer)? == 0);
+        }
+        right.read_exact(&mut right_buffer[..read])?;
+        if left_buffer[..read] != right_buffer[..read] {
+            return Ok(false);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+// Recursive searches by name, type, contents, size and age: `:find`, and the
+// saved searches that `:search` opens as virtual directories
+use crate::{app::Notifications, config::glob_match};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Files bigger than this are not searched for contents
+const MAX_CONTENT_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl FileKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "f" | "file" => Some(FileKind::File),
+            "d" | "dir" | "directory" => Some(FileKind::Dir),
+            "l" | "link" | "symlink" => Some(FileKind::Symlink),
+            _ => None,
+        }
+    }
+}
+
+// A parsed query such as `*.rs content:TODO size>10k mtime<7d`. Every term has
+// to match.
+#[derive(Default, Debug, PartialEq)]
+pub struct Query {
+    // Globs, a word without `*` or `?` matches anywhere in the name
+    pub names: Vec<String>,
+    pub kind: Option<FileKind>,
+    // Any of them, without the dot
+    pub extensions: Vec<String>,
+    pub content: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    // Modified less, or more, than this long ago
+    pub newer_than: Option<Duration>,
+    pub older_than: Option<Duration>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = Query::default();
+        for term in input.split_whitespace() {
+            if let Some(kind) = term.strip_prefix("type:") {
+                query.kind = Some(FileKind::parse(kind).ok_or_else(|| {
+                    format!("Invalid type: {}, expected file, dir or link", kind)
+                })?);
+            } else if let Some(extensions) = term.strip_prefix("ext:") {
+                query.extensions.extend(
+                    extensions
+                        .split(',')
+                        .filter(|ext| !ext.is_empty())
+                        .map(|ext| ext.trim_start_matches('.').to_lowercase()),
+                );
+            } else if let Some(text) = term.strip_prefix("content:") {
+                query.content = Some(text.to_string());
+            } else if let Some(size) = term.strip_prefix("size>") {
+                query.min_size = Some(parse_size(size)?);
+            } else if let Some(size) = term.strip_prefix("size<") {
+                query.max_size = Some(parse_size(size)?);
+            } else if let Some(age) = term.strip_prefix("mtime<") {
+                query.newer_than = Some(parse_age(age)?);
+            } else if let Some(age) = term.strip_prefix("mtime>") {
+                query.older_than = Some(parse_age(age)?);
+            } else if term.contains(['*', '?']) {
+                query.names.push(term.to_string());
+            } else {
+                query.names.push(format!("*{}*", term));
+            }
+        }
+        if query == Query::default() {
+            return Err("Empty search".to_string());
+        }
+        Ok(query)
+    }
+
+    // Contents are only looked at once everything else matched. `metadata`
+    // is the entry's own, symlinks are not followed.
+    pub fn matches(&self, path: &Path, metadata: &Metadata) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        if !self.names.iter().all(|pattern| glob_match(pattern, &name)) {
+            return false;
+        }
+        let kind = if metadata.file_type().is_symlink() {
+            FileKind::Symlink
+        } else if metadata.is_dir() {
+            FileKind::Dir
+        } else {
+            FileKind::File
+        };
+        if self.kind.is_some_and(|wanted| wanted != kind) {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            if !extension.is_some_and(|ext| self.extensions.contains(&ext)) || kind == FileKind::Dir
+            {
+                return false;
+            }
+        }
+        let constrains_files = self.min_size.is_some() || self.max_size.is_some();
+        if (constrains_files || self.content.is_some()) && !metadata.is_file() {
+            return false;
+        }
+        if self.min_size.is_some_and(|min| metadata.len() <= min)
+            || self.max_size.is_some_and(|max| metadata.len() >= max)
+        {
+            return false;
+        }
+        if self.newer_than.is_some() || self.older_than.is_some() {
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            if self.newer_than.is_some_and(|limit| age >= limit)
+                || self.older_than.is_some_and(|limit| age <= limit)
+            {
+                return false;
+            }
+        }
+        match &self.content {
+            Some(text) => {
+                metadata.len() <= MAX_CONTENT_SIZE
+                    && fs::read(path).is_ok_and(|bytes| contains(&bytes, text.as_bytes()))
+            }
+            None => true,
+        }
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+// `512`, `10k`, `1.5M`, `2G`, in powers of 1024
+fn parse_size(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid size: {}", value);
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(value.len()),
+    );
+    let factor: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    Ok((number * factor as f64) as u64)
+}
+
+// `30m`, `12h`, `7d`, `2w`; a bare number counts days
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid age: {}", value);
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(value.len()),
+    );
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    Ok(Duration::from_secs(number * seconds))
+}
+
+// Paths below `root` matching the query, relative to it. Symlinks are not
+// followed and hidden entries are only searched when shown.
+pub fn find(root: &Path, query: &Query, show_hidden: bool) -> io::Result<Vec<PathBuf>> {
+    find_with(root, query, show_hidden, |_| true)
+}
+
+// `find` handing over the matches of every directory as they are found. The
+// search stops early when `on_batch` returns false.
+pub fn find_with(
+    root: &Path,
+    query: &Query,
+    show_hidden: bool,
+    mut on_batch: impl FnMut(&[PathBuf]) -> bool,
+) -> io::Result<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let found = results.len();
+        let entries = match fs::read_dir(root.join(&relative)) {
+            Ok(entries) => entries,
+            // Unreadable subdirectories are skipped, only the root has to work
+            Err(e) if relative.as_os_str().is_empty() => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name();
+            if !show_hidden && name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = relative.join(&name);
+            if query.matches(&entry.path(), &metadata) {
+                results.push(path.clone());
+            }
+            if metadata.is_dir() {
+                pending.push(path);
+            }
+        }
+        if results.len() > found && !on_batch(&results[found..]) {
+            break;
+        }
+    }
+    results.sort();
+    Ok(results)
+}
+
+// What `:search` does
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchAction {
+    // Show the names of the saved searches
+    List,
+    // Save a query searching the current directory
+    Save { name: String, query: String },
+    Delete(String),
+    // Show the results of a saved search as a directory
+    Open(String),
+    // Search the open one again
+    Refresh,
+}
+
+impl SearchAction {
+    pub const NAMES: [&str; 3] = ["save", "delete", "refresh"];
+
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        let (action, rest) = match argument.split_once(char::is_whitespace) {
+            Some((action, rest)) => (action, rest.trim()),
+            None => (argument, ""),
+        };
+        match action {
+            "" => Ok(SearchAction::List),
+            "save" => match rest.split_once(char::is_whitespace) {
+                Some((name, query)) => {
+                    Query::parse(query)?;
+                    Ok(SearchAction::Save {
+                        name: name.to_string(),
+                        query: query.trim().to_string(),
+                    })
+                }
+                None => Err("Usage: :search save <name> <query>".to_string()),
+            },
+            "delete" if rest.is_empty() => Err("Usage: :search delete <name>".to_string()),
+            "delete" => Ok(SearchAction::Delete(rest.to_string())),
+            "refresh" => Ok(SearchAction::Refresh),
+            name if rest.is_empty() => Ok(SearchAction::Open(name.to_string())),
+            _ => {
+                Err("Usage: :search [<name>|save <name> <query>|delete <name>|refresh]".to_string())
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedSearch {
+    // Where the search starts
+    pub root: PathBuf,
+    pub query: String,
+}
+
+// Saved searches by name, kept in `searches.json` next to `opener.toml`
+#[derive(Default)]
+pub struct SavedSearches {
+    // None keeps the searches in memory only
+    path: Option<PathBuf>,
+    pub searches: BTreeMap<String, SavedSearch>,
+}
+
+impl SavedSearches {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let path = config_path.with_file_name("searches.json");
+        let searches = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                notifications.error(format!("Failed to read {}: {}", path.display(), e));
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            searches,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let serialized = serde_json::to_string_pretty(&self.searches).map_err(io::Error::other)?;
+        fs::write(path, serialized)
+    }
+}
@@ -0,0 +1,266 @@
+// Finder's color tags, which macOS keeps in the `com.apple.metadata:
+// _kMDItemUserTags` extended attribute as a binary property list: an array of
+// strings, each the tag's name followed by a newline and its color number
+// when it has one ("Red\n6"). Only what that array needs of the format is
+// read and written here.
+use crate::ui::Color as TuiColor;
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+const ATTRIBUTE: &std::ffi::CStr = c"com.apple.metadata:_kMDItemUserTags";
+
+// Finder's numbers for its seven colors, 0 being none
+pub const COLORS: [(&str, u8); 7] = [
+    ("Gray", 1),
+    ("Green", 2),
+    ("Purple", 3),
+    ("Blue", 4),
+    ("Yellow", 5),
+    ("Red", 6),
+    ("Orange", 7),
+];
+
+pub fn color(number: u8) -> Option<TuiColor> {
+    match number {
+        1 => Some(TuiColor::Gray),
+        2 => Some(TuiColor::Green),
+        3 => Some(TuiColor::Magenta),
+        4 => Some(TuiColor::Blue),
+        5 => Some(TuiColor::Yellow),
+        6 => Some(TuiColor::Red),
+        7 => Some(TuiColor::Rgb(0xff, 0x95, 0x00)),
+        _ => None,
+    }
+}
+
+// "Red\n6" into the name and the color number, 0 when there is none
+pub fn split_tag(tag: &str) -> (&str, u8) {
+    match tag.rsplit_once('\n') {
+        Some((name, number)) => (name, number.parse().unwrap_or(0)),
+        None => (tag, 0),
+    }
+}
+
+// The tags as written to the attribute: with the color number they were
+// last seen with in Finder, or that of a color's name
+pub fn join_tag(name: &str, number: Option<u8>) -> String {
+    let number = number.or_else(|| {
+        COLORS
+            .iter()
+            .find(|(color, _)| *color == name)
+            .map(|(_, number)| *number)
+    });
+    match number {
+        Some(number) if number > 0 => format!("{}\n{}", name, number),
+        _ => name.to_string(),
+    }
+}
+
+// A binary property list holding an array of strings
+pub fn encode(tags: &[String]) -> Vec<u8> {
+    let ref_size = if tags.len() < 255 { 1 } else { 2 };
+    let mut data = b"bplist00".to_vec();
+    let mut offsets = vec![data.len()];
+    push_marker(&mut data, 0xA, tags.len());
+    for index in 1..=tags.len() {
+        data.extend_from_slice(&(index as u64).to_be_bytes()[8 - ref_size..]);
+    }
+    for tag in tags {
+        offsets.push(data.len());
+        if tag.is_ascii() {
+            push_marker(&mut data, 0x5, tag.len());
+            data.extend_from_slice(tag.as_bytes());
+        } else {
+            let units: Vec<u16> = tag.encode_utf16().collect();
+            push_marker(&mut data, 0x6, units.len());
+            for unit in units {
+                data.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+    let table_offset = data.len();
+    let offset_size = match offsets.last().copied().unwrap_or(0) {
+        offset if offset < 0x100 => 1,
+        offset if offset < 0x1_0000 => 2,
+        _ => 4,
+    };
+    for offset in &offsets {
+        data.extend_from_slice(&(*offset as u64).to_be_bytes()[8 - offset_size..]);
+    }
+    data.extend_from_slice(&[0; 6]);
+    data.extend_from_slice(&[offset_size as u8, ref_size as u8]);
+    data.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+    data.extend_from_slice(&0u64.to_be_bytes());
+    data.extend_from_slice(&(table_offset as u64).to_be_bytes());
+    data
+}
+
+fn push_marker(data: &mut Vec<u8>, kind: u8, len: usize) {
+    if len < 0xF {
+        data.push(kind << 4 | len as u8);
+        return;
+    }
+    data.push(kind << 4 | 0xF);
+    match len {
+        len if len < 0x100 => data.extend_from_slice(&[0x10, len as u8]),
+        len if len < 0x1_0000 => {
+            data.push(0x11);
+            data.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            data.push(0x12);
+            data.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+// The strings of a binary property list whose top object is an array of
+// them, None for anything else
+pub fn decode(data: &[u8]) -> Option<Vec<String>> {
+    if data.len() < 40 || !data.starts_with(b"bplist00") {
+        return None;
+    }
+    let trailer = &data[data.len() - 32..];
+    let (offset_size, ref_size) = (trailer[6] as usize, trailer[7] as usize);
+    let objects = uint(&trailer[8..16])? as usize;
+    let top = uint(&trailer[16..24])? as usize;
+    let table = uint(&trailer[24..32])? as usize;
+    let offset = |object: usize| -> Option<usize> {
+        if object >= objects {
+            return None;
+        }
+        let start = table.checked_add(object.checked_mul(offset_size)?)?;
+        uint(data.get(start..start + offset_size)?).map(|offset| offset as usize)
+    };
+
+    let (kind, len, start) = object(data, offset(top)?)?;
+    if kind != 0xA {
+        return None;
+    }
+    let mut tags = Vec::new();
+    for index in 0..len {
+        let at = start + index * ref_size;
+        let reference = uint(data.get(at..at + ref_size)?)? as usize;
+        let (kind, len, start) = object(data, offset(reference)?)?;
+        let tag = match kind {
+            0x5 => String::from_utf8(data.get(start..start + len)?.to_vec()).ok()?,
+            0x6 => {
+                let bytes = data.get(start..start + len * 2)?;
+                let units: Vec<u16> = bytes
+                    .chunks(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()?
+            }
+            _ => return None,
+        };
+        tags.push(tag);
+    }
+    Some(tags)
+}
+
+// The object's type, its length and where its contents start
+fn object(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let marker = *data.get(offset)?;
+    let (kind, len) = (marker >> 4, (marker & 0xF) as usize);
+    if len < 0xF {
+        return Some((kind, len, offset + 1));
+    }
+    let size_marker = *data.get(offset + 1)?;
+    if size_marker >> 4 != 0x1 {
+        return None;
+    }
+    let size = 1 << (size_marker & 0xF);
+    let len = uint(data.get(offset + 2..offset + 2 + size)?)? as usize;
+    Some((kind, len, offset + 2 + size))
+}
+
+fn uint(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0u64, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+// The file's tags as Finder wrote them, None without any
+#[cfg(target_os = "macos")]
+pub fn read(path: &Path) -> Option<Vec<String>> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: both names are NUL-terminated, a null buffer asks for the size
+    let size = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            ATTRIBUTE.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            0,
+            0,
+        )
+    };
+    if size <= 0 {
+        return None;
+    }
+    let mut buffer = vec![0u8; size as usize];
+    // SAFETY: as above, and the buffer holds `size` bytes
+    let read = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            ATTRIBUTE.as_ptr(),
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            0,
+            0,
+        )
+    };
+    if read < 0 {
+        return None;
+    }
+    buffer.truncate(read as usize);
+    decode(&buffer)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read(_path: &Path) -> Option<Vec<String>> {
+    None
+}
+
+// Replaces the file's Finder tags, removing the attribute when there are none
+#[cfg(target_os = "macos")]
+pub fn write(path: &Path, tags: &[String]) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: both names are NUL-terminated and the value outlives the call
+    let result = if tags.is_empty() {
+        match unsafe { libc::removexattr(path.as_ptr(), ATTRIBUTE.as_ptr(), 0) } {
+            -1 if std::io::Error::last_os_error().raw_os_error() == Some(libc::ENOATTR) => 0,
+            result => result,
+        }
+    } else {
+        let value = encode(tags);
+        unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                ATTRIBUTE.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+                0,
+            )
+        }
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn write(_path: &Path, _tags: &[String]) -> std::io::Result<()> {
+    Ok(())
+}
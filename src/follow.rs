@@ -0,0 +1,122 @@
+// `tail -f` for the preview pane: the end of a file, extended as lines are
+// appended to it
+use crate::preview::expand_tabs;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Files that are followed as soon as they are selected, others with `F`
+pub const EXTENSIONS: [&str; 1] = ["log"];
+// Lines kept in memory, the pane shows as many of the last ones as fit
+pub const FOLLOW_LINES: usize = 1000;
+// How much of the end of the file is read when following starts
+const TAIL_BYTES: u64 = 256 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct Follow {
+    pub path: PathBuf,
+    pub lines: VecDeque<String>,
+    // Bytes of the file read so far
+    offset: u64,
+    // The last line until its newline has been written
+    partial: Vec<u8>,
+    last_poll: Instant,
+}
+
+impl Follow {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut follow = Self {
+            path: path.to_path_buf(),
+            lines: VecDeque::new(),
+            offset: 0,
+            partial: Vec::new(),
+            last_poll: Instant::now(),
+        };
+        let len = fs::metadata(path)?.len();
+        let start = len.saturating_sub(TAIL_BYTES);
+        let bytes = follow.read_from(start)?;
+        // Starting in the middle of the file cuts the first line
+        let bytes = match bytes.iter().position(|&b| b == b'\n') {
+            Some(newline) if start > 0 => &bytes[newline + 1..],
+            _ => &bytes[..],
+        };
+        follow.push(bytes);
+        Ok(follow)
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_poll.elapsed() >= POLL_INTERVAL
+    }
+
+    // When the file is looked at next
+    pub fn due(&self) -> Instant {
+        self.last_poll + POLL_INTERVAL
+    }
+
+    // Reads what was appended since the last poll. A file that got shorter was
+    // truncated or replaced (log rotation) and is followed from its new end.
+    // Returns whether anything changed.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        self.last_poll = Instant::now();
+        let len = fs::metadata(&self.path)?.len();
+        if len < self.offset {
+            *self = Self::open(&self.path)?;
+            return Ok(true);
+        }
+        if len == self.offset {
+            return Ok(false);
+        }
+        let bytes = self.read_from(self.offset)?;
+        self.push(&bytes);
+        Ok(true)
+    }
+
+    // The complete lines followed by the one still being written
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        let partial = (!self.partial.is_empty()).then(|| decode_line(&self.partial));
+        let complete = count.saturating_sub(partial.is_some() as usize);
+        let skip = self.lines.len().saturating_sub(complete);
+        self.lines
+            .iter()
+            .skip(skip)
+            .cloned()
+            .chain(partial)
+            .collect()
+    }
+
+    fn read_from(&mut self, start: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        self.offset = start + bytes.len() as u64;
+        Ok(bytes)
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return;
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        for line in complete[..end].split(|&b| b == b'\n') {
+            self.lines.push_back(decode_line(line));
+        }
+        while self.lines.len() > FOLLOW_LINES {
+            self.lines.pop_front();
+        }
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    let line = String::from_utf8_lossy(bytes);
+    expand_tabs(line.strip_suffix('\r').unwrap_or(&line))
+}
+
+pub fn follows_automatically(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
@@ -0,0 +1,1158 @@
+use crate::{
+    app::Notifications,
+    config::glob_match,
+    jobs::{LOAD_BATCH_INTERVAL, LOAD_BATCH_SIZE},
+    preview::detect_encoding,
+};
+use std::collections::BTreeMap;
+#[cfg(unix)]
+use std::ffi::CString;
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::{
+    ffi::OsStrExt,
+    fs::{FileExt, MetadataExt, PermissionsExt},
+};
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+use toml::Value;
+
+// One directory entry, stat'ed once while listing so that sorting and drawing
+// don't have to go back to the filesystem
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    // A link whose target is gone, only known for stat'ed entries
+    pub is_broken: bool,
+    // None when the mount is listed without stats or the stat failed
+    pub metadata: Option<fs::Metadata>,
+}
+
+impl Entry {
+    // Follows symlinks, so a link to a directory sorts with the directories.
+    // Only links cost a second stat.
+    pub fn load(dir: &Path, name: String) -> Self {
+        let path = dir.join(&name);
+        let metadata = fs::symlink_metadata(&path).ok();
+        let is_symlink = metadata
+            .as_ref()
+            .is_some_and(|m| m.file_type().is_symlink());
+        let metadata = if is_symlink {
+            fs::metadata(&path).ok()
+        } else {
+            metadata
+        };
+        Entry {
+            name,
+            is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+            is_symlink,
+            is_broken: is_symlink && metadata.is_none(),
+            metadata,
+        }
+    }
+
+    // Stand-in rows such as "<Loading...>"
+    pub fn placeholder(text: &str) -> Self {
+        Entry {
+            name: text.to_string(),
+            is_dir: false,
+            is_symlink: false,
+            is_broken: false,
+            metadata: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortMode {
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::Modified,
+            SortMode::Modified => SortMode::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Size => "size",
+            SortMode::Modified => "mtime",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "name" => Some(SortMode::Name),
+            "size" => Some(SortMode::Size),
+            "mtime" => Some(SortMode::Modified),
+            _ => None,
+        }
+    }
+}
+
+// How the filesystem under the current directory should be treated
+#[derive(Clone, PartialEq)]
+pub struct MountStrategy {
+    pub fs_type: String,
+    // Remote filesystem where every request is a round trip
+    pub network: bool,
+    // Whether a stat() per listed entry is cheap enough for sorting and columns
+    pub stat_entries: bool,
+}
+
+impl MountStrategy {
+    // Shown in the status bar whenever something is restricted or slow
+    pub fn is_special(&self) -> bool {
+        self.network || !self.stat_entries
+    }
+}
+
+pub struct MountOverride {
+    pub pattern: String,
+    pub network: Option<bool>,
+    pub stat_entries: Option<bool>,
+}
+
+// Built-in defaults per filesystem type plus the `[mounts]` overrides
+#[derive(Default)]
+pub struct MountRules {
+    pub overrides: Vec<MountOverride>,
+}
+
+impl MountRules {
+    pub const NETWORK_FS: [&'static str; 10] = [
+        "nfs*",
+        "cifs",
+        "smb*",
+        "9p",
+        "afs",
+        "ceph",
+        "glusterfs",
+        "lustre",
+        "fuse.sshfs",
+        "fuse.rclone",
+    ];
+
+    pub fn from_config(config_path: &Path) -> Self {
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("mounts").cloned());
+        let Some(Value::Table(section)) = section else {
+            return Self::default();
+        };
+
+        let overrides = section
+            .iter()
+            .map(|(pattern, rule)| MountOverride {
+                pattern: pattern.to_lowercase(),
+                network: rule.get("network").and_then(|v| v.as_bool()),
+                stat_entries: rule.get("stats").and_then(|v| v.as_bool()),
+            })
+            .collect();
+        Self { overrides }
+    }
+
+    pub fn strategy_for(&self, dir: &Path) -> MountStrategy {
+        let fs_type = mount_fs_type(dir).unwrap_or_else(|| "unknown".to_string());
+        let mut strategy = MountStrategy {
+            network: Self::NETWORK_FS
+                .iter()
+                .any(|pattern| glob_match(pattern, &fs_type)),
+            // Every stat on a FUSE mount goes through a userspace daemon
+            stat_entries: !fs_type.starts_with("fuse") && fs_type != "sshfs",
+            fs_type,
+        };
+        for rule in &self.overrides {
+            if glob_match(&rule.pattern, &strategy.fs_type) {
+                strategy.network = rule.network.unwrap_or(strategy.network);
+                strategy.stat_entries = rule.stat_entries.unwrap_or(strategy.stat_entries);
+            }
+        }
+        strategy
+    }
+}
+
+// With `stat_entries` off only the file type reported by readdir is used, so
+// symlinks to directories sort with files and size/mtime fall back to name.
+pub fn list_files(
+    dir: &Path,
+    show_hidden: bool,
+    sort_mode: SortMode,
+    stat_entries: bool,
+) -> io::Result<Vec<Entry>> {
+    let mut entries = read_entries(dir, show_hidden, stat_entries, |_| true)?;
+    sort_entries(&mut entries, sort_mode, stat_entries);
+    Ok(entries)
+}
+
+// Reads `dir` in directory order, handing every new batch of entries to
+// `on_batch` along the way; it returns false to stop reading early
+pub fn read_entries(
+    dir: &Path,
+    show_hidden: bool,
+    stat_entries: bool,
+    mut on_batch: impl FnMut(&[Entry]) -> bool,
+) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut sent = 0;
+    let mut last_batch = Instant::now();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().into_string().unwrap_or_default();
+
+        if !show_hidden && file_name.starts_with('.') {
+            continue;
+        }
+
+        entries.push(if stat_entries {
+            Entry::load(dir, file_name)
+        } else {
+            Entry {
+                is_dir: entry.file_type().is_ok_and(|t| t.is_dir()),
+                is_symlink: entry.file_type().is_ok_and(|t| t.is_symlink()),
+                is_broken: false,
+                name: file_name,
+                metadata: None,
+            }
+        });
+
+        if entries.len() - sent >= LOAD_BATCH_SIZE || last_batch.elapsed() >= LOAD_BATCH_INTERVAL {
+            if !on_batch(&entries[sent..]) {
+                break;
+            }
+            sent = entries.len();
+            last_batch = Instant::now();
+        }
+    }
+    Ok(entries)
+}
+
+pub fn sort_entries(entries: &mut [Entry], sort_mode: SortMode, stat_entries: bool) {
+    let sort_mode = if stat_entries {
+        sort_mode
+    } else {
+        SortMode::Name
+    };
+
+    entries.sort_by(|a, b| {
+        // Directories first
+        b.is_dir.cmp(&a.is_dir).then_with(|| match sort_mode {
+            SortMode::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            // Largest and most recently modified entries first
+            SortMode::Size => {
+                let size = |e: &Entry| e.metadata.as_ref().map_or(0, |m| m.len());
+                size(b).cmp(&size(a))
+            }
+            SortMode::Modified => {
+                let time = |e: &Entry| e.metadata.as_ref().and_then(|m| m.modified().ok());
+                time(b).cmp(&time(a))
+            }
+        })
+    });
+}
+
+// Filesystem type of the mount containing `path`, from the longest matching
+// mount point in /proc/self/mounts
+pub fn mount_fs_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+
+    let mut best: Option<(usize, String)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(_), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        // Spaces and other separators are octal-escaped, e.g. `\040`
+        let mount_point = unescape_mount_path(mount_point);
+        let len = mount_point.as_os_str().len();
+        if path.starts_with(&mount_point) && best.as_ref().is_none_or(|(l, _)| len >= *l) {
+            best = Some((len, fs_type.to_lowercase()));
+        }
+    }
+    best.map(|(_, fs_type)| fs_type)
+}
+
+pub fn unescape_mount_path(field: &str) -> PathBuf {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|digits| digits.iter().all(|b| (b'0'..=b'7').contains(b)));
+        if let (b'\\', Some(digits)) = (bytes[i], octal) {
+            let value = digits
+                .iter()
+                .fold(0u32, |acc, b| acc * 8 + (b - b'0') as u32);
+            out.push(value as u8);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    path_from_bytes(out)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(std::ffi::OsStr::from_bytes(&bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn mount_notice(mount: &MountStrategy) -> String {
+    if mount.stat_entries {
+        format!(
+            "Network mount ({}): size sorting and detailed view may be slow",
+            mount.fs_type
+        )
+    } else {
+        format!(
+            "{} mount: per-file stats disabled, sorting by name and no detailed view",
+            mount.fs_type
+        )
+    }
+}
+
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+pub fn format_time(time: Option<std::time::SystemTime>) -> String {
+    match time {
+        Some(time) => chrono::DateTime::<chrono::Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => "????-??-?? ??:??".to_string(),
+    }
+}
+
+// Permission bits as `ls -l` shows them. Outside Unix only the read-only flag
+// is known: it takes away the write bits, and directories are searchable.
+#[cfg(unix)]
+pub fn mode(metadata: &fs::Metadata) -> u32 {
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+pub fn mode(metadata: &fs::Metadata) -> u32 {
+    let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+    if metadata.permissions().readonly() {
+        mode & !0o222
+    } else {
+        mode
+    }
+}
+
+// User and group ids of the owner, which only Unix has
+#[cfg(unix)]
+pub fn owner_ids(metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    Some((metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+pub fn owner_ids(_metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> String {
+    let passwd = unsafe { libc::getpwuid(uid) };
+    if passwd.is_null() {
+        return uid.to_string();
+    }
+    unsafe { std::ffi::CStr::from_ptr((*passwd).pw_name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> String {
+    let group = unsafe { libc::getgrgid(gid) };
+    if group.is_null() {
+        return gid.to_string();
+    }
+    unsafe { std::ffi::CStr::from_ptr((*group).gr_name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(not(unix))]
+pub fn user_name(uid: u32) -> String {
+    uid.to_string()
+}
+
+#[cfg(not(unix))]
+pub fn group_name(gid: u32) -> String {
+    gid.to_string()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+    None,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextFormat {
+    pub line_ending: LineEnding,
+    pub bom: bool,
+}
+
+impl TextFormat {
+    pub fn detect(text: &str, bom: bool) -> Self {
+        let bytes = text.as_bytes();
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    crlf += 1;
+                    i += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        let line_ending = match (lf > 0, crlf > 0, cr > 0) {
+            (false, false, false) => LineEnding::None,
+            (true, false, false) => LineEnding::Lf,
+            (false, true, false) => LineEnding::Crlf,
+            (false, false, true) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        };
+        Self { line_ending, bom }
+    }
+
+    pub fn label(&self) -> String {
+        let line_ending = match self.line_ending {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+            LineEnding::Cr => "CR",
+            LineEnding::Mixed => "mixed EOL",
+            LineEnding::None => "no EOL",
+        };
+        if self.bom {
+            format!("{} BOM", line_ending)
+        } else {
+            line_ending.to_string()
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Conversion {
+    Lf,
+    Crlf,
+    StripBom,
+}
+
+// Rewrites line endings or strips the BOM of a UTF-8 text file in place.
+// Returns whether the file had to be changed.
+pub fn convert_text_file(path: &Path, conversion: Conversion) -> Result<bool, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if detect_encoding(&bytes) != Some(encoding_rs::UTF_8) {
+        return Err("not a UTF-8 text file".to_string());
+    }
+
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+    let unix: Vec<u8> = {
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut iter = bytes.iter().peekable();
+        while let Some(&b) = iter.next() {
+            if b == b'\r' && iter.peek() == Some(&&b'\n') {
+                continue;
+            }
+            out.push(b);
+        }
+        out
+    };
+    let converted = match conversion {
+        Conversion::Lf => unix,
+        Conversion::Crlf => {
+            let mut out = Vec::with_capacity(unix.len());
+            for b in unix {
+                if b == b'\n' {
+                    out.push(b'\r');
+                }
+                out.push(b);
+            }
+            out
+        }
+        Conversion::StripBom => bytes.strip_prefix(BOM).unwrap_or(&bytes).to_vec(),
+    };
+    if converted == bytes {
+        return Ok(false);
+    }
+
+    // Write next to the original and rename over it so a failure can't truncate it
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.termfm-tmp", file_name));
+    let permissions = fs::metadata(path).map_err(|e| e.to_string())?.permissions();
+    fs::write(&temp_path, &converted)
+        .and_then(|_| fs::set_permissions(&temp_path, permissions))
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            e.to_string()
+        })?;
+    Ok(true)
+}
+
+// What a rename fails with across filesystems
+#[cfg(not(windows))]
+const CROSS_DEVICE: i32 = libc::EXDEV;
+// ERROR_NOT_SAME_DEVICE, across drives
+#[cfg(windows)]
+const CROSS_DEVICE: i32 = 17;
+
+// Renames, falling back to copy and delete across filesystems
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.raw_os_error() == Some(CROSS_DEVICE) => {
+            // A move keeps everything it can, like mv
+            let meta = fs::symlink_metadata(from)?;
+            if meta.is_dir() {
+                // The target may be left over from an interrupted move
+                match fs::create_dir(to) {
+                    Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                    _ => {}
+                }
+                for entry in fs::read_dir(from)? {
+                    let entry = entry?;
+                    move_path(&entry.path(), &to.join(entry.file_name()))?;
+                }
+                preserve_attributes(from, to, &meta, Preserve::ALL)?;
+                fs::remove_dir(from)
+            } else {
+                copy_path(from, to, Preserve::ALL)?;
+                fs::remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+// Copies a file, or a directory with everything in it, keeping what
+// `preserve` asks for. Symlinks are copied as links rather than followed.
+// Returns the slowest way a file's contents were copied, None when there were
+// no files.
+pub fn copy_path(from: &Path, to: &Path, preserve: Preserve) -> io::Result<Option<CopyStrategy>> {
+    let meta = fs::symlink_metadata(from)?;
+    let strategy = if meta.file_type().is_symlink() {
+        symlink(&fs::read_link(from)?, to)?;
+        None
+    } else if meta.is_dir() {
+        fs::create_dir(to)?;
+        let mut slowest = None;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            let target = to.join(entry.file_name());
+            slowest = slowest.max(copy_path(&entry.path(), &target, preserve)?);
+        }
+        slowest
+    } else {
+        Some(copy_file(from, to)?)
+    };
+    // Last, as copying into a directory changes its times
+    preserve_attributes(from, to, &meta, preserve)?;
+    Ok(strategy)
+}
+
+// What `:link`, `:alink` and `:rlink` create, like `ln`, `ln -s` and `ln -sr`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    Hard,
+    // A symlink to the absolute path
+    Absolute,
+    // A symlink to the path relative to the link's directory, which survives
+    // moving both together
+    Relative,
+}
+
+impl LinkKind {
+    pub fn parse(command: &str) -> Option<Self> {
+        match command {
+            "link" => Some(LinkKind::Hard),
+            "alink" => Some(LinkKind::Absolute),
+            "rlink" => Some(LinkKind::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn command(self) -> &'static str {
+        match self {
+            LinkKind::Hard => "link",
+            LinkKind::Absolute => "alink",
+            LinkKind::Relative => "rlink",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkKind::Hard => "hard link",
+            LinkKind::Absolute | LinkKind::Relative => "symlink",
+        }
+    }
+
+    // Creates `link` pointing to `target`, both absolute
+    pub fn create(self, target: &Path, link: &Path) -> io::Result<()> {
+        match self {
+            LinkKind::Hard => fs::hard_link(target, link),
+            LinkKind::Absolute => symlink(target, link),
+            LinkKind::Relative => {
+                let dir = link.parent().unwrap_or(Path::new("/"));
+                symlink(&relative_path(dir, target), link)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+// Windows has separate links to files and directories; a relative target is
+// looked up from the link's directory
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+// `to` as seen from the directory `from`, e.g. `../docs/a.txt`; both absolute
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative: PathBuf = from
+        .components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(to.components().skip(common));
+    relative
+}
+
+// What a copy keeps of the original besides its contents, like cp's
+// `--preserve`. Permissions are kept by default, as cp does for new files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Preserve {
+    pub mode: bool,
+    pub timestamps: bool,
+    // Owner and group; only root may give files away, for others this is
+    // kept where allowed
+    pub ownership: bool,
+    pub xattrs: bool,
+}
+
+impl Default for Preserve {
+    fn default() -> Self {
+        Self {
+            mode: true,
+            timestamps: false,
+            ownership: false,
+            xattrs: false,
+        }
+    }
+}
+
+impl Preserve {
+    pub const NAMES: [&'static str; 4] = ["mode", "timestamps", "ownership", "xattr"];
+
+    // `cp -a`
+    pub const ALL: Self = Self {
+        mode: true,
+        timestamps: true,
+        ownership: true,
+        xattrs: true,
+    };
+
+    pub const NONE: Self = Self {
+        mode: false,
+        timestamps: false,
+        ownership: false,
+        xattrs: false,
+    };
+
+    // `preserve` in `[copy]`: "all", "none" or a list of `NAMES`
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let preserve = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("copy")?.get("preserve").cloned());
+        let names = match preserve {
+            None => return Self::default(),
+            Some(Value::String(name)) if name == "all" => return Self::ALL,
+            Some(Value::String(name)) if name == "none" => return Self::NONE,
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect(),
+            Some(_) => {
+                notifications.error("copy.preserve must be \"all\", \"none\" or a list");
+                return Self::default();
+            }
+        };
+        let mut preserve = Self::NONE;
+        for name in names {
+            match name.as_str() {
+                "mode" => preserve.mode = true,
+                "timestamps" => preserve.timestamps = true,
+                "ownership" => preserve.ownership = true,
+                "xattr" => preserve.xattrs = true,
+                _ => notifications.error(format!(
+                    "Unknown attribute {} in copy.preserve, expected one of {}",
+                    name,
+                    Self::NAMES.join(", ")
+                )),
+            }
+        }
+        preserve
+    }
+}
+
+// Gives `to` the attributes of `from` (whose metadata is `meta`) that
+// `preserve` asks for. Symlinks themselves get owner and times, their targets
+// are left alone.
+pub fn preserve_attributes(
+    from: &Path,
+    to: &Path,
+    meta: &fs::Metadata,
+    preserve: Preserve,
+) -> io::Result<()> {
+    let is_symlink = meta.file_type().is_symlink();
+    // Before the mode, changing the owner drops setuid bits
+    #[cfg(unix)]
+    if preserve.ownership {
+        match std::os::unix::fs::lchown(to, Some(meta.uid()), Some(meta.gid())) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            result => result?,
+        }
+    }
+    if preserve.xattrs && !is_symlink {
+        copy_xattrs(from, to)?;
+    }
+    if preserve.mode && !is_symlink {
+        fs::set_permissions(to, meta.permissions())?;
+    }
+    if preserve.timestamps {
+        set_times(to, meta)?;
+    }
+    Ok(())
+}
+
+// Gives `to` the access and modification times of `meta`, itself if it's a
+// symlink
+#[cfg(unix)]
+fn set_times(to: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    let time = |seconds, nanoseconds| libc::timespec {
+        tv_sec: seconds,
+        tv_nsec: nanoseconds,
+    };
+    let times = [
+        time(meta.atime(), meta.atime_nsec()),
+        time(meta.mtime(), meta.mtime_nsec()),
+    ];
+    let path = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: the path and the times outlive the call
+    let result = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Symlinks keep their own times, only files and directories get them
+#[cfg(not(unix))]
+fn set_times(to: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    if meta.file_type().is_symlink() {
+        return Ok(());
+    }
+    let times = fs::FileTimes::new()
+        .set_accessed(meta.accessed()?)
+        .set_modified(meta.modified()?);
+    fs::File::options().write(true).open(to)?.set_times(times)
+}
+
+// Extended attributes the target's filesystem doesn't support, or that only
+// root may set such as `trusted.*`, are left out
+#[cfg(target_os = "linux")]
+fn copy_xattrs(from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (
+        CString::new(from.as_os_str().as_bytes())?,
+        CString::new(to.as_os_str().as_bytes())?,
+    );
+    // Each call tells the size it needs when given no buffer
+    let read = |fill: &dyn Fn(*mut libc::c_char, usize) -> isize| -> io::Result<Vec<u8>> {
+        loop {
+            let size = fill(std::ptr::null_mut(), 0);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buffer = vec![0u8; size as usize];
+            let read = fill(buffer.as_mut_ptr().cast(), buffer.len());
+            match read {
+                // Grew meanwhile
+                -1 if io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) => {}
+                -1 => return Err(io::Error::last_os_error()),
+                read => {
+                    buffer.truncate(read as usize);
+                    return Ok(buffer);
+                }
+            }
+        }
+    };
+    // SAFETY: the paths live as long as the closures, the buffers are as
+    // long as said
+    let names = match read(&|buffer, size| unsafe { libc::llistxattr(from.as_ptr(), buffer, size) })
+    {
+        Ok(names) => names,
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for name in names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+    {
+        let name = CString::new(name)?;
+        let value = read(&|buffer, size| unsafe {
+            libc::lgetxattr(from.as_ptr(), name.as_ptr(), buffer.cast(), size)
+        })?;
+        // SAFETY: as above
+        let result = unsafe {
+            libc::lsetxattr(
+                to.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            let e = io::Error::last_os_error();
+            if !matches!(e.raw_os_error(), Some(libc::ENOTSUP | libc::EPERM)) {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_xattrs(_from: &Path, _to: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// How the contents of a file were copied, fastest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CopyStrategy {
+    // The copy shares the original's blocks until either changes, on
+    // filesystems such as btrfs, XFS and APFS
+    Reflink,
+    // copy_file_range(2), the kernel copies without a round trip through
+    // termfm and may offload it to the storage
+    KernelCopy,
+    Buffered,
+}
+
+impl CopyStrategy {
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyStrategy::Reflink => "reflink",
+            CopyStrategy::KernelCopy => "kernel copy",
+            CopyStrategy::Buffered => "buffered copy",
+        }
+    }
+}
+
+// Copies a file's contents the fastest way the filesystems allow. Only the
+// data regions of sparse files are copied, so their holes stay holes.
+pub fn copy_file(from: &Path, to: &Path) -> io::Result<CopyStrategy> {
+    #[cfg(target_os = "macos")]
+    if clone_file(from, to).is_ok() {
+        return Ok(CopyStrategy::Reflink);
+    }
+    let source = fs::File::open(from)?;
+    let len = source.metadata()?.len();
+    let target = fs::File::create(to)?;
+    let strategy = copy_contents(&source, &target, len)?;
+    target.set_len(len)?;
+    Ok(strategy)
+}
+
+// clonefile(2): APFS shares the blocks, like a reflink. It fails when `to`
+// exists or the filesystem can't, and the copy is made as elsewhere.
+#[cfg(target_os = "macos")]
+fn clone_file(from: &Path, to: &Path) -> io::Result<()> {
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // CLONE_NOFOLLOW, which libc doesn't name
+    const NOFOLLOW: u32 = 0x0001;
+    // SAFETY: both paths are NUL-terminated and outlive the call
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), NOFOLLOW) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn copy_contents(source: &fs::File, target: &fs::File, len: u64) -> io::Result<CopyStrategy> {
+    use std::os::fd::AsRawFd;
+    let (source_fd, target_fd) = (source.as_raw_fd(), target.as_raw_fd());
+    // SAFETY: both descriptors are open for as long as the files live
+    if unsafe { libc::ioctl(target_fd, libc::FICLONE, source_fd) } == 0 {
+        return Ok(CopyStrategy::Reflink);
+    }
+    let mut strategy = CopyStrategy::KernelCopy;
+    for (start, end) in data_regions(source, len) {
+        let mut offset = start;
+        while offset < end && strategy == CopyStrategy::KernelCopy {
+            let (mut offset_in, mut offset_out) = (offset as libc::loff_t, offset as libc::loff_t);
+            // SAFETY: as above, and the offsets outlive the call
+            let copied = unsafe {
+                libc::copy_file_range(
+                    source_fd,
+                    &mut offset_in,
+                    target_fd,
+                    &mut offset_out,
+                    (end - offset) as usize,
+                    0,
+                )
+            };
+            match copied {
+                // The file got shorter meanwhile
+                0 => offset = end,
+                copied if copied > 0 => offset += copied as u64,
+                _ => {
+                    let e = io::Error::last_os_error();
+                    // Not supported for these files, e.g. across filesystems
+                    // on older kernels
+                    match e.raw_os_error() {
+                        Some(libc::EXDEV | libc::ENOSYS | libc::EINVAL | libc::EOPNOTSUPP) => {
+                            strategy = CopyStrategy::Buffered
+                        }
+                        _ => return Err(e),
+                    }
+                }
+            }
+        }
+        copy_buffered(source, target, offset, end)?;
+    }
+    Ok(strategy)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_contents(source: &fs::File, target: &fs::File, len: u64) -> io::Result<CopyStrategy> {
+    copy_buffered(source, target, 0, len)?;
+    Ok(CopyStrategy::Buffered)
+}
+
+// The parts of a file holding data as (start, end), without the holes of a
+// sparse file. The whole file when the filesystem can't tell.
+#[cfg(target_os = "linux")]
+fn data_regions(file: &fs::File, len: u64) -> Vec<(u64, u64)> {
+    use std::os::fd::AsRawFd;
+    let fd = file.as_raw_fd();
+    let mut regions = Vec::new();
+    let mut offset = 0;
+    while offset < len {
+        // SAFETY: the descriptor is open for as long as the file lives
+        let start = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            // Only a hole is left
+            if io::Error::last_os_error().raw_os_error() == Some(libc::ENXIO) {
+                break;
+            }
+            return vec![(0, len)];
+        }
+        // SAFETY: as above
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return vec![(0, len)];
+        }
+        regions.push((start as u64, (end as u64).min(len)));
+        offset = end as u64;
+    }
+    regions
+}
+
+// Copies bytes `start..end` with reads and writes at the same offsets
+fn copy_buffered(source: &fs::File, target: &fs::File, start: u64, end: u64) -> io::Result<()> {
+    let mut buffer = vec![0; 128 * 1024];
+    let mut offset = start;
+    while offset < end {
+        let wanted = buffer.len().min((end - offset) as usize);
+        let read = read_at(source, &mut buffer[..wanted], offset)?;
+        if read == 0 {
+            break;
+        }
+        write_all_at(target, &buffer[..read], offset)?;
+        offset += read as u64;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_at(file: &fs::File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.read_at(buffer, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &fs::File, buffer: &[u8], offset: u64) -> io::Result<()> {
+    file.write_all_at(buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &fs::File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.seek_read(buffer, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &fs::File, mut buffer: &[u8], mut offset: u64) -> io::Result<()> {
+    while !buffer.is_empty() {
+        match file.seek_write(buffer, offset)? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            written => {
+                buffer = &buffer[written..];
+                offset += written as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Moves the values of `from` and of every path below it over to `to`, for
+// data kept by path such as tags and notes
+pub fn rename_keys<T>(map: &mut BTreeMap<PathBuf, T>, from: &Path, to: &Path) {
+    let moved: Vec<PathBuf> = map
+        .keys()
+        .filter(|path| path.starts_with(from))
+        .cloned()
+        .collect();
+    for path in moved {
+        let value = map.remove(&path).unwrap();
+        let relative = path.strip_prefix(from).unwrap();
+        let target = if relative.as_os_str().is_empty() {
+            to.to_path_buf()
+        } else {
+            to.join(relative)
+        };
+        map.insert(target, value);
+    }
+}
+
+pub fn files_identical(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a_meta), Ok(b_meta)) if a_meta.len() == b_meta.len() => {
+            matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+        }
+        _ => false,
+    }
+}
+
+// `name (1).ext`, `name (2).ext`, ... whichever is free first
+pub fn unique_path(path: &Path, is_free: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| is_free(candidate))
+        .unwrap()
+}
+
+// Removes `dir` and any subdirectories left empty, keeping everything else
+pub fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    let _ = fs::remove_dir(dir);
+}
+
+pub fn search_files(dir: &Path, keyword: &str) -> io::Result<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            if name.contains(keyword) {
+                results.push(path);
+            }
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,83 @@
+use crate::config::Programs;
+use std::path::{Path, PathBuf};
+
+// Small git workflows on the marked files or the selection
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitAction {
+    Stage,
+    Unstage,
+    // Throws away the changes in the working tree, after a confirmation
+    Discard,
+    Commit(String),
+}
+
+impl GitAction {
+    pub const NAMES: [&str; 4] = ["stage", "unstage", "discard", "commit"];
+
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        let (action, rest) = match argument.split_once(char::is_whitespace) {
+            Some((action, rest)) => (action, rest.trim()),
+            None => (argument, ""),
+        };
+        match action {
+            "stage" | "add" => Ok(GitAction::Stage),
+            "unstage" | "reset" => Ok(GitAction::Unstage),
+            "discard" | "restore" => Ok(GitAction::Discard),
+            "commit" if rest.is_empty() => Err("Usage: :git commit <message>".to_string()),
+            "commit" => Ok(GitAction::Commit(rest.to_string())),
+            _ => Err("Usage: :git stage|unstage|discard|commit <message>".to_string()),
+        }
+    }
+}
+
+// Runs git in `dir` and returns its output, or the first line it complained with
+fn run(
+    dir: &Path,
+    args: &[&str],
+    paths: &[PathBuf],
+    programs: &Programs,
+) -> Result<String, String> {
+    let output = programs
+        .command("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .args(paths)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = stderr
+            .lines()
+            .chain(stdout.lines())
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("git failed")
+            .trim()
+            .to_string();
+        Err(message)
+    }
+}
+
+// Deletions are staged as well
+pub fn stage(dir: &Path, paths: &[PathBuf], programs: &Programs) -> Result<(), String> {
+    run(dir, &["add", "-A", "--"], paths, programs).map(|_| ())
+}
+
+pub fn unstage(dir: &Path, paths: &[PathBuf], programs: &Programs) -> Result<(), String> {
+    run(dir, &["reset", "-q", "--"], paths, programs).map(|_| ())
+}
+
+// Back to the staged version; untracked files are left alone
+pub fn discard(dir: &Path, paths: &[PathBuf], programs: &Programs) -> Result<(), String> {
+    run(dir, &["checkout", "--"], paths, programs).map(|_| ())
+}
+
+// Commits what is staged and returns git's summary line
+pub fn commit(dir: &Path, message: &str, programs: &Programs) -> Result<String, String> {
+    run(dir, &["commit", "-q", "-m", message], &[], programs)?;
+    run(dir, &["log", "-1", "--format=%h %s"], &[], programs)
+        .map(|summary| summary.trim().to_string())
+}
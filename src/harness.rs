@@ -0,0 +1,406 @@
+// Runs the file manager without a terminal, for integration tests and
+// scripted checks: key events are queued up front, frames are drawn to a
+// `TestBackend` and the files live in a throwaway directory.
+use crate::{
+    app::App,
+    cli::StartupOptions,
+    config::{load_opener_config, OpenerConfig, Programs},
+    terminal::EventSource,
+    ui::{Terminal, TestBackend},
+};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+#[cfg(unix)]
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, FromRawFd};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(unix)]
+use std::sync::Mutex;
+use std::sync::{Arc, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long a directory listing may take before the harness gives up
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Events handed to the app one per step, in order
+#[derive(Default)]
+pub struct ScriptedEvents {
+    queue: VecDeque<Event>,
+}
+
+impl ScriptedEvents {
+    pub fn push(&mut self, event: Event) {
+        self.queue.push_back(event);
+    }
+
+    pub fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        self.push(Event::Key(KeyEvent::new(code, modifiers)));
+    }
+
+    // Every character is typed as a key of its own, e.g. "jjl" or ":mkdir a"
+    pub fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.key(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn next_event(&mut self, _deadline: Option<Instant>) -> io::Result<Option<Event>> {
+        Ok(self.queue.pop_front())
+    }
+}
+
+// A directory tree under the system temp directory, removed again on drop.
+// The app is started in `root`; the configuration is kept next to it so that
+// it doesn't show up in the listing.
+pub struct Fixture {
+    base: PathBuf,
+    pub root: PathBuf,
+    programs: RefCell<Programs>,
+}
+
+impl Fixture {
+    pub fn new() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let base = env::temp_dir().join(format!(
+            "termfm-fixture-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(base.join("files"))?;
+        // The temp directory may be behind a symlink (macOS), the app shows
+        // and compares resolved paths
+        let base = fs::canonicalize(base)?;
+        Ok(Self {
+            root: base.join("files"),
+            base,
+            programs: RefCell::new(Programs::default()),
+        })
+    }
+
+    // Creates a file below the root, along with its parent directories
+    pub fn file(&self, path: &str, contents: &str) -> io::Result<&Self> {
+        let path = self.path(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+        Ok(self)
+    }
+
+    pub fn dir(&self, path: &str) -> io::Result<&Self> {
+        fs::create_dir_all(self.path(path))?;
+        Ok(self)
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.base.join("opener.toml")
+    }
+
+    // Where the stand-ins written by `program` live, and where they can leave
+    // what they were given for the test to check
+    pub fn bin(&self) -> PathBuf {
+        self.base.join("bin")
+    }
+
+    // A shell script standing in for the external program `name`. Harnesses
+    // on the fixture run it instead of the real one; PATH is left alone.
+    pub fn program(&self, name: &str, script: &str) -> io::Result<&Self> {
+        let path = self.bin().join(name);
+        fs::create_dir_all(self.bin())?;
+        fs::write(&path, format!("#!/bin/sh\n{}", script))?;
+        #[cfg(unix)]
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        self.programs.borrow_mut().insert(name, path);
+        Ok(self)
+    }
+
+    // The stand-ins, as the `[programs]` table would name them
+    pub fn programs(&self) -> Programs {
+        self.programs.borrow().clone()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.base);
+    }
+}
+
+// Keeps the To-Do list, command history and journal of whoever runs the tests
+// out of reach. HOME is process-wide, so all harnesses share one.
+fn isolate_home() {
+    static HOME: Once = Once::new();
+    HOME.call_once(|| {
+        let home = env::temp_dir().join(format!("termfm-harness-home-{}", std::process::id()));
+        // Left over by an earlier run whose process had the same id
+        let _ = fs::remove_dir_all(&home);
+        let _ = fs::create_dir_all(&home);
+        env::set_var("HOME", home);
+    });
+}
+
+pub struct Harness {
+    pub app: App,
+    pub terminal: Terminal<TestBackend>,
+    pub events: ScriptedEvents,
+    pub fixture: Fixture,
+}
+
+impl Harness {
+    // An 80x24 screen showing the fixture's root with the default settings
+    pub fn new(fixture: Fixture) -> io::Result<Self> {
+        Self::with_options(fixture, StartupOptions::default(), 80, 24)
+    }
+
+    // `options.left` defaults to the fixture's root. An `opener.toml` written
+    // to `fixture.config_path()` beforehand is picked up.
+    pub fn with_options(
+        fixture: Fixture,
+        mut options: StartupOptions,
+        width: u16,
+        height: u16,
+    ) -> io::Result<Self> {
+        isolate_home();
+        options.left.get_or_insert_with(|| fixture.root.clone());
+        let config_path = fixture.config_path();
+        let mut opener_config = if config_path.exists() {
+            load_opener_config(&config_path)?
+        } else {
+            OpenerConfig::default()
+        };
+        opener_config.programs.merge(fixture.programs());
+        let app = App::new(&options, &config_path, Arc::new(opener_config), None)?;
+        // The test backend can't fail
+        let Ok(terminal) = Terminal::new(TestBackend::new(width, height));
+        let mut harness = Self {
+            app,
+            terminal,
+            events: ScriptedEvents::default(),
+            fixture,
+        };
+        harness.settle()?;
+        Ok(harness)
+    }
+
+    // Types `keys` and runs until all of them are handled
+    pub fn keys(&mut self, keys: &str) -> io::Result<&mut Self> {
+        self.events.text(keys);
+        self.run()
+    }
+
+    // Runs `command` at the `:` prompt, e.g. "mkdir a"
+    pub fn command(&mut self, command: &str) -> io::Result<&mut Self> {
+        self.keys(&format!(":{}", command))?;
+        self.press(KeyCode::Enter)
+    }
+
+    pub fn press(&mut self, code: KeyCode) -> io::Result<&mut Self> {
+        self.events.key(code, KeyModifiers::NONE);
+        self.run()
+    }
+
+    // A left click on the cell at `column`, `row`
+    pub fn click(&mut self, column: u16, row: u16) -> io::Result<&mut Self> {
+        self.events.push(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }));
+        self.run()
+    }
+
+    // Changes the screen size the way a terminal does, with a resize event
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<&mut Self> {
+        self.terminal.backend_mut().resize(width, height);
+        self.events.push(Event::Resize(width, height));
+        self.run()
+    }
+
+    // Handles the queued events one at a time, letting directory listings
+    // finish in between so every key sees a complete listing
+    pub fn run(&mut self) -> io::Result<&mut Self> {
+        self.settle()?;
+        while !self.events.is_empty() && !self.app.quit {
+            self.app.step(&mut self.terminal, &mut self.events)?;
+            self.settle()?;
+        }
+        Ok(self)
+    }
+
+    // Waits for the listing of the current directory and draws a frame
+    pub fn settle(&mut self) -> io::Result<&mut Self> {
+        let deadline = Instant::now() + SETTLE_TIMEOUT;
+        loop {
+            self.app.tick();
+            if self.app.background_loader.is_none() && self.app.current_dir == self.app.last_dir {
+                break;
+            }
+            if Instant::now() > deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("listing {} did not finish", self.app.current_dir.display()),
+                ));
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let Ok(_) = self.terminal.draw(|f| self.app.draw(f));
+        Ok(self)
+    }
+
+    pub fn current_dir(&self) -> &Path {
+        &self.app.current_dir
+    }
+
+    pub fn selected(&self) -> Option<String> {
+        self.app.selected_file()
+    }
+
+    // The newest message in the status bar's log
+    pub fn last_message(&self) -> &str {
+        &self.app.state.notifications.log.back().unwrap().message
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.app
+            .state
+            .files
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    // The last frame as text, one line per row
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// A pseudo-terminal for running the real binary, which needs one to draw on.
+// What the program writes to it is collected, escape sequences included.
+#[cfg(unix)]
+pub struct Pty {
+    slave: fs::File,
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+#[cfg(unix)]
+impl Pty {
+    pub fn new() -> io::Result<Self> {
+        let (mut master, mut slave) = (0, 0);
+        let size = libc::winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let opened = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &size,
+            )
+        };
+        if opened != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (mut master, slave) =
+            unsafe { (fs::File::from_raw_fd(master), fs::File::from_raw_fd(slave)) };
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let collected = Arc::clone(&output);
+        let mut answers = master.try_clone()?;
+        // Reading fails once no one has the terminal open any more
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = master.read(&mut buffer) {
+                let read = &buffer[..read];
+                // Cursor position requests are answered like a terminal would
+                for _ in read.windows(4).filter(|bytes| bytes == b"\x1b[6n") {
+                    let _ = answers.write_all(b"\x1b[1;1R");
+                }
+                collected.lock().unwrap().extend_from_slice(read);
+            }
+        });
+        Ok(Self { slave, output })
+    }
+
+    // Runs `program` in a session of its own with the terminal as its
+    // controlling one, on stdin, stdout and stderr
+    pub fn command(&self, program: impl AsRef<OsStr>) -> io::Result<Command> {
+        let mut command = Command::new(program);
+        command
+            .stdin(self.slave.try_clone()?)
+            .stdout(self.slave.try_clone()?)
+            .stderr(self.slave.try_clone()?);
+        unsafe {
+            command.pre_exec(|| {
+                if libc::login_tty(0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(command)
+    }
+
+    // Everything written to the terminal so far
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output.lock().unwrap()).into_owned()
+    }
+
+    // Waits for the output to pass `check`, false if it didn't in time
+    pub fn wait_for(&self, check: impl Fn(&str) -> bool) -> bool {
+        let deadline = Instant::now() + SETTLE_TIMEOUT;
+        while Instant::now() < deadline {
+            if check(&self.output()) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    // Whether the terminal is in raw mode, without line editing
+    pub fn is_raw(&self) -> bool {
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+        unsafe { libc::tcgetattr(self.slave.as_raw_fd(), &mut termios) };
+        termios.c_lflag & libc::ICANON == 0
+    }
+}
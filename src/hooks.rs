@@ -0,0 +1,77 @@
+// Shell commands from the `[hooks]` section of opener.toml, run in the
+// background when something happens: a directory is entered, a file opened or
+// a paste, merge, sync or transfer finished. What happened is passed on in
+// `TERMFM_*` environment variables.
+use crate::{
+    app::Notifications,
+    opener::{shell_command, spawn_detached},
+};
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookEvent {
+    EnterDir,
+    Open,
+    JobDone,
+}
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 3] = [HookEvent::EnterDir, HookEvent::Open, HookEvent::JobDone];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::EnterDir => "enter_dir",
+            HookEvent::Open => "open",
+            HookEvent::JobDone => "job_done",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Hooks {
+    pub commands: Vec<(HookEvent, String)>,
+}
+
+impl Hooks {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("hooks").cloned());
+        let Some(Value::Table(section)) = section else {
+            return Self::default();
+        };
+        let mut hooks = Self::default();
+        for (key, value) in section {
+            let Some(event) = HookEvent::ALL.into_iter().find(|e| e.name() == key) else {
+                let names: Vec<&str> = HookEvent::ALL.iter().map(|e| e.name()).collect();
+                notifications.error(format!(
+                    "Unknown hook hooks.{}, expected one of {}",
+                    key,
+                    names.join(", ")
+                ));
+                continue;
+            };
+            match value {
+                Value::String(command) => hooks.commands.push((event, command)),
+                _ => notifications.error(format!("hooks.{} must be a string", key)),
+            }
+        }
+        hooks
+    }
+
+    // Runs the event's command through `sh -c` (`cmd /C` on Windows) without waiting for it, with
+    // `TERMFM_EVENT` and `vars` in its environment
+    pub fn fire(&self, event: HookEvent, vars: &[(&str, &str)]) -> Result<(), String> {
+        let Some((_, command)) = self.commands.iter().find(|(e, _)| *e == event) else {
+            return Ok(());
+        };
+        let mut process = shell_command(command);
+        process
+            .env("TERMFM_EVENT", event.name())
+            .envs(vars.iter().copied());
+        spawn_detached(process).map_err(|e| format!("Hook {} failed: {}", event.name(), e))
+    }
+}
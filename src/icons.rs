@@ -0,0 +1,150 @@
+use crate::{
+    app::Notifications,
+    fs_ops::{mode, Entry},
+};
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+// Icons in front of file names. Nerd Font glyphs need a patched font, the
+// ASCII set works everywhere and only tells the kind of entry apart.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum IconStyle {
+    #[default]
+    None,
+    Nerd,
+    Ascii,
+}
+
+// Nerd Font glyphs by extension, checked after the kind of entry
+const NERD_EXTENSIONS: &[(&[&str], &str)] = &[
+    (&["rs"], "\u{e7a8}"),
+    (&["py", "pyc", "ipynb"], "\u{e606}"),
+    (&["js", "mjs", "cjs"], "\u{e74e}"),
+    (&["ts", "tsx"], "\u{e628}"),
+    (&["go"], "\u{e626}"),
+    (&["c", "h"], "\u{e61e}"),
+    (&["cpp", "cc", "cxx", "hpp"], "\u{e61d}"),
+    (&["java", "kt"], "\u{e738}"),
+    (&["lua"], "\u{e620}"),
+    (&["sh", "bash", "zsh", "fish"], "\u{f489}"),
+    (&["html", "htm"], "\u{e736}"),
+    (&["css", "scss"], "\u{e749}"),
+    (&["md", "markdown"], "\u{f48a}"),
+    (&["json"], "\u{e60b}"),
+    (&["toml", "yaml", "yml", "ini", "conf", "cfg"], "\u{e615}"),
+    (&["lock"], "\u{f023}"),
+    (
+        &[
+            "png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "heic", "ico",
+        ],
+        "\u{f1c5}",
+    ),
+    (&["mp4", "mkv", "webm", "avi", "mov"], "\u{f1c8}"),
+    (&["mp3", "flac", "ogg", "wav", "m4a", "opus"], "\u{f1c7}"),
+    (
+        &[
+            "zip", "tar", "gz", "xz", "bz2", "zst", "7z", "rar", "deb", "rpm",
+        ],
+        "\u{f1c6}",
+    ),
+    (&["pdf"], "\u{f1c1}"),
+    (&["doc", "docx", "odt"], "\u{f1c2}"),
+    (&["xls", "xlsx", "ods", "csv"], "\u{f1c3}"),
+    (&["ppt", "pptx", "odp"], "\u{f1c4}"),
+    (&["txt", "log"], "\u{f15c}"),
+];
+
+const NERD_DIRECTORY: &str = "\u{f07b}";
+const NERD_SYMLINK: &str = "\u{f0c1}";
+const NERD_EXECUTABLE: &str = "\u{f489}";
+const NERD_FILE: &str = "\u{f15b}";
+
+impl IconStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(IconStyle::None),
+            "nerd" => Some(IconStyle::Nerd),
+            "ascii" => Some(IconStyle::Ascii),
+            _ => None,
+        }
+    }
+
+    // `style` in the `[icons]` section, off unless set
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let style = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| match table.get("icons") {
+                Some(Value::Table(section)) => section
+                    .get("style")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                _ => None,
+            });
+        let Some(style) = style else {
+            return Self::default();
+        };
+        Self::parse(&style).unwrap_or_else(|| {
+            notifications.error(format!(
+                "Unknown icon style {}, expected nerd, ascii or none",
+                style
+            ));
+            Self::default()
+        })
+    }
+
+    // The icon and a space, empty when icons are off
+    pub fn prefix(self, entry: &Entry) -> String {
+        match self {
+            IconStyle::None => String::new(),
+            IconStyle::Nerd => format!("{} ", nerd_icon(entry)),
+            IconStyle::Ascii => format!("{} ", ascii_icon(entry)),
+        }
+    }
+}
+
+fn is_executable(entry: &Entry) -> bool {
+    entry
+        .metadata
+        .as_ref()
+        .is_some_and(|m| m.is_file() && mode(m) & 0o111 != 0)
+}
+
+pub fn nerd_icon(entry: &Entry) -> &'static str {
+    if entry.is_symlink {
+        return NERD_SYMLINK;
+    }
+    if entry.is_dir {
+        return NERD_DIRECTORY;
+    }
+    let extension = Path::new(&entry.name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    if let Some(extension) = extension {
+        if let Some((_, icon)) = NERD_EXTENSIONS
+            .iter()
+            .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+        {
+            return icon;
+        }
+    }
+    if is_executable(entry) {
+        NERD_EXECUTABLE
+    } else {
+        NERD_FILE
+    }
+}
+
+// The classifiers of `ls -F`
+pub fn ascii_icon(entry: &Entry) -> char {
+    if entry.is_symlink {
+        '@'
+    } else if entry.is_dir {
+        '/'
+    } else if is_executable(entry) {
+        '*'
+    } else {
+        '-'
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{app::Notifications, fs_ops::Entry};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+// What happens to entries matched by `.gitignore`, `.ignore` or the
+// configured patterns
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum IgnoreMode {
+    #[default]
+    Show,
+    Dim,
+    Hide,
+}
+
+impl IgnoreMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "show" => Some(IgnoreMode::Show),
+            "dim" => Some(IgnoreMode::Dim),
+            "hide" => Some(IgnoreMode::Hide),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            IgnoreMode::Show => IgnoreMode::Dim,
+            IgnoreMode::Dim => IgnoreMode::Hide,
+            IgnoreMode::Hide => IgnoreMode::Show,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IgnoreMode::Show => "shown",
+            IgnoreMode::Dim => "dimmed",
+            IgnoreMode::Hide => "hidden",
+        }
+    }
+}
+
+// The ignore files that apply to one directory, nearest first, and the
+// patterns from the `[ignore]` section after them
+#[derive(Default)]
+pub struct IgnoreRules {
+    pub mode: IgnoreMode,
+    patterns: Vec<String>,
+    files: Vec<Gitignore>,
+    global: Option<Gitignore>,
+}
+
+impl IgnoreRules {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("ignore").cloned());
+        let Some(Value::Table(section)) = section else {
+            return Self::default();
+        };
+
+        let mode = match section.get("mode").and_then(|v| v.as_str()) {
+            Some(value) => IgnoreMode::parse(value).unwrap_or_else(|| {
+                notifications.error(format!(
+                    "Unknown ignore mode {}, expected show, dim or hide",
+                    value
+                ));
+                IgnoreMode::Show
+            }),
+            None => IgnoreMode::Show,
+        };
+        let patterns: Vec<String> = section
+            .get("patterns")
+            .and_then(|v| v.as_array())
+            .map(|patterns| {
+                patterns
+                    .iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut builder = GitignoreBuilder::new("/");
+        for pattern in &patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                notifications.error(format!("Invalid ignore pattern {}: {}", pattern, e));
+            }
+        }
+        Self {
+            mode,
+            patterns,
+            ..Self::default()
+        }
+    }
+
+    // Reads the ignore files for `dir`: `.ignore` in every directory above it,
+    // `.gitignore` up to the root of the repository it is in
+    pub fn update(&mut self, dir: &Path) {
+        self.files.clear();
+        self.global = None;
+        if self.mode == IgnoreMode::Show {
+            return;
+        }
+        let repository = dir.ancestors().find(|d| d.join(".git").exists());
+        for ancestor in dir.ancestors() {
+            let in_repository = repository.is_some_and(|root| ancestor.starts_with(root));
+            let mut names = vec![".ignore"];
+            if in_repository {
+                names.push(".gitignore");
+            }
+            for name in names {
+                let path = ancestor.join(name);
+                if path.is_file() {
+                    // Lines that don't parse are left out
+                    let (matcher, _) = Gitignore::new(&path);
+                    self.files.push(matcher);
+                }
+            }
+        }
+        if !self.patterns.is_empty() {
+            let mut builder = GitignoreBuilder::new(dir);
+            for pattern in &self.patterns {
+                let _ = builder.add_line(None, pattern);
+            }
+            self.global = builder.build().ok();
+        }
+    }
+
+    // The nearest file with a matching rule decides, `!` rules included
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.mode == IgnoreMode::Show {
+            return false;
+        }
+        for matcher in self.files.iter().chain(&self.global) {
+            if !path.starts_with(matcher.path()) {
+                continue;
+            }
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    // Drops the ignored entries when they are to be hidden
+    pub fn filter(&self, dir: &Path, entries: &mut Vec<Entry>) {
+        if self.mode == IgnoreMode::Hide {
+            entries.retain(|entry| !self.is_ignored(&dir.join(&entry.name), entry.is_dir));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+// Remote control of a running instance. Every instance listens on a socket of
+// its own and passes its path on as `$TERMFM_SOCKET` to the shells and
+// programs it starts; `termfm --remote <command>` sends a `:` command there
+// and prints the answer. One JSON request and one JSON reply per line.
+// Sockets are unix sockets, elsewhere there is no remote control.
+#[cfg(unix)]
+use crate::terminal::wake;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    net::{UnixListener, UnixStream},
+};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::{Duration, SystemTime};
+
+// The interface may be busy with a terminal program for much longer, and
+// then the command waits until it's back
+#[cfg(unix)]
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[cfg(not(unix))]
+pub(crate) fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "only unix has sockets")
+}
+
+// Where the sockets of the daemon and the instances go: the runtime
+// directory, or without one a directory of the user's own in the shared temp
+// directory, which others can't put sockets into
+#[cfg(unix)]
+pub fn socket_dir() -> PathBuf {
+    if let Some(dir) = dirs::runtime_dir() {
+        return dir;
+    }
+    let dir = env::temp_dir().join(format!("termfm-{}", user_id()));
+    let _ = fs::DirBuilder::new().mode(0o700).create(&dir);
+    dir
+}
+
+#[cfg(not(unix))]
+pub fn socket_dir() -> PathBuf {
+    env::temp_dir()
+}
+
+#[cfg(unix)]
+pub fn user_id() -> u32 {
+    // SAFETY: getuid can't fail
+    unsafe { libc::getuid() }
+}
+
+#[cfg(unix)]
+fn socket_prefix() -> String {
+    format!("termfm-{}-", user_id())
+}
+
+#[cfg(not(unix))]
+fn socket_prefix() -> String {
+    "termfm-".to_string()
+}
+
+// Connects to a socket of the user's own. Anyone can make one in a shared
+// directory, under any name, to serve made-up listings or read commands.
+#[cfg(unix)]
+pub fn connect(path: &Path) -> io::Result<UnixStream> {
+    let owner = fs::symlink_metadata(path)?.uid();
+    if owner != user_id() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", path.display()),
+        ));
+    }
+    UnixStream::connect(path)
+}
+
+pub fn instance_socket() -> PathBuf {
+    socket_dir().join(format!("{}{}.sock", socket_prefix(), std::process::id()))
+}
+
+// `$TERMFM_SOCKET` inside the file manager, otherwise the instance started
+// last that still answers
+#[cfg(unix)]
+pub fn target_socket() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("TERMFM_SOCKET") {
+        return Some(PathBuf::from(path));
+    }
+    let prefix = socket_prefix();
+    let mut sockets: Vec<(SystemTime, PathBuf)> = fs::read_dir(socket_dir())
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(&prefix) && name.ends_with(".sock")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    sockets.sort();
+    sockets
+        .into_iter()
+        .rev()
+        .map(|(_, path)| path)
+        .find(|path| connect(path).is_ok())
+}
+
+#[cfg(not(unix))]
+pub fn target_socket() -> Option<PathBuf> {
+    env::var_os("TERMFM_SOCKET").map(PathBuf::from)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Request {
+    pub command: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reply {
+    // "ok" or "error", as in the results of `--script`
+    pub status: String,
+    pub message: String,
+    pub cwd: PathBuf,
+    // The marked entries, or the one under the cursor
+    pub selection: Vec<PathBuf>,
+}
+
+pub struct IpcServer {
+    pub path: PathBuf,
+    receiver: mpsc::Receiver<(String, mpsc::Sender<Reply>)>,
+}
+
+impl IpcServer {
+    // Listens on `path`; the commands are picked up with `requests`
+    #[cfg(unix)]
+    pub fn bind(path: PathBuf) -> io::Result<Self> {
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || forward(stream, &sender));
+            }
+        });
+        Ok(Self { path, receiver })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_path: PathBuf) -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    pub fn requests(&self) -> Vec<(String, mpsc::Sender<Reply>)> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Hands each request to the interface and writes back its reply
+#[cfg(unix)]
+fn forward(stream: UnixStream, sender: &mpsc::Sender<(String, mpsc::Sender<Reply>)>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = writeln!(writer, "{}", serde_json::json!({ "error": e.to_string() }));
+                return;
+            }
+        };
+        let (reply_sender, reply) = mpsc::channel();
+        if sender.send((request.command, reply_sender)).is_err() {
+            return;
+        }
+        wake();
+        let Ok(reply) = reply.recv() else {
+            return;
+        };
+        let Ok(json) = serde_json::to_string(&reply) else {
+            return;
+        };
+        if writeln!(writer, "{}", json).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn send(socket: &Path, command: &str) -> io::Result<Reply> {
+    let mut stream = connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let request = serde_json::to_string(&Request {
+        command: command.to_string(),
+    })?;
+    writeln!(stream, "{}", request)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(not(unix))]
+pub fn send(_socket: &Path, _command: &str) -> io::Result<Reply> {
+    Err(unsupported())
+}
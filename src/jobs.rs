@@ -0,0 +1,568 @@
+use crate::{
+    app::Notifications,
+    command::expand_path,
+    config::glob_match,
+    daemon::request_listing,
+    find::{find_with, Query},
+    fs_ops::{
+        files_identical, format_size, format_time, move_path, read_entries, remove_empty_dirs,
+        sort_entries, unique_path, Entry, SortMode,
+    },
+    terminal::wake,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use toml::Value;
+
+pub type LoadResult = Result<Vec<Entry>, String>;
+
+// A batch is sent once it has this many entries or the previous one is this
+// old, so slow mounts still show progress
+pub const LOAD_BATCH_SIZE: usize = 1000;
+pub const LOAD_BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+pub enum LoadUpdate {
+    // More entries in directory order, shown while the rest is read
+    Batch(Vec<Entry>),
+    // The whole listing, sorted
+    Done(LoadResult),
+}
+
+pub struct BackgroundLoader {
+    pub current_dir: PathBuf,
+    pub show_hidden: bool,
+    pub sort_mode: SortMode,
+    pub stat_entries: bool,
+    pub receiver: Option<mpsc::Receiver<LoadUpdate>>,
+}
+
+impl BackgroundLoader {
+    pub fn new(dir: PathBuf, show_hidden: bool, sort_mode: SortMode, stat_entries: bool) -> Self {
+        Self {
+            current_dir: dir,
+            show_hidden,
+            sort_mode,
+            stat_entries,
+            receiver: None,
+        }
+    }
+
+    // Dropping the loader stops the thread at its next batch
+    pub fn start(&mut self) {
+        let dir = self.current_dir.clone();
+        let show_hidden = self.show_hidden;
+        let sort_mode = self.sort_mode;
+        let stat_entries = self.stat_entries;
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+
+        thread::spawn(move || {
+            let send = |update| {
+                let sent = sender.send(update).is_ok();
+                wake();
+                sent
+            };
+            // A running daemon may have the listing already
+            let files = match request_listing(&dir) {
+                Some(names) => Ok(names
+                    .iter()
+                    .filter(|name| show_hidden || !name.name.starts_with('.'))
+                    .map(|name| name.entry(&dir, stat_entries))
+                    .collect()),
+                None => read_entries(&dir, show_hidden, stat_entries, |batch| {
+                    send(LoadUpdate::Batch(batch.to_vec()))
+                }),
+            }
+            .map(|mut entries: Vec<Entry>| {
+                sort_entries(&mut entries, sort_mode, stat_entries);
+                entries
+            })
+            .map_err(|e| format!("Failed to load {}: {}", dir.display(), e));
+            send(LoadUpdate::Done(files));
+        });
+    }
+
+    // Lists what `query` finds below the directory instead of its entries,
+    // with paths relative to it as names
+    pub fn start_find(&mut self, query: Query) {
+        let dir = self.current_dir.clone();
+        let show_hidden = self.show_hidden;
+        let sort_mode = self.sort_mode;
+        let stat_entries = self.stat_entries;
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+
+        let load = |dir: &Path, paths: &[PathBuf]| -> Vec<Entry> {
+            paths
+                .iter()
+                .map(|path| Entry::load(dir, path.to_string_lossy().into_owned()))
+                .collect()
+        };
+        thread::spawn(move || {
+            let send = |update| {
+                let sent = sender.send(update).is_ok();
+                wake();
+                sent
+            };
+            let files = find_with(&dir, &query, show_hidden, |batch| {
+                send(LoadUpdate::Batch(load(&dir, batch)))
+            })
+            .map(|paths| {
+                let mut entries = load(&dir, &paths);
+                sort_entries(&mut entries, sort_mode, stat_entries);
+                entries
+            })
+            .map_err(|e| format!("Failed to search {}: {}", dir.display(), e));
+            send(LoadUpdate::Done(files));
+        });
+    }
+
+    pub fn updates(&self) -> Vec<LoadUpdate> {
+        match &self.receiver {
+            Some(receiver) => receiver.try_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConflictChoice {
+    KeepNewest,
+    KeepLarger,
+    KeepBoth,
+    Skip,
+}
+
+// Moves the contents of `source` into `dest` one entry at a time, stopping at
+// every file that exists on both sides until the user picks a resolution
+pub struct Merge {
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    // Paths relative to `source` still to be merged
+    pub pending: VecDeque<PathBuf>,
+    // Relative path waiting for a decision
+    pub conflict: Option<PathBuf>,
+    pub remembered: Option<ConflictChoice>,
+    pub moved: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+    pub journal: Journal,
+}
+
+impl Merge {
+    pub fn new(source: PathBuf, dest: PathBuf) -> io::Result<Self> {
+        let pending = Self::children(&source, Path::new(""))?;
+        let journal = Journal::begin(JournalOperation::Merge {
+            source: source.clone(),
+            dest: dest.clone(),
+        });
+        Ok(Self {
+            source,
+            dest,
+            pending,
+            conflict: None,
+            remembered: None,
+            moved: 0,
+            skipped: 0,
+            errors: Vec::new(),
+            journal,
+        })
+    }
+
+    pub fn children(source: &Path, rel: &Path) -> io::Result<VecDeque<PathBuf>> {
+        let mut children: Vec<PathBuf> = fs::read_dir(source.join(rel))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| rel.join(entry.file_name()))
+            .collect();
+        children.sort();
+        Ok(children.into())
+    }
+
+    // Processes entries until a conflict needs an answer or everything is done.
+    // Returns true once the merge has finished.
+    pub fn advance(&mut self) -> bool {
+        while self.conflict.is_none() {
+            let Some(rel) = self.pending.pop_front() else {
+                remove_empty_dirs(&self.source);
+                return true;
+            };
+            let (from, to) = (self.source.join(&rel), self.dest.join(&rel));
+            let (Ok(from_meta), to_meta) = (fs::symlink_metadata(&from), fs::symlink_metadata(&to))
+            else {
+                continue;
+            };
+            match to_meta {
+                // Whole subtrees move in one go when nothing is in the way
+                Err(_) => {
+                    let result = self.journal.move_path(&from, &to);
+                    self.record(&rel, result);
+                }
+                Ok(to_meta) if from_meta.is_dir() && to_meta.is_dir() => {
+                    match Self::children(&self.source, &rel) {
+                        Ok(children) => self.pending.extend(children),
+                        Err(e) => self.errors.push(format!("{}: {}", rel.display(), e)),
+                    }
+                }
+                Ok(to_meta) if from_meta.is_dir() != to_meta.is_dir() => {
+                    self.skipped += 1;
+                    self.errors.push(format!(
+                        "{}: a file and a directory share this name",
+                        rel.display()
+                    ));
+                }
+                // Exact duplicates need no decision
+                Ok(_) if files_identical(&from, &to) => {
+                    self.record(&rel, fs::remove_file(&from));
+                }
+                Ok(_) => match self.remembered {
+                    Some(choice) => self.resolve_with(rel, choice),
+                    None => self.conflict = Some(rel),
+                },
+            }
+        }
+        false
+    }
+
+    // Applies the user's answer to the current conflict, optionally for all
+    // later ones too
+    pub fn resolve(&mut self, choice: ConflictChoice, remember: bool) {
+        if remember {
+            self.remembered = Some(choice);
+        }
+        if let Some(rel) = self.conflict.take() {
+            self.resolve_with(rel, choice);
+        }
+    }
+
+    pub fn resolve_with(&mut self, rel: PathBuf, choice: ConflictChoice) {
+        let (from, to) = (self.source.join(&rel), self.dest.join(&rel));
+        // Links are compared as themselves, as `advance` saw them
+        let (from_meta, to_meta) = match (fs::symlink_metadata(&from), fs::symlink_metadata(&to)) {
+            (Ok(from_meta), Ok(to_meta)) => (from_meta, to_meta),
+            (Err(e), _) | (_, Err(e)) => {
+                self.errors.push(format!("{}: {}", rel.display(), e));
+                return;
+            }
+        };
+        // Whether the incoming file replaces the existing one
+        let replace = match choice {
+            ConflictChoice::KeepNewest => from_meta.modified().ok() > to_meta.modified().ok(),
+            ConflictChoice::KeepLarger => from_meta.len() > to_meta.len(),
+            ConflictChoice::KeepBoth => {
+                let to = unique_path(&to, |path| fs::symlink_metadata(path).is_err());
+                let result = self.journal.move_path(&from, &to);
+                self.record(&rel, result);
+                return;
+            }
+            ConflictChoice::Skip => {
+                self.skipped += 1;
+                return;
+            }
+        };
+        let result = if replace {
+            self.journal.move_path(&from, &to)
+        } else {
+            fs::remove_file(&from)
+        };
+        self.record(&rel, result);
+    }
+
+    pub fn record(&mut self, rel: &Path, result: io::Result<()>) {
+        match result {
+            Ok(()) => self.moved += 1,
+            Err(e) => self.errors.push(format!("{}: {}", rel.display(), e)),
+        }
+    }
+
+    pub fn finish(self, notifications: &mut Notifications) {
+        self.journal.finish();
+        notifications.info(format!(
+            "Merged {} into {}: {} merged, {} skipped, {} failed",
+            self.source.display(),
+            self.dest.display(),
+            self.moved,
+            self.skipped,
+            self.errors.len()
+        ));
+        for error in self.errors {
+            notifications.error(error);
+        }
+    }
+
+    pub fn conflict_lines(&self) -> Vec<String> {
+        let Some(rel) = &self.conflict else {
+            return Vec::new();
+        };
+        let describe = |label: &str, path: &Path| match fs::metadata(path) {
+            Ok(meta) => format!(
+                "{:<8}{:>10}  {}",
+                label,
+                format_size(meta.len()),
+                format_time(meta.modified().ok())
+            ),
+            Err(e) => format!("{:<8}{}", label, e),
+        };
+        vec![
+            rel.display().to_string(),
+            String::new(),
+            describe("source", &self.source.join(rel)),
+            describe("target", &self.dest.join(rel)),
+            String::new(),
+            "[n] keep newest  [l] keep larger  [b] keep both  [s] skip".to_string(),
+            "Shift remembers the choice for the rest, Esc stops the merge".to_string(),
+        ]
+    }
+}
+
+// Moves files matching any of `patterns` into `target`, a directory relative
+// to the organized one whose strftime fields are filled from the file's mtime
+pub struct FilingRule {
+    pub patterns: Vec<String>,
+    pub target: String,
+}
+
+pub fn load_filing_rules(config_path: &Path) -> Vec<FilingRule> {
+    let rules = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| contents.parse::<toml::Table>().ok())
+        .and_then(|table| table.get("filing").cloned());
+    let Some(Value::Array(rules)) = rules else {
+        return Vec::new();
+    };
+
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let patterns = match rule.get("match")? {
+                Value::String(pattern) => vec![pattern.clone()],
+                Value::Array(patterns) => patterns
+                    .iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect(),
+                _ => return None,
+            };
+            let target = rule.get("to")?.as_str()?.to_string();
+            Some(FilingRule { patterns, target })
+        })
+        .collect()
+}
+
+// Pairs each file in `dir` with its destination under the first matching rule.
+// Existing files at the destination are never overwritten.
+pub fn plan_filing(
+    dir: &Path,
+    rules: &[FilingRule],
+    show_hidden: bool,
+) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut plan = Vec::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if (!show_hidden && name.starts_with('.')) || !entry.file_type()?.is_file() {
+            continue;
+        }
+        let Some(rule) = rules
+            .iter()
+            .find(|rule| rule.patterns.iter().any(|p| glob_match(p, &name)))
+        else {
+            continue;
+        };
+
+        let modified: chrono::DateTime<chrono::Local> = entry.metadata()?.modified()?.into();
+        let target_dir = expand_path(&modified.format(&rule.target).to_string(), dir);
+        if target_dir == dir {
+            continue;
+        }
+        let is_free = |path: &Path| !path.exists() && !claimed.contains(path);
+        let mut target = target_dir.join(&name);
+        if !is_free(&target) {
+            target = unique_path(&target, is_free);
+        }
+        claimed.insert(target.clone());
+        plan.push((entry.path(), target));
+    }
+    Ok(plan)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum JournalOperation {
+    // Moves still to be done, e.g. by `:organize`
+    Moves(Vec<(PathBuf, PathBuf)>),
+    Merge { source: PathBuf, dest: PathBuf },
+}
+
+// Record of a running file operation, kept in `~/.termfm_journal.<pid>.json`
+// and removed when the operation ends. Finding one at startup whose process is
+// gone means that run died in the middle of it; those of running instances are
+// theirs.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Journal {
+    // Process the journal belongs to, taken from its file name
+    #[serde(skip)]
+    pid: u32,
+    pub operation: JournalOperation,
+    // Move running at the last write; a copy across filesystems may have
+    // left its target incomplete
+    pub in_flight: Option<(PathBuf, PathBuf)>,
+}
+
+impl Journal {
+    fn path_of(pid: u32) -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(format!(".termfm_journal.{}.json", pid)))
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        Self::path_of(self.pid)
+    }
+
+    pub fn begin(operation: JournalOperation) -> Self {
+        let journal = Self {
+            pid: std::process::id(),
+            operation,
+            in_flight: None,
+        };
+        journal.save();
+        journal
+    }
+
+    // The journal of an instance that is no longer running, if there is one
+    pub fn load() -> Option<Self> {
+        let home = dirs::home_dir()?;
+        fs::read_dir(home).ok()?.flatten().find_map(|entry| {
+            let name = entry.file_name();
+            let pid: u32 = name
+                .to_str()?
+                .strip_prefix(".termfm_journal.")?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()?;
+            if pid == std::process::id() || process_alive(pid) {
+                return None;
+            }
+            let contents = fs::read_to_string(entry.path()).ok()?;
+            let journal: Self = serde_json::from_str(&contents).ok()?;
+            Some(Self { pid, ..journal })
+        })
+    }
+
+    // Takes over the journal of a dead instance before acting on it. Fails if
+    // another instance got to it first.
+    pub fn adopt(&mut self) -> io::Result<()> {
+        let own = std::process::id();
+        if self.pid != own {
+            let (Some(from), Some(to)) = (self.path(), Self::path_of(own)) else {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no home directory"));
+            };
+            fs::rename(from, to)?;
+            self.pid = own;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) {
+        if let (Some(path), Ok(serialized)) = (self.path(), serde_json::to_string(self)) {
+            let _ = fs::write(path, serialized);
+        }
+    }
+
+    pub fn finish(self) {
+        if let Some(path) = self.path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    // `move_path` with the journal written before and after
+    pub fn move_path(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        self.in_flight = Some((from.to_path_buf(), to.to_path_buf()));
+        self.save();
+        let result = move_path(from, to);
+        if let JournalOperation::Moves(moves) = &mut self.operation {
+            moves.retain(|(pending, _)| pending != from);
+        }
+        self.in_flight = None;
+        self.save();
+        result
+    }
+
+    // Performs the remaining moves of a `Moves` journal, creating target
+    // directories as needed. Returns the number of moved files and the errors.
+    pub fn apply_moves(&mut self) -> (usize, Vec<String>) {
+        let JournalOperation::Moves(moves) = self.operation.clone() else {
+            return (0, Vec::new());
+        };
+        let mut moved = 0;
+        let mut errors = Vec::new();
+        for (from, to) in &moves {
+            let result = to
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| self.move_path(from, to));
+            match result {
+                Ok(()) => moved += 1,
+                Err(e) => errors.push(format!("Failed to move {}: {}", from.display(), e)),
+            }
+        }
+        (moved, errors)
+    }
+
+    // Target of an interrupted file copy. Directories are never reported: their
+    // moved children only exist on the target side.
+    pub fn partial_target(&self) -> Option<&Path> {
+        let (from, to) = self.in_flight.as_ref()?;
+        let from_is_file = fs::symlink_metadata(from).is_ok_and(|meta| !meta.is_dir());
+        (from_is_file && fs::symlink_metadata(to).is_ok()).then_some(to.as_path())
+    }
+
+    // Completes the interrupted move, overwriting an incomplete target
+    pub fn resume_in_flight(&mut self) -> io::Result<()> {
+        match self.in_flight.clone() {
+            Some((from, to)) if fs::symlink_metadata(&from).is_ok() => self.move_path(&from, &to),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = match &self.operation {
+            JournalOperation::Moves(moves) => {
+                vec![format!("Moving files, {} move(s) left", moves.len())]
+            }
+            JournalOperation::Merge { source, dest } => vec![format!(
+                "Merging {} into {}",
+                source.display(),
+                dest.display()
+            )],
+        };
+        if let Some(target) = self.partial_target() {
+            lines.push(format!("Incomplete copy: {}", target.display()));
+        }
+        lines.push(String::new());
+        lines.push("[r] resume  [c] clean up and discard  [Esc] decide later".to_string());
+        lines
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Without a way to tell, every journal is taken to belong to a running
+// instance and left alone
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,46 @@
+pub mod app;
+pub mod cache;
+pub mod checksum;
+pub mod chords;
+pub mod cli;
+pub mod clipboard;
+pub mod columns;
+pub mod command;
+pub mod config;
+pub mod custom;
+pub mod daemon;
+pub mod devices;
+pub mod diff;
+pub mod find;
+pub mod finder_tags;
+pub mod follow;
+pub mod fs_ops;
+pub mod git;
+pub mod harness;
+pub mod hooks;
+pub mod icons;
+pub mod ignored;
+pub mod ipc;
+pub mod jobs;
+pub mod macros;
+pub mod markdown;
+pub mod media;
+pub mod notes;
+pub mod opener;
+pub mod photo;
+pub mod preview;
+pub mod previewers;
+pub mod register;
+pub mod repeat;
+pub mod script;
+pub mod select;
+pub mod shell;
+pub mod sync;
+pub mod tags;
+pub mod terminal;
+pub mod theme;
+pub mod todo;
+pub mod tree;
+pub mod tutorial;
+pub mod ui;
+pub mod vfs;
@@ -0,0 +1,48 @@
+// Keyboard macros: `Q<register>` records the keys typed until the next `Q`,
+// `@<register>` types them again. They are kept for the session only.
+use crossterm::event::Event;
+use std::collections::HashMap;
+
+// How deep macros may replay other macros, which stops one replaying itself
+// forever
+pub const MAX_MACRO_DEPTH: usize = 8;
+
+#[derive(Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<Event>>,
+    // The register being recorded into and the keys so far
+    pub recording: Option<(char, Vec<Event>)>,
+    // What `@@` replays
+    pub last: Option<char>,
+    // Replays in progress, the keys they type are not recorded again
+    pub depth: usize,
+}
+
+impl Macros {
+    pub fn start(&mut self, register: char) {
+        self.recording = Some((register, Vec::new()));
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if self.depth > 0 {
+            return;
+        }
+        if let Some((_, keys)) = self.recording.as_mut() {
+            keys.push(event.clone());
+        }
+    }
+
+    // Keeps the recording, without the `Q` that ended it. Returns the
+    // register and the number of keys.
+    pub fn stop(&mut self) -> Option<(char, usize)> {
+        let (register, mut keys) = self.recording.take()?;
+        keys.pop();
+        let count = keys.len();
+        self.registers.insert(register, keys);
+        Some((register, count))
+    }
+
+    pub fn get(&self, register: char) -> Option<&[Event]> {
+        self.registers.get(&register).map(Vec::as_slice)
+    }
+}
@@ -0,0 +1,212 @@
+// Renders Markdown for the preview pane: headings, emphasis, lists, quotes
+// and code blocks get styles of their own, the markup itself is dropped.
+use crate::ui::{Color as TuiColor, Line, Modifier, Span, Style};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+pub const EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+struct Renderer {
+    lines: Vec<Line<'static>>,
+    current: Vec<Span<'static>>,
+    // Inline styles that are open, innermost last
+    styles: Vec<Style>,
+    // Per open list: the next number, None for bullets
+    lists: Vec<Option<u64>>,
+    quote_depth: usize,
+    in_code_block: bool,
+}
+
+impl Renderer {
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, next| style.patch(*next))
+    }
+
+    // Quote bars and list indentation in front of every line of a block
+    fn prefix(&self) -> Vec<Span<'static>> {
+        let mut prefix = Vec::new();
+        for _ in 0..self.quote_depth {
+            prefix.push(Span::styled("│ ", Style::default().fg(TuiColor::DarkGray)));
+        }
+        if !self.lists.is_empty() {
+            prefix.push(Span::raw("  ".repeat(self.lists.len())));
+        }
+        prefix
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.current.is_empty() {
+            self.current = self.prefix();
+        }
+        self.current
+            .push(Span::styled(text.to_string(), self.style()));
+    }
+
+    fn finish_line(&mut self) {
+        if !self.current.is_empty() {
+            self.lines
+                .push(Line::from(std::mem::take(&mut self.current)));
+        }
+    }
+
+    // An empty line between blocks, but never two
+    fn blank_line(&mut self) {
+        self.finish_line();
+        if self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+}
+
+pub fn render(text: &str) -> Vec<Line<'static>> {
+    let mut renderer = Renderer {
+        lines: Vec::new(),
+        current: Vec::new(),
+        styles: Vec::new(),
+        lists: Vec::new(),
+        quote_depth: 0,
+        in_code_block: false,
+    };
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Heading { level, .. } => {
+                    renderer.blank_line();
+                    let style = match level {
+                        HeadingLevel::H1 => Style::default()
+                            .fg(TuiColor::Cyan)
+                            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                        HeadingLevel::H2 => Style::default()
+                            .fg(TuiColor::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                        _ => Style::default().add_modifier(Modifier::BOLD),
+                    };
+                    renderer.styles.push(style);
+                }
+                Tag::Paragraph if renderer.lists.is_empty() => renderer.blank_line(),
+                Tag::BlockQuote(_) => {
+                    renderer.blank_line();
+                    renderer.quote_depth += 1;
+                    renderer
+                        .styles
+                        .push(Style::default().add_modifier(Modifier::ITALIC));
+                }
+                Tag::CodeBlock(kind) => {
+                    renderer.blank_line();
+                    renderer.in_code_block = true;
+                    if let CodeBlockKind::Fenced(language) = kind {
+                        if !language.is_empty() {
+                            renderer.push_text(&format!("[{}]", language));
+                            renderer.finish_line();
+                        }
+                    }
+                    renderer.styles.push(Style::default().fg(TuiColor::Yellow));
+                }
+                Tag::List(start) => {
+                    if renderer.lists.is_empty() {
+                        renderer.blank_line();
+                    } else {
+                        renderer.finish_line();
+                    }
+                    renderer.lists.push(start);
+                }
+                Tag::Item => {
+                    renderer.finish_line();
+                    // The marker takes the place of the innermost indentation
+                    let depth = renderer.lists.len();
+                    let marker = match renderer.lists.last_mut() {
+                        Some(Some(number)) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        }
+                        _ => "• ".to_string(),
+                    };
+                    let mut prefix: Vec<Span<'static>> = (0..renderer.quote_depth)
+                        .map(|_| Span::styled("│ ", Style::default().fg(TuiColor::DarkGray)))
+                        .collect();
+                    prefix.push(Span::raw("  ".repeat(depth - 1)));
+                    prefix.push(Span::styled(marker, Style::default().fg(TuiColor::Cyan)));
+                    renderer.current = prefix;
+                }
+                Tag::Emphasis => renderer
+                    .styles
+                    .push(Style::default().add_modifier(Modifier::ITALIC)),
+                Tag::Strong => renderer
+                    .styles
+                    .push(Style::default().add_modifier(Modifier::BOLD)),
+                Tag::Strikethrough => renderer
+                    .styles
+                    .push(Style::default().add_modifier(Modifier::CROSSED_OUT)),
+                Tag::Link { .. } | Tag::Image { .. } => renderer.styles.push(
+                    Style::default()
+                        .fg(TuiColor::Blue)
+                        .add_modifier(Modifier::UNDERLINED),
+                ),
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Heading(_) => {
+                    renderer.styles.pop();
+                    renderer.finish_line();
+                }
+                TagEnd::Paragraph => renderer.finish_line(),
+                TagEnd::BlockQuote(_) => {
+                    renderer.finish_line();
+                    renderer.quote_depth -= 1;
+                    renderer.styles.pop();
+                }
+                TagEnd::CodeBlock => {
+                    renderer.finish_line();
+                    renderer.in_code_block = false;
+                    renderer.styles.pop();
+                }
+                TagEnd::List(_) => {
+                    renderer.finish_line();
+                    renderer.lists.pop();
+                }
+                TagEnd::Item => renderer.finish_line(),
+                TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Link
+                | TagEnd::Image => {
+                    renderer.styles.pop();
+                }
+                _ => {}
+            },
+            Event::Text(text) if renderer.in_code_block => {
+                for line in text.lines() {
+                    renderer.push_text(&format!("  {}", line));
+                    renderer.finish_line();
+                }
+            }
+            Event::Text(text) => renderer.push_text(&text),
+            Event::Code(code) => {
+                if renderer.current.is_empty() {
+                    renderer.current = renderer.prefix();
+                }
+                renderer.current.push(Span::styled(
+                    code.to_string(),
+                    renderer.style().fg(TuiColor::Yellow),
+                ));
+            }
+            Event::SoftBreak => renderer.push_text(" "),
+            Event::HardBreak => renderer.finish_line(),
+            Event::Rule => {
+                renderer.blank_line();
+                renderer.lines.push(Line::from(Span::styled(
+                    "─".repeat(40),
+                    Style::default().fg(TuiColor::DarkGray),
+                )));
+            }
+            Event::TaskListMarker(done) => {
+                renderer.push_text(if done { "[x] " } else { "[ ] " });
+            }
+            _ => {}
+        }
+    }
+    renderer.finish_line();
+    renderer.lines
+}
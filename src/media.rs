@@ -0,0 +1,179 @@
+// Audio and video previews: what `ffprobe` knows about the file instead of
+// its binary contents
+use crate::{
+    config::Programs,
+    preview::{cancellable_output, Preview},
+};
+use serde_json::Value;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+pub const EXTENSIONS: [&str; 18] = [
+    "mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "wma", "mp4", "m4v", "mkv", "webm", "avi",
+    "mov", "wmv", "flv", "mpg", "mpeg",
+];
+
+// Tags worth showing, in this order
+const TAGS: [&str; 6] = ["title", "artist", "album", "date", "genre", "track"];
+
+// None for files that aren't audio or video
+pub fn preview_media(
+    file_path: &Path,
+    programs: &Programs,
+    cancel: &AtomicBool,
+) -> Option<Preview> {
+    let extension = file_path.extension()?.to_string_lossy().to_lowercase();
+    if !EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let output = cancellable_output(
+        programs
+            .command("ffprobe")
+            .args([
+                "-v",
+                "quiet",
+                "-print_format",
+                "json",
+                "-show_format",
+                "-show_streams",
+            ])
+            .arg(file_path),
+        cancel,
+    );
+    let message = match output {
+        Ok(output) if output.status.success() => {
+            match serde_json::from_slice::<Value>(&output.stdout) {
+                Ok(probe) => {
+                    return Some(Preview {
+                        lines: describe(&probe),
+                        encoding: None,
+                        text_format: None,
+                        rendered: None,
+                        tail: None,
+                    })
+                }
+                Err(_) => "<ffprobe printed something unexpected>".to_string(),
+            }
+        }
+        Ok(_) => "<ffprobe could not read the file>".to_string(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            format!("<Install ffprobe (ffmpeg) to preview .{} files>", extension)
+        }
+        Err(e) => format!("<Failed to run ffprobe: {}>", e),
+    };
+    Some(Preview::message(&message))
+}
+
+// `ffprobe -show_format -show_streams` output as lines for the preview
+pub fn describe(probe: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    let format = &probe["format"];
+    if let Some(name) = format["format_long_name"]
+        .as_str()
+        .or(format["format_name"].as_str())
+    {
+        lines.push(format!("Format:   {}", name));
+    }
+    if let Some(seconds) = number(&format["duration"]) {
+        lines.push(format!("Duration: {}", format_duration(seconds)));
+    }
+    if let Some(bit_rate) = number(&format["bit_rate"]) {
+        lines.push(format!("Bitrate:  {} kb/s", (bit_rate / 1000.0).round()));
+    }
+
+    for stream in probe["streams"].as_array().into_iter().flatten() {
+        let codec = stream["codec_name"].as_str().unwrap_or("unknown");
+        match stream["codec_type"].as_str() {
+            Some("video") => {
+                // Cover art is a video stream of a single picture
+                if stream["disposition"]["attached_pic"].as_i64() == Some(1) {
+                    lines.push(format!("Cover:    {}", codec));
+                    continue;
+                }
+                let mut line = format!("Video:    {}", codec);
+                if let (Some(width), Some(height)) =
+                    (stream["width"].as_i64(), stream["height"].as_i64())
+                {
+                    line.push_str(&format!(", {}x{}", width, height));
+                }
+                if let Some(fps) = stream["avg_frame_rate"].as_str().and_then(frame_rate) {
+                    line.push_str(&format!(", {:.2} fps", fps));
+                }
+                lines.push(line);
+            }
+            Some("audio") => {
+                let mut line = format!("Audio:    {}", codec);
+                if let Some(rate) = number(&stream["sample_rate"]) {
+                    line.push_str(&format!(", {} Hz", rate));
+                }
+                if let Some(layout) = stream["channel_layout"].as_str() {
+                    line.push_str(&format!(", {}", layout));
+                } else if let Some(channels) = stream["channels"].as_i64() {
+                    line.push_str(&format!(", {} channels", channels));
+                }
+                lines.push(line);
+            }
+            Some("subtitle") => lines.push(format!("Subtitle: {}", codec)),
+            _ => {}
+        }
+    }
+
+    // Ogg keeps its tags on the streams, and the case of tag names differs
+    // between containers
+    let tags: Vec<&serde_json::Map<String, Value>> = std::iter::once(&format["tags"])
+        .chain(
+            probe["streams"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|stream| &stream["tags"]),
+        )
+        .filter_map(|tags| tags.as_object())
+        .collect();
+    let mut first = true;
+    for name in TAGS {
+        let value = tags.iter().find_map(|tags| {
+            tags.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .and_then(|(_, value)| value.as_str())
+        });
+        if let Some(value) = value {
+            if first {
+                lines.push(String::new());
+                first = false;
+            }
+            let label = format!("{}{}:", name[..1].to_uppercase(), &name[1..]);
+            lines.push(format!("{:<10}{}", label, value));
+        }
+    }
+    if lines.is_empty() {
+        lines.push("<No media information>".to_string());
+    }
+    lines
+}
+
+// ffprobe prints most numbers as strings
+fn number(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+// "30000/1001" and the like
+fn frame_rate(value: &str) -> Option<f64> {
+    let (numerator, denominator) = value.split_once('/')?;
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+    (denominator > 0.0 && numerator > 0.0).then(|| numerator / denominator)
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
@@ -0,0 +1,78 @@
+// Short notes on files and directories, kept in `notes.json` next to
+// `opener.toml` and shown in the properties popup
+use crate::{app::Notifications, fs_ops::rename_keys};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+#[derive(Default)]
+pub struct Notes {
+    // None keeps the notes in memory only
+    path: Option<PathBuf>,
+    // Notes also follow the names in the listing
+    pub in_listing: bool,
+    pub notes: BTreeMap<PathBuf, String>,
+}
+
+impl Notes {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let in_listing = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| match table.get("notes") {
+                Some(Value::Table(section)) => section.get("in_listing")?.as_bool(),
+                _ => None,
+            })
+            .unwrap_or(false);
+
+        let path = config_path.with_file_name("notes.json");
+        let notes = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                notifications.error(format!("Failed to read {}: {}", path.display(), e));
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            in_listing,
+            notes,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let serialized = serde_json::to_string_pretty(&self.notes).map_err(io::Error::other)?;
+        fs::write(path, serialized)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        self.notes.get(path).map(|note| note.as_str())
+    }
+
+    // None removes the note
+    pub fn set(&mut self, path: &Path, note: Option<&str>) {
+        match note.map(str::trim).filter(|note| !note.is_empty()) {
+            Some(note) => {
+                self.notes.insert(path.to_path_buf(), note.to_string());
+            }
+            None => {
+                self.notes.remove(path);
+            }
+        }
+    }
+
+    // Keeps the notes of a moved or renamed entry, and of everything below it
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        rename_keys(&mut self.notes, from, to);
+    }
+
+    // Drops the notes of deleted entries and everything below them
+    pub fn forget(&mut self, removed: &Path) {
+        self.notes.retain(|path, _| !path.starts_with(removed));
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{
+    config::{detect_mime, Opener, OpenerConfig, Programs},
+    terminal::with_suspended_tui,
+    vfs::percent_encode,
+};
+use std::collections::HashSet;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+
+// Quotes a string for safe interpolation into a `shell_command` line
+pub fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        posix_quote(value)
+    }
+}
+
+// Quoting for `sh`, `bash`, `zsh` and `fish` on any platform
+pub fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Runs a command line through `sh -c`, or `cmd /C` on Windows
+pub fn shell_command(line: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(line);
+    command
+}
+
+// Splits a command line into arguments, honouring single and double quotes
+// and backslash escapes the way a POSIX shell would
+pub fn split_command_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    current.push(c);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// Expands an opener template into program arguments:
+//   %f  the file, %d  its directory, %s  all selected files, %%  a literal %
+// A template without placeholders gets the file appended.
+// `%s` standing alone expands to one argument per file.
+pub fn expand_template(
+    template: &str,
+    file: &Path,
+    selected: &[PathBuf],
+) -> Result<Vec<String>, String> {
+    let words = split_command_line(template)?;
+    if words.is_empty() {
+        return Err("Empty opener command".to_string());
+    }
+    let file_str = file.to_string_lossy().into_owned();
+    let dir_str = file
+        .parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let selected_strs: Vec<String> = selected
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    let has_placeholder = ["%f", "%d", "%s"].iter().any(|p| template.contains(p));
+    let mut args = Vec::new();
+    for word in words {
+        if word == "%s" {
+            args.extend(selected_strs.iter().cloned());
+            continue;
+        }
+        let mut expanded = String::new();
+        let mut chars = word.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('f') => expanded.push_str(&file_str),
+                Some('d') => expanded.push_str(&dir_str),
+                Some('s') => expanded.push_str(&selected_strs.join(" ")),
+                Some('%') => expanded.push('%'),
+                Some(other) => {
+                    expanded.push('%');
+                    expanded.push(other);
+                }
+                None => expanded.push('%'),
+            }
+        }
+        args.push(expanded);
+    }
+    if !has_placeholder {
+        args.push(file_str);
+    }
+    Ok(args)
+}
+
+// Whether the template uses shell syntax (pipes, redirections, lists,
+// substitutions) outside of quotes and so has to run through `sh -c`
+pub fn needs_shell(template: &str) -> bool {
+    let mut quote: Option<char> = None;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\\') => {
+                chars.next();
+            }
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '|' | '&' | ';' | '<' | '>' | '$' | '`' | '(' | ')') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// Like `expand_template`, but produces a script for `sh -c`. Every substituted
+// path is quoted, so placeholders must not be quoted in the template itself.
+pub fn expand_shell_template(template: &str, file: &Path, selected: &[PathBuf]) -> String {
+    let mut script = expand_placeholders(template, file, selected);
+    if !["%f", "%d", "%s"].iter().any(|p| template.contains(p)) {
+        script.push(' ');
+        script.push_str(&shell_quote(&file.to_string_lossy()));
+    }
+    script
+}
+
+// The placeholders of a shell line replaced by quoted paths, without adding
+// the file when there are none
+pub fn expand_placeholders(template: &str, file: &Path, selected: &[PathBuf]) -> String {
+    let quote = |path: &Path| shell_quote(&path.to_string_lossy());
+    let file_str = quote(file);
+    let dir_str = quote(file.parent().unwrap_or(Path::new("/")));
+    let selected_str = selected
+        .iter()
+        .map(|path| quote(path))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut script = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            script.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => script.push_str(&file_str),
+            Some('d') => script.push_str(&dir_str),
+            Some('s') => script.push_str(&selected_str),
+            Some('%') => script.push('%'),
+            Some(other) => {
+                script.push('%');
+                script.push(other);
+            }
+            None => script.push('%'),
+        }
+    }
+    script
+}
+
+// Builds the process for a template, returning the program name for messages
+pub fn template_command(
+    template: &str,
+    file: &Path,
+    selected: &[PathBuf],
+) -> Result<(String, Command), String> {
+    if needs_shell(template) {
+        let program = split_command_line(template)
+            .ok()
+            .and_then(|words| words.into_iter().next())
+            .unwrap_or_else(|| "sh".to_string());
+        let command = shell_command(&expand_shell_template(template, file, selected));
+        return Ok((program, command));
+    }
+
+    let args = expand_template(template, file, selected)?;
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    Ok((args[0].clone(), command))
+}
+
+pub fn open_file(
+    file_path: &Path,
+    selected: &[PathBuf],
+    opener_config: &Arc<OpenerConfig>,
+) -> Result<String, String> {
+    let fallback;
+    // Entries may only define a preview
+    let opener = match opener_config
+        .lookup(file_path)
+        .filter(|opener| !opener.command.is_empty())
+    {
+        Some(opener) => opener,
+        None if opener_config.system_fallback => {
+            fallback = Opener::new(OpenerConfig::SYSTEM_OPENER.to_string(), false);
+            &fallback
+        }
+        None => {
+            return Err(format!(
+                "No opener configured for {} ({})",
+                file_path.display(),
+                detect_mime(file_path).unwrap_or_else(|| "unknown type".to_string())
+            ))
+        }
+    };
+    run_opener(opener, file_path, selected, &opener_config.programs)
+}
+
+// %s refers to the marked files, or the file itself
+pub fn open_targets(marked: &HashSet<PathBuf>, file_path: &Path) -> Vec<PathBuf> {
+    let mut selected: Vec<PathBuf> = marked.iter().cloned().collect();
+    if selected.is_empty() {
+        selected.push(file_path.to_path_buf());
+    }
+    selected.sort();
+    selected
+}
+
+pub fn run_opener(
+    opener: &Opener,
+    file_path: &Path,
+    selected: &[PathBuf],
+    programs: &Programs,
+) -> Result<String, String> {
+    let (program, command) = template_command(&opener.command, file_path, selected)?;
+    let mut command = programs.resolve(command);
+
+    if opener.terminal {
+        // Hand the terminal over to the program until it exits
+        let status = with_suspended_tui(|| command.status());
+        return match status {
+            Ok(status) if status.success() => Ok(format!("{} exited", program)),
+            Ok(status) => Err(format!("{} exited with {}", program, status)),
+            Err(e) => Err(format!("Failed to run {}: {}", program, e)),
+        };
+    }
+
+    match spawn_detached(command) {
+        Ok(()) => Ok(format!("Opened {} with {}", file_path.display(), program)),
+        Err(e) => Err(format!("Failed to run {}: {}", program, e)),
+    }
+}
+
+// GUI programs get their own session and must not write into the TUI
+pub(crate) fn spawn_detached(mut command: Command) -> io::Result<()> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    // DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP: no console, and Ctrl+C in
+    // ours doesn't reach it
+    #[cfg(windows)]
+    command.creation_flags(0x0000_0008 | 0x0000_0200);
+    let mut child = command.spawn()?;
+    // Reap the child in the background so it doesn't linger as a zombie
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+// Opens a directory in the desktop file manager
+pub fn show_folder(dir: &Path, programs: &Programs) -> Result<String, String> {
+    let (_, command) = template_command(
+        &format!("{} %f", OpenerConfig::SYSTEM_OPENER),
+        dir,
+        &[dir.to_path_buf()],
+    )?;
+    spawn_detached(programs.resolve(command))
+        .map(|_| format!("Opened {} in the file manager", dir.display()))
+        .map_err(|e| format!("Failed to run {}: {}", OpenerConfig::SYSTEM_OPENER, e))
+}
+
+// Opens the file manager on the directory holding `path`, with `path`
+// selected: Finder's `open -R` on macOS, elsewhere the FileManager1 D-Bus
+// interface that Nautilus, Dolphin, Nemo, Thunar and others implement. Without
+// one of those the directory is opened as it is.
+pub fn reveal(path: &Path, programs: &Programs) -> Result<String, String> {
+    let revealed = format!("Revealed {} in the file manager", path.display());
+    if cfg!(target_os = "macos") {
+        let mut command = programs.command("open");
+        command.arg("-R").arg(path);
+        return spawn_detached(command)
+            .map(|_| revealed)
+            .map_err(|e| format!("Failed to run open: {}", e));
+    }
+    let uri = format!("file://{}", percent_encode(&path.to_string_lossy()));
+    // Waited for, as it only asks the file manager and tells whether one answered
+    let shown = programs
+        .command("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(revealed);
+    }
+    show_folder(path.parent().unwrap_or(path), programs)
+}
@@ -0,0 +1,136 @@
+// Image previews: the dimensions and the EXIF data of photos, enough to sort
+// a camera dump without opening every file
+use crate::preview::Preview;
+use exif::{Exif, In, Reader, Tag, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+pub const EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "tif", "tiff", "png", "webp", "heic", "heif"];
+
+// None for files that aren't images
+pub fn preview_photo(file_path: &Path) -> Option<Preview> {
+    let extension = file_path.extension()?.to_string_lossy().to_lowercase();
+    if !EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    Some(Preview {
+        lines: describe(file_path),
+        encoding: None,
+        text_format: None,
+        rendered: None,
+        tail: None,
+    })
+}
+
+pub fn describe(file_path: &Path) -> Vec<String> {
+    let exif = File::open(file_path).ok().and_then(|file| {
+        Reader::new()
+            .read_from_container(&mut BufReader::new(file))
+            .ok()
+    });
+    let mut lines = Vec::new();
+
+    let dimensions = imagesize::size(file_path)
+        .ok()
+        .map(|size| (size.width as u32, size.height as u32))
+        .or_else(|| {
+            let exif = exif.as_ref()?;
+            Some((
+                uint(exif, Tag::PixelXDimension)?,
+                uint(exif, Tag::PixelYDimension)?,
+            ))
+        });
+    if let Some((width, height)) = dimensions {
+        lines.push(format!("Dimensions: {}x{}", width, height));
+    }
+
+    let Some(exif) = exif else {
+        lines.push("<No EXIF data>".to_string());
+        return lines;
+    };
+    let make = ascii(&exif, Tag::Make);
+    let model = ascii(&exif, Tag::Model);
+    let camera = match (make, model) {
+        // Most models repeat the make already
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    if let Some(camera) = camera {
+        lines.push(format!("Camera:     {}", camera));
+    }
+    if let Some(lens) = ascii(&exif, Tag::LensModel) {
+        lines.push(format!("Lens:       {}", lens));
+    }
+    if let Some(taken) = ascii(&exif, Tag::DateTimeOriginal).or_else(|| ascii(&exif, Tag::DateTime))
+    {
+        // "2023:05:01 12:00:00" has colons in the date as well
+        lines.push(format!("Taken:      {}", taken.replacen(':', "-", 2)));
+    }
+
+    let mut exposure = Vec::new();
+    if let Some(time) = rational(&exif, Tag::ExposureTime, 0) {
+        exposure.push(if time < 1.0 && time > 0.0 {
+            format!("1/{} s", (1.0 / time).round())
+        } else {
+            format!("{} s", time)
+        });
+    }
+    if let Some(f_number) = rational(&exif, Tag::FNumber, 0) {
+        exposure.push(format!("f/{:.1}", f_number));
+    }
+    if let Some(iso) = uint(&exif, Tag::PhotographicSensitivity) {
+        exposure.push(format!("ISO {}", iso));
+    }
+    if !exposure.is_empty() {
+        lines.push(format!("Exposure:   {}", exposure.join(", ")));
+    }
+    if let Some(focal_length) = rational(&exif, Tag::FocalLength, 0) {
+        lines.push(format!("Focal:      {} mm", focal_length));
+    }
+    if let (Some(latitude), Some(longitude)) = (
+        coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef),
+        coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef),
+    ) {
+        lines.push(format!("GPS:        {:.6}, {:.6}", latitude, longitude));
+    }
+    lines
+}
+
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+fn uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn rational(exif: &Exif, tag: Tag, index: usize) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values
+            .get(index)
+            .filter(|value| value.denom != 0)
+            .map(|value| value.to_f64()),
+        _ => None,
+    }
+}
+
+// Degrees, minutes and seconds to signed decimal degrees, south and west
+// being negative
+fn coordinate(exif: &Exif, tag: Tag, reference: Tag) -> Option<f64> {
+    let degrees = rational(exif, tag, 0)?;
+    let minutes = rational(exif, tag, 1).unwrap_or(0.0);
+    let seconds = rational(exif, tag, 2).unwrap_or(0.0);
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    match ascii(exif, reference).as_deref() {
+        Some("S") | Some("W") => Some(-value),
+        _ => Some(value),
+    }
+}
@@ -0,0 +1,611 @@
+use crate::{
+    config::{OpenerConfig, Programs},
+    fs_ops::{format_size, TextFormat},
+    markdown,
+    opener::{shell_quote, template_command},
+    previewers::PreviewerRegistry,
+    terminal::wake,
+    ui::Line,
+};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone)]
+pub struct Preview {
+    pub lines: Vec<String>,
+    // Detected text encoding, shown in the preview title
+    pub encoding: Option<&'static str>,
+    pub text_format: Option<TextFormat>,
+    // Markdown as it would be displayed, shown instead of `lines` unless the
+    // source is asked for
+    pub rendered: Option<Vec<Line<'static>>>,
+    // The end of a file too large to read whole, shown instead of `lines`
+    // when asked for
+    pub tail: Option<Vec<String>>,
+}
+
+impl Preview {
+    pub fn message(text: &str) -> Self {
+        Self {
+            lines: vec![text.to_string()],
+            encoding: None,
+            text_format: None,
+            rendered: None,
+            tail: None,
+        }
+    }
+
+    pub fn title(&self, base: &str) -> String {
+        match (self.encoding, self.text_format) {
+            (Some(encoding), Some(format)) => {
+                format!("{} [{}, {}]", base, encoding, format.label())
+            }
+            (Some(encoding), None) => format!("{} [{}]", base, encoding),
+            _ => base.to_string(),
+        }
+    }
+}
+
+// A preview over the whole terminal, scrolled like a pager. The rows are
+// counted when it is drawn.
+pub struct Pager {
+    pub path: PathBuf,
+    pub scroll: usize,
+    pub rows: usize,
+    pub height: usize,
+}
+
+impl Pager {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            scroll: 0,
+            rows: 0,
+            height: 0,
+        }
+    }
+
+    // Moves by `rows`, keeping the last page full
+    pub fn scroll_by(&mut self, rows: isize) {
+        let last = self.rows.saturating_sub(self.height);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(last);
+    }
+}
+
+// Tabs have no width of their own on screen, they would leave whatever was
+// drawn there before visible
+pub fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = 8 - column % 8;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += c.width().unwrap_or(0);
+        }
+    }
+    expanded
+}
+
+// Selection has to rest this long before a preview is generated, so scrolling
+// through a directory doesn't start a batcat for every entry passed
+pub const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(75);
+// Previews generated at the same time, cancelled ones included until their
+// process has been killed
+pub const PREVIEW_JOB_LIMIT: usize = 2;
+
+// Puts the file to preview in place on the worker thread first, e.g. downloads
+// a remote one. What it fails with is shown instead.
+pub type Fetch = Box<dyn FnOnce(&AtomicBool) -> Result<(), String> + Send>;
+
+// Generates previews on worker threads. Only the latest requested file is
+// previewed, an in-flight preview of an earlier selection is cancelled.
+pub struct PreviewWorker {
+    // File waiting for the debounce, how to fetch it and since when
+    pub requested: Option<(PathBuf, Option<Fetch>, Instant)>,
+    // Generation and cancel flag of the job whose result is wanted
+    pub current: Option<(u64, Arc<AtomicBool>)>,
+    pub generation: u64,
+    // Free job slots
+    pub slots: Arc<(Mutex<usize>, Condvar)>,
+    pub sender: mpsc::Sender<(u64, PathBuf, Preview)>,
+    pub receiver: mpsc::Receiver<(u64, PathBuf, Preview)>,
+    pub previewers: Arc<PreviewerRegistry>,
+}
+
+impl Default for PreviewWorker {
+    fn default() -> Self {
+        Self::new(Arc::new(PreviewerRegistry::default()))
+    }
+}
+
+impl PreviewWorker {
+    pub fn new(previewers: Arc<PreviewerRegistry>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            requested: None,
+            current: None,
+            generation: 0,
+            slots: Arc::new((Mutex::new(PREVIEW_JOB_LIMIT), Condvar::new())),
+            sender,
+            receiver,
+            previewers,
+        }
+    }
+
+    pub fn request(&mut self, path: PathBuf) {
+        self.request_fetched(path, None);
+    }
+
+    // Like `request`, `fetch` run before the preview is generated
+    pub fn request_fetched(&mut self, path: PathBuf, fetch: Option<Fetch>) {
+        if let Some((_, cancel)) = self.current.take() {
+            cancel.store(true, Ordering::SeqCst);
+        }
+        self.requested = Some((path, fetch, Instant::now()));
+    }
+
+    // When the requested preview starts, if one is waiting
+    pub fn deadline(&self) -> Option<Instant> {
+        self.requested
+            .as_ref()
+            .map(|(_, _, since)| *since + PREVIEW_DEBOUNCE)
+    }
+
+    // Starts the requested preview once the selection has settled and returns
+    // the result of the current one when it is ready
+    pub fn poll(&mut self, opener_config: &Arc<OpenerConfig>) -> Option<(PathBuf, Preview)> {
+        if let Some((_, _, since)) = &self.requested {
+            if since.elapsed() >= PREVIEW_DEBOUNCE {
+                let (path, fetch, _) = self.requested.take().unwrap();
+                self.start(path, fetch, Arc::clone(opener_config));
+            }
+        }
+
+        let mut ready = None;
+        for (generation, path, preview) in self.receiver.try_iter() {
+            if self.current.as_ref().is_some_and(|(g, _)| *g == generation) {
+                ready = Some((path, preview));
+            }
+        }
+        if ready.is_some() {
+            self.current = None;
+        }
+        ready
+    }
+
+    pub fn start(&mut self, path: PathBuf, fetch: Option<Fetch>, opener_config: Arc<OpenerConfig>) {
+        self.generation += 1;
+        let generation = self.generation;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.current = Some((generation, Arc::clone(&cancel)));
+        let slots = Arc::clone(&self.slots);
+        let sender = self.sender.clone();
+        let previewers = Arc::clone(&self.previewers);
+
+        thread::spawn(move || {
+            let (free, available) = &*slots;
+            {
+                let mut free = free.lock().unwrap();
+                while *free == 0 {
+                    if cancel.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    free = available
+                        .wait_timeout(free, Duration::from_millis(50))
+                        .unwrap()
+                        .0;
+                }
+                *free -= 1;
+            }
+            let preview = match fetch.map_or(Ok(()), |fetch| fetch(&cancel)) {
+                Ok(()) => preview_file(&path, &opener_config, &previewers, &cancel),
+                Err(e) => Preview::message(&e),
+            };
+            *free.lock().unwrap() += 1;
+            available.notify_one();
+            if !cancel.load(Ordering::SeqCst) {
+                let _ = sender.send((generation, path, preview));
+                wake();
+            }
+        });
+    }
+}
+
+impl Drop for PreviewWorker {
+    fn drop(&mut self) {
+        if let Some((_, cancel)) = self.current.take() {
+            cancel.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+// Like Command::output, but kills the process as soon as `cancel` is set.
+// On Unix it runs in a process group of its own so that the children of
+// `sh -c` templates are killed along with it.
+pub fn cancellable_output(command: &mut Command, cancel: &AtomicBool) -> io::Result<Output> {
+    cancellable_output_with_input(command, None, cancel)
+}
+
+// `cancellable_output` with `input` written to the process's stdin
+pub fn cancellable_output_with_input(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    cancel: &AtomicBool,
+) -> io::Result<Output> {
+    let stdin = match input {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command.spawn()?;
+    // Drained on their own threads so a chatty process can't fill the pipe
+    // and block while we wait for it
+    let read_all = |mut pipe: Box<dyn io::Read + Send>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = pipe.read_to_end(&mut buffer);
+            buffer
+        })
+    };
+    let stdout = read_all(Box::new(child.stdout.take().unwrap()));
+    let stderr = read_all(Box::new(child.stderr.take().unwrap()));
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        // A process that exits without reading it fails on its own
+        let _ = stdin.write_all(input);
+    }
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancel.load(Ordering::SeqCst) {
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            #[cfg(not(unix))]
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+// Detects the encoding of text from its BOM, the NUL byte pattern of UTF-16
+// or byte statistics. Returns None for binary data.
+pub fn detect_encoding(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Some(encoding_rs::UTF_8);
+    }
+
+    // ASCII-heavy UTF-16 without a BOM leaves every other byte zero
+    let sample = &bytes[..bytes.len().min(4096)];
+    let pairs = sample.len() / 2;
+    let even_nuls = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_nuls = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+    if pairs > 0 && odd_nuls * 4 > pairs * 3 && even_nuls * 10 < pairs {
+        return Some(encoding_rs::UTF_16LE);
+    }
+    if pairs > 0 && even_nuls * 4 > pairs * 3 && odd_nuls * 10 < pairs {
+        return Some(encoding_rs::UTF_16BE);
+    }
+    if sample.contains(&0) {
+        return None;
+    }
+
+    let mut detector = chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Allow);
+    detector.feed(bytes, true);
+    Some(detector.guess(None, chardetng::Utf8Detection::Allow))
+}
+
+// Documents that are binary on disk, with the converters that turn them
+// into text in order of preference and what to install when none is there
+pub const DOCUMENT_EXTENSIONS: [&str; 5] = ["pdf", "docx", "odt", "epub", "rtf"];
+
+const DOCUMENT_CONVERTERS: &[(&[&str], &[&str], &str)] = &[
+    (
+        &["pdf"],
+        &[
+            "pdftotext -l 3 -layout %f -",
+            "mutool draw -F txt -o - %f 1-3",
+        ],
+        "pdftotext (poppler-utils)",
+    ),
+    (
+        &["docx"],
+        &["pandoc -t plain %f", "docx2txt %f -"],
+        "pandoc",
+    ),
+    (&["odt"], &["pandoc -t plain %f", "odt2txt %f"], "pandoc"),
+    (&["epub", "rtf"], &["pandoc -t plain %f"], "pandoc"),
+];
+
+// The text of a PDF or office document, None for other files
+pub fn preview_document(
+    file_path: &Path,
+    programs: &Programs,
+    cancel: &AtomicBool,
+) -> Option<Preview> {
+    let extension = file_path.extension()?.to_string_lossy().to_lowercase();
+    let (_, converters, install) = DOCUMENT_CONVERTERS
+        .iter()
+        .find(|(extensions, _, _)| extensions.contains(&extension.as_str()))?;
+    for template in *converters {
+        let Ok((program, command)) =
+            template_command(template, file_path, &[file_path.to_path_buf()])
+        else {
+            continue;
+        };
+        match cancellable_output(&mut programs.resolve(command), cancel) {
+            Ok(output) => return Some(output_preview(output)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Some(Preview::message(&format!(
+                    "<Failed to run {}: {}>",
+                    program, e
+                )))
+            }
+        }
+    }
+    Some(Preview::message(&format!(
+        "<Install {} to preview .{} files>",
+        install, extension
+    )))
+}
+
+// Runs a user preview template and shows its first lines
+pub fn preview_command(template: &str, file_path: &Path, cancel: &AtomicBool) -> Preview {
+    let output = template_command(template, file_path, &[file_path.to_path_buf()]).and_then(
+        |(program, mut command)| {
+            cancellable_output(&mut command, cancel)
+                .map_err(|e| format!("Failed to run {}: {}", program, e))
+        },
+    );
+    match output {
+        Ok(output) => output_preview(output),
+        Err(e) => Preview::message(&format!("<{}>", e)),
+    }
+}
+
+// The first lines a preview command printed, falling back to stderr so
+// failures are visible
+pub fn output_preview(output: Output) -> Preview {
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    if text.is_empty() {
+        return Preview::message("<Preview command produced no output>");
+    }
+    Preview {
+        lines: String::from_utf8_lossy(&text)
+            .lines()
+            .take(PREVIEW_LINES)
+            .map(expand_tabs)
+            .collect(),
+        encoding: None,
+        text_format: None,
+        rendered: None,
+        tail: None,
+    }
+}
+
+// An opener's own preview template wins, everything else goes through the
+// previewers in order of priority
+pub fn preview_file(
+    file_path: &Path,
+    opener_config: &OpenerConfig,
+    previewers: &PreviewerRegistry,
+    cancel: &AtomicBool,
+) -> Preview {
+    if let Some(template) = opener_config
+        .lookup(file_path)
+        .and_then(|opener| opener.preview.as_ref())
+    {
+        return preview_command(template, file_path, cancel);
+    }
+    previewers.preview(file_path, &opener_config.programs, cancel)
+}
+
+// Lines kept of a preview, more than the pane shows for the full-screen
+// preview. The end of a large file keeps only what the pane shows.
+pub const PREVIEW_LINES: usize = 1000;
+const TAIL_LINES: usize = 20;
+
+// Files larger than this are only read in part
+pub const LARGE_FILE: u64 = 1_000_000;
+// What is read from the start and from the end of a large file
+pub const PARTIAL_BYTES: u64 = 64 * 1024;
+
+// The start and the end of a large text file, each from a partial read and
+// marked as such. Neither is highlighted, batcat would read the whole file.
+fn preview_large(file_path: &Path, len: u64) -> io::Result<Preview> {
+    let mut file = fs::File::open(file_path)?;
+    let mut head = Vec::new();
+    (&mut file).take(PARTIAL_BYTES).read_to_end(&mut head)?;
+    let Some(encoding) = detect_encoding(&head) else {
+        return Ok(Preview::message(&format!(
+            "<Binary file of {}, too large for preview>",
+            format_size(len)
+        )));
+    };
+    // UTF-16 has to be read from the start of a code unit
+    let mut start = len - PARTIAL_BYTES;
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        start -= start % 2;
+    }
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut tail)?;
+
+    // The reads cut a line at either end, those are dropped. The marker comes
+    // first so that it is visible in a small pane.
+    let head_text = encoding.decode(&head).0;
+    let mut head_lines: Vec<&str> = head_text.lines().collect();
+    if head_lines.len() > 1 {
+        head_lines.pop();
+    }
+    let mut lines = vec![format!(
+        "<Showing the first {} of {}, T shows the end>",
+        format_size(PARTIAL_BYTES),
+        format_size(len)
+    )];
+    lines.extend(
+        head_lines
+            .iter()
+            .take(PREVIEW_LINES)
+            .enumerate()
+            .map(|(i, line)| expand_tabs(&format!("{:>6}\t{}", i + 1, line))),
+    );
+
+    let (tail_text, _) = encoding.decode_without_bom_handling(&tail);
+    let tail_lines: Vec<&str> = tail_text.lines().skip(1).collect();
+    let mut tail = vec![format!(
+        "<Showing the last {} of {}, T shows the start>",
+        format_size(PARTIAL_BYTES),
+        format_size(len)
+    )];
+    tail.extend(
+        tail_lines[tail_lines.len().saturating_sub(TAIL_LINES)..]
+            .iter()
+            .map(|line| expand_tabs(line)),
+    );
+
+    Ok(Preview {
+        lines,
+        encoding: Some(encoding.name()),
+        text_format: None,
+        rendered: None,
+        tail: Some(tail),
+    })
+}
+
+// The first lines of a text file, highlighted by batcat when it is installed.
+// Documents, media and photos are handled by previewers before this, their
+// size doesn't matter as only the converter's output is shown.
+pub fn preview_text(file_path: &Path, programs: &Programs, cancel: &AtomicBool) -> Preview {
+    if let Ok(metadata) = fs::metadata(file_path) {
+        if metadata.len() > LARGE_FILE {
+            return preview_large(file_path, metadata.len())
+                .unwrap_or_else(|e| Preview::message(&format!("<Failed to read file: {}>", e)));
+        }
+    }
+
+    let encoding = fs::read(file_path)
+        .ok()
+        .and_then(|bytes| detect_encoding(&bytes).map(|encoding| (encoding, bytes)));
+    let text_format = encoding.as_ref().map(|(encoding, bytes)| {
+        let bom = encoding_rs::Encoding::for_bom(bytes).is_some();
+        TextFormat::detect(&encoding.decode(bytes).0, bom)
+    });
+    if let Some((encoding, bytes)) = &encoding {
+        // batcat only understands UTF-8, transcode everything else ourselves
+        if *encoding != encoding_rs::UTF_8 {
+            let (text, _, _) = encoding.decode(bytes);
+            return Preview {
+                lines: text
+                    .lines()
+                    .take(PREVIEW_LINES)
+                    .enumerate()
+                    .map(|(i, line)| expand_tabs(&format!("{:>6}\t{}", i + 1, line)))
+                    .collect(),
+                encoding: Some(encoding.name()),
+                text_format,
+                rendered: None,
+                tail: None,
+            };
+        }
+    }
+
+    let is_markdown = file_path.extension().is_some_and(|ext| {
+        markdown::EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    });
+    let rendered = match &encoding {
+        Some((_, bytes)) if is_markdown => Some(markdown::render(&String::from_utf8_lossy(bytes))),
+        _ => None,
+    };
+
+    let output = cancellable_output(
+        programs
+            .command("batcat")
+            .args([
+                "-n",
+                "--style=plain",
+                "--color=always",
+                "--paging=never",
+                "--wrap=never",
+            ])
+            .arg(file_path),
+        cancel,
+    )
+    .or_else(|e| match e.kind() {
+        io::ErrorKind::Interrupted => Err(e),
+        _ => cancellable_output(
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!("nl {}", shell_quote(&file_path.to_string_lossy()))),
+            cancel,
+        ),
+    })
+    .unwrap_or_else(|_| Output {
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        status: std::process::ExitStatus::default(),
+    });
+
+    if output.stdout.is_empty() {
+        if !file_path.exists() {
+            return Preview::message("<File does not exist>");
+        }
+        if fs::metadata(file_path).map(|m| m.len()).unwrap_or(0) == 0 {
+            return Preview::message("<Empty file>");
+        }
+        return Preview::message("<Failed to preview file>");
+    }
+
+    Preview {
+        lines: String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .take(PREVIEW_LINES)
+            .map(expand_tabs)
+            .collect(),
+        encoding: encoding.map(|(encoding, _)| encoding.name()),
+        text_format,
+        rendered,
+        tail: None,
+    }
+}
@@ -0,0 +1,236 @@
+// Previewers decide what the preview pane shows for a file. The built-in ones
+// cover documents, media, photos and text; `[[previewers]]` in opener.toml
+// adds user commands in the spirit of ranger's scope.sh.
+use crate::{
+    app::Notifications,
+    config::{detect_mime, glob_match, Programs},
+    media,
+    opener::template_command,
+    photo,
+    preview::{
+        cancellable_output, output_preview, preview_document, preview_text, Preview,
+        DOCUMENT_EXTENSIONS,
+    },
+};
+use std::cell::OnceCell;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use toml::Value;
+
+// The file being previewed, and where to find the programs previewing it. Its
+// MIME type is only sniffed when a previewer asks for it.
+pub struct PreviewTarget<'a> {
+    pub path: &'a Path,
+    pub extension: String,
+    pub programs: &'a Programs,
+    mime: OnceCell<Option<String>>,
+}
+
+impl<'a> PreviewTarget<'a> {
+    pub fn new(path: &'a Path, programs: &'a Programs) -> Self {
+        Self {
+            path,
+            programs,
+            extension: path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+            mime: OnceCell::new(),
+        }
+    }
+
+    pub fn mime(&self) -> Option<&str> {
+        self.mime.get_or_init(|| detect_mime(self.path)).as_deref()
+    }
+}
+
+pub trait Previewer: Send + Sync {
+    fn id(&self) -> &str;
+    // Higher priorities are asked first
+    fn priority(&self) -> i64;
+    fn matches(&self, target: &PreviewTarget) -> bool;
+    // None passes the file on to the next matching previewer
+    fn preview(&self, target: &PreviewTarget, cancel: &AtomicBool) -> Option<Preview>;
+}
+
+type BuiltinFn = fn(&Path, &Programs, &AtomicBool) -> Option<Preview>;
+
+struct BuiltinPreviewer {
+    id: &'static str,
+    priority: i64,
+    // Empty for every file
+    extensions: &'static [&'static str],
+    preview: BuiltinFn,
+}
+
+impl Previewer for BuiltinPreviewer {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn priority(&self) -> i64 {
+        self.priority
+    }
+
+    fn matches(&self, target: &PreviewTarget) -> bool {
+        self.extensions.is_empty() || self.extensions.contains(&target.extension.as_str())
+    }
+
+    fn preview(&self, target: &PreviewTarget, cancel: &AtomicBool) -> Option<Preview> {
+        (self.preview)(target.path, target.programs, cancel)
+    }
+}
+
+// A command template from the config. Like scope.sh, a script that exits
+// with an error declines the file and the next previewer gets it.
+pub struct ScriptPreviewer {
+    pub id: String,
+    pub priority: i64,
+    pub extensions: Vec<String>,
+    pub mime: Vec<String>,
+    pub command: String,
+}
+
+impl Previewer for ScriptPreviewer {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn priority(&self) -> i64 {
+        self.priority
+    }
+
+    fn matches(&self, target: &PreviewTarget) -> bool {
+        if self.extensions.is_empty() && self.mime.is_empty() {
+            return true;
+        }
+        self.extensions.contains(&target.extension)
+            || (!self.mime.is_empty()
+                && target
+                    .mime()
+                    .is_some_and(|mime| self.mime.iter().any(|p| glob_match(p, mime))))
+    }
+
+    fn preview(&self, target: &PreviewTarget, cancel: &AtomicBool) -> Option<Preview> {
+        let (_, mut command) =
+            template_command(&self.command, target.path, &[target.path.to_path_buf()]).ok()?;
+        let output = cancellable_output(&mut command, cancel).ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(output_preview(output))
+    }
+}
+
+pub struct PreviewerRegistry {
+    // Sorted by priority, highest first
+    previewers: Vec<Box<dyn Previewer>>,
+}
+
+impl Default for PreviewerRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            previewers: Vec::new(),
+        };
+        registry.register(Box::new(BuiltinPreviewer {
+            id: "document",
+            priority: 30,
+            extensions: &DOCUMENT_EXTENSIONS,
+            preview: preview_document,
+        }));
+        registry.register(Box::new(BuiltinPreviewer {
+            id: "media",
+            priority: 20,
+            extensions: &media::EXTENSIONS,
+            preview: media::preview_media,
+        }));
+        registry.register(Box::new(BuiltinPreviewer {
+            id: "photo",
+            priority: 10,
+            extensions: &photo::EXTENSIONS,
+            preview: |path, _, _| photo::preview_photo(path),
+        }));
+        registry.register(Box::new(BuiltinPreviewer {
+            id: "text",
+            priority: 0,
+            extensions: &[],
+            preview: |path, programs, cancel| Some(preview_text(path, programs, cancel)),
+        }));
+        registry
+    }
+}
+
+impl PreviewerRegistry {
+    // Priority of user previewers that don't name one: above every built-in
+    pub const DEFAULT_PRIORITY: i64 = 100;
+
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let mut registry = Self::default();
+        let previewers = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("previewers").cloned());
+        let Some(Value::Array(previewers)) = previewers else {
+            return registry;
+        };
+
+        for (index, previewer) in previewers.iter().enumerate() {
+            let id = previewer
+                .get("name")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("previewer {}", index + 1));
+            let Some(command) = previewer.get("command").and_then(|v| v.as_str()) else {
+                notifications.error(format!("[[previewers]] {} has no command", id));
+                continue;
+            };
+            let strings = |key: &str| match previewer.get(key) {
+                Some(Value::String(value)) => vec![value.to_lowercase()],
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_lowercase))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            registry.register(Box::new(ScriptPreviewer {
+                extensions: strings("extensions")
+                    .into_iter()
+                    .map(|ext| ext.trim_start_matches('.').to_string())
+                    .collect(),
+                mime: strings("mime"),
+                priority: previewer
+                    .get("priority")
+                    .and_then(|v| v.as_integer())
+                    .unwrap_or(Self::DEFAULT_PRIORITY),
+                command: command.to_string(),
+                id,
+            }));
+        }
+        registry
+    }
+
+    // Later registrations replace previewers with the same id, so a user
+    // previewer named after a built-in one takes its place
+    pub fn register(&mut self, previewer: Box<dyn Previewer>) {
+        self.previewers.retain(|p| p.id() != previewer.id());
+        self.previewers.push(previewer);
+        // Stable, so equal priorities keep the order they were registered in
+        self.previewers.sort_by_key(|p| -p.priority());
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.previewers.iter().map(|p| p.id()).collect()
+    }
+
+    // The first matching previewer that accepts the file, falling back to the
+    // plain text preview when all of them decline
+    pub fn preview(&self, file_path: &Path, programs: &Programs, cancel: &AtomicBool) -> Preview {
+        let target = PreviewTarget::new(file_path, programs);
+        self.previewers
+            .iter()
+            .filter(|previewer| previewer.matches(&target))
+            .find_map(|previewer| previewer.preview(&target, cancel))
+            .unwrap_or_else(|| preview_text(file_path, programs, cancel))
+    }
+}
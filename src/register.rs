@@ -0,0 +1,328 @@
+// The yank register: files picked with `y` (copy) or `x` (cut), waiting for
+// `P` to paste them. It is kept in `~/.termfm_register.json`, so every running
+// instance pastes what any of them yanked, also after a restart, like vifm's
+// shared registers.
+use crate::{
+    fs_ops::{copy_path, format_size, format_time, unique_path, CopyStrategy, Preserve},
+    jobs::{Journal, JournalOperation},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterOperation {
+    Copy,
+    Cut,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Register {
+    pub operation: RegisterOperation,
+    pub paths: Vec<PathBuf>,
+}
+
+impl Register {
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".termfm_register.json"))
+    }
+
+    // Read on every paste, the register may have changed in another instance
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    // Written to a temporary file first, so another instance never reads half
+    // of it
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+        let temp_path = path.with_extension(format!("json.{}", std::process::id()));
+        let serialized = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(&temp_path, serialized)
+            .and_then(|()| fs::rename(&temp_path, &path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temp_path);
+            })
+    }
+
+    pub fn clear() {
+        if let Some(path) = Self::path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    // Where each file lands in `dir`. Names already taken wait in the
+    // returned `Paste` for the user's choice, unless a file is pasted next to
+    // itself or two pasted files share a name, which gets a free one such as
+    // `name (1).ext`. Files that are gone, directories pasted into themselves
+    // and cuts into the directory they are in are left out, the first two
+    // with an error.
+    pub fn plan(&self, dir: &Path) -> Paste {
+        let mut paste = Paste {
+            operation: self.operation,
+            dir: dir.to_path_buf(),
+            files: Vec::new(),
+            replaced: Vec::new(),
+            pending: VecDeque::new(),
+            conflict: None,
+            remembered: None,
+            skipped: 0,
+            errors: Vec::new(),
+        };
+        for source in &self.paths {
+            let Some(name) = source.file_name() else {
+                continue;
+            };
+            if fs::symlink_metadata(source).is_err() {
+                paste
+                    .errors
+                    .push(format!("{} no longer exists", source.display()));
+                continue;
+            }
+            if dir.starts_with(source) {
+                paste
+                    .errors
+                    .push(format!("Cannot paste {} into itself", source.display()));
+                continue;
+            }
+            if self.operation == RegisterOperation::Cut && source.parent() == Some(dir) {
+                continue;
+            }
+            let mut target = dir.join(name);
+            if fs::symlink_metadata(&target).is_ok() && target != *source {
+                paste.pending.push_back((source.clone(), target));
+                continue;
+            }
+            if !paste.is_free(&target) {
+                target = unique_path(&target, |path| paste.is_free(path));
+            }
+            paste.files.push((source.clone(), target));
+        }
+        paste
+    }
+}
+
+// What becomes of a pasted file whose name is taken
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteChoice {
+    Overwrite,
+    // Overwrites only what is older than the pasted file
+    OverwriteIfNewer,
+    // Pastes under a free name such as `name (1).ext`
+    Rename,
+    Skip,
+}
+
+// A paste waiting to run, stopping at every name already taken in the
+// directory until the user picks what to do with it
+pub struct Paste {
+    pub operation: RegisterOperation,
+    pub dir: PathBuf,
+    // Where each file goes
+    pub files: Vec<(PathBuf, PathBuf)>,
+    // Existing files the pasted ones take the place of, removed only once
+    // those are complete
+    pub replaced: Vec<PathBuf>,
+    // Files and the taken names still to be decided on
+    pub pending: VecDeque<(PathBuf, PathBuf)>,
+    // The one waiting for a decision
+    pub conflict: Option<(PathBuf, PathBuf)>,
+    pub remembered: Option<PasteChoice>,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+impl Paste {
+    fn is_free(&self, path: &Path) -> bool {
+        fs::symlink_metadata(path).is_err() && !self.files.iter().any(|(_, to)| to == path)
+    }
+
+    // Decides conflicts with the remembered choice until one needs the user.
+    // Returns true once every file has its place.
+    pub fn advance(&mut self) -> bool {
+        while self.conflict.is_none() {
+            let Some((from, to)) = self.pending.pop_front() else {
+                return true;
+            };
+            match self.remembered {
+                Some(choice) => self.resolve_with(from, to, choice),
+                None => self.conflict = Some((from, to)),
+            }
+        }
+        false
+    }
+
+    // Applies the user's answer to the current conflict, optionally for all
+    // later ones too
+    pub fn resolve(&mut self, choice: PasteChoice, remember: bool) {
+        if remember {
+            self.remembered = Some(choice);
+        }
+        if let Some((from, to)) = self.conflict.take() {
+            self.resolve_with(from, to, choice);
+        }
+    }
+
+    fn resolve_with(&mut self, from: PathBuf, to: PathBuf, choice: PasteChoice) {
+        let replace = match choice {
+            PasteChoice::Overwrite => true,
+            PasteChoice::OverwriteIfNewer => {
+                let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+                modified(&from) > modified(&to)
+            }
+            PasteChoice::Rename => {
+                let to = unique_path(&to, |path| self.is_free(path));
+                self.files.push((from, to));
+                return;
+            }
+            PasteChoice::Skip => false,
+        };
+        if !replace {
+            self.skipped += 1;
+        } else if from.starts_with(&to) {
+            self.errors.push(format!(
+                "Cannot overwrite {} with something inside it",
+                to.display()
+            ));
+        } else if self.files.iter().any(|(_, target)| *target == to) {
+            // Two pasted files with the name, the first one keeps it
+            let to = unique_path(&to, |path| self.is_free(path));
+            self.files.push((from, to));
+        } else {
+            self.replaced.push(to.clone());
+            self.files.push((from, to));
+        }
+    }
+
+    // Copies or moves the files into place, copies keeping what `preserve`
+    // asks for. Moves are journaled like `:organize`. A file overwriting
+    // another is pasted under a temporary name next to it first, so a copy or
+    // move that fails leaves the existing one as it was. Returns where the
+    // files that made it went, the errors and the slowest way file contents
+    // were copied.
+    pub fn run(
+        mut self,
+        preserve: Preserve,
+    ) -> (Vec<(PathBuf, PathBuf)>, Vec<String>, Option<CopyStrategy>) {
+        let mut pasted = Vec::new();
+        let mut slowest = None;
+        let files: Vec<(PathBuf, PathBuf, PathBuf)> = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(|(from, to)| {
+                let staged = match self.replaced.contains(&to) {
+                    true => set_aside_path(&to, "paste"),
+                    false => to.clone(),
+                };
+                (from, to, staged)
+            })
+            .collect();
+        let mut journal = (self.operation == RegisterOperation::Cut).then(|| {
+            let moves = files
+                .iter()
+                .map(|(from, _, staged)| (from.clone(), staged.clone()))
+                .collect();
+            Journal::begin(JournalOperation::Moves(moves))
+        });
+        for (from, to, staged) in files {
+            let result = match journal.as_mut() {
+                Some(journal) => journal.move_path(&from, &staged).map(|()| None),
+                None => copy_path(&from, &staged, preserve),
+            };
+            let strategy = match result {
+                Ok(strategy) => strategy,
+                Err(e) => {
+                    let verb = match journal {
+                        Some(_) => "move",
+                        // What there is of the copy is of no use
+                        None => {
+                            if staged != to {
+                                let _ = remove_path(&staged);
+                            }
+                            "copy"
+                        }
+                    };
+                    self.errors
+                        .push(format!("Failed to {} {}: {}", verb, from.display(), e));
+                    continue;
+                }
+            };
+            if staged != to {
+                if let Err(e) = replace_path(&staged, &to) {
+                    self.errors.push(format!(
+                        "Failed to overwrite {}: {}, the pasted file is {}",
+                        to.display(),
+                        e,
+                        staged.display()
+                    ));
+                    continue;
+                }
+            }
+            slowest = slowest.max(strategy);
+            pasted.push((from, to));
+        }
+        if let Some(journal) = journal {
+            journal.finish();
+        }
+        (pasted, self.errors, slowest)
+    }
+
+    pub fn conflict_lines(&self) -> Vec<String> {
+        let Some((from, to)) = &self.conflict else {
+            return Vec::new();
+        };
+        let describe = |label: &str, path: &Path| match fs::symlink_metadata(path) {
+            Ok(meta) => format!(
+                "{:<10}{:>10}  {}",
+                label,
+                format_size(meta.len()),
+                format_time(meta.modified().ok())
+            ),
+            Err(e) => format!("{:<10}{}", label, e),
+        };
+        vec![
+            to.display().to_string(),
+            String::new(),
+            describe("pasted", from),
+            describe("existing", to),
+            String::new(),
+            "[o] overwrite  [n] overwrite if newer  [r] rename  [s] skip".to_string(),
+            "Shift applies the choice to the rest, Esc cancels the paste".to_string(),
+        ]
+    }
+}
+
+// A free hidden name next to `path` to keep something under for a moment
+fn set_aside_path(path: &Path, purpose: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let aside = path.with_file_name(format!(".{}.termfm-{}", name, purpose));
+    unique_path(&aside, |path| fs::symlink_metadata(path).is_err())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+// Puts `staged` in the place of `existing`, which is only removed once it is
+// there. A file is renamed over a file; directories can't be, so the existing
+// one is set aside first and put back if that fails.
+fn replace_path(staged: &Path, existing: &Path) -> io::Result<()> {
+    let is_dir = |path: &Path| fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir());
+    if !is_dir(staged) && !is_dir(existing) {
+        return fs::rename(staged, existing);
+    }
+    let aside = set_aside_path(existing, "old");
+    fs::rename(existing, &aside)?;
+    if let Err(e) = fs::rename(staged, existing) {
+        let _ = fs::rename(&aside, existing);
+        return Err(e);
+    }
+    remove_path(&aside)
+}
@@ -0,0 +1,123 @@
+// What `.` does again: the last change made to files, applied to the marked
+// files or the selection of the moment, like Vim's `.`
+use crate::{command::CommandAction, register::RegisterOperation};
+use std::path::PathBuf;
+
+#[derive(Clone)]
+pub enum Change {
+    // Commands acting on the marked files or the selection, e.g. `:delete`
+    Action(CommandAction),
+    Rename(RenamePattern),
+    // Files yanked or cut and pasted into a directory
+    Paste(RegisterOperation, PathBuf),
+}
+
+impl Change {
+    // The commands worth repeating; the rest don't change files or would do
+    // the same thing again
+    pub fn from_action(action: &CommandAction) -> Option<Self> {
+        match action {
+            CommandAction::Delete
+            | CommandAction::Convert(_)
+            | CommandAction::Tag(_)
+            | CommandAction::Untag(_)
+            | CommandAction::Note(Some(_))
+            | CommandAction::Unnote => Some(Change::Action(action.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchor {
+    Start,
+    End,
+    // Right before the unchanged end
+    BeforeEnd,
+}
+
+// How a rename changed a name, reduced to the part that differs and where it
+// is: `IMG_1.jpg` to `2024_IMG_1.jpg` adds a prefix, `a.txt` to `a.md` swaps
+// the extension, `a.txt` to `a-old.txt` inserts before `.txt`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenamePattern {
+    from: String,
+    to: String,
+    // The unchanged end, for `BeforeEnd`
+    suffix: String,
+    anchor: Anchor,
+}
+
+impl RenamePattern {
+    pub fn new(old: &str, new: &str) -> Self {
+        let mut prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or(old.len().min(new.len()));
+        let room = old.len().min(new.len()) - prefix;
+        let mut suffix = 0;
+        for (a, b) in old.chars().rev().zip(new.chars().rev()) {
+            if a != b || suffix + a.len_utf8() > room {
+                break;
+            }
+            suffix += a.len_utf8();
+        }
+        // The change grows to whole words, so that `jpg` to `jpeg` is about
+        // the extension rather than a `g`, unless it starts or ends with a
+        // separator itself
+        let changed = [
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        ];
+        if !changed.iter().any(|text| text.starts_with(is_separator)) {
+            while let Some(c) = old[..prefix]
+                .chars()
+                .next_back()
+                .filter(|c| !is_separator(*c))
+            {
+                prefix -= c.len_utf8();
+            }
+        }
+        if !changed.iter().any(|text| text.ends_with(is_separator)) {
+            while let Some(c) = old[old.len() - suffix..]
+                .chars()
+                .next()
+                .filter(|c| !is_separator(*c))
+            {
+                suffix -= c.len_utf8();
+            }
+        }
+        let anchor = if prefix == 0 {
+            Anchor::Start
+        } else if suffix == 0 {
+            Anchor::End
+        } else {
+            Anchor::BeforeEnd
+        };
+        Self {
+            from: old[prefix..old.len() - suffix].to_string(),
+            to: new[prefix..new.len() - suffix].to_string(),
+            suffix: old[old.len() - suffix..].to_string(),
+            anchor,
+        }
+    }
+
+    // The new name for `name`, None when the pattern doesn't fit it
+    pub fn apply(&self, name: &str) -> Option<String> {
+        let renamed = match self.anchor {
+            Anchor::Start => format!("{}{}", self.to, name.strip_prefix(&self.from)?),
+            Anchor::End => format!("{}{}", name.strip_suffix(&self.from)?, self.to),
+            Anchor::BeforeEnd => {
+                let rest = name.strip_suffix(&self.suffix)?.strip_suffix(&self.from)?;
+                format!("{}{}{}", rest, self.to, self.suffix)
+            }
+        };
+        Some(renamed).filter(|renamed| renamed != name && !renamed.is_empty())
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '.' | '_' | '-' | ' ')
+}
@@ -0,0 +1,360 @@
+// Extensions written in Rhai (https://rhai.rs), read from `init.rhai` next to
+// opener.toml. The script runs once at startup and registers what it adds by
+// calling `command`, `bind`, `previewer` and `status`; the functions named
+// after a `ScriptHook` are called when something happens. What a script asks
+// of the interface (`notify`, `error`, `run`) is queued and carried out once
+// the script returns.
+use crate::{
+    app::Notifications,
+    command::COMMANDS,
+    custom::parse_key,
+    preview::{expand_tabs, Preview, PREVIEW_LINES},
+    previewers::{PreviewTarget, Previewer, PreviewerRegistry},
+};
+use crossterm::event::{KeyCode, KeyModifiers};
+use rhai::{Array, Dynamic, Engine, FnPtr, FuncArgs, Map, AST};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+pub const SCRIPT_FILE: &str = "init.rhai";
+// How deep commands run by scripts may call scripts again, which stops a
+// command that runs itself
+pub const MAX_DEPTH: usize = 10;
+// Steps a single call may take, so that a script stuck in a loop can't hang
+// the interface
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptHook {
+    Startup,
+    DirChange,
+    Select,
+    Open,
+}
+
+impl ScriptHook {
+    pub fn name(self) -> &'static str {
+        match self {
+            ScriptHook::Startup => "on_startup",
+            ScriptHook::DirChange => "on_dir_change",
+            ScriptHook::Select => "on_select",
+            ScriptHook::Open => "on_open",
+        }
+    }
+}
+
+// What a script asked the interface to do
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptAction {
+    Notify(String),
+    Error(String),
+    // A `:` command line
+    Run(String),
+}
+
+// The directory, the selected entry and the marked ones, as scripts get them
+pub struct ScriptContext<'a> {
+    pub dir: &'a Path,
+    pub file: Option<&'a Path>,
+    pub selected: &'a [PathBuf],
+}
+
+impl ScriptContext<'_> {
+    fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.insert("dir".into(), path_value(self.dir));
+        map.insert("file".into(), self.file.map_or(Dynamic::UNIT, path_value));
+        map.insert(
+            "selected".into(),
+            Dynamic::from_array(self.selected.iter().map(|p| path_value(p)).collect()),
+        );
+        map
+    }
+}
+
+fn path_value(path: &Path) -> Dynamic {
+    path.to_string_lossy().into_owned().into()
+}
+
+// Filled while the script runs
+#[derive(Default)]
+struct Registrations {
+    commands: Vec<(String, FnPtr)>,
+    keys: Vec<((KeyCode, KeyModifiers), String)>,
+    previewers: Vec<(Vec<String>, FnPtr)>,
+    segments: Vec<FnPtr>,
+    errors: Vec<String>,
+}
+
+struct Runtime {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Runtime {
+    fn call(&self, function: &FnPtr, args: impl FuncArgs) -> Result<Dynamic, String> {
+        function
+            .call(&self.engine, &self.ast, args)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Default)]
+pub struct Scripts {
+    runtime: Option<Arc<Runtime>>,
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    commands: Vec<(String, FnPtr)>,
+    keys: Vec<((KeyCode, KeyModifiers), String)>,
+    previewers: Vec<(Vec<String>, FnPtr)>,
+    segments: Vec<FnPtr>,
+}
+
+impl Scripts {
+    // Runs the script next to `config_path`, if there is one
+    pub fn load(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let path = config_path.with_file_name(SCRIPT_FILE);
+        let Ok(source) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let registered = Arc::new(Mutex::new(Registrations::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let queue = |action: fn(String) -> ScriptAction| {
+            let actions = Arc::clone(&actions);
+            move |text: &str| actions.lock().unwrap().push(action(text.to_string()))
+        };
+        engine.register_fn("notify", queue(ScriptAction::Notify));
+        engine.register_fn("error", queue(ScriptAction::Error));
+        engine.register_fn("run", queue(ScriptAction::Run));
+
+        let register = Arc::clone(&registered);
+        engine.register_fn("command", move |name: &str, function: FnPtr| {
+            let mut registered = register.lock().unwrap();
+            if COMMANDS.contains(&name) {
+                registered.errors.push(format!(
+                    "command {} is taken by the built-in :{}",
+                    name, name
+                ));
+            } else {
+                registered.commands.push((name.to_string(), function));
+            }
+        });
+        let register = Arc::clone(&registered);
+        engine.register_fn("bind", move |key: &str, command: &str| {
+            let mut registered = register.lock().unwrap();
+            match parse_key(key) {
+                Some(binding) => registered.keys.push((binding, command.to_string())),
+                None => registered.errors.push(format!(
+                    "bind {}: keys are a character, ctrl-<c> or alt-<c>",
+                    key
+                )),
+            }
+        });
+        let register = Arc::clone(&registered);
+        engine.register_fn("previewer", move |extensions: Array, function: FnPtr| {
+            let extensions = extensions
+                .into_iter()
+                .map(|ext| ext.to_string().trim_start_matches('.').to_lowercase())
+                .collect();
+            register
+                .lock()
+                .unwrap()
+                .previewers
+                .push((extensions, function));
+        });
+        let register = Arc::clone(&registered);
+        engine.register_fn("status", move |function: FnPtr| {
+            register.lock().unwrap().segments.push(function);
+        });
+
+        let ast = match engine.compile(&source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                notifications.error(format!("Failed to load {}: {}", path.display(), e));
+                return Self::default();
+            }
+        };
+        if let Err(e) = engine.run_ast(&ast) {
+            notifications.error(format!("Failed to run {}: {}", path.display(), e));
+            return Self::default();
+        }
+        let registered = mem::take(&mut *registered.lock().unwrap());
+        for error in registered.errors {
+            notifications.error(format!("{}: {}", SCRIPT_FILE, error));
+        }
+        Self {
+            runtime: Some(Arc::new(Runtime { engine, ast })),
+            actions,
+            commands: registered.commands,
+            keys: registered.keys,
+            previewers: registered.previewers,
+            segments: registered.segments,
+        }
+    }
+
+    // What the scripts asked for since the last time
+    pub fn take_actions(&self) -> Vec<ScriptAction> {
+        mem::take(&mut *self.actions.lock().unwrap())
+    }
+
+    // Calls the hook's function if the script defines it, with the path it
+    // is about
+    pub fn fire(&self, hook: ScriptHook, path: Option<&Path>) -> Result<(), String> {
+        let Some(runtime) = &self.runtime else {
+            return Ok(());
+        };
+        let arity = usize::from(path.is_some());
+        if !runtime
+            .ast
+            .iter_functions()
+            .any(|f| f.name == hook.name() && f.params.len() == arity)
+        {
+            return Ok(());
+        }
+        let function = FnPtr::new(hook.name()).map_err(|e| e.to_string())?;
+        let result = match path {
+            Some(path) => runtime.call(&function, (path_value(path),)),
+            None => runtime.call(&function, ()),
+        };
+        result
+            .map(|_| ())
+            .map_err(|e| format!("{} failed: {}", hook.name(), e))
+    }
+
+    pub fn has_command(&self, name: &str) -> bool {
+        self.commands.iter().any(|(n, _)| n == name)
+    }
+
+    pub fn command_names(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().map(|(name, _)| name.as_str())
+    }
+
+    // Runs a command the script registered, `line` being what was typed
+    // after the `:`. Its function gets the context with the rest of the line
+    // as `args`.
+    pub fn run_command(&self, line: &str, context: &ScriptContext) -> Result<(), String> {
+        let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+        let (Some(runtime), Some((_, function))) =
+            (&self.runtime, self.commands.iter().find(|(n, _)| n == name))
+        else {
+            return Err(format!("Unknown command: {}", name));
+        };
+        let mut map = context.to_map();
+        map.insert("args".into(), args.trim().to_string().into());
+        runtime
+            .call(function, (map,))
+            .map(|_| ())
+            .map_err(|e| format!(":{} failed: {}", name, e))
+    }
+
+    // The command line bound to a key. Shift is part of the character already.
+    pub fn for_key(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<&str> {
+        let modifiers = modifiers - KeyModifiers::SHIFT;
+        self.keys
+            .iter()
+            .find(|(key, _)| *key == (code, modifiers))
+            .map(|(_, command)| command.as_str())
+    }
+
+    // Previewers of the script go before the built-in ones, like those of
+    // the config
+    pub fn register_previewers(&self, registry: &mut PreviewerRegistry) {
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        for (index, (extensions, function)) in self.previewers.iter().enumerate() {
+            registry.register(Box::new(RhaiPreviewer {
+                id: format!("{} previewer {}", SCRIPT_FILE, index + 1),
+                extensions: extensions.clone(),
+                function: function.clone(),
+                runtime: Arc::clone(runtime),
+            }));
+        }
+    }
+
+    pub fn has_segments(&self) -> bool {
+        !self.segments.is_empty()
+    }
+
+    // The status bar segments, empty ones left out. A segment that fails is
+    // reported and dropped, or it would fail on every frame.
+    pub fn segments(
+        &mut self,
+        context: &ScriptContext,
+        notifications: &mut Notifications,
+    ) -> Vec<String> {
+        let Some(runtime) = &self.runtime else {
+            return Vec::new();
+        };
+        let map = context.to_map();
+        let mut texts = Vec::new();
+        self.segments
+            .retain(|function| match runtime.call(function, (map.clone(),)) {
+                Ok(text) => {
+                    if !text.is_unit() {
+                        texts.push(text.to_string());
+                    }
+                    true
+                }
+                Err(e) => {
+                    notifications.error(format!("Status segment failed and is removed: {}", e));
+                    false
+                }
+            });
+        texts.retain(|text| !text.is_empty());
+        texts
+    }
+}
+
+// A `previewer` of the script. Returning a string or an array of lines
+// previews the file, returning nothing passes it on to the next previewer.
+struct RhaiPreviewer {
+    id: String,
+    extensions: Vec<String>,
+    function: FnPtr,
+    runtime: Arc<Runtime>,
+}
+
+impl Previewer for RhaiPreviewer {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn priority(&self) -> i64 {
+        PreviewerRegistry::DEFAULT_PRIORITY
+    }
+
+    fn matches(&self, target: &PreviewTarget) -> bool {
+        self.extensions.is_empty() || self.extensions.contains(&target.extension)
+    }
+
+    fn preview(&self, target: &PreviewTarget, _cancel: &AtomicBool) -> Option<Preview> {
+        let result = match self
+            .runtime
+            .call(&self.function, (path_value(target.path),))
+        {
+            Ok(result) => result,
+            Err(e) => return Some(Preview::message(&format!("Preview script failed: {}", e))),
+        };
+        let lines: Vec<String> = if result.is_unit() {
+            return None;
+        } else if result.is_array() {
+            result
+                .into_array()
+                .unwrap_or_default()
+                .iter()
+                .map(|line| expand_tabs(&line.to_string()))
+                .collect()
+        } else {
+            result.to_string().lines().map(expand_tabs).collect()
+        };
+        let mut preview = Preview::message("");
+        preview.lines = lines.into_iter().take(PREVIEW_LINES).collect();
+        Some(preview)
+    }
+}
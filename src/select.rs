@@ -0,0 +1,32 @@
+// Patterns for `:select`: a glob such as `*.log`, or a regex between slashes
+// such as `/^IMG_\d+/`. Globs ignore case like the rest of termfm; a word
+// without wildcards matches anywhere in the name, as in `:find`.
+use crate::config::glob_match;
+use regex_automata::meta::Regex;
+
+pub enum NamePattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        match pattern
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(regex) => Regex::new(regex)
+                .map(NamePattern::Regex)
+                .map_err(|e| format!("Invalid regex: {}", e)),
+            None if pattern.contains(['*', '?']) => Ok(NamePattern::Glob(pattern.to_string())),
+            None => Ok(NamePattern::Glob(format!("*{}*", pattern))),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(glob) => glob_match(glob, name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
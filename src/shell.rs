@@ -0,0 +1,114 @@
+// Shell commands typed at the `!` prompt. They run in the background with
+// their output piped, and what they print is read line by line into the
+// output panel rather than written over the screen.
+use crate::{
+    opener::{expand_placeholders, shell_command},
+    preview::expand_tabs,
+    terminal::wake,
+};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Lines kept for the panel, the oldest are dropped past this
+pub const OUTPUT_LINES: usize = 10_000;
+// How often the exit is looked for once the output has ended, which nothing
+// signals
+const EXIT_POLL: Duration = Duration::from_millis(10);
+
+pub struct ShellRun {
+    pub line: String,
+    child: Child,
+    // Lines of stdout and stderr as they are printed, closed once both end
+    receiver: mpsc::Receiver<String>,
+    output_ended: bool,
+}
+
+impl ShellRun {
+    // Runs `line` through `sh -c` in `dir`, `%f`, `%d` and `%s` replaced by
+    // the file, its directory and the selected files
+    pub fn start(
+        line: &str,
+        file: &Path,
+        selected: &[PathBuf],
+        dir: &Path,
+    ) -> Result<Self, String> {
+        let mut command = shell_command(&expand_placeholders(line, file, selected));
+        command
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", line, e))?;
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            read_lines(stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            read_lines(stderr, sender);
+        }
+        Ok(Self {
+            line: line.to_string(),
+            child,
+            receiver,
+            output_ended: false,
+        })
+    }
+
+    // The lines printed since the last poll, and the exit status once the
+    // command has finished and all of its output is read
+    pub fn poll(&mut self) -> (Vec<String>, Option<ExitStatus>) {
+        let mut lines = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) => lines.push(line),
+                Err(mpsc::TryRecvError::Empty) => return (lines, None),
+                Err(mpsc::TryRecvError::Disconnected) => break,
+            }
+        }
+        self.output_ended = true;
+        (lines, self.child.try_wait().ok().flatten())
+    }
+
+    // When to poll again for the exit of a command whose output has ended
+    pub fn deadline(&self) -> Option<Instant> {
+        self.output_ended.then(|| Instant::now() + EXIT_POLL)
+    }
+
+    pub fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for ShellRun {
+    fn drop(&mut self) {
+        if matches!(self.child.try_wait(), Ok(None)) {
+            self.stop();
+        }
+    }
+}
+
+fn read_lines(stream: impl Read + Send + 'static, sender: mpsc::Sender<String>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            if sender.send(expand_tabs(text)).is_err() {
+                return;
+            }
+            wake();
+            line.clear();
+        }
+        // Dropping the sender closes the channel once both streams end
+        drop(sender);
+        wake();
+    });
+}
@@ -0,0 +1,297 @@
+// One-way synchronization of a directory into another, like `rsync -a
+// --delete`: the plan of what to copy and delete is shown for review first,
+// then carried out in the background with progress. Files count as changed
+// when their size or modification time differ, as with rsync's quick check.
+use crate::{
+    fs_ops::{copy_path, format_size, Preserve},
+    terminal::wake,
+    ui::ListState,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl SyncAction {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            SyncAction::Create => "+",
+            SyncAction::Update => "~",
+            SyncAction::Delete => "-",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncStep {
+    pub action: SyncAction,
+    // Relative to both directories
+    pub path: PathBuf,
+    pub is_dir: bool,
+    // Bytes to copy
+    pub size: u64,
+}
+
+struct Side {
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+fn children(dir: &Path) -> io::Result<BTreeMap<String, Side>> {
+    let mut children = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Links are copied as links, so they are compared as such
+        let meta = fs::symlink_metadata(entry.path())?;
+        children.insert(
+            entry.file_name().to_string_lossy().into_owned(),
+            Side {
+                is_dir: meta.is_dir(),
+                size: meta.len(),
+                modified: meta.modified().ok(),
+            },
+        );
+    }
+    Ok(children)
+}
+
+// What makes `dest` the same as `source`. Directories only in `dest` are
+// deleted whole; directories only in `source` are created, then their files.
+pub fn plan_sync(source: &Path, dest: &Path) -> io::Result<Vec<SyncStep>> {
+    let mut steps = Vec::new();
+    plan_dir(source, dest, Path::new(""), &mut steps)?;
+    Ok(steps)
+}
+
+fn plan_dir(source: &Path, dest: &Path, rel: &Path, steps: &mut Vec<SyncStep>) -> io::Result<()> {
+    let from = children(&source.join(rel))?;
+    let to = match fs::symlink_metadata(dest.join(rel)) {
+        Ok(meta) if meta.is_dir() => children(&dest.join(rel))?,
+        _ => BTreeMap::new(),
+    };
+    for (name, old) in &to {
+        let replaced = from.get(name).is_some_and(|new| new.is_dir != old.is_dir);
+        if !from.contains_key(name) || replaced {
+            steps.push(SyncStep {
+                action: SyncAction::Delete,
+                path: rel.join(name),
+                is_dir: old.is_dir,
+                size: 0,
+            });
+        }
+    }
+    for (name, new) in &from {
+        let path = rel.join(name);
+        let old = to.get(name).filter(|old| old.is_dir == new.is_dir);
+        if new.is_dir {
+            if old.is_none() {
+                steps.push(SyncStep {
+                    action: SyncAction::Create,
+                    path: path.clone(),
+                    is_dir: true,
+                    size: 0,
+                });
+            }
+            plan_dir(source, dest, &path, steps)?;
+            continue;
+        }
+        let action = match old {
+            None => SyncAction::Create,
+            Some(old) if old.size != new.size || old.modified != new.modified => SyncAction::Update,
+            Some(_) => continue,
+        };
+        steps.push(SyncStep {
+            action,
+            path,
+            is_dir: false,
+            size: new.size,
+        });
+    }
+    Ok(())
+}
+
+// A reviewed plan and, once started, its progress
+pub struct DirSync {
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    pub steps: Vec<SyncStep>,
+    pub state: ListState,
+    // Steps done so far and their errors, None until started
+    pub done: Option<usize>,
+    pub bytes_done: u64,
+    pub errors: Vec<String>,
+    receiver: Option<mpsc::Receiver<(usize, Result<(), String>)>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DirSync {
+    pub fn new(source: PathBuf, dest: PathBuf) -> Result<Self, String> {
+        if dest.starts_with(&source) || source.starts_with(&dest) {
+            return Err("Cannot sync a directory with one inside it".to_string());
+        }
+        let steps = plan_sync(&source, &dest)
+            .map_err(|e| format!("Failed to compare {}: {}", source.display(), e))?;
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Ok(Self {
+            source,
+            dest,
+            steps,
+            state,
+            done: None,
+            bytes_done: 0,
+            errors: Vec::new(),
+            receiver: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    // Copies keep their times, or the next sync would copy them again
+    pub fn start(&mut self, preserve: Preserve) {
+        let preserve = Preserve {
+            timestamps: true,
+            ..preserve
+        };
+        let (sender, receiver) = mpsc::channel();
+        let (source, dest) = (self.source.clone(), self.dest.clone());
+        let steps = self.steps.clone();
+        let cancel = Arc::clone(&self.cancel);
+        thread::spawn(move || {
+            let _ = fs::create_dir_all(&dest);
+            for (index, step) in steps.iter().enumerate() {
+                if cancel.load(Ordering::SeqCst) {
+                    return;
+                }
+                let (from, to) = (source.join(&step.path), dest.join(&step.path));
+                let result = match step.action {
+                    SyncAction::Delete if step.is_dir => fs::remove_dir_all(&to),
+                    SyncAction::Delete => fs::remove_file(&to),
+                    _ if step.is_dir => fs::create_dir_all(&to),
+                    // Replaced rather than written over, like rsync does, so
+                    // links and other hard links to it are left alone
+                    SyncAction::Update => fs::remove_file(&to)
+                        .and_then(|_| copy_path(&from, &to, preserve))
+                        .map(|_| ()),
+                    SyncAction::Create => copy_path(&from, &to, preserve).map(|_| ()),
+                };
+                let result = result.map_err(|e| format!("Failed to sync {}: {}", to.display(), e));
+                if sender.send((index, result)).is_err() {
+                    return;
+                }
+                wake();
+            }
+        });
+        self.receiver = Some(receiver);
+        self.done = Some(0);
+    }
+
+    // Picks up finished steps, returns whether all of them are done
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        for (index, result) in receiver.try_iter() {
+            self.done = Some(index + 1);
+            self.bytes_done += self.steps[index].size;
+            if let Err(e) = result {
+                self.errors.push(e);
+            }
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done == Some(self.steps.len())
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn count(&self, action: SyncAction) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.action == action)
+            .count()
+    }
+
+    pub fn title(&self) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+        let total: u64 = self.steps.iter().map(|step| step.size).sum();
+        let status = match self.done {
+            None => format!(
+                "{} new, {} changed, {} deleted, Enter to sync, Esc to cancel",
+                self.count(SyncAction::Create),
+                self.count(SyncAction::Update),
+                self.count(SyncAction::Delete)
+            ),
+            Some(done) => format!(
+                "{} of {} steps, {} of {}, Esc stops",
+                done,
+                self.steps.len(),
+                format_size(self.bytes_done),
+                format_size(total)
+            ),
+        };
+        format!(
+            "Sync {} → {}: {}",
+            name(&self.source),
+            name(&self.dest),
+            status
+        )
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let done = match self.done {
+                    Some(done) if index < done => "✓ ",
+                    Some(_) => "  ",
+                    None => "",
+                };
+                let slash = if step.is_dir { "/" } else { "" };
+                format!(
+                    "{}{} {}{}",
+                    done,
+                    step.action.symbol(),
+                    step.path.display(),
+                    slash
+                )
+            })
+            .collect()
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        let index = self
+            .state
+            .selected()
+            .unwrap_or(0)
+            .saturating_add_signed(delta)
+            .min(self.steps.len().saturating_sub(1));
+        self.state.select(Some(index));
+    }
+}
+
+impl Drop for DirSync {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
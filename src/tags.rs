@@ -0,0 +1,203 @@
+// Named labels on files and directories, kept in `tags.json` next to
+// `opener.toml`. Each tag gets the color set for it in `[tags]`, or one of
+// the palette's picked by its name. On macOS they are Finder's tags as well.
+use crate::{
+    app::Notifications,
+    config::parse_color,
+    finder_tags,
+    fs_ops::{rename_keys, Entry},
+    ui::Color as TuiColor,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+const PALETTE: [TuiColor; 6] = [
+    TuiColor::Red,
+    TuiColor::Green,
+    TuiColor::Yellow,
+    TuiColor::Blue,
+    TuiColor::Magenta,
+    TuiColor::Cyan,
+];
+
+// Shown in front of the name, once per tag
+pub const TAG_SYMBOL: &str = "●";
+
+#[derive(Default)]
+pub struct Tags {
+    // None keeps the tags in memory only
+    path: Option<PathBuf>,
+    colors: HashMap<String, TuiColor>,
+    // Finder's color numbers of the tags seen on files
+    finder_colors: HashMap<String, u8>,
+    pub files: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl Tags {
+    pub fn from_config(config_path: &Path, notifications: &mut Notifications) -> Self {
+        let mut colors = HashMap::new();
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("tags").cloned());
+        if let Some(Value::Table(section)) = section {
+            for (name, value) in section {
+                match value.as_str().and_then(parse_color) {
+                    Some(color) => {
+                        colors.insert(name, color);
+                    }
+                    None => notifications.error(format!("Invalid color for tag {}", name)),
+                }
+            }
+        }
+
+        let path = config_path.with_file_name("tags.json");
+        let files = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                notifications.error(format!("Failed to read {}: {}", path.display(), e));
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            colors,
+            finder_colors: HashMap::new(),
+            files,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let serialized = serde_json::to_string_pretty(&self.files).map_err(io::Error::other)?;
+        fs::write(path, serialized)
+    }
+
+    pub fn get(&self, path: &Path) -> impl Iterator<Item = &str> {
+        self.files
+            .get(path)
+            .into_iter()
+            .flatten()
+            .map(|t| t.as_str())
+    }
+
+    pub fn has(&self, path: &Path, tag: &str) -> bool {
+        self.files.get(path).is_some_and(|tags| tags.contains(tag))
+    }
+
+    pub fn color(&self, tag: &str) -> TuiColor {
+        let finder = || {
+            self.finder_colors
+                .get(tag)
+                .and_then(|number| finder_tags::color(*number))
+        };
+        self.colors
+            .get(tag)
+            .copied()
+            .or_else(finder)
+            .unwrap_or_else(|| {
+                let hash = tag.bytes().fold(0usize, |hash, b| hash * 31 + b as usize);
+                PALETTE[hash % PALETTE.len()]
+            })
+    }
+
+    // Every tag in use, with the number of entries carrying it
+    pub fn names(&self) -> BTreeMap<&str, usize> {
+        let mut names = BTreeMap::new();
+        for tag in self.files.values().flatten() {
+            *names.entry(tag.as_str()).or_default() += 1;
+        }
+        names
+    }
+
+    pub fn add(&mut self, paths: &[PathBuf], tag: &str) {
+        for path in paths {
+            self.files
+                .entry(path.clone())
+                .or_default()
+                .insert(tag.to_string());
+            self.write_finder(path);
+        }
+    }
+
+    // Removes `tag`, or every tag when None
+    pub fn remove(&mut self, paths: &[PathBuf], tag: Option<&str>) {
+        for path in paths {
+            match (self.files.get_mut(path), tag) {
+                (Some(tags), Some(tag)) => {
+                    tags.remove(tag);
+                    if tags.is_empty() {
+                        self.files.remove(path);
+                    }
+                }
+                (Some(_), None) => {
+                    self.files.remove(path);
+                }
+                (None, _) => continue,
+            }
+            self.write_finder(path);
+        }
+    }
+
+    // Takes the Finder tags of the listed entries over, as they may have
+    // changed outside termfm. Entries without any keep theirs.
+    pub fn read_finder(&mut self, dir: &Path, entries: &[Entry]) {
+        if !cfg!(target_os = "macos") {
+            return;
+        }
+        for entry in entries {
+            let path = dir.join(&entry.name);
+            let Some(tags) = finder_tags::read(&path) else {
+                continue;
+            };
+            let mut names = BTreeSet::new();
+            for tag in &tags {
+                let (name, number) = finder_tags::split_tag(tag);
+                if number > 0 {
+                    self.finder_colors.insert(name.to_string(), number);
+                }
+                names.insert(name.to_string());
+            }
+            if names.is_empty() {
+                self.files.remove(&path);
+            } else {
+                self.files.insert(path, names);
+            }
+        }
+    }
+
+    // tags.json has them either way, so files that can't carry Finder tags,
+    // e.g. on filesystems without extended attributes, keep them there only
+    fn write_finder(&self, path: &Path) {
+        if !cfg!(target_os = "macos") {
+            return;
+        }
+        let tags: Vec<String> = self
+            .get(path)
+            .map(|name| finder_tags::join_tag(name, self.finder_colors.get(name).copied()))
+            .collect();
+        let _ = finder_tags::write(path, &tags);
+    }
+
+    // Keeps the entries of `dir` that have `tag`
+    pub fn filter(&self, dir: &Path, tag: Option<&str>, entries: &mut Vec<Entry>) {
+        if let Some(tag) = tag {
+            entries.retain(|entry| self.has(&dir.join(&entry.name), tag));
+        }
+    }
+
+    // Keeps the tags of a moved or renamed entry, and of everything below it
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        rename_keys(&mut self.files, from, to);
+    }
+
+    // Drops the tags of deleted entries and everything below them
+    pub fn forget(&mut self, removed: &Path) {
+        self.files.retain(|path, _| !path.starts_with(removed));
+    }
+}
@@ -0,0 +1,277 @@
+use crossterm::{
+    cursor::Show,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+#[cfg(unix)]
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGTSTP};
+use signal_hook::consts::{SIGINT, SIGTERM};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long the input reader waits for a key at a time before it checks
+// whether the terminal has been handed to another program
+const READ_SLICE: Duration = Duration::from_millis(50);
+
+// Flags raised by signal handlers and polled from the main loop.
+// `shutdown` is set by SIGINT, SIGTERM and SIGHUP, `suspend` by a SIGTSTP sent
+// from outside (Ctrl+Z itself arrives as a key in raw mode) and `resumed` by
+// SIGCONT after any stop, including an uncatchable SIGSTOP
+pub struct SignalFlags {
+    pub shutdown: Arc<AtomicBool>,
+    pub suspend: Arc<AtomicBool>,
+    pub resumed: Arc<AtomicBool>,
+}
+
+// Where the main loop gets its input from. Waits until an event arrives,
+// background work is ready or `deadline` passes, returning None for the
+// latter two so that the work can be picked up between events.
+pub trait EventSource {
+    fn next_event(&mut self, deadline: Option<Instant>) -> io::Result<Option<Event>>;
+}
+
+// What wakes the main loop: input from the terminal, or a worker thread
+// that has handed something over on its channel
+enum Wakeup {
+    Input(io::Result<Event>),
+    Work,
+}
+
+// The channel of the main loop once it waits on one, see `wake`
+static WAKER: OnceLock<mpsc::Sender<Wakeup>> = OnceLock::new();
+
+// Wakes the main loop to pick up what the calling thread just sent on its
+// own channel. Does nothing without a terminal, e.g. in the test harness,
+// which ticks the app itself.
+pub fn wake() {
+    if let Some(waker) = WAKER.get() {
+        let _ = waker.send(Wakeup::Work);
+    }
+}
+
+// Whether another program has the terminal (`with_suspended_tui`) and
+// whether the input reader is waiting for a key. The reader doesn't start
+// waiting while the terminal is handed over, and the handing over waits for
+// the reader to stop, so that it never takes the other program's keys.
+struct InputState {
+    suspended: bool,
+    reading: bool,
+}
+
+static INPUT: (Mutex<InputState>, Condvar) = (
+    Mutex::new(InputState {
+        suspended: false,
+        reading: false,
+    }),
+    Condvar::new(),
+);
+
+// Keys, mouse clicks and resizes from the real terminal, read on a thread
+// of their own into the channel that the worker threads wake the main loop
+// through
+pub struct TerminalEvents {
+    receiver: mpsc::Receiver<Wakeup>,
+}
+
+impl TerminalEvents {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let input = sender.clone();
+        thread::spawn(move || read_input(&input));
+        let _ = WAKER.set(sender);
+        Self { receiver }
+    }
+}
+
+impl Default for TerminalEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, deadline: Option<Instant>) -> io::Result<Option<Event>> {
+        let mut wakeup = match deadline {
+            Some(deadline) => {
+                match self
+                    .receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                {
+                    Ok(wakeup) => wakeup,
+                    Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                        return Ok(None)
+                    }
+                }
+            }
+            None => match self.receiver.recv() {
+                Ok(wakeup) => wakeup,
+                Err(_) => return Ok(None),
+            },
+        };
+        // Work handed over while the app was busy is picked up by one tick
+        loop {
+            match wakeup {
+                Wakeup::Input(event) => return event.map(Some),
+                Wakeup::Work => match self.receiver.try_recv() {
+                    Ok(next) => wakeup = next,
+                    Err(_) => return Ok(None),
+                },
+            }
+        }
+    }
+}
+
+// Runs on the input thread until the main loop is gone or the terminal
+// fails
+fn read_input(sender: &mpsc::Sender<Wakeup>) {
+    let (state, changed) = &INPUT;
+    loop {
+        {
+            let mut state = state.lock().unwrap();
+            while state.suspended {
+                state = changed.wait(state).unwrap();
+            }
+            state.reading = true;
+        }
+        let event = match event::poll(READ_SLICE) {
+            Ok(true) => Some(event::read()),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        };
+        state.lock().unwrap().reading = false;
+        changed.notify_all();
+        let Some(event) = event else {
+            continue;
+        };
+        let failed = event.is_err();
+        if sender.send(Wakeup::Input(event)).is_err() || failed {
+            return;
+        }
+    }
+}
+
+// Where the TUI is drawn: stdout, or the controlling terminal when stdout is
+// captured (e.g. `$(termfm --pick)`) so that results can be printed there
+pub fn tui_output() -> Box<dyn Write + Send> {
+    if !io::stdout().is_terminal() {
+        if let Ok(tty) = fs::OpenOptions::new().write(true).open("/dev/tty") {
+            return Box::new(tty);
+        }
+    }
+    Box::new(io::stdout())
+}
+
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        tui_output(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    );
+}
+
+// Whether a `TerminalGuard` holds the terminal. Without one, e.g. in the test
+// harness, there is no TUI to hand over.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Owns raw mode and the alternate screen, giving them back on every way out
+// of `main`: normal exit, `?` errors and panics unwinding through it
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(tui_output(), EnterAlternateScreen, EnableMouseCapture)?;
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        TUI_ACTIVE.store(false, Ordering::SeqCst);
+        restore_terminal();
+    }
+}
+
+// The default hook would print the message into the alternate screen where
+// nobody sees it, so the terminal is restored first
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+}
+
+// Hands the terminal back to the shell while `f` runs, e.g. for an editor or
+// a line prompt. The caller must clear the terminal afterwards to force a redraw.
+pub fn with_suspended_tui<T>(f: impl FnOnce() -> T) -> T {
+    if !TUI_ACTIVE.load(Ordering::SeqCst) {
+        return f();
+    }
+    let (state, changed) = &INPUT;
+    {
+        let mut state = state.lock().unwrap();
+        state.suspended = true;
+        while state.reading {
+            state = changed.wait(state).unwrap();
+        }
+    }
+    restore_terminal();
+    let result = f();
+    let _ = enable_raw_mode();
+    let _ = execute!(tui_output(), EnterAlternateScreen, EnableMouseCapture);
+    state.lock().unwrap().suspended = false;
+    changed.notify_all();
+    result
+}
+
+pub fn init_signal_handler() -> io::Result<SignalFlags> {
+    let signals = SignalFlags {
+        shutdown: Arc::new(AtomicBool::new(false)),
+        suspend: Arc::new(AtomicBool::new(false)),
+        resumed: Arc::new(AtomicBool::new(false)),
+    };
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&signals.shutdown))?;
+    }
+    // Hangups and job control are Unix's
+    #[cfg(unix)]
+    {
+        signal_hook::flag::register(SIGHUP, Arc::clone(&signals.shutdown))?;
+        signal_hook::flag::register(SIGTSTP, Arc::clone(&signals.suspend))?;
+        signal_hook::flag::register(SIGCONT, Arc::clone(&signals.resumed))?;
+        // The flags are only looked at when the main loop wakes up
+        let mut caught = Signals::new([SIGINT, SIGTERM, SIGHUP, SIGTSTP, SIGCONT])?;
+        thread::spawn(move || {
+            for _ in caught.forever() {
+                wake();
+            }
+        });
+    }
+    Ok(signals)
+}
+
+// Gives the terminal back and stops the process the way a shell's Ctrl+Z
+// would; returns once it has been continued with the TUI set up again
+#[cfg(unix)]
+pub fn suspend_process(signals: &SignalFlags) {
+    with_suspended_tui(|| {
+        let _ = signal_hook::low_level::emulate_default_handler(SIGTSTP);
+    });
+    signals.resumed.store(false, Ordering::SeqCst);
+}
+
+// Without job control there is nothing to stop for
+#[cfg(not(unix))]
+pub fn suspend_process(_signals: &SignalFlags) {}
@@ -0,0 +1,532 @@
+use crate::{
+    app::Notifications,
+    config::{parse_color, ColorDepth},
+    fs_ops::{mode, Entry},
+    ui::{Block, Borders, Color as TuiColor, Modifier, Span, Style},
+};
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use toml::Value;
+
+// Colors and symbols of the interface. Opener colors still apply to the files
+// they match, the theme covers everything else.
+#[derive(Clone)]
+pub struct Theme {
+    // The cursor row, and the cursor in the pane without focus
+    pub highlight: TuiColor,
+    pub inactive_highlight: TuiColor,
+    pub highlight_symbol: String,
+    pub border: TuiColor,
+    pub title: TuiColor,
+    pub directory: TuiColor,
+    pub file: TuiColor,
+    pub symlink: TuiColor,
+    pub broken_link: TuiColor,
+    pub executable: TuiColor,
+    // Setuid and setgid files are shown reversed
+    pub setuid: TuiColor,
+    pub socket: TuiColor,
+    pub pipe: TuiColor,
+    pub device: TuiColor,
+    // Entries matched by ignore files while those are dimmed
+    pub ignored: TuiColor,
+    // Marked entries get the symbol in front and the color
+    pub marked: TuiColor,
+    pub mark_symbol: String,
+    pub status_bar: TuiColor,
+    pub status_text: TuiColor,
+    // Counters and mount types in the status bar
+    pub accent: TuiColor,
+    pub info: TuiColor,
+    pub error: TuiColor,
+    // Styles from $LS_COLORS on top of the colors above
+    pub ls_colors: Option<LsColors>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset("dark").unwrap()
+    }
+}
+
+impl Theme {
+    pub const PRESETS: [&str; 4] = ["dark", "light", "gruvbox", "solarized"];
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let rgb = |hex: u32| TuiColor::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8);
+        let theme = match name {
+            "dark" => Self {
+                highlight: TuiColor::Yellow,
+                inactive_highlight: TuiColor::DarkGray,
+                border: TuiColor::Reset,
+                title: TuiColor::Reset,
+                directory: TuiColor::LightBlue,
+                file: TuiColor::White,
+                symlink: TuiColor::Cyan,
+                broken_link: TuiColor::Red,
+                executable: TuiColor::Green,
+                setuid: TuiColor::Red,
+                socket: TuiColor::Magenta,
+                pipe: TuiColor::Yellow,
+                device: TuiColor::Yellow,
+                ignored: TuiColor::DarkGray,
+                marked: TuiColor::LightMagenta,
+                status_bar: TuiColor::Reset,
+                status_text: TuiColor::Reset,
+                accent: TuiColor::Yellow,
+                info: TuiColor::Cyan,
+                error: TuiColor::Red,
+                ..Self::base()
+            },
+            "light" => Self {
+                highlight: TuiColor::Blue,
+                inactive_highlight: TuiColor::Gray,
+                border: TuiColor::DarkGray,
+                title: TuiColor::Black,
+                directory: TuiColor::Blue,
+                file: TuiColor::Black,
+                symlink: TuiColor::Magenta,
+                broken_link: TuiColor::Red,
+                executable: TuiColor::Green,
+                setuid: TuiColor::Red,
+                socket: TuiColor::Magenta,
+                pipe: TuiColor::DarkGray,
+                device: TuiColor::DarkGray,
+                ignored: TuiColor::Gray,
+                marked: TuiColor::Red,
+                status_bar: TuiColor::Reset,
+                status_text: TuiColor::Black,
+                accent: TuiColor::Magenta,
+                info: TuiColor::Blue,
+                error: TuiColor::Red,
+                ..Self::base()
+            },
+            "gruvbox" => Self {
+                highlight: rgb(0xfabd2f),
+                inactive_highlight: rgb(0x928374),
+                border: rgb(0x665c54),
+                title: rgb(0xfe8019),
+                directory: rgb(0x83a598),
+                file: rgb(0xebdbb2),
+                symlink: rgb(0x8ec07c),
+                broken_link: rgb(0xfb4934),
+                executable: rgb(0xb8bb26),
+                setuid: rgb(0xfb4934),
+                socket: rgb(0xd3869b),
+                pipe: rgb(0xfabd2f),
+                device: rgb(0xfabd2f),
+                ignored: rgb(0x7c6f64),
+                marked: rgb(0xd3869b),
+                status_bar: rgb(0x3c3836),
+                status_text: rgb(0xebdbb2),
+                accent: rgb(0xfe8019),
+                info: rgb(0x8ec07c),
+                error: rgb(0xfb4934),
+                ..Self::base()
+            },
+            "solarized" => Self {
+                highlight: rgb(0xb58900),
+                inactive_highlight: rgb(0x586e75),
+                border: rgb(0x586e75),
+                title: rgb(0x268bd2),
+                directory: rgb(0x268bd2),
+                file: rgb(0x839496),
+                symlink: rgb(0x2aa198),
+                broken_link: rgb(0xdc322f),
+                executable: rgb(0x859900),
+                setuid: rgb(0xdc322f),
+                socket: rgb(0x6c71c4),
+                pipe: rgb(0xb58900),
+                device: rgb(0xb58900),
+                ignored: rgb(0x586e75),
+                marked: rgb(0xd33682),
+                status_bar: rgb(0x073642),
+                status_text: rgb(0x93a1a1),
+                accent: rgb(0xcb4b16),
+                info: rgb(0x2aa198),
+                error: rgb(0xdc322f),
+                ..Self::base()
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    // The symbols shared by all presets
+    fn base() -> Self {
+        Self {
+            highlight: TuiColor::Reset,
+            inactive_highlight: TuiColor::Reset,
+            highlight_symbol: ">> ".to_string(),
+            border: TuiColor::Reset,
+            title: TuiColor::Reset,
+            directory: TuiColor::Reset,
+            file: TuiColor::Reset,
+            symlink: TuiColor::Reset,
+            broken_link: TuiColor::Reset,
+            executable: TuiColor::Reset,
+            setuid: TuiColor::Reset,
+            socket: TuiColor::Reset,
+            pipe: TuiColor::Reset,
+            device: TuiColor::Reset,
+            ignored: TuiColor::Reset,
+            marked: TuiColor::Reset,
+            mark_symbol: "* ".to_string(),
+            status_bar: TuiColor::Reset,
+            status_text: TuiColor::Reset,
+            accent: TuiColor::Reset,
+            info: TuiColor::Reset,
+            error: TuiColor::Reset,
+            ls_colors: None,
+        }
+    }
+
+    // The `[theme]` section: a preset to start from and single colors on top.
+    // Mistakes are reported and otherwise ignored.
+    pub fn from_config(
+        config_path: &Path,
+        color_depth: ColorDepth,
+        notifications: &mut Notifications,
+    ) -> Self {
+        let section = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("theme").cloned());
+        let Some(Value::Table(section)) = section else {
+            return Self::default().adapt(color_depth);
+        };
+
+        let mut theme = match section.get("preset").and_then(|v| v.as_str()) {
+            Some(name) => Self::preset(name).unwrap_or_else(|| {
+                notifications.error(format!(
+                    "Unknown theme preset {}, expected one of {}",
+                    name,
+                    Self::PRESETS.join(", ")
+                ));
+                Self::default()
+            }),
+            None => Self::default(),
+        };
+        for (key, value) in &section {
+            if key == "ls_colors" {
+                if value.as_bool() == Some(true) {
+                    theme.ls_colors = env::var("LS_COLORS").ok().map(|v| LsColors::parse(&v));
+                }
+                continue;
+            }
+            let value = value.as_str().unwrap_or_default();
+            let color = match key.as_str() {
+                "preset" => continue,
+                "highlight_symbol" => {
+                    theme.highlight_symbol = value.to_string();
+                    continue;
+                }
+                "mark_symbol" => {
+                    theme.mark_symbol = value.to_string();
+                    continue;
+                }
+                "highlight" => &mut theme.highlight,
+                "inactive_highlight" => &mut theme.inactive_highlight,
+                "border" => &mut theme.border,
+                "title" => &mut theme.title,
+                "directory" => &mut theme.directory,
+                "file" => &mut theme.file,
+                "symlink" => &mut theme.symlink,
+                "broken_link" => &mut theme.broken_link,
+                "executable" => &mut theme.executable,
+                "setuid" => &mut theme.setuid,
+                "socket" => &mut theme.socket,
+                "pipe" => &mut theme.pipe,
+                "device" => &mut theme.device,
+                "ignored" => &mut theme.ignored,
+                "marked" => &mut theme.marked,
+                "status_bar" => &mut theme.status_bar,
+                "status_text" => &mut theme.status_text,
+                "accent" => &mut theme.accent,
+                "info" => &mut theme.info,
+                "error" => &mut theme.error,
+                _ => {
+                    notifications.error(format!("Unknown theme setting {}", key));
+                    continue;
+                }
+            };
+            match parse_color(value) {
+                Some(parsed) => *color = parsed,
+                None => notifications.error(format!("Unknown color {} for {}", value, key)),
+            }
+        }
+        theme.adapt(color_depth)
+    }
+
+    // Maps RGB colors to what the terminal can show
+    pub fn adapt(mut self, color_depth: ColorDepth) -> Self {
+        for color in [
+            &mut self.highlight,
+            &mut self.inactive_highlight,
+            &mut self.border,
+            &mut self.title,
+            &mut self.directory,
+            &mut self.file,
+            &mut self.symlink,
+            &mut self.broken_link,
+            &mut self.executable,
+            &mut self.setuid,
+            &mut self.socket,
+            &mut self.pipe,
+            &mut self.device,
+            &mut self.ignored,
+            &mut self.marked,
+            &mut self.status_bar,
+            &mut self.status_text,
+            &mut self.accent,
+            &mut self.info,
+            &mut self.error,
+        ] {
+            *color = color_depth.adapt(*color);
+        }
+        if let Some(ls_colors) = &mut self.ls_colors {
+            ls_colors.adapt(color_depth);
+        }
+        self
+    }
+
+    // Everything but plain files and executables by its kind. Those go by
+    // their opener's color, then by $LS_COLORS' extensions, then by kind.
+    pub fn entry_style(&self, entry: &Entry, opener_color: Option<TuiColor>) -> Style {
+        let kind = EntryKind::of(entry);
+        let ls_colors = self.ls_colors.as_ref();
+        let by_name = || match kind {
+            EntryKind::File | EntryKind::Executable => opener_color
+                .map(|color| Style::default().fg(color))
+                .or_else(|| ls_colors.and_then(|ls| ls.extension(&entry.name))),
+            _ => None,
+        };
+        by_name()
+            .or_else(|| ls_colors.and_then(|ls| ls.kind(kind)))
+            .unwrap_or_else(|| self.kind_style(kind))
+    }
+
+    // Bold for the kinds `ls` shows bold by default
+    fn kind_style(&self, kind: EntryKind) -> Style {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        match kind {
+            EntryKind::BrokenLink => bold.fg(self.broken_link),
+            EntryKind::Symlink => Style::default().fg(self.symlink),
+            EntryKind::Directory => bold.fg(self.directory),
+            EntryKind::Socket => bold.fg(self.socket),
+            EntryKind::Pipe => Style::default().fg(self.pipe),
+            EntryKind::Device => bold.fg(self.device),
+            EntryKind::Setuid => Style::default()
+                .fg(self.setuid)
+                .add_modifier(Modifier::REVERSED),
+            EntryKind::Executable => bold.fg(self.executable),
+            EntryKind::File => Style::default().fg(self.file),
+        }
+    }
+
+    // A bordered panel with a title
+    pub fn block<'a>(&self, title: impl Into<Cow<'a, str>>) -> Block<'a> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.border))
+            .title(Span::styled(title, Style::default().fg(self.title)))
+    }
+}
+
+// What an entry is as far as its color goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryKind {
+    BrokenLink,
+    Symlink,
+    Directory,
+    Socket,
+    Pipe,
+    Device,
+    Setuid,
+    Executable,
+    File,
+}
+
+impl EntryKind {
+    pub fn of(entry: &Entry) -> Self {
+        if entry.is_broken {
+            return EntryKind::BrokenLink;
+        }
+        if entry.is_symlink {
+            return EntryKind::Symlink;
+        }
+        if entry.is_dir {
+            return EntryKind::Directory;
+        }
+        let Some(metadata) = &entry.metadata else {
+            return EntryKind::File;
+        };
+        let mode = mode(metadata);
+        if let Some(kind) = special_kind(&metadata.file_type()) {
+            kind
+        } else if mode & 0o6000 != 0 {
+            EntryKind::Setuid
+        } else if mode & 0o111 != 0 {
+            EntryKind::Executable
+        } else {
+            EntryKind::File
+        }
+    }
+
+    // The LS_COLORS keys of the kind
+    fn codes(self) -> &'static [&'static str] {
+        match self {
+            EntryKind::BrokenLink => &["or"],
+            EntryKind::Symlink => &["ln"],
+            EntryKind::Directory => &["di"],
+            EntryKind::Socket => &["so"],
+            EntryKind::Pipe => &["pi"],
+            EntryKind::Device => &["bd", "cd"],
+            EntryKind::Setuid => &["su", "sg"],
+            EntryKind::Executable => &["ex"],
+            EntryKind::File => &["fi"],
+        }
+    }
+}
+
+// Sockets, pipes and devices, which only Unix has
+#[cfg(unix)]
+fn special_kind(file_type: &fs::FileType) -> Option<EntryKind> {
+    if file_type.is_socket() {
+        Some(EntryKind::Socket)
+    } else if file_type.is_fifo() {
+        Some(EntryKind::Pipe)
+    } else if file_type.is_block_device() || file_type.is_char_device() {
+        Some(EntryKind::Device)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_file_type: &fs::FileType) -> Option<EntryKind> {
+    None
+}
+
+// The `dircolors` format: `di=01;34:ln=01;36:*.tar=01;31:...`
+#[derive(Clone, Default)]
+pub struct LsColors {
+    kinds: Vec<(String, Style)>,
+    // File name suffixes such as ".tar", the last match wins like in `ls`
+    extensions: Vec<(String, Style)>,
+}
+
+impl LsColors {
+    // Entries that don't parse are skipped
+    pub fn parse(value: &str) -> Self {
+        let mut ls_colors = Self::default();
+        for item in value.split(':') {
+            let Some((key, codes)) = item.split_once('=') else {
+                continue;
+            };
+            let Some(style) = parse_sgr(codes) else {
+                continue;
+            };
+            match key.strip_prefix('*') {
+                Some(suffix) => ls_colors.extensions.push((suffix.to_lowercase(), style)),
+                None => ls_colors.kinds.push((key.to_string(), style)),
+            }
+        }
+        ls_colors
+    }
+
+    pub fn kind(&self, kind: EntryKind) -> Option<Style> {
+        kind.codes().iter().find_map(|code| {
+            self.kinds
+                .iter()
+                .rev()
+                .find(|(key, _)| key == code)
+                .map(|(_, style)| *style)
+        })
+    }
+
+    pub fn extension(&self, name: &str) -> Option<Style> {
+        let name = name.to_lowercase();
+        self.extensions
+            .iter()
+            .rev()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .map(|(_, style)| *style)
+    }
+
+    fn adapt(&mut self, color_depth: ColorDepth) {
+        for (_, style) in self.kinds.iter_mut().chain(self.extensions.iter_mut()) {
+            if let Some(fg) = style.fg {
+                style.fg = Some(color_depth.adapt(fg));
+            }
+            if let Some(bg) = style.bg {
+                style.bg = Some(color_depth.adapt(bg));
+            }
+        }
+    }
+}
+
+// SGR codes as `ls` writes them. None for values like `target` that aren't
+// styles.
+fn parse_sgr(codes: &str) -> Option<Style> {
+    let numbers: Vec<u8> = codes
+        .split(';')
+        .filter(|code| !code.is_empty())
+        .map(|code| code.parse().ok())
+        .collect::<Option<_>>()?;
+    let base = [
+        TuiColor::Black,
+        TuiColor::Red,
+        TuiColor::Green,
+        TuiColor::Yellow,
+        TuiColor::Blue,
+        TuiColor::Magenta,
+        TuiColor::Cyan,
+        TuiColor::Gray,
+    ];
+    let bright = [
+        TuiColor::DarkGray,
+        TuiColor::LightRed,
+        TuiColor::LightGreen,
+        TuiColor::LightYellow,
+        TuiColor::LightBlue,
+        TuiColor::LightMagenta,
+        TuiColor::LightCyan,
+        TuiColor::White,
+    ];
+    let mut style = Style::default();
+    let mut numbers = numbers.into_iter();
+    while let Some(code) = numbers.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            30..=37 => style.fg(base[code as usize - 30]),
+            40..=47 => style.bg(base[code as usize - 40]),
+            90..=97 => style.fg(bright[code as usize - 90]),
+            100..=107 => style.bg(bright[code as usize - 100]),
+            38 | 48 => {
+                let color = match numbers.next()? {
+                    5 => TuiColor::Indexed(numbers.next()?),
+                    2 => TuiColor::Rgb(numbers.next()?, numbers.next()?, numbers.next()?),
+                    _ => return None,
+                };
+                if code == 38 {
+                    style.fg(color)
+                } else {
+                    style.bg(color)
+                }
+            }
+            _ => style,
+        };
+    }
+    Some(style)
+}
@@ -0,0 +1,222 @@
+use crate::terminal::with_suspended_tui;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// The global list is kept in the home directory, a project's list in its
+// top directory, under the same name
+pub const TODO_FILE: &str = ".termfm_todo.json";
+// Completed to-dos moved out of the list, next to it
+pub const ARCHIVE_FILE: &str = ".termfm_todo_archive.json";
+// In front of the names of files with an open to-do linked to them
+pub const TODO_SYMBOL: &str = "☐";
+
+// Declared from the most to the least urgent, which is also the order of the list
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Medium, Priority::Low];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Todo {
+    pub description: String,
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    // The file or directory the to-do is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl Todo {
+    pub fn new(description: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            completed: false,
+            priority: None,
+            due: None,
+            path: None,
+        }
+    }
+
+    // "!high 2024-08-01 @disks.txt buy disk": a priority, a due date and a
+    // linked path, in any order and all optional, in front of the description.
+    // The path is taken as it is, relative ones are up to the caller.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut todo = Self::new("");
+        let mut rest = line.trim();
+        while let Some((word, after)) = rest
+            .split_once(char::is_whitespace)
+            .or(Some((rest, "")))
+            .filter(|(word, _)| !word.is_empty())
+        {
+            if let Some(name) = word.strip_prefix('!') {
+                let names: Vec<&str> = Priority::ALL.iter().map(|p| p.name()).collect();
+                todo.priority = Some(
+                    Priority::ALL
+                        .into_iter()
+                        .find(|p| p.name() == name)
+                        .ok_or_else(|| {
+                            format!("Unknown priority {}, expected !{}", word, names.join(", !"))
+                        })?,
+                );
+            } else if let Some(path) = word.strip_prefix('@').filter(|p| !p.is_empty()) {
+                todo.path = Some(PathBuf::from(path));
+            } else if word.len() == 10 && word.as_bytes()[4] == b'-' {
+                todo.due = Some(
+                    NaiveDate::parse_from_str(word, "%Y-%m-%d")
+                        .map_err(|_| format!("Not a date: {}", word))?,
+                );
+            } else {
+                break;
+            }
+            rest = after.trim_start();
+        }
+        if rest.is_empty() {
+            return Err("The to-do has no description".to_string());
+        }
+        todo.description = rest.to_string();
+        Ok(todo)
+    }
+
+    // How the list and the archive show it
+    pub fn label(&self) -> String {
+        let mut label = format!("{} ", if self.completed { "✓ " } else { "☐ " });
+        if let Some(priority) = self.priority {
+            label.push_str(&format!("!{} ", priority.name()));
+        }
+        label.push_str(&self.description);
+        if let Some(due) = self.due {
+            label.push_str(&format!("  due {}", due));
+        }
+        if let Some(path) = &self.path {
+            label.push_str(&format!("  → {}", path.display()));
+        }
+        label
+    }
+
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.completed && self.due.is_some_and(|due| due < today)
+    }
+
+    // Where the to-do goes in the list: by priority, then by due date, those
+    // without either after the ones with
+    fn rank(&self) -> (bool, Option<Priority>, bool, Option<NaiveDate>) {
+        (
+            self.priority.is_none(),
+            self.priority,
+            self.due.is_none(),
+            self.due,
+        )
+    }
+}
+
+// Puts `todo` after the ones that come before it by priority and due date,
+// returning where it went
+pub fn insert_todo(todos: &mut Vec<Todo>, todo: Todo) -> usize {
+    let index = todos
+        .iter()
+        .position(|other| other.rank() > todo.rank())
+        .unwrap_or(todos.len());
+    todos.insert(index, todo);
+    index
+}
+
+pub fn global_todo_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(TODO_FILE))
+}
+
+// The list of the project `dir` is in: the nearest one in it or above, other
+// than the global list
+pub fn project_todo_path(dir: &Path) -> Option<PathBuf> {
+    let global = global_todo_path();
+    dir.ancestors()
+        .map(|dir| dir.join(TODO_FILE))
+        .find(|path| path.is_file() && Some(path) != global.as_ref())
+}
+
+pub fn load_todos(todo_path: &Path) -> Vec<Todo> {
+    if todo_path.exists() {
+        if let Ok(file_content) = fs::read_to_string(todo_path) {
+            if let Ok(todos) = serde_json::from_str(&file_content) {
+                return todos;
+            }
+        }
+    }
+    vec![]
+}
+
+pub fn save_todos(todo_path: &Path, todos: &[Todo]) -> io::Result<()> {
+    let serialized_todos = serde_json::to_string(&todos)?;
+    fs::write(todo_path, serialized_todos)
+}
+
+pub fn archive_path(todo_path: &Path) -> PathBuf {
+    todo_path.with_file_name(ARCHIVE_FILE)
+}
+
+// Moves the completed to-dos to the end of the list's archive, returning how
+// many there were. They stay in the list when the archive can't be written.
+pub fn archive_todos(todo_path: &Path, todos: &mut Vec<Todo>) -> io::Result<usize> {
+    let completed: Vec<Todo> = todos.iter().filter(|t| t.completed).cloned().collect();
+    if completed.is_empty() {
+        return Ok(0);
+    }
+    let archive = archive_path(todo_path);
+    let mut archived = load_todos(&archive);
+    archived.extend(completed);
+    save_todos(&archive, &archived)?;
+    let before = todos.len();
+    todos.retain(|todo| !todo.completed);
+    Ok(before - todos.len())
+}
+
+// Moves the to-do at `index` by `offset` places, as far as the list goes,
+// returning where it ended up
+pub fn move_todo(todos: &mut [Todo], index: usize, offset: isize) -> usize {
+    let target = index
+        .saturating_add_signed(offset)
+        .min(todos.len().saturating_sub(1));
+    if index < target {
+        todos[index..=target].rotate_left(1);
+    } else if target < index {
+        todos[target..=index].rotate_right(1);
+    }
+    target
+}
+
+pub fn add_todo() -> Option<Result<Todo, String>> {
+    let new_task = with_suspended_tui(|| {
+        println!("Enter new task (optionally starting with !high, !medium or !low, a YYYY-MM-DD due date and @<file>): ");
+        let _ = io::stdout().flush();
+
+        let mut new_task = String::new();
+        io::stdin().read_line(&mut new_task).ok().map(|_| new_task)
+    })?;
+
+    let trimmed_task = new_task.trim();
+    if !trimmed_task.is_empty() {
+        Some(Todo::parse(trimmed_task))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,165 @@
+// The directory tree panel: the hierarchy from `/` down, with the ancestors of
+// the current directory expanded and any other node expanded on demand
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct TreeRow {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub expanded: bool,
+}
+
+impl TreeRow {
+    pub fn label(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string());
+        let marker = if self.expanded { "▾" } else { "▸" };
+        format!("{}{} {}", "  ".repeat(self.depth), marker, name)
+    }
+}
+
+pub struct DirTree {
+    pub rows: Vec<TreeRow>,
+    pub cursor: usize,
+    // Keys go to the tree instead of the file list
+    pub focused: bool,
+    // The directory the file list shows
+    pub current: PathBuf,
+    expanded: HashSet<PathBuf>,
+    show_hidden: bool,
+}
+
+impl DirTree {
+    pub fn new(current: &Path, show_hidden: bool) -> Self {
+        let mut tree = Self {
+            rows: Vec::new(),
+            cursor: 0,
+            focused: true,
+            current: PathBuf::new(),
+            expanded: HashSet::new(),
+            show_hidden,
+        };
+        tree.sync(current, show_hidden);
+        tree
+    }
+
+    // Follows the file list into `current`: its ancestors are expanded and the
+    // cursor moves onto it
+    pub fn sync(&mut self, current: &Path, show_hidden: bool) {
+        self.current = current.to_path_buf();
+        self.show_hidden = show_hidden;
+        self.expanded
+            .extend(current.ancestors().map(Path::to_path_buf));
+        self.rebuild();
+        if let Some(index) = self.rows.iter().position(|row| row.path == current) {
+            self.cursor = index;
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let selected = self.selected().map(Path::to_path_buf);
+        let root = self
+            .current
+            .ancestors()
+            .last()
+            .unwrap_or(Path::new("/"))
+            .to_path_buf();
+        self.rows.clear();
+        self.add_rows(root, 0);
+        if let Some(index) = selected.and_then(|path| self.rows.iter().position(|r| r.path == path))
+        {
+            self.cursor = index;
+        }
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn add_rows(&mut self, path: PathBuf, depth: usize) {
+        let expanded = self.expanded.contains(&path);
+        self.rows.push(TreeRow {
+            path: path.clone(),
+            depth,
+            expanded,
+        });
+        if expanded {
+            for child in subdirectories(&path, self.show_hidden) {
+                self.add_rows(child, depth + 1);
+            }
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Path> {
+        self.rows.get(self.cursor).map(|row| row.path.as_path())
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(self.rows.len().saturating_sub(1));
+    }
+
+    // Expands the node, or steps into an expanded one
+    pub fn expand(&mut self) {
+        let Some(row) = self.rows.get(self.cursor) else {
+            return;
+        };
+        if row.expanded {
+            let depth = row.depth;
+            if self
+                .rows
+                .get(self.cursor + 1)
+                .is_some_and(|r| r.depth > depth)
+            {
+                self.cursor += 1;
+            }
+        } else {
+            self.expanded.insert(row.path.clone());
+            self.rebuild();
+        }
+    }
+
+    // Collapses the node, or steps out to its parent
+    pub fn collapse(&mut self) {
+        let Some(row) = self.rows.get(self.cursor) else {
+            return;
+        };
+        if row.expanded {
+            self.expanded.remove(&row.path.clone());
+            self.rebuild();
+        } else if let Some(parent) = row.path.parent() {
+            if let Some(index) = self.rows.iter().position(|r| r.path == parent) {
+                self.cursor = index;
+            }
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        match self.rows.get(self.cursor) {
+            Some(row) if row.expanded => self.collapse(),
+            Some(_) => self.expand(),
+            None => {}
+        }
+    }
+}
+
+// Sorted like the file list's name order
+fn subdirectories(dir: &Path, show_hidden: bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort_by_key(|path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+    });
+    dirs
+}
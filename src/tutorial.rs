@@ -0,0 +1,155 @@
+use crate::ui::{Color as TuiColor, Line, Span, Style};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// What the tutorial steps look at to tell whether they were done
+pub struct TutorialView<'a> {
+    pub dir: &'a Path,
+    pub marked: &'a HashSet<PathBuf>,
+    pub search_query: &'a str,
+}
+
+pub struct TutorialStep {
+    pub title: &'static str,
+    pub instruction: &'static str,
+    // Gets the sandbox root
+    pub done: fn(&Path, &TutorialView) -> bool,
+}
+
+pub const TUTORIAL_STEPS: &[TutorialStep] = &[
+    TutorialStep {
+        title: "Navigation",
+        instruction:
+            "Move the cursor with j/k or the arrow keys onto `docs` and press l to enter it.",
+        done: |root, view| view.dir == root.join("docs"),
+    },
+    TutorialStep {
+        title: "Going back",
+        instruction: "Press h to return to the parent directory.",
+        done: |root, view| view.dir == root,
+    },
+    TutorialStep {
+        title: "Marking",
+        instruction: "Mark both `draft.txt` and `old.log` with m.",
+        done: |root, view| {
+            view.marked.contains(&root.join("draft.txt"))
+                && view.marked.contains(&root.join("old.log"))
+        },
+    },
+    TutorialStep {
+        title: "Deleting",
+        instruction: "Type :delete and press Enter to delete the marked files.",
+        done: |root, _| !root.join("draft.txt").exists() && !root.join("old.log").exists(),
+    },
+    TutorialStep {
+        title: "Creating",
+        instruction: "Type :mkdir archive and press Enter to create a directory.",
+        done: |root, _| root.join("archive").is_dir(),
+    },
+    TutorialStep {
+        title: "Renaming",
+        instruction: "Select `report.txt` and type :rename final.txt to rename it.",
+        done: |root, _| root.join("final.txt").exists() && !root.join("report.txt").exists(),
+    },
+    TutorialStep {
+        title: "Searching",
+        instruction: "Enter `photos`, press / and search for `beach`.",
+        done: |root, view| {
+            view.dir == root.join("photos")
+                && !view.search_query.is_empty()
+                && "beach.jpg".contains(view.search_query)
+        },
+    },
+];
+
+// Sandbox and progress of `--tutorial`
+pub struct Tutorial {
+    pub root: PathBuf,
+    pub step: usize,
+}
+
+impl Tutorial {
+    // Creates a fresh sandbox under the temp directory with a few files to
+    // practice on
+    pub fn new() -> io::Result<Self> {
+        let root = env::temp_dir().join(format!("termfm-tutorial-{}", std::process::id()));
+        if root.exists() {
+            fs::remove_dir_all(&root)?;
+        }
+        let files: [(&str, &str); 7] = [
+            ("docs/readme.txt", "You found the docs directory.\n"),
+            ("docs/notes.md", "# Notes\n"),
+            ("photos/beach.jpg", ""),
+            ("photos/mountains.png", ""),
+            ("draft.txt", "An early draft.\n"),
+            ("old.log", "Nothing to see here.\n"),
+            ("report.txt", "The final report.\n"),
+        ];
+        for (name, content) in files {
+            let path = root.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        // Directory changes are canonicalized, compare against the same form
+        let root = fs::canonicalize(&root)?;
+        Ok(Tutorial { root, step: 0 })
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step >= TUTORIAL_STEPS.len()
+    }
+
+    // Moves past every step that is done, returning the titles of those
+    pub fn update(&mut self, view: &TutorialView) -> Vec<&'static str> {
+        let mut completed = Vec::new();
+        while let Some(step) = TUTORIAL_STEPS.get(self.step) {
+            if !(step.done)(&self.root, view) {
+                break;
+            }
+            completed.push(step.title);
+            self.step += 1;
+        }
+        completed
+    }
+
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for (i, step) in TUTORIAL_STEPS.iter().enumerate() {
+            if i < self.step {
+                lines.push(Line::from(Span::styled(
+                    format!("✓ {}", step.title),
+                    Style::default().fg(TuiColor::Green),
+                )));
+            } else if i == self.step {
+                lines.push(Line::from(Span::styled(
+                    format!("> {}", step.title),
+                    Style::default().fg(TuiColor::Yellow),
+                )));
+                lines.push(Line::from(format!("  {}", step.instruction)));
+            } else {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}", step.title),
+                    Style::default().fg(TuiColor::DarkGray),
+                )));
+            }
+        }
+        if self.finished() {
+            lines.push(Line::from(Span::styled(
+                "All done! Press q to quit, the sandbox is removed on exit.",
+                Style::default().fg(TuiColor::Green),
+            )));
+        }
+        lines
+    }
+}
+
+impl Drop for Tutorial {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,1460 @@
+use crate::{
+    app::{App, Level, Panel, SplitDirection},
+    chords,
+    columns::permission_spans,
+    config::get_file_style,
+    fs_ops::{format_size, format_time, group_name, owner_ids, user_name, Entry, TextFormat},
+    ignored::IgnoreMode,
+    preview::detect_encoding,
+    sync::SyncAction,
+    tags::TAG_SYMBOL,
+    todo::{Priority, TODO_SYMBOL},
+    tutorial::TUTORIAL_STEPS,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout},
+    widgets::{Clear, List, ListItem, Paragraph, Widget, Wrap},
+    Frame,
+};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+// The types of the terminal UI crate that the rest of termfm uses, which
+// takes them from here so that the crate is named in this module only
+pub use ratatui::{
+    backend::{Backend, CrosstermBackend, TestBackend},
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, ListState},
+    Terminal,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Columns and rows below which only a placeholder is drawn: the path bar,
+// a few entries and the status bar, next to a preview
+pub const MIN_SIZE: (u16, u16) = (40, 10);
+
+// How far the preview column can be resized, in percent of the width, and
+// by how much at a time
+pub const PREVIEW_WIDTHS: RangeInclusive<u16> = 10..=90;
+pub const PREVIEW_STEP: i32 = 5;
+
+impl App {
+    // Percent of the width for the file list, the rest is the preview column
+    pub fn list_width(&self) -> u16 {
+        if self.state.preview_hidden {
+            return 100;
+        }
+        // The detailed view and side by side panes need room
+        let default = match &self.state.split {
+            _ if self.state.detailed_view => 60,
+            Some(split) if split.direction == SplitDirection::Vertical => 50,
+            _ => 30,
+        };
+        self.state
+            .preview_width
+            .map_or(default, |preview| 100 - preview)
+    }
+
+    pub fn draw(&mut self, f: &mut Frame) {
+        let size = f.area();
+        if size.width < MIN_SIZE.0 || size.height < MIN_SIZE.1 {
+            let message = format!(
+                "Terminal too small ({}x{}), termfm needs {}x{}",
+                size.width, size.height, MIN_SIZE.0, MIN_SIZE.1
+            );
+            let top = Rect::new(0, size.height.saturating_sub(1) / 2, size.width, 1);
+            f.render_widget(
+                Paragraph::new(truncate_end(&message, size.width as usize))
+                    .alignment(Alignment::Center),
+                top,
+            );
+            return;
+        }
+        let selected_file = self.selected_file();
+        let outer_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(f.area());
+
+        let list_width = self.list_width();
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(list_width),
+                    Constraint::Percentage(100 - list_width),
+                ]
+                .as_ref(),
+            )
+            .split(outer_chunks[0]);
+
+        // The path bar keeps its one line on small screens
+        let left_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(chunks[0]);
+
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Percentage(63),
+                    Constraint::Percentage(30),
+                ]
+                .as_ref(),
+            )
+            .split(chunks[1]);
+
+        // Upper Left Panel: the current directory as breadcrumbs
+        let path_area = left_chunks[0];
+        // Remote directories show their URL, which can't be clicked through
+        let crumbs = match &self.remote {
+            Some(_) => Vec::new(),
+            None => breadcrumbs(&self.current_dir, inner_width(path_area)),
+        };
+        let mut spans = Vec::new();
+        if let Some(remote) = &self.remote {
+            spans.push(Span::styled(
+                truncate_middle(&remote.location.to_string(), inner_width(path_area)),
+                Style::default().fg(self.theme.highlight),
+            ));
+        }
+        let mut column = path_area.x + 1;
+        self.breadcrumbs.clear();
+        for crumb in crumbs {
+            let start = column;
+            if let Some(levels) = crumb.levels.filter(|&levels| levels > 0) {
+                spans.push(Span::styled(
+                    levels.to_string(),
+                    Style::default().fg(self.theme.accent),
+                ));
+                column += 1;
+            }
+            let style = if crumb.levels == Some(0) {
+                Style::default().fg(self.theme.highlight)
+            } else {
+                Style::default()
+            };
+            column += crumb.name.width() as u16;
+            spans.push(Span::styled(crumb.name, style));
+            if let Some(dir) = crumb.dir {
+                self.breadcrumbs
+                    .push((Rect::new(start, path_area.y + 1, column - start, 1), dir));
+            }
+            if !crumb.separator.is_empty() {
+                column += 1;
+                spans.push(Span::raw(crumb.separator));
+            }
+        }
+        let upper_left_panel = List::new(vec![ListItem::new(Line::from(spans))])
+            .block(self.theme.block("Current Directory"));
+        f.render_widget(upper_left_panel, path_area);
+
+        // The highlight symbol takes room on every row
+        let symbol_width = self.theme.highlight_symbol.width();
+        let mut list_area = left_chunks[1];
+        if let Some(tree) = &self.state.tree {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+                .split(list_area);
+            list_area = columns[1];
+
+            let area = columns[0];
+            let offset = scroll_offset(0, tree.cursor, inner_height(area));
+            let items: Vec<ListItem> = tree
+                .rows
+                .iter()
+                .skip(offset)
+                .take(inner_height(area))
+                .map(|row| {
+                    let item = ListItem::new(truncate_end(
+                        &row.label(),
+                        inner_width(area).saturating_sub(symbol_width),
+                    ));
+                    // The directory the file list shows stands out
+                    if row.path == tree.current {
+                        item.style(Style::default().fg(self.theme.accent))
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            let highlight = if tree.focused {
+                self.theme.highlight
+            } else {
+                self.theme.inactive_highlight
+            };
+            let mut state = ListState::default();
+            state.select(Some(tree.cursor - offset));
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(self.theme.block("Tree"))
+                    .highlight_style(Style::default().fg(highlight))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                area,
+                &mut state,
+            );
+        }
+        if let Some(split) = &self.state.split {
+            let direction = match split.direction {
+                SplitDirection::Vertical => Direction::Horizontal,
+                SplitDirection::Horizontal => Direction::Vertical,
+            };
+            let panes = Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(list_area);
+            let (focused, other) = if split.focus_second {
+                (panes[1], panes[0])
+            } else {
+                (panes[0], panes[1])
+            };
+            list_area = focused;
+
+            let offset = scroll_offset(0, split.other.cursor, inner_height(other));
+            let items: Vec<ListItem> = split
+                .other
+                .files
+                .iter()
+                .skip(offset)
+                .take(inner_height(other))
+                .map(|entry| {
+                    ListItem::new(truncate_end(
+                        &format!("{}{}", self.icons.prefix(entry), entry.name),
+                        inner_width(other).saturating_sub(symbol_width),
+                    ))
+                    .style(
+                        self.theme
+                            .entry_style(entry, get_file_style(&entry.name, &self.opener_config)),
+                    )
+                })
+                .collect();
+            let title = match &split.other.remote {
+                Some(remote) => remote.location.to_string(),
+                None => split.other.dir.to_string_lossy().into_owned(),
+            };
+            let title = truncate_middle(&title, inner_width(other));
+            let mut state = ListState::default();
+            state.select(Some(split.other.cursor - offset));
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(self.theme.block(title))
+                    .highlight_style(Style::default().fg(self.theme.inactive_highlight))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                other,
+                &mut state,
+            );
+            f.render_widget(self.scrollbar(offset, split.other.files.len()), other);
+        }
+
+        // Bottom Left Panel (File Listing)
+        let name_width = inner_width(list_area).saturating_sub(symbol_width);
+        // Only the rows on screen are built, huge directories would
+        // otherwise cost a ListItem per entry every frame
+        self.list_height = inner_height(list_area);
+        // Without rows left empty at the end, e.g. after the terminal grew
+        self.list_offset = scroll_offset(
+            self.list_offset,
+            self.cursor_position,
+            inner_height(list_area),
+        )
+        .min(self.state.files.len().saturating_sub(self.list_height));
+        // Files with open to-dos about them
+        let linked: HashSet<&Path> = self
+            .todos
+            .iter()
+            .filter(|todo| !todo.completed)
+            .filter_map(|todo| todo.path.as_deref())
+            .collect();
+        let items: Vec<ListItem> = if self.state.loading && self.state.files.is_empty() {
+            vec![ListItem::new("<Loading directory...>")
+                .style(Style::default().fg(self.theme.accent))]
+        } else {
+            self.state
+                .files
+                .iter()
+                .skip(self.list_offset)
+                .take(inner_height(list_area))
+                .map(|entry| {
+                    let full_path = self.current_dir.join(&entry.name);
+                    let (marker, style) = if self.state.marked.contains(&full_path) {
+                        (
+                            self.theme.mark_symbol.as_str(),
+                            Style::default().fg(self.theme.marked),
+                        )
+                    } else if self.ignore.mode == IgnoreMode::Dim
+                        && self.ignore.is_ignored(&full_path, entry.is_dir)
+                    {
+                        ("", Style::default().fg(self.theme.ignored))
+                    } else {
+                        (
+                            "",
+                            self.theme.entry_style(
+                                entry,
+                                get_file_style(&entry.name, &self.opener_config),
+                            ),
+                        )
+                    };
+                    let name = format!("{}{}{}", marker, self.icons.prefix(entry), entry.name);
+                    // One colored dot per tag, in front of the name
+                    let mut tag_spans: Vec<Span> = self
+                        .tags
+                        .get(&full_path)
+                        .map(|tag| {
+                            Span::styled(TAG_SYMBOL, Style::default().fg(self.tags.color(tag)))
+                        })
+                        .collect();
+                    if linked.contains(full_path.as_path()) {
+                        tag_spans.push(Span::styled(
+                            TODO_SYMBOL,
+                            Style::default().fg(self.theme.accent),
+                        ));
+                    }
+                    if !tag_spans.is_empty() {
+                        tag_spans.push(Span::raw(" "));
+                    }
+                    let tags_width: usize = tag_spans.iter().map(|span| span.width()).sum();
+                    let note = self.notes.get(&full_path).filter(|_| self.notes.in_listing);
+                    if !self.state.detailed_view && tag_spans.is_empty() && note.is_none() {
+                        let name = truncate_end(&name, name_width);
+                        return ListItem::new(Line::from(search_spans(
+                            name,
+                            &self.search_query,
+                            style,
+                            self.theme.accent,
+                        )));
+                    }
+
+                    let mut spans = if self.state.detailed_view {
+                        self.columns.render(&full_path, entry.metadata.as_ref())
+                    } else {
+                        Vec::new()
+                    };
+                    let columns_width: usize = spans.iter().map(|span| span.width()).sum();
+                    spans.extend(tag_spans);
+                    let room = name_width.saturating_sub(columns_width + tags_width);
+                    let name = truncate_end(&name, room);
+                    // Notes get whatever room the name leaves, dimmed
+                    let note_room = room.saturating_sub(name.width() + 2);
+                    spans.extend(search_spans(
+                        name,
+                        &self.search_query,
+                        style,
+                        self.theme.accent,
+                    ));
+                    if let Some(note) = note.filter(|_| note_room > 0) {
+                        spans.push(Span::styled(
+                            format!("  {}", truncate_end(note, note_room)),
+                            Style::default().fg(self.theme.ignored),
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
+                })
+                .collect()
+        };
+
+        let list = List::new(items)
+            .block(self.panel_block(Panel::Files, "Files"))
+            .highlight_style(Style::default().fg(self.panel_highlight(Panel::Files)))
+            .highlight_symbol(self.theme.highlight_symbol.as_str());
+
+        let mut state = ListState::default();
+        state.select(Some(self.cursor_position.saturating_sub(self.list_offset)));
+        f.render_stateful_widget(list, list_area, &mut state);
+        f.render_widget(
+            self.scrollbar(self.list_offset, self.state.files.len()),
+            list_area,
+        );
+
+        // Right Panel
+        let upper_right_panel =
+            List::new(vec![ListItem::new("To be updated")]).block(self.theme.block("New Panel"));
+        f.render_widget(upper_right_panel, right_chunks[0]);
+
+        let preview_area = match &self.state.pinned_preview {
+            Some((pinned_path, pinned)) => {
+                let halves = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(right_chunks[1]);
+                let title = pinned.title(&format!(
+                    "Pinned: {}",
+                    pinned_path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                ));
+                let pinned_panel = List::new(
+                    pinned
+                        .lines
+                        .iter()
+                        .map(|line| ListItem::new(truncate_end(line, inner_width(halves[0]))))
+                        .collect::<Vec<ListItem>>(),
+                )
+                .block(self.theme.block(title));
+                f.render_widget(pinned_panel, halves[0]);
+                halves[1]
+            }
+            None => right_chunks[1],
+        };
+        let preview_width = inner_width(preview_area);
+        let mut rendered_markdown = None;
+        // Lines of the preview, of which the pane shows those from `scroll` on
+        let mut preview_rows = 0;
+        let preview_path = selected_file
+            .as_ref()
+            .map(|file| self.current_dir.join(file));
+        let mut scroll = match (&self.state.preview_scroll, &preview_path) {
+            (Some((scrolled, scroll)), Some(path)) if scrolled == path => *scroll,
+            _ => 0,
+        };
+        let preview_height = inner_height(preview_area);
+        let middle_right_panel = match &selected_file {
+            Some(file) => {
+                let full_path = self.current_dir.join(file);
+                if self.metadata_cache.is_dir(&full_path) {
+                    // Show directory contents preview
+                    let preview_entries: Vec<Entry> = match self.dir_cache.get_entries(
+                        &full_path,
+                        self.show_hidden,
+                        self.state.sort_mode,
+                        self.state.mount.stat_entries,
+                    ) {
+                        Ok(entries) => {
+                            preview_rows = entries.len();
+                            scroll = scroll.min(preview_rows.saturating_sub(preview_height));
+                            entries
+                                .iter()
+                                .skip(scroll)
+                                .take(preview_height)
+                                .cloned()
+                                .collect()
+                        }
+                        Err(_) => vec![Entry::placeholder("<Error loading>")],
+                    };
+
+                    let items_with_color: Vec<ListItem> = preview_entries
+                        .iter()
+                        .map(|entry| {
+                            ListItem::new(truncate_end(
+                                &format!("{}{}", self.icons.prefix(entry), entry.name),
+                                preview_width,
+                            ))
+                            .style(self.theme.entry_style(
+                                entry,
+                                get_file_style(&entry.name, &self.opener_config),
+                            ))
+                        })
+                        .collect();
+
+                    List::new(items_with_color)
+                        .block(self.panel_block(Panel::Preview, "Directory Contents"))
+                } else if let Some(follow) = self
+                    .follow
+                    .as_ref()
+                    .filter(|follow| follow.path == full_path)
+                {
+                    List::new(
+                        follow
+                            .tail(inner_height(preview_area))
+                            .iter()
+                            .map(|line| ListItem::new(truncate_end(line, preview_width)))
+                            .collect::<Vec<ListItem>>(),
+                    )
+                    .block(self.panel_block(Panel::Preview, "File Preview [following]"))
+                } else {
+                    match &self.preview_cache {
+                        Some((cached_path, preview))
+                            if cached_path == &full_path
+                                && preview.rendered.is_some()
+                                && !self.state.markdown_source =>
+                        {
+                            rendered_markdown = preview.rendered.clone();
+                            // Wrapped, so only roughly
+                            preview_rows = preview.rendered.as_ref().map_or(0, |l| l.len());
+                            scroll = scroll.min(preview_rows.saturating_sub(1));
+                            List::new(Vec::<ListItem>::new())
+                        }
+                        Some((cached_path, preview)) if cached_path == &full_path => {
+                            let lines = match (&preview.tail, self.state.preview_tail) {
+                                (Some(tail), true) => tail,
+                                _ => &preview.lines,
+                            };
+                            preview_rows = lines.len();
+                            scroll = scroll.min(preview_rows.saturating_sub(preview_height));
+                            List::new(
+                                lines
+                                    .iter()
+                                    .skip(scroll)
+                                    .take(preview_height)
+                                    .map(|line| ListItem::new(truncate_end(line, preview_width)))
+                                    .collect::<Vec<ListItem>>(),
+                            )
+                            .block(self.panel_block(Panel::Preview, preview.title("File Preview")))
+                        }
+                        _ => List::new(vec![ListItem::new("<Loading preview...>".to_string())])
+                            .block(self.panel_block(Panel::Preview, "File Preview")),
+                    }
+                }
+            }
+            None => List::new(Vec::<ListItem>::new()),
+        };
+        match rendered_markdown {
+            // Paragraphs are wrapped rather than cut off
+            Some(lines) => f.render_widget(
+                Paragraph::new(lines)
+                    .block(self.panel_block(Panel::Preview, "File Preview [rendered]"))
+                    .wrap(Wrap { trim: false })
+                    .scroll((scroll.min(u16::MAX as usize) as u16, 0)),
+                preview_area,
+            ),
+            None => {
+                f.render_widget(middle_right_panel, preview_area);
+                f.render_widget(self.scrollbar(scroll, preview_rows), preview_area);
+            }
+        }
+        // Scrolling past the end is taken back
+        if let Some((_, scrolled)) = self.state.preview_scroll.as_mut() {
+            *scrolled = scroll;
+        }
+
+        let today = chrono::Local::now().date_naive();
+        let bottom_right_panel: Vec<ListItem> = self
+            .todos
+            .iter()
+            .map(|todo| {
+                let status = if todo.completed { "✓ " } else { "☐ " };
+                let mut spans = vec![Span::raw(format!("{} ", status))];
+                if let Some(priority) = todo.priority {
+                    let color = match priority {
+                        Priority::High => self.theme.error,
+                        Priority::Medium => self.theme.accent,
+                        Priority::Low => self.theme.ignored,
+                    };
+                    spans.push(Span::styled(
+                        format!("!{} ", priority.name()),
+                        Style::default().fg(color),
+                    ));
+                }
+                spans.push(Span::raw(todo.description.clone()));
+                if let Some(due) = todo.due {
+                    spans.push(Span::styled(
+                        format!("  due {}", due),
+                        Style::default().fg(self.theme.ignored),
+                    ));
+                }
+                if let Some(name) = todo.path.as_deref().and_then(Path::file_name) {
+                    spans.push(Span::styled(
+                        format!("  → {}", name.to_string_lossy()),
+                        Style::default().fg(self.theme.ignored),
+                    ));
+                }
+                let item = ListItem::new(Line::from(spans));
+                // Overdue ones are red all over
+                if todo.is_overdue(today) {
+                    item.style(Style::default().fg(self.theme.error))
+                } else {
+                    item
+                }
+            })
+            .collect();
+
+        let todo_list = List::new(bottom_right_panel)
+            .block(self.panel_block(
+                Panel::Todos,
+                match self.todo_project() {
+                    Some(project) => format!(
+                        "To-Do List: {}",
+                        truncate_middle(
+                            &project.to_string_lossy(),
+                            inner_width(right_chunks[2]).saturating_sub(12)
+                        )
+                    ),
+                    None => "To-Do List".to_string(),
+                },
+            ))
+            .highlight_style(Style::default().fg(self.panel_highlight(Panel::Todos)));
+
+        // The tutorial takes the place of the to-do list
+        match &self.tutorial {
+            Some(tutorial) => {
+                let title = format!(
+                    "Tutorial {}/{}",
+                    tutorial.step.min(TUTORIAL_STEPS.len() - 1) + 1,
+                    TUTORIAL_STEPS.len()
+                );
+                f.render_widget(
+                    Paragraph::new(tutorial.lines())
+                        .block(self.theme.block(title))
+                        .wrap(Wrap { trim: false }),
+                    right_chunks[2],
+                );
+            }
+            None => f.render_stateful_widget(todo_list, right_chunks[2], &mut self.todo_list_state),
+        }
+
+        // Status Bar
+        let position = if self.state.files.is_empty() {
+            "0/0".to_string()
+        } else {
+            format!("{}/{}", self.cursor_position + 1, self.state.files.len())
+        };
+        let mut status = vec![
+            Span::styled(position, Style::default().fg(self.theme.accent)),
+            Span::raw(format!("  marked: {}", self.state.marked.len())),
+            Span::raw(format!(
+                "  free: {}",
+                self.state
+                    .free_space
+                    .map(format_size)
+                    .unwrap_or_else(|| "?".to_string())
+            )),
+            Span::raw(format!("  sort: {}", self.state.sort_mode.label())),
+        ];
+        if self.state.loading {
+            status.push(Span::styled(
+                format!("  {} entries loaded…", self.state.files.len()),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        if self.state.mount.is_special() {
+            status.push(Span::styled(
+                format!("  [{}]", self.state.mount.fs_type),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        if let (Some(file), Some((path, preview))) = (&selected_file, &self.preview_cache) {
+            if let Some(format) = preview
+                .text_format
+                .filter(|_| *path == self.current_dir.join(file))
+            {
+                status.push(Span::raw(format!("  {}", format.label())));
+            }
+        }
+        if self.search_filtered {
+            status.push(Span::raw(format!("  filter: {}", self.search_query)));
+        } else if !self.search_query.is_empty() {
+            status.push(Span::raw(format!("  search: {}", self.search_query)));
+        }
+        if let Some((label, _)) = &self.virtual_dir {
+            status.push(Span::styled(
+                format!("  {}", label),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        if let Some(tag) = &self.tag_filter {
+            status.push(Span::styled(
+                format!("  tag: {}", tag),
+                Style::default().fg(self.tags.color(tag)),
+            ));
+        }
+        if self.ignore.mode != IgnoreMode::Show {
+            status.push(Span::raw(format!(
+                "  ignored: {}",
+                self.ignore.mode.label()
+            )));
+        }
+        if self.visual.is_some() {
+            status.push(Span::styled(
+                "  VISUAL",
+                Style::default().fg(self.theme.marked),
+            ));
+        }
+        if let Some((register, _)) = &self.macros.recording {
+            status.push(Span::styled(
+                format!("  recording @{}", register),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        if let Some(count) = self.state.count {
+            status.push(Span::styled(
+                format!("  {}", count),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        if !self.state.pending_keys.is_empty() {
+            status.push(Span::styled(
+                format!(
+                    "  {}: {}",
+                    chords::display(&self.state.pending_keys),
+                    chords::continuations(&self.state.pending_keys)
+                ),
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        for segment in &self.script_segments {
+            status.push(Span::raw(format!("  {}", segment)));
+        }
+        if let Some(notification) = self.state.notifications.current() {
+            let color = match notification.level {
+                Level::Info => self.theme.info,
+                Level::Error => self.theme.error,
+            };
+            status.push(Span::styled(
+                format!("  {}", notification.message),
+                Style::default().fg(color),
+            ));
+        }
+        if let Some(prompt) = &self.state.prompt {
+            status = vec![Span::raw(format!("{}{}", prompt.prefix, prompt.input))];
+        }
+        f.render_widget(
+            Paragraph::new(Line::from(status)).style(
+                Style::default()
+                    .fg(self.theme.status_text)
+                    .bg(self.theme.status_bar),
+            ),
+            outer_chunks[1],
+        );
+
+        // Message Log
+        if self.state.show_messages {
+            let lines: Vec<ListItem> = self
+                .state
+                .notifications
+                .log
+                .iter()
+                .rev()
+                .map(|n| {
+                    let color = match n.level {
+                        Level::Info => self.theme.file,
+                        Level::Error => self.theme.error,
+                    };
+                    ListItem::new(format!("{} {}", n.timestamp.format("%H:%M:%S"), n.message))
+                        .style(Style::default().fg(color))
+                })
+                .collect();
+            let area = centered_rect(80, 60, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block("Messages")), area);
+        }
+
+        // Output of a `[commands]` entry or a `!` command, the end of it
+        // unless it was scrolled back
+        if let Some((name, output)) = &self.state.command_output {
+            let area = centered_rect(80, 60, f.area());
+            let rows = area.height.saturating_sub(2) as usize;
+            self.output_rows = rows;
+            let last = output.len().saturating_sub(rows);
+            let scroll = self.state.output_scroll.unwrap_or(last).min(last);
+            let lines: Vec<ListItem> = output[scroll..output.len().min(scroll + rows)]
+                .iter()
+                .map(|line| ListItem::new(line.as_str()))
+                .collect();
+            let name = if name.starts_with('!') {
+                name.clone()
+            } else {
+                format!(":{}", name)
+            };
+            let title = match self.shell {
+                Some(_) => format!("{} (running, Esc stops)", name),
+                None => format!("{} (Esc closes)", name),
+            };
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block(&title)), area);
+            f.render_widget(self.scrollbar(scroll, output.len()), area);
+        }
+
+        // Cache Statistics
+        if self.state.show_cache_stats {
+            let lines = vec![
+                ListItem::new(self.dir_cache.entries.stats("Directories")),
+                ListItem::new(self.metadata_cache.metadata.stats("Metadata")),
+            ];
+            let area = centered_rect(80, 20, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block("Cache")), area);
+        }
+
+        // Properties Dialog
+        if self.state.show_properties {
+            if let Some(file) = &selected_file {
+                let path = self.current_dir.join(file);
+                let mut lines: Vec<ListItem> = properties_lines(&path)
+                    .into_iter()
+                    .map(ListItem::new)
+                    .collect();
+                let mut tags = vec![Span::raw("Tags:       ")];
+                for tag in self.tags.get(&path) {
+                    tags.push(Span::styled(
+                        format!(" {}", tag),
+                        Style::default().fg(self.tags.color(tag)),
+                    ));
+                }
+                if tags.len() > 1 {
+                    lines.push(ListItem::new(Line::from(tags)));
+                }
+                if let Some(note) = self.notes.get(&path) {
+                    lines.push(ListItem::new(format!("Note:        {}", note)));
+                }
+                let area = centered_rect(60, 50, f.area());
+                f.render_widget(Clear, area);
+                f.render_widget(List::new(lines).block(self.theme.block("Properties")), area);
+            }
+        }
+
+        // Recovery Dialog
+        if let Some(journal) = &self.state.recovery {
+            let lines: Vec<ListItem> = journal.describe().into_iter().map(ListItem::new).collect();
+            let area = centered_rect(60, 30, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(
+                List::new(lines).block(self.theme.block("Interrupted operation")),
+                area,
+            );
+        }
+
+        // Filing Plan
+        if let Some(plan) = &self.state.filing_plan {
+            let lines: Vec<ListItem> = plan
+                .iter()
+                .map(|(from, to)| {
+                    let to = to.strip_prefix(&self.current_dir).unwrap_or(to);
+                    ListItem::new(format!(
+                        "{} → {}",
+                        from.file_name().unwrap_or_default().to_string_lossy(),
+                        to.display()
+                    ))
+                })
+                .collect();
+            let title = format!(
+                "Organize: {} move(s), Enter to apply, Esc to cancel",
+                plan.len()
+            );
+            let area = centered_rect(80, 60, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block(title)), area);
+        }
+
+        // Discard Confirmation
+        if let Some(paths) = &self.state.pending_discard {
+            let lines: Vec<ListItem> = paths
+                .iter()
+                .map(|path| {
+                    let path = path.strip_prefix(&self.current_dir).unwrap_or(path);
+                    ListItem::new(path.to_string_lossy().into_owned())
+                })
+                .collect();
+            let title = format!(
+                "Discard changes to {} file(s)? Enter to discard, Esc to cancel",
+                paths.len()
+            );
+            let area = centered_rect(80, 40, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block(title)), area);
+        }
+
+        // Delete Confirmation
+        if let Some(paths) = &self.state.pending_delete {
+            let lines: Vec<ListItem> = paths
+                .iter()
+                .map(|path| {
+                    let path = path.strip_prefix(&self.current_dir).unwrap_or(path);
+                    ListItem::new(path.to_string_lossy().into_owned())
+                })
+                .collect();
+            let title = format!(
+                "Delete {} item(s)? Enter to delete, Esc to cancel",
+                paths.len()
+            );
+            let area = centered_rect(80, 40, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block(title)), area);
+        }
+
+        // Merge Conflict Dialog
+        if let Some(merge) = &self.state.merge {
+            let lines: Vec<ListItem> = merge
+                .conflict_lines()
+                .into_iter()
+                .map(ListItem::new)
+                .collect();
+            let area = centered_rect(60, 30, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(
+                List::new(lines).block(self.theme.block("Merge conflict")),
+                area,
+            );
+        }
+
+        // Paste Conflict Dialog
+        if let Some(paste) = &self.state.paste {
+            let lines: Vec<ListItem> = paste
+                .conflict_lines()
+                .into_iter()
+                .map(ListItem::new)
+                .collect();
+            let title = format!("File exists ({} more)", paste.pending.len());
+            let area = centered_rect(60, 40, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(List::new(lines).block(self.theme.block(title)), area);
+        }
+
+        // Diff
+        if let Some(view) = &self.state.diff {
+            let area = centered_rect(90, 90, f.area());
+            let lines: Vec<Line> = view
+                .lines(inner_width(area))
+                .into_iter()
+                .skip(view.scroll)
+                .collect();
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(lines).block(self.theme.block(view.title())),
+                area,
+            );
+        }
+
+        // Checksums
+        if let Some(checksums) = &self.state.checksums {
+            let items: Vec<ListItem> = checksums
+                .entries
+                .iter()
+                .map(|entry| {
+                    let item = ListItem::new(entry.line());
+                    if entry.is_mismatch() {
+                        item.style(Style::default().fg(self.theme.error))
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            let area = centered_rect(80, 50, f.area());
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(self.theme.block(checksums.title()))
+                    .highlight_style(Style::default().fg(self.theme.highlight))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                area,
+                &mut checksums.state.clone(),
+            );
+        }
+
+        // Sync Plan
+        if let Some(sync) = &self.state.sync {
+            let items: Vec<ListItem> = sync
+                .lines()
+                .into_iter()
+                .zip(&sync.steps)
+                .map(|(line, step)| {
+                    let color = match step.action {
+                        SyncAction::Create => self.theme.accent,
+                        SyncAction::Update => self.theme.highlight,
+                        SyncAction::Delete => self.theme.error,
+                    };
+                    ListItem::new(line).style(Style::default().fg(color))
+                })
+                .collect();
+            let area = centered_rect(80, 60, f.area());
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(self.theme.block(sync.title()))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                area,
+                &mut sync.state.clone(),
+            );
+        }
+
+        // Remote Transfer
+        if let Some(transfer) = &self.state.transfer {
+            let items: Vec<ListItem> = transfer
+                .names
+                .iter()
+                .enumerate()
+                .map(|(index, name)| match index.cmp(&transfer.done) {
+                    Ordering::Less => ListItem::new(format!("✓ {}", name))
+                        .style(Style::default().fg(self.theme.accent)),
+                    Ordering::Equal => ListItem::new(format!("→ {}", name))
+                        .style(Style::default().fg(self.theme.highlight)),
+                    Ordering::Greater => ListItem::new(format!("  {}", name)),
+                })
+                .collect();
+            let area = centered_rect(80, 40, f.area());
+            f.render_widget(Clear, area);
+            f.render_widget(
+                List::new(items).block(self.theme.block(transfer.title())),
+                area,
+            );
+        }
+
+        // Devices View
+        if let Some(devices) = &self.state.devices {
+            let items: Vec<ListItem> = if devices.devices.is_empty() {
+                vec![ListItem::new("No block devices found")]
+            } else {
+                devices
+                    .devices
+                    .iter()
+                    .map(|device| ListItem::new(device.line()))
+                    .collect()
+            };
+            let area = centered_rect(80, 50, f.area());
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(
+                        self.theme
+                            .block("Devices: Enter opens, m mounts, u unmounts, r refreshes"),
+                    )
+                    .highlight_style(Style::default().fg(self.theme.highlight))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                area,
+                &mut devices.state.clone(),
+            );
+        }
+
+        // Open With Menu
+        if let Some(menu) = &self.state.open_with {
+            let mut items: Vec<ListItem> = menu
+                .choices
+                .iter()
+                .map(|opener| {
+                    let suffix = if opener.terminal { "  (terminal)" } else { "" };
+                    ListItem::new(format!("{}{}", opener.command, suffix))
+                })
+                .collect();
+            items.push(
+                ListItem::new("Other command…")
+                    .style(Style::default().fg(self.theme.inactive_highlight)),
+            );
+            let title = format!(
+                "Open {} with",
+                menu.file.file_name().unwrap_or_default().to_string_lossy()
+            );
+            let area = centered_rect(50, 50, f.area());
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(self.theme.block(title))
+                    .highlight_style(Style::default().fg(self.theme.highlight))
+                    .highlight_symbol(self.theme.highlight_symbol.as_str()),
+                area,
+                &mut menu.state.clone(),
+            );
+        }
+
+        // Full-Screen Preview
+        if let Some(path) = self.state.pager.as_ref().map(|pager| pager.path.clone()) {
+            let area = f.area();
+            let width = inner_width(area).max(1);
+            let (lines, wrapped) = self.pager_lines(&path, width);
+            let rows = if wrapped {
+                lines
+                    .iter()
+                    .map(|line| line.width().max(1).div_ceil(width))
+                    .sum()
+            } else {
+                lines.len()
+            };
+            let pager = self.state.pager.as_mut().unwrap();
+            pager.rows = rows;
+            pager.height = inner_height(area);
+            pager.scroll_by(0);
+            let scroll = pager.scroll;
+            let title = format!(
+                "{} {}/{} (Esc closes)",
+                truncate_middle(&path.to_string_lossy(), width.saturating_sub(24)),
+                (pager.scroll + pager.height).min(rows),
+                rows
+            );
+            let paragraph = if wrapped {
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .scroll((pager.scroll.min(u16::MAX as usize) as u16, 0))
+            } else {
+                Paragraph::new(
+                    lines
+                        .into_iter()
+                        .skip(pager.scroll)
+                        .take(pager.height)
+                        .collect::<Vec<_>>(),
+                )
+            };
+            f.render_widget(Clear, area);
+            f.render_widget(paragraph.block(self.theme.block(title)), area);
+            f.render_widget(self.scrollbar(scroll, rows), area);
+        }
+    }
+
+    fn has_focus(&self, panel: Panel) -> bool {
+        self.state.focus == panel
+            && !(panel == Panel::Files && self.state.tree.as_ref().is_some_and(|t| t.focused))
+    }
+
+    // The focused panel's border stands out
+    fn panel_block<'a>(&self, panel: Panel, title: impl Into<Cow<'a, str>>) -> Block<'a> {
+        let block = self.theme.block(title);
+        if self.has_focus(panel) {
+            block.border_style(Style::default().fg(self.theme.highlight))
+        } else {
+            block
+        }
+    }
+
+    // The selection of a panel without the focus is dimmed
+    fn panel_highlight(&self, panel: Panel) -> Color {
+        if self.has_focus(panel) {
+            self.theme.highlight
+        } else {
+            self.theme.inactive_highlight
+        }
+    }
+
+    fn scrollbar(&self, offset: usize, rows: usize) -> Scrollbar {
+        Scrollbar {
+            offset,
+            rows,
+            style: Style::default().fg(self.theme.accent),
+        }
+    }
+
+    // What the full-screen preview shows of `path`: the entries of a
+    // directory, or the file's preview cut to `width`. True when the lines
+    // are wrapped instead, for rendered Markdown.
+    fn pager_lines(&mut self, path: &Path, width: usize) -> (Vec<Line<'static>>, bool) {
+        let line = |text: &str| Line::from(truncate_end(text, width));
+        if self.metadata_cache.is_dir(path) {
+            let lines = match self.dir_cache.get_entries(
+                path,
+                self.show_hidden,
+                self.state.sort_mode,
+                self.state.mount.stat_entries,
+            ) {
+                Ok(entries) => entries
+                    .iter()
+                    .map(|entry| {
+                        Line::from(Span::styled(
+                            truncate_end(
+                                &format!("{}{}", self.icons.prefix(entry), entry.name),
+                                width,
+                            ),
+                            self.theme.entry_style(
+                                entry,
+                                get_file_style(&entry.name, &self.opener_config),
+                            ),
+                        ))
+                    })
+                    .collect(),
+                Err(e) => vec![line(&format!("<Error loading: {}>", e))],
+            };
+            return (lines, false);
+        }
+        match &self.preview_cache {
+            Some((cached, preview)) if cached == path => match (&preview.rendered, &preview.tail) {
+                (Some(rendered), _) if !self.state.markdown_source => (rendered.clone(), true),
+                (_, Some(tail)) if self.state.preview_tail => {
+                    (tail.iter().map(|text| line(text)).collect(), false)
+                }
+                _ => (preview.lines.iter().map(|text| line(text)).collect(), false),
+            },
+            _ => (vec![line("<Loading preview...>")], false),
+        }
+    }
+}
+
+pub fn properties_lines(path: &Path) -> Vec<Line<'static>> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return vec![Line::from(format!("<Failed to read metadata: {}>", e))],
+    };
+
+    let file_type = if metadata.file_type().is_symlink() {
+        "symlink"
+    } else if metadata.is_dir() {
+        "directory"
+    } else {
+        "file"
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut permissions = vec![Span::raw("Permissions: ")];
+    permissions.extend(permission_spans(&metadata));
+
+    let mut lines = vec![
+        Line::from(format!("Name:        {}", name)),
+        Line::from(format!("Path:        {}", path.display())),
+        Line::from(format!("Type:        {}", file_type)),
+        Line::from(format!(
+            "Size:        {} ({} bytes)",
+            format_size(metadata.len()),
+            metadata.len()
+        )),
+        Line::from(format!(
+            "Modified:    {}",
+            format_time(metadata.modified().ok())
+        )),
+        Line::from(format!(
+            "Owner:       {}",
+            owner_ids(&metadata).map_or("-".to_string(), |(uid, gid)| format!(
+                "{}:{}",
+                user_name(uid),
+                group_name(gid)
+            ))
+        )),
+        Line::from(permissions),
+    ];
+    if let Ok(target) = fs::read_link(path) {
+        lines.push(Line::from(format!("Target:      {}", target.display())));
+    }
+    if metadata.is_file() && metadata.len() <= 1_000_000 {
+        if let Ok(bytes) = fs::read(path) {
+            if let Some(encoding) = detect_encoding(&bytes) {
+                let bom = encoding_rs::Encoding::for_bom(&bytes).is_some();
+                let format = TextFormat::detect(&encoding.decode(&bytes).0, bom);
+                lines.push(Line::from(format!(
+                    "Text:        {}, {}",
+                    encoding.name(),
+                    format.label()
+                )));
+            }
+        }
+    }
+    lines
+}
+
+// Columns available inside a bordered block
+// One directory of the path bar
+pub struct Breadcrumb {
+    pub name: String,
+    // None for the `…` standing in for directories that don't fit
+    pub dir: Option<PathBuf>,
+    // How far up from the current directory it is, shown for the nearest
+    // nine as the number that jumps there with Alt
+    pub levels: Option<usize>,
+    pub separator: &'static str,
+}
+
+// `dir` from `/` down, as much of it as fits in `width`: leading directories
+// that don't are dropped for a `…`
+pub fn breadcrumbs(dir: &Path, width: usize) -> Vec<Breadcrumb> {
+    let mut crumbs: Vec<Breadcrumb> = dir
+        .ancestors()
+        .enumerate()
+        .map(|(levels, path)| Breadcrumb {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            dir: Some(path.to_path_buf()),
+            levels: (levels > 0 && levels < 10).then_some(levels),
+            // The root's name is a separator already
+            separator: if levels == 0 || path.parent().is_none() {
+                ""
+            } else {
+                "/"
+            },
+        })
+        .collect();
+    crumbs.reverse();
+    if let Some(current) = crumbs.last_mut() {
+        current.levels = Some(0);
+    }
+    let crumb_width = |crumb: &Breadcrumb| {
+        crumb.name.width() + crumb.separator.len() + usize::from(crumb.levels.unwrap_or(0) > 0)
+    };
+
+    let mut total: usize = crumbs.iter().map(crumb_width).sum();
+    let mut dropped = 0;
+    while total > width && crumbs.len() - dropped > 1 {
+        total -= crumb_width(&crumbs[dropped]);
+        dropped += 1;
+    }
+    if dropped == 0 {
+        return crumbs;
+    }
+    let ellipsis = Breadcrumb {
+        name: "…".to_string(),
+        dir: None,
+        levels: None,
+        separator: "/",
+    };
+    total += crumb_width(&ellipsis);
+    let mut crumbs: Vec<Breadcrumb> = std::iter::once(ellipsis)
+        .chain(crumbs.into_iter().skip(dropped))
+        .collect();
+    // Only the current directory is left and it is still too long
+    if total > width {
+        let current = crumbs.last_mut().unwrap();
+        let room = current.name.width().saturating_sub(total - width);
+        current.name = truncate_middle(&current.name, room);
+    }
+    crumbs
+}
+
+pub fn inner_width(area: Rect) -> usize {
+    area.width.saturating_sub(2) as usize
+}
+
+// Rows available inside a bordered block
+pub fn inner_height(area: Rect) -> usize {
+    area.height.saturating_sub(2) as usize
+}
+
+// A name in the listing, with the matches of the last search in it standing
+// out in `accent`
+pub fn search_spans(name: String, query: &str, style: Style, accent: Color) -> Vec<Span<'static>> {
+    if query.is_empty() {
+        return vec![Span::styled(name, style)];
+    }
+    let matched = style
+        .fg(accent)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for (start, part) in name.match_indices(query) {
+        if start > end {
+            spans.push(Span::styled(name[end..start].to_string(), style));
+        }
+        spans.push(Span::styled(part.to_string(), matched));
+        end = start + part.len();
+    }
+    if end < name.len() || spans.is_empty() {
+        spans.push(Span::styled(name[end..].to_string(), style));
+    }
+    spans
+}
+
+// Drawn over the right border of a bordered block whose `rows` don't all fit:
+// the thumb covers the part of the border that matches the rows on screen,
+// starting at `offset`
+pub struct Scrollbar {
+    pub offset: usize,
+    pub rows: usize,
+    pub style: Style,
+}
+
+impl Scrollbar {
+    // First row and length of the thumb, within `track` rows
+    pub fn thumb(&self, track: usize) -> Option<(usize, usize)> {
+        if track == 0 || self.rows <= track {
+            return None;
+        }
+        let length = (track * track / self.rows).max(1);
+        let scrollable = self.rows - track;
+        let start = ((track - length) * self.offset.min(scrollable) + scrollable / 2) / scrollable;
+        Some((start, length))
+    }
+}
+
+impl Widget for Scrollbar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let track = area.height.saturating_sub(2);
+        let Some((start, length)) = self.thumb(track as usize) else {
+            return;
+        };
+        if area.width < 2 {
+            return;
+        }
+        let x = area.right() - 1;
+        for row in start..start + length {
+            buf[(x, area.y + 1 + row as u16)]
+                .set_symbol("┃")
+                .set_style(self.style);
+        }
+    }
+}
+
+// First row to draw so that the cursor stays on screen, scrolling as little
+// as possible from the previous offset
+pub fn scroll_offset(offset: usize, cursor: usize, height: usize) -> usize {
+    if cursor < offset {
+        cursor
+    } else if height > 0 && cursor >= offset + height {
+        cursor + 1 - height
+    } else {
+        offset
+    }
+}
+
+// Cuts `text` down to `width` terminal columns, ending in an ellipsis when
+// anything was dropped. Wide characters (CJK, emoji) count as two columns.
+pub fn truncate_end(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width - 1 {
+            break;
+        }
+        truncated.push(c);
+        used += w;
+    }
+    truncated.push('…');
+    truncated
+}
+
+// Drops the middle instead, so both the root and the innermost directories
+// of a deep path stay visible
+pub fn truncate_middle(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let budget = width - 1;
+    let take = |chars: &mut dyn Iterator<Item = char>, limit: usize| {
+        let mut taken = Vec::new();
+        let mut used = 0;
+        for c in chars {
+            let w = c.width().unwrap_or(0);
+            if used + w > limit {
+                break;
+            }
+            taken.push(c);
+            used += w;
+        }
+        taken
+    };
+    let head = take(&mut text.chars(), budget / 2);
+    let tail = take(&mut text.chars().rev(), budget - budget / 2);
+    head.into_iter()
+        .chain(std::iter::once('…'))
+        .chain(tail.into_iter().rev())
+        .collect()
+}
+
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
@@ -0,0 +1,61 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::path::Path;
+use termfm::harness::{Fixture, Harness};
+use termfm::ui::breadcrumbs;
+
+fn tree() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a/b/c/notes.txt", "").unwrap();
+    fixture
+}
+
+// The path bar as it is drawn
+fn path_bar(harness: &Harness) -> String {
+    harness.screen().lines().nth(1).unwrap().to_string()
+}
+
+#[test]
+fn segments_are_numbered_by_distance() {
+    let crumbs = breadcrumbs(Path::new("/home/user/code"), 80);
+    let drawn: Vec<_> = crumbs
+        .iter()
+        .map(|crumb| (crumb.name.as_str(), crumb.levels, crumb.separator))
+        .collect();
+    assert_eq!(
+        drawn,
+        [
+            ("/", Some(3), ""),
+            ("home", Some(2), "/"),
+            ("user", Some(1), "/"),
+            ("code", Some(0), "")
+        ]
+    );
+
+    // Leading directories give way to an ellipsis
+    let crumbs = breadcrumbs(Path::new("/home/user/code"), 12);
+    let names: Vec<_> = crumbs.iter().map(|crumb| crumb.name.as_str()).collect();
+    assert_eq!(names, ["…", "user", "code"]);
+    assert!(crumbs[0].dir.is_none());
+}
+
+#[test]
+fn alt_and_a_number_jumps_up() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("lll").unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("a/b/c"));
+    assert!(path_bar(&harness).contains("2a/1b/c"));
+
+    harness.events.key(KeyCode::Char('2'), KeyModifiers::ALT);
+    harness.run().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("a"));
+}
+
+#[test]
+fn clicking_a_segment_opens_it() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("lll").unwrap();
+    let line = path_bar(&harness);
+    let column = line[..line.find("2a/").unwrap()].chars().count() as u16;
+    harness.click(column + 1, 1).unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("a"));
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use termfm::checksum::Algorithm;
+use termfm::harness::{Fixture, Harness};
+
+fn wait_for_checksums(harness: &mut Harness) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !harness.app.state.checksums.as_ref().unwrap().is_done() {
+        assert!(Instant::now() < deadline, "checksums not done");
+        std::thread::sleep(Duration::from_millis(5));
+        harness.settle().unwrap();
+    }
+    harness.settle().unwrap();
+}
+
+#[test]
+fn digests_match_the_coreutils_ones() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("abc.txt", "abc").unwrap();
+    let path = fixture.path("abc.txt");
+    let cancel = AtomicBool::new(false);
+    assert_eq!(
+        Algorithm::Md5.hash_file(&path, &cancel).unwrap(),
+        "900150983cd24fb0d6963f7d28e17f72"
+    );
+    assert_eq!(
+        Algorithm::Sha1.hash_file(&path, &cancel).unwrap(),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+        Algorithm::Sha256.hash_file(&path, &cancel).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn checksums_can_be_written_next_to_the_file() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("abc.txt", "abc").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("checksum md5").unwrap();
+    wait_for_checksums(&mut harness);
+    assert!(harness
+        .screen()
+        .contains("900150983cd24fb0d6963f7d28e17f72  abc.txt"));
+
+    harness.keys("w").unwrap();
+    assert!(harness.app.state.checksums.is_none());
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("abc.txt.md5sum")).unwrap(),
+        "900150983cd24fb0d6963f7d28e17f72  abc.txt\n"
+    );
+}
+
+#[test]
+fn marked_files_share_one_checksum_file() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "abc").unwrap();
+    fixture.file("b.txt", "abc").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("mm").unwrap();
+    harness.command("checksum").unwrap();
+    wait_for_checksums(&mut harness);
+    harness.keys("w").unwrap();
+    let written = fs::read_to_string(harness.fixture.path("checksums.sha256sum")).unwrap();
+    let names: Vec<&str> = written.lines().map(|line| &line[66..]).collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+}
+
+#[test]
+fn checksum_files_are_verified() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("good.txt", "abc").unwrap();
+    fixture.file("bad.txt", "abd").unwrap();
+    fixture
+        .file(
+            "all.sha1sum",
+            "a9993e364706816aba3e25717850c26c9cd0d89d  good.txt\n\
+             a9993e364706816aba3e25717850c26c9cd0d89d *bad.txt\n\
+             a9993e364706816aba3e25717850c26c9cd0d89d  gone.txt\n",
+        )
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("all.sha1sum"));
+    harness.command("checksum verify").unwrap();
+    wait_for_checksums(&mut harness);
+
+    let checksums = harness.app.state.checksums.as_ref().unwrap();
+    assert_eq!(checksums.algorithm, Algorithm::Sha1);
+    let lines: Vec<String> = checksums.entries.iter().map(|e| e.line()).collect();
+    assert_eq!(lines[0], "good.txt: OK");
+    assert_eq!(lines[1], "bad.txt: FAILED");
+    assert!(lines[2].starts_with("gone.txt: No such file"));
+    assert!(harness.screen().contains("2 failed"));
+}
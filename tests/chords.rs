@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use termfm::chords::{continuations, lookup, Chord, Lookup, CHORD_TIMEOUT};
+use termfm::harness::{Fixture, Harness};
+
+fn tree() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "a").unwrap();
+    fixture.file("docs/readme.md", "").unwrap();
+    fixture
+}
+
+#[test]
+fn chords_are_looked_up_key_by_key() {
+    assert_eq!(lookup("g"), Lookup::Pending);
+    assert_eq!(lookup("gg"), Lookup::Found(Chord::Top));
+    assert_eq!(lookup("gq"), Lookup::Unbound);
+    assert_eq!(
+        continuations("g"),
+        "g top, h home, / root, m mounts, O file manager"
+    );
+}
+
+#[test]
+fn g_goes_home_and_to_the_root() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("gh").unwrap();
+    assert_eq!(harness.current_dir(), dirs::home_dir().unwrap());
+    harness.keys("g/").unwrap();
+    assert_eq!(harness.current_dir(), Path::new("/"));
+}
+
+#[test]
+fn dd_cuts_and_yy_yanks() {
+    let mut harness = Harness::new(tree()).unwrap();
+    // `a.txt` comes after the directory
+    harness.keys("jddklP").unwrap();
+    assert!(harness.fixture.path("docs/a.txt").exists());
+    assert!(!harness.fixture.path("a.txt").exists());
+
+    harness.keys("yyhP").unwrap();
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("docs/a.txt")).unwrap(),
+        "a"
+    );
+    assert_eq!(harness.names(), ["docs", "a.txt"]);
+}
+
+#[test]
+fn pending_keys_are_shown_until_dropped() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("g").unwrap();
+    assert!(harness.screen().contains("g: g top, h home, / root"));
+    // A key no chord goes on with drops them
+    harness.keys("qj").unwrap();
+    assert!(!harness.app.quit);
+    assert!(harness.app.state.pending_keys.is_empty());
+    assert_eq!(harness.selected().as_deref(), Some("a.txt"));
+
+    // So does a pause
+    harness.keys("d").unwrap();
+    harness.app.state.pending_since = Instant::now() - Duration::from_secs(2);
+    harness.settle().unwrap();
+    assert!(harness.app.state.pending_keys.is_empty());
+    assert!(!harness.screen().contains("d: d cut"));
+}
+
+#[test]
+fn the_main_loop_wakes_up_to_drop_pending_keys() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("d").unwrap();
+    let timeout = harness.app.state.pending_since + CHORD_TIMEOUT;
+    assert!(harness
+        .app
+        .next_deadline()
+        .is_some_and(|deadline| deadline <= timeout));
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use termfm::app::SplitDirection;
+use termfm::cli::{parse_args, shell_init, OutputFormat, Shell, StartupCommand, StartupOptions};
+use termfm::harness::{Fixture, Harness};
+
+fn parse(args: &[&str]) -> Result<StartupOptions, clap::Error> {
+    parse_args(std::iter::once("termfm").chain(args.iter().copied()))
+}
+
+#[test]
+fn positional_directory_is_the_left_pane() {
+    let options = parse(&["/tmp", "--show-hidden"]).unwrap();
+    assert_eq!(options.left.as_deref(), Some("/tmp".as_ref()));
+    assert!(options.dir.is_none());
+    assert!(options.show_hidden);
+}
+
+#[test]
+fn positional_file_is_selected() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "").unwrap();
+    let path = fixture.path("notes.txt");
+    let options = parse(&[path.to_str().unwrap()]).unwrap();
+    assert_eq!(options.select, Some(path));
+    assert!(options.left.is_none());
+}
+
+#[test]
+fn accepts_values_inline_and_separately() {
+    let options = parse(&["--cwd-file=out.txt", "--config", "my.toml", "--split", "h"]).unwrap();
+    assert_eq!(options.cwd_file.as_deref(), Some("out.txt".as_ref()));
+    assert_eq!(options.config.as_deref(), Some("my.toml".as_ref()));
+    assert!(options.split == Some(SplitDirection::Horizontal));
+}
+
+#[test]
+fn implied_options() {
+    let options = parse(&["--right", "/tmp", "--script", "-", "--output", "json"]).unwrap();
+    assert!(options.split == Some(SplitDirection::Vertical));
+    assert!(options.headless);
+    assert!(options.output == OutputFormat::Json);
+}
+
+#[test]
+fn choose_files_is_a_picker() {
+    let options = parse(&["--choose-files", "-"]).unwrap();
+    assert!(options.pick);
+    assert_eq!(options.choose_files.as_deref(), Some("-".as_ref()));
+}
+
+#[test]
+fn rejects_conflicting_modes() {
+    assert!(parse(&["--pick", "--headless"]).is_err());
+    assert!(parse(&["--tutorial", "--script", "x"]).is_err());
+    assert!(parse(&["--choose-files", "out", "--headless"]).is_err());
+    assert!(parse(&["--chooser-file", "out", "--pick"]).is_err());
+    assert!(parse(&["/tmp", "--left", "/"]).is_err());
+    assert!(parse(&["/tmp", "--select", "/tmp/x"]).is_err());
+    assert!(parse(&["--no-such-flag"]).is_err());
+}
+
+#[test]
+fn select_highlights_the_entry() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap()
+        .file("c.txt", "")
+        .unwrap();
+    let options = StartupOptions {
+        select: Some(fixture.path("c.txt")),
+        ..Default::default()
+    };
+    let harness = Harness::with_options(fixture, options, 80, 24).unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("c.txt"));
+}
+
+#[test]
+fn init_takes_a_shell() {
+    let options = parse(&["init", "zsh", "--name", "fm"]).unwrap();
+    assert!(
+        options.command
+            == Some(StartupCommand::Init {
+                shell: Shell::Zsh,
+                name: "fm".to_string()
+            })
+    );
+    assert!(parse(&["init", "tcsh"]).is_err());
+    assert!(parse(&["/tmp"]).unwrap().command.is_none());
+}
+
+#[test]
+fn init_passes_the_config_on() {
+    let wrapper = shell_init(Shell::Fish, "tfm", Some(Path::new("/my conf/it's.toml")));
+    assert!(wrapper.starts_with("function tfm\n"));
+    assert!(wrapper.contains("command termfm --config '/my conf/it'\\''s.toml' --cwd-file="));
+    assert!(!shell_init(Shell::Bash, "tfm", None).contains("--config"));
+}
+
+// A stand-in `termfm` writes its last argument to the cwd file, so the shell
+// should end up there
+#[test]
+fn the_wrapper_changes_directory() {
+    let fixture = Fixture::new().unwrap();
+    fixture.dir("target dir").unwrap();
+    fixture
+        .program(
+            "termfm",
+            "file=${1#--cwd-file=}\nprintf %s \"$2\" > \"$file\"\n",
+        )
+        .unwrap();
+
+    let script = format!("{}tfm \"$1\" && pwd", shell_init(Shell::Bash, "tfm", None));
+    let output = Command::new("sh")
+        .args(["-c", &script, "sh"])
+        .arg(fixture.path("target dir"))
+        .env(
+            "PATH",
+            format!("{}:{}", fixture.bin().display(), env::var("PATH").unwrap()),
+        )
+        .output()
+        .unwrap();
+    let pwd = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Path::new(pwd.trim()), fixture.path("target dir"));
+}
@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use termfm::clipboard::{osc52, uri_list};
+use termfm::harness::{Fixture, Harness};
+
+// A stand-in wl-copy that keeps what it was given in `bin/clipboard` and its
+// arguments in `bin/args`
+fn fake_wl_copy(fixture: &Fixture) {
+    let script = format!(
+        "echo \"$*\" > {0}/args\ncat > {0}/clipboard\n",
+        fixture.bin().display()
+    );
+    fixture.program("wl-copy", &script).unwrap();
+}
+
+fn clipboard(bin: &Path) -> (String, String) {
+    (
+        fs::read_to_string(bin.join("clipboard")).unwrap(),
+        fs::read_to_string(bin.join("args")).unwrap(),
+    )
+}
+
+#[test]
+fn osc52_encodes_the_text() {
+    assert_eq!(osc52("hello"), "\x1b]52;c;aGVsbG8=\x07");
+}
+
+#[test]
+fn uri_lists_escape_paths() {
+    let paths = [PathBuf::from("/tmp/a b/ü.txt"), PathBuf::from("/x")];
+    assert_eq!(
+        uri_list(&paths),
+        "file:///tmp/a%20b/%C3%BC.txt\r\nfile:///x\r\n"
+    );
+}
+
+#[test]
+fn names_paths_and_files_are_copied() {
+    // Over SSH text goes to the terminal with OSC 52 instead
+    if env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+        return;
+    }
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "").unwrap();
+    fake_wl_copy(&fixture);
+    let bin = fixture.bin();
+    let mut harness = Harness::new(fixture).unwrap();
+    let path = harness.fixture.root.join("notes.txt");
+
+    harness.keys("Yn").unwrap();
+    assert_eq!(clipboard(&bin), ("notes.txt".to_string(), "\n".to_string()));
+    harness.keys("Yp").unwrap();
+    assert_eq!(clipboard(&bin).0, path.display().to_string());
+    harness.keys("Yd").unwrap();
+    assert_eq!(
+        clipboard(&bin).0,
+        harness.fixture.root.display().to_string()
+    );
+    harness.keys("Yf").unwrap();
+    assert_eq!(
+        clipboard(&bin),
+        (
+            format!("file://{}\r\n", path.display()),
+            "--type text/uri-list\n".to_string()
+        )
+    );
+    assert_eq!(harness.last_message(), "Copied 1 file(s) (wl-copy)");
+
+    // Any other key cancels
+    fs::remove_file(bin.join("clipboard")).unwrap();
+    harness.keys("Yx").unwrap();
+    assert!(harness.app.state.pending_keys.is_empty());
+    assert!(!bin.join("clipboard").exists());
+}
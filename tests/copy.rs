@@ -0,0 +1,164 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::{FileExt, MetadataExt, PermissionsExt};
+use std::time::{Duration, SystemTime};
+use termfm::app::Notifications;
+#[cfg(unix)]
+use termfm::fs_ops::{copy_file, CopyStrategy};
+use termfm::fs_ops::{copy_path, Preserve};
+use termfm::harness::Fixture;
+
+#[cfg(unix)]
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+#[cfg(unix)]
+fn copies_keep_contents_and_permissions() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("run.sh", "echo hi").unwrap();
+    fs::set_permissions(fixture.path("run.sh"), fs::Permissions::from_mode(0o750)).unwrap();
+    copy_path(
+        &fixture.path("run.sh"),
+        &fixture.path("copy.sh"),
+        Preserve::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(fixture.path("copy.sh")).unwrap(),
+        "echo hi"
+    );
+    let mode = fs::metadata(fixture.path("copy.sh")).unwrap().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
+
+#[test]
+#[cfg(unix)]
+fn holes_of_sparse_files_stay_holes() {
+    let fixture = Fixture::new().unwrap();
+    let file = fs::File::create(fixture.path("sparse.img")).unwrap();
+    file.set_len(8 * MIB).unwrap();
+    file.write_all_at(b"middle", 4 * MIB).unwrap();
+    drop(file);
+    let strategy = copy_file(&fixture.path("sparse.img"), &fixture.path("copy.img")).unwrap();
+
+    let copy = fs::read(fixture.path("copy.img")).unwrap();
+    assert_eq!(copy.len() as u64, 8 * MIB);
+    assert_eq!(&copy[4 * MIB as usize..4 * MIB as usize + 6], b"middle");
+    assert!(copy[..4 * MIB as usize].iter().all(|byte| *byte == 0));
+    // Only where the filesystem keeps holes at all
+    let allocated = |name: &str| fs::metadata(fixture.path(name)).unwrap().blocks() * 512;
+    if allocated("sparse.img") < MIB && strategy != CopyStrategy::Buffered {
+        assert!(allocated("copy.img") < MIB);
+    }
+}
+
+#[test]
+fn directories_report_their_slowest_copy() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("src/a.txt", "a").unwrap();
+    fixture.file("src/deep/b.txt", "b").unwrap();
+    fixture.dir("empty").unwrap();
+    assert!(
+        copy_path(&fixture.path("src"), &fixture.path("dest"), Preserve::NONE)
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(
+        fs::read_to_string(fixture.path("dest/deep/b.txt")).unwrap(),
+        "b"
+    );
+    // Nothing was copied
+    assert_eq!(
+        copy_path(
+            &fixture.path("empty"),
+            &fixture.path("empty copy"),
+            Preserve::NONE
+        )
+        .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn archive_copies_keep_times_and_extended_attributes() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("dir/file.txt", "content").unwrap();
+    let a_day_ago = SystemTime::now() - Duration::from_secs(86400);
+    for path in ["dir/file.txt", "dir"] {
+        let file = fs::File::open(fixture.path(path)).unwrap();
+        file.set_modified(a_day_ago).unwrap();
+    }
+    // Not every filesystem has user attributes
+    #[cfg(target_os = "linux")]
+    let has_xattrs = xattr_set(&fixture.path("dir/file.txt"), "user.origin", "test");
+
+    copy_path(&fixture.path("dir"), &fixture.path("copy"), Preserve::ALL).unwrap();
+    for path in ["copy", "copy/file.txt"] {
+        let modified = fs::metadata(fixture.path(path))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(modified, a_day_ago);
+    }
+    #[cfg(target_os = "linux")]
+    if has_xattrs {
+        let mut value = [0u8; 16];
+        let path = std::ffi::CString::new(fixture.path("copy/file.txt").to_str().unwrap()).unwrap();
+        let name = std::ffi::CString::new("user.origin").unwrap();
+        // SAFETY: the buffer is as long as said
+        let len = unsafe {
+            libc::getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        assert_eq!(&value[..len as usize], b"test");
+    }
+
+    // Without timestamps the copy is new
+    copy_path(
+        &fixture.path("dir"),
+        &fixture.path("plain"),
+        Preserve::default(),
+    )
+    .unwrap();
+    let modified = fs::metadata(fixture.path("plain/file.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert!(modified > a_day_ago);
+}
+
+#[cfg(target_os = "linux")]
+fn xattr_set(path: &std::path::Path, name: &str, value: &str) -> bool {
+    let path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+    let name = std::ffi::CString::new(name).unwrap();
+    // SAFETY: the strings outlive the call
+    unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        ) == 0
+    }
+}
+
+#[test]
+fn what_copies_keep_is_configured() {
+    let fixture = Fixture::new().unwrap();
+    let preserve = |config: &str| {
+        fs::write(fixture.config_path(), config).unwrap();
+        let mut notifications = Notifications::default();
+        let preserve = Preserve::from_config(&fixture.config_path(), &mut notifications);
+        (preserve, notifications.log.len())
+    };
+    assert_eq!(preserve(""), (Preserve::default(), 0));
+    assert_eq!(preserve("[copy]\npreserve = \"all\"\n"), (Preserve::ALL, 0));
+    let (some, errors) = preserve("[copy]\npreserve = [\"timestamps\", \"acl\"]\n");
+    assert!(some.timestamps && !some.mode && !some.ownership);
+    assert_eq!(errors, 1);
+}
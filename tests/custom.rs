@@ -0,0 +1,88 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::fs;
+use termfm::app::Notifications;
+use termfm::custom::{parse_key, CustomCommands};
+use termfm::harness::{Fixture, Harness};
+
+const CONFIG: &str = r#"
+[commands.words]
+command = "wc -w %f"
+key = "ctrl-e"
+output = true
+
+[commands.backup]
+command = "cp %f %f.bak"
+refresh = true
+output = true
+"#;
+
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("poem.txt", "two words").unwrap();
+    fs::write(fixture.config_path(), CONFIG).unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+#[test]
+fn commands_are_read_from_the_config() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.config_path(),
+        format!(
+            "{}\n[commands.cd]\ncommand = \"ls\"\n\n[commands.bad]\nkey = \"ctrl-\"\nlater = 1\n",
+            CONFIG
+        ),
+    )
+    .unwrap();
+    let mut notifications = Notifications::default();
+    let commands = CustomCommands::from_config(&fixture.config_path(), &mut notifications);
+    assert_eq!(commands.names().collect::<Vec<_>>(), ["words", "backup"]);
+    assert_eq!(
+        commands.get("words").unwrap().key,
+        Some((KeyCode::Char('e'), KeyModifiers::CONTROL))
+    );
+    let errors: Vec<&str> = notifications
+        .log
+        .iter()
+        .map(|n| n.message.as_str())
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "commands.cd is taken by the built-in :cd",
+            "commands.bad.key must be a character, ctrl-<c> or alt-<c>",
+            "Unknown setting commands.bad.later, expected one of command, key, refresh, output",
+            "commands.bad has no command",
+        ]
+    );
+    assert_eq!(
+        parse_key("K"),
+        Some((KeyCode::Char('K'), KeyModifiers::NONE))
+    );
+}
+
+#[test]
+fn output_is_shown_in_a_panel() {
+    let mut harness = harness();
+    harness.command("words").unwrap();
+    let (name, lines) = harness.app.state.command_output.clone().unwrap();
+    assert_eq!(name, "words");
+    assert!(lines[0].starts_with("2 "));
+    assert!(harness.screen().contains(":words (Esc closes)"));
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.state.command_output.is_none());
+
+    // The same with its key
+    harness
+        .events
+        .key(KeyCode::Char('e'), KeyModifiers::CONTROL);
+    harness.run().unwrap();
+    assert!(harness.app.state.command_output.is_some());
+}
+
+#[test]
+fn the_listing_is_refreshed_when_asked_for() {
+    let mut harness = harness();
+    harness.command("backup").unwrap();
+    assert_eq!(harness.names(), ["poem.txt", "poem.txt.bak"]);
+}
@@ -0,0 +1,85 @@
+use crossterm::event::KeyCode;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::daemon::{request_listing, serve, socket_path, Name};
+use termfm::harness::{Fixture, Harness};
+
+static DAEMON: Once = Once::new();
+
+// One daemon for every test here, on a socket of its own
+fn start_daemon() -> PathBuf {
+    DAEMON.call_once(|| {
+        let socket =
+            env::temp_dir().join(format!("termfm-daemon-test-{}.sock", std::process::id()));
+        env::set_var("TERMFM_DAEMON_SOCKET", &socket);
+        thread::spawn(move || serve(&socket).unwrap());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while request_listing(Path::new("/")).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+    socket_path()
+}
+
+// Adds a file without the directory looking changed
+fn sneak_in(dir: &Path, name: &str) {
+    let modified = fs::metadata(dir).unwrap().modified().unwrap();
+    fs::write(dir.join(name), "").unwrap();
+    fs::File::open(dir).unwrap().set_modified(modified).unwrap();
+}
+
+#[test]
+fn listings_are_remembered_until_the_directory_changes() {
+    start_daemon();
+    let fixture = Fixture::new().unwrap();
+    fixture.file("big/a.txt", "").unwrap();
+    fixture.file("big/.hidden", "").unwrap();
+    let dir = fixture.path("big");
+    let mut names = request_listing(&dir).unwrap();
+    names.sort_by(|a, b| a.name.cmp(&b.name));
+    let name = |name: &str| Name {
+        name: name.to_string(),
+        is_dir: false,
+        is_symlink: false,
+    };
+    assert_eq!(names, [name(".hidden"), name("a.txt")]);
+
+    sneak_in(&dir, "b.txt");
+    assert_eq!(request_listing(&dir).unwrap().len(), 2);
+    fs::write(dir.join("c.txt"), "").unwrap();
+    assert_eq!(request_listing(&dir).unwrap().len(), 4);
+
+    assert_eq!(request_listing(&fixture.path("missing")), None);
+}
+
+#[test]
+fn instances_list_directories_through_the_daemon() {
+    start_daemon();
+    let fixture = Fixture::new().unwrap();
+    fixture.file("big/a.txt", "").unwrap();
+    fixture.file("big/.hidden", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys(":cd big").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.names(), ["a.txt"]);
+
+    // Only the daemon can still be missing it
+    sneak_in(&harness.fixture.path("big"), "b.txt");
+    harness.keys("h").unwrap();
+    harness.keys("l").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("big"));
+    assert_eq!(harness.names(), ["a.txt"]);
+}
+
+#[test]
+fn a_running_daemon_keeps_its_socket() {
+    let socket = start_daemon();
+    let error = serve(&socket).unwrap_err();
+    assert!(error.to_string().starts_with("A daemon is already running"));
+}
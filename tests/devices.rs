@@ -0,0 +1,108 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use std::path::PathBuf;
+use termfm::devices::{parse_mounts, Device, Devices};
+use termfm::harness::{Fixture, Harness};
+
+// A stand-in udisksctl that mounts everything at `usb` and keeps its
+// arguments in `bin/args`
+fn fake_udisksctl(fixture: &Fixture) {
+    let script = format!(
+        "echo \"$*\" >> {}/args\n\
+         case $1 in\n  mount) echo \"Mounted $4 at {}.\" ;;\n  unmount) echo \"Unmounted $4.\" ;;\nesac\n",
+        fixture.bin().display(),
+        fixture.path("usb").display()
+    );
+    fixture.program("udisksctl", &script).unwrap();
+}
+
+fn usb(mount_point: Option<PathBuf>) -> Devices {
+    Devices::new(vec![Device {
+        path: PathBuf::from("/dev/sdz1"),
+        label: Some("USB STICK".to_string()),
+        size: Some(8 << 30),
+        mount_point,
+        removable: true,
+    }])
+}
+
+#[test]
+fn mounts_are_read_with_escaped_paths() {
+    let mounts = "/dev/sda2 / ext4 rw,relatime 0 0\n\
+                  proc /proc proc rw 0 0\n\
+                  /dev/sdb1 /run/media/me/USB\\040STICK vfat rw 0 0\n";
+    assert_eq!(
+        parse_mounts(mounts),
+        [
+            (
+                "/dev/sda2".to_string(),
+                PathBuf::from("/"),
+                "ext4".to_string()
+            ),
+            (
+                "proc".to_string(),
+                PathBuf::from("/proc"),
+                "proc".to_string()
+            ),
+            (
+                "/dev/sdb1".to_string(),
+                PathBuf::from("/run/media/me/USB STICK"),
+                "vfat".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn the_view_lists_devices_and_opens_mount_points() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("usb/photo.jpg", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("gm").unwrap();
+    assert!(harness.app.state.devices.is_some());
+    assert!(harness.screen().contains("Devices"));
+
+    harness.app.state.devices = Some(usb(Some(harness.fixture.path("usb"))));
+    harness.settle().unwrap();
+    assert!(harness.screen().contains("USB STICK"));
+    harness.press(KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert!(harness.app.state.devices.is_none());
+    assert_eq!(harness.current_dir(), harness.fixture.path("usb"));
+    assert_eq!(harness.names(), ["photo.jpg"]);
+}
+
+#[test]
+fn devices_are_mounted_and_unmounted_with_udisksctl() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("usb/photo.jpg", "").unwrap();
+    fake_udisksctl(&fixture);
+    let mut harness = Harness::new(fixture).unwrap();
+
+    // Enter mounts a device that isn't, then opens it
+    harness.app.state.devices = Some(usb(None));
+    harness.press(KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("usb"));
+    assert!(harness
+        .app
+        .state
+        .notifications
+        .log
+        .iter()
+        .any(|n| n.message.starts_with("Mounted /dev/sdz1 at ")));
+
+    // Leaving the mount point before unmounting it
+    harness.app.state.devices = Some(usb(Some(harness.fixture.path("usb"))));
+    harness.keys("u").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.root);
+    let args = fs::read_to_string(harness.fixture.bin().join("args")).unwrap();
+    assert_eq!(
+        args.lines().collect::<Vec<_>>(),
+        [
+            "mount --no-user-interaction -b /dev/sdz1",
+            "unmount --no-user-interaction -b /dev/sdz1"
+        ]
+    );
+}
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+use termfm::diff::{DiffContent, DiffView, DirDifference};
+use termfm::harness::{Fixture, Harness};
+use termfm::ui::Line;
+
+fn text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+#[test]
+fn files_get_a_unified_diff() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("old.txt", "one\ntwo\nthree\n").unwrap();
+    fixture.file("new.txt", "one\n2\nthree\nfour\n").unwrap();
+    let view = DiffView::new(fixture.path("old.txt"), fixture.path("new.txt")).unwrap();
+    let lines: Vec<String> = view.lines(80).iter().map(text).collect();
+    assert_eq!(
+        lines,
+        ["@@ -1,3 +1,4 @@", " one", "-two", "+2", " three", "+four"]
+    );
+}
+
+#[test]
+fn side_by_side_pairs_changed_lines() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("old.txt", "one\ntwo\n").unwrap();
+    fixture.file("new.txt", "one\n2\nextra\n").unwrap();
+    let mut view = DiffView::new(fixture.path("old.txt"), fixture.path("new.txt")).unwrap();
+    view.side_by_side = true;
+    let lines: Vec<String> = view.lines(43).iter().map(text).collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1], "   1 one             │    1 one            ");
+    assert_eq!(lines[2], "   2 two             │    2 2              ");
+    assert_eq!(lines[3], "                     │    3 extra          ");
+}
+
+#[test]
+fn directories_are_compared_recursively() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("left/same.txt", "same").unwrap();
+    fixture.file("right/same.txt", "same").unwrap();
+    fixture.file("left/sub/changed.txt", "before").unwrap();
+    fixture.file("right/sub/changed.txt", "after!").unwrap();
+    fixture.file("left/gone/file.txt", "x").unwrap();
+    fixture.file("right/new.txt", "x").unwrap();
+    let view = DiffView::new(fixture.path("left"), fixture.path("right")).unwrap();
+    let DiffContent::Dirs(differences) = &view.content else {
+        panic!("not a directory comparison");
+    };
+    assert_eq!(
+        differences,
+        &[
+            (DirDifference::OnlyLeft, PathBuf::from("gone")),
+            (DirDifference::OnlyRight, PathBuf::from("new.txt")),
+            (DirDifference::Differs, PathBuf::from("sub/changed.txt")),
+        ]
+    );
+}
+
+#[test]
+fn two_marked_files_open_the_diff_view() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "same\n").unwrap();
+    fixture.file("b.txt", "same\n").unwrap();
+    fixture.file("c", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("D").unwrap();
+    assert!(harness.app.state.diff.is_none());
+
+    harness.keys("mmD").unwrap();
+    assert!(harness.screen().contains("Files are identical"));
+    harness.keys("q").unwrap();
+    assert!(harness.app.state.diff.is_none());
+    assert_eq!(harness.names(), ["a.txt", "b.txt", "c"]);
+}
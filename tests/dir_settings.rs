@@ -0,0 +1,68 @@
+use termfm::harness::{Fixture, Harness};
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file(
+            "project/.termfm.toml",
+            "sort = \"size\"\nhidden = true\n\n[openers]\ntxt = { opener = \"less\", terminal = true }\n",
+        )
+        .unwrap()
+        .file("project/small.txt", "1")
+        .unwrap()
+        .file("project/big.txt", "1234567890")
+        .unwrap()
+        .file("project/.env", "12345")
+        .unwrap()
+        .file("project/sub/.termfm.toml", "sort = \"name\"\n")
+        .unwrap()
+        .file("project/sub/b.txt", "1")
+        .unwrap()
+        .file("project/sub/a.txt", "1234")
+        .unwrap()
+        .file(".hidden", "")
+        .unwrap()
+        .file("notes.txt", "")
+        .unwrap();
+    fixture
+}
+
+#[test]
+fn applies_the_settings_of_the_tree() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    assert_eq!(harness.names(), ["project", "notes.txt"]);
+
+    harness.keys("l").unwrap();
+    assert!(harness.app.show_hidden);
+    let names = harness.names();
+    assert_eq!(
+        &names[1..],
+        [".termfm.toml", "big.txt", ".env", "small.txt"]
+    );
+    let opener = harness.app.opener_config.lookup_name("x.txt").unwrap();
+    assert_eq!(opener.command, "less");
+
+    harness.keys("h").unwrap();
+    assert!(!harness.app.show_hidden);
+    assert_eq!(harness.names(), ["project", "notes.txt"]);
+    assert!(harness.app.opener_config.lookup_name("x.txt").is_none());
+}
+
+#[test]
+fn nearest_file_wins() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    // sub is listed first as the only directory
+    harness.keys("ll").unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("project/sub"));
+    // Sorted by name again, hidden files and openers still from project/
+    assert_eq!(harness.names(), [".termfm.toml", "a.txt", "b.txt"]);
+    assert!(harness.app.opener_config.lookup_name("x.txt").is_some());
+}
+
+#[test]
+fn user_choices_outside_the_tree_are_kept() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("s").unwrap();
+    harness.keys("lh").unwrap();
+    assert!(harness.app.state.sort_mode == termfm::fs_ops::SortMode::Size);
+}
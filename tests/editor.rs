@@ -0,0 +1,41 @@
+use std::env;
+use std::fs;
+use termfm::harness::{Fixture, Harness};
+
+// VISUAL and EDITOR are process-wide, so both are tried in the one test
+#[test]
+fn files_are_edited_in_visual_before_editor() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap();
+    let edited = fixture.bin().join("edited");
+    for name in ["visual", "editor"] {
+        fixture
+            .program(
+                name,
+                &format!("echo \"{} $*\" >> {}\n", name, edited.display()),
+            )
+            .unwrap();
+    }
+    let (a, b) = (fixture.path("a.txt"), fixture.path("b.txt"));
+    let mut harness = Harness::new(fixture).unwrap();
+    env::set_var("VISUAL", "visual");
+    env::set_var("EDITOR", "editor");
+    harness.keys("e").unwrap();
+
+    // Marked files are all edited at once
+    env::remove_var("VISUAL");
+    harness.keys("VjVe").unwrap();
+    assert_eq!(
+        fs::read_to_string(&edited).unwrap(),
+        format!(
+            "visual {}\neditor {} {}\n",
+            a.display(),
+            a.display(),
+            b.display()
+        )
+    );
+}
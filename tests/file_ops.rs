@@ -0,0 +1,95 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use termfm::harness::{Fixture, Harness};
+
+#[test]
+fn creates_a_directory() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+
+    harness.command("mkdir new").unwrap();
+    assert!(harness.fixture.path("new").is_dir());
+    assert_eq!(harness.names(), ["new", "a.txt"]);
+}
+
+#[test]
+fn renames_the_selection() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "a")
+        .unwrap()
+        .file("b.txt", "b")
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+
+    harness.keys("j").unwrap();
+    harness.command("rename c.txt").unwrap();
+    assert!(!harness.fixture.path("b.txt").exists());
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("c.txt")).unwrap(),
+        "b"
+    );
+    assert_eq!(harness.names(), ["a.txt", "c.txt"]);
+}
+
+#[test]
+fn deletes_the_marked_files() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap()
+        .file("c.txt", "")
+        .unwrap()
+        .file("dir/inner.txt", "")
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+
+    // Marking moves the cursor down: marks dir and a.txt, skips b.txt
+    harness.keys("mmjm").unwrap();
+    assert_eq!(harness.app.state.marked.len(), 3);
+    harness.command("delete").unwrap();
+    assert!(harness
+        .screen()
+        .contains("Delete 3 item(s)? Enter to delete, Esc to cancel"));
+    harness.press(KeyCode::Enter).unwrap();
+
+    assert_eq!(harness.names(), ["b.txt"]);
+    assert!(!harness.fixture.path("dir").exists());
+    assert!(harness.app.state.marked.is_empty());
+}
+
+#[test]
+fn deletes_the_selection_without_marks() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+
+    harness.command("delete").unwrap();
+    harness.keys("y").unwrap();
+    assert_eq!(harness.names(), ["b.txt"]);
+}
+
+#[test]
+fn deleting_lists_marks_of_other_directories_first() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    fixture.file("sub/b.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("lm").unwrap();
+    harness.keys("h").unwrap();
+    harness.command("delete").unwrap();
+    let b = harness.fixture.path("sub/b.txt");
+    assert_eq!(harness.app.state.pending_delete, Some(vec![b.clone()]));
+    assert!(harness.screen().contains("│sub/b.txt"));
+
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(b.exists());
+    assert!(harness.app.state.marked.contains(&b));
+}
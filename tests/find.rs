@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use termfm::find::{find, Query};
+use termfm::harness::{Fixture, Harness};
+
+#[test]
+fn queries_are_parsed_into_terms() {
+    let query = Query::parse("*.rs main content:TODO size>1.5k size<2M mtime<7d").unwrap();
+    assert_eq!(query.names, ["*.rs", "*main*"]);
+    assert_eq!(query.content.as_deref(), Some("TODO"));
+    assert_eq!(query.min_size, Some(1536));
+    assert_eq!(query.max_size, Some(2 * 1024 * 1024));
+    assert_eq!(
+        query.newer_than,
+        Some(Duration::from_secs(7 * 24 * 60 * 60))
+    );
+    assert_eq!(Query::parse("size>lots").unwrap_err(), "Invalid size: lots");
+    assert!(Query::parse("  ").is_err());
+}
+
+#[test]
+fn searches_go_through_the_whole_tree() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "needle").unwrap();
+    fixture.file("src/deep/code.rs", "a needle here").unwrap();
+    fixture.file("src/other.rs", "nothing").unwrap();
+    fixture.file(".hidden/secret.txt", "needle").unwrap();
+
+    let query = Query::parse("content:needle").unwrap();
+    assert_eq!(
+        find(&fixture.root, &query, false).unwrap(),
+        [
+            PathBuf::from("notes.txt"),
+            PathBuf::from("src/deep/code.rs")
+        ]
+    );
+    assert_eq!(find(&fixture.root, &query, true).unwrap().len(), 3);
+
+    let query = Query::parse("*.rs size<10").unwrap();
+    assert_eq!(
+        find(&fixture.root, &query, false).unwrap(),
+        [PathBuf::from("src/other.rs")]
+    );
+}
+
+#[test]
+fn saved_searches_open_as_directories() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a/report.pdf", "").unwrap();
+    fixture.file("b/c/summary.pdf", "").unwrap();
+    fixture.file("b/notes.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("search save pdfs *.pdf").unwrap();
+    harness.command("search pdfs").unwrap();
+    assert_eq!(harness.names(), ["a/report.pdf", "b/c/summary.pdf"]);
+    assert!(harness.screen().contains("search: pdfs"));
+
+    // Results are live, but only refreshed on demand
+    fs::write(harness.fixture.path("b/new.pdf"), "").unwrap();
+    assert_eq!(harness.names().len(), 2);
+    harness.command("search refresh").unwrap();
+    assert_eq!(
+        harness.names(),
+        ["a/report.pdf", "b/c/summary.pdf", "b/new.pdf"]
+    );
+
+    // A new session knows the search
+    let mut harness = Harness::new(harness.fixture).unwrap();
+    harness.command("search").unwrap();
+    assert_eq!(harness.last_message(), "Saved searches: pdfs");
+    harness.command("search delete pdfs").unwrap();
+    assert!(harness.app.searches.searches.is_empty());
+}
+
+#[test]
+fn entering_a_directory_leaves_the_search() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("docs/guide.md", "").unwrap();
+    fixture.file("docs/more/docs.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("search save docs docs").unwrap();
+    harness.command("search docs").unwrap();
+    assert_eq!(harness.names(), ["docs", "docs/more/docs.txt"]);
+
+    harness.keys("l").unwrap();
+    assert!(harness.app.virtual_dir.is_none());
+    assert_eq!(harness.names(), ["more", "guide.md"]);
+}
+
+#[test]
+fn results_are_renamed_in_their_own_directory() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("docs/guide.md", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("find *.md").unwrap();
+    assert_eq!(harness.names(), ["docs/guide.md"]);
+    harness.command("rename manual.md").unwrap();
+    assert!(harness.fixture.path("docs/manual.md").exists());
+    assert!(!harness.fixture.path("manual.md").exists());
+
+    harness.command("search save docs *.md").unwrap();
+    harness.command("search docs").unwrap();
+    harness.command("rename guide.md").unwrap();
+    assert!(harness.fixture.path("docs/guide.md").exists());
+    assert_eq!(harness.names(), ["docs/guide.md"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn type_and_extension_terms_narrow_the_results() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("src/main.rs", "").unwrap();
+    fixture.file("src/lib.RS", "").unwrap();
+    fixture.file("Cargo.toml", "").unwrap();
+    fixture.file("target.rs/readme", "").unwrap();
+    std::os::unix::fs::symlink("src", fixture.path("link")).unwrap();
+
+    let query = Query::parse("ext:rs,toml").unwrap();
+    assert_eq!(
+        find(&fixture.root, &query, false).unwrap(),
+        [
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("src/lib.RS"),
+            PathBuf::from("src/main.rs"),
+        ]
+    );
+    let query = Query::parse("type:dir").unwrap();
+    assert_eq!(
+        find(&fixture.root, &query, false).unwrap(),
+        [PathBuf::from("src"), PathBuf::from("target.rs")]
+    );
+    let query = Query::parse("type:link").unwrap();
+    assert_eq!(
+        find(&fixture.root, &query, false).unwrap(),
+        [PathBuf::from("link")]
+    );
+    assert!(Query::parse("type:pipe").is_err());
+}
+
+#[test]
+fn find_lists_the_results_in_the_file_list() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a/small.log", "x").unwrap();
+    fixture.file("b/big.log", &"x".repeat(4096)).unwrap();
+    fixture.file("b/big.txt", &"x".repeat(4096)).unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("find ext:log size>1k").unwrap();
+    assert_eq!(harness.names(), ["b/big.log"]);
+    assert!(harness.screen().contains("find: ext:log size>1k"));
+
+    harness.command("find *.log").unwrap();
+    harness.command("sort size").unwrap();
+    assert_eq!(harness.names(), ["b/big.log", "a/small.log"]);
+    assert!(harness.app.virtual_dir.is_some());
+}
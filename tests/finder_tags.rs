@@ -0,0 +1,40 @@
+use termfm::finder_tags::{decode, encode, join_tag, split_tag};
+
+// What Python's plistlib writes for ["Red\n6", "Arbeit ü", "x" * 20], the
+// same layout as Finder's
+const WRITTEN_ELSEWHERE: &str = "62706c6973743030a3010203555265640a3668004100720062006500690074002000fc5f10147878787878787878787878787878787878787878080c1223000000000000010100000000000000040000000000000000000000000000003a";
+
+fn tags() -> Vec<String> {
+    vec!["Red\n6".to_string(), "Arbeit ü".to_string(), "x".repeat(20)]
+}
+
+fn bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn property_lists_are_read() {
+    assert_eq!(decode(&bytes(WRITTEN_ELSEWHERE)), Some(tags()));
+    assert_eq!(decode(b"bplist00"), None);
+    assert_eq!(decode(&[0; 64]), None);
+}
+
+#[test]
+fn property_lists_are_written_the_same_way() {
+    assert_eq!(encode(&tags()), bytes(WRITTEN_ELSEWHERE));
+    let many: Vec<String> = (0..300).map(|i| format!("tag {}", i)).collect();
+    assert_eq!(decode(&encode(&many)), Some(many));
+    assert_eq!(decode(&encode(&[])), Some(Vec::new()));
+}
+
+#[test]
+fn colors_follow_the_name() {
+    assert_eq!(split_tag("Red\n6"), ("Red", 6));
+    assert_eq!(split_tag("work"), ("work", 0));
+    assert_eq!(join_tag("Red", None), "Red\n6");
+    assert_eq!(join_tag("work", Some(4)), "work\n4");
+    assert_eq!(join_tag("work", None), "work");
+}
@@ -0,0 +1,61 @@
+use termfm::harness::{Fixture, Harness};
+
+fn tree() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("README.md", "hello").unwrap();
+    fixture.file("src/main.rs", "fn main() {}").unwrap();
+    fixture
+        .file("src/big/data.bin", &"x".repeat(10_000))
+        .unwrap();
+    fixture.file(".config/settings", "").unwrap();
+    fixture
+}
+
+#[test]
+fn the_flat_view_lists_every_file_below() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("R").unwrap();
+    assert_eq!(
+        harness.names(),
+        ["README.md", "src/big/data.bin", "src/main.rs"]
+    );
+    assert!(harness.screen().contains("flattened"));
+
+    harness.keys("R").unwrap();
+    assert_eq!(harness.names(), ["src", "README.md"]);
+}
+
+#[test]
+fn the_flat_view_can_be_sorted() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.command("flatten").unwrap();
+    harness.command("sort size").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("src/big/data.bin"));
+}
+
+#[test]
+fn entries_are_renamed_in_their_own_directory() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("R").unwrap();
+    harness.keys("G").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("src/main.rs"));
+    harness.command("rename lib.rs").unwrap();
+    assert!(harness.fixture.path("src/lib.rs").exists());
+    assert!(!harness.fixture.path("lib.rs").exists());
+    assert!(harness.names().contains(&"src/lib.rs".to_string()));
+}
+
+#[test]
+fn hidden_files_join_when_shown() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("Rzh").unwrap();
+    assert_eq!(
+        harness.names(),
+        [
+            ".config/settings",
+            "README.md",
+            "src/big/data.bin",
+            "src/main.rs"
+        ]
+    );
+}
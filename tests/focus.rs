@@ -0,0 +1,86 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use termfm::app::Panel;
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::Todo;
+
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    let lines: Vec<String> = (0..100).map(|n| format!("line {}", n)).collect();
+    fixture.file("a-long.txt", &lines.join("\n")).unwrap();
+    fixture.file("b.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.app.todos = ["first", "second", "third"].map(Todo::new).to_vec();
+    harness.app.todo_list_state.select(Some(0));
+    while harness.app.preview_cache.is_none() {
+        harness.settle().unwrap();
+    }
+    harness
+}
+
+// Ctrl+w and then `key`
+fn focus(harness: &mut Harness, key: char) {
+    harness
+        .events
+        .key(KeyCode::Char('w'), KeyModifiers::CONTROL);
+    harness.run().unwrap();
+    assert!(harness.screen().contains("Ctrl+w : h left, j down"));
+    harness.keys(&key.to_string()).unwrap();
+}
+
+#[test]
+fn j_and_k_scroll_the_focused_preview() {
+    let mut harness = harness();
+    focus(&mut harness, 'l');
+    assert_eq!(harness.app.state.focus, Panel::Preview);
+    assert!(harness.screen().contains("line 0 "));
+    harness.keys("5j").unwrap();
+    let screen = harness.screen();
+    assert!(!screen.contains("line 0 "));
+    assert!(screen.contains("line 5 "));
+    assert_eq!(harness.selected().as_deref(), Some("a-long.txt"));
+
+    // Never past the last line
+    harness.keys("G").unwrap();
+    assert!(harness.screen().contains("line 99"));
+    harness.keys("k").unwrap();
+    assert!(harness.screen().contains("line 98"));
+
+    harness.press(KeyCode::Esc).unwrap();
+    assert_eq!(harness.app.state.focus, Panel::Files);
+    harness.keys("j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("b.txt"));
+}
+
+#[test]
+fn j_and_k_move_through_the_focused_todo_list() {
+    let mut harness = harness();
+    focus(&mut harness, 'w');
+    focus(&mut harness, 'j');
+    assert_eq!(harness.app.state.focus, Panel::Todos);
+    harness.keys("j").unwrap();
+    assert_eq!(harness.app.todo_list_state.selected(), Some(1));
+    harness.keys("G").unwrap();
+    assert_eq!(harness.app.todo_list_state.selected(), Some(2));
+    assert_eq!(harness.selected().as_deref(), Some("a-long.txt"));
+
+    // Other keys still reach the file list and the to-dos
+    harness.keys(" ").unwrap();
+    assert!(harness.app.todos[2].completed);
+    focus(&mut harness, 'h');
+    assert_eq!(harness.app.state.focus, Panel::Files);
+    harness.keys("j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("b.txt"));
+    assert_eq!(harness.app.todo_list_state.selected(), Some(2));
+}
+
+#[test]
+fn a_hidden_preview_column_cannot_take_the_focus() {
+    let mut harness = harness();
+    focus(&mut harness, 'l');
+    harness.keys("zp").unwrap();
+    assert_eq!(harness.app.state.focus, Panel::Files);
+    focus(&mut harness, 'l');
+    assert_eq!(harness.app.state.focus, Panel::Files);
+    let message = harness.last_message();
+    assert_eq!(message, "The preview column is hidden, zp shows it");
+}
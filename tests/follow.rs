@@ -0,0 +1,71 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use termfm::follow::{Follow, FOLLOW_LINES};
+use termfm::harness::{Fixture, Harness};
+
+fn append(path: &std::path::Path, text: &str) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+#[test]
+fn appended_lines_are_picked_up() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("app.log", "one\ntwo\n").unwrap();
+    let path = fixture.path("app.log");
+    let mut follow = Follow::open(&path).unwrap();
+    assert_eq!(follow.tail(10), ["one", "two"]);
+    assert!(!follow.poll().unwrap());
+
+    append(&path, "three\nfou");
+    assert!(follow.poll().unwrap());
+    assert_eq!(follow.tail(10), ["one", "two", "three", "fou"]);
+    append(&path, "r\r\n");
+    follow.poll().unwrap();
+    assert_eq!(follow.tail(2), ["three", "four"]);
+}
+
+#[test]
+fn truncated_files_are_followed_from_the_start() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("app.log", "old line\nanother old line\n")
+        .unwrap();
+    let path = fixture.path("app.log");
+    let mut follow = Follow::open(&path).unwrap();
+    fs::write(&path, "new\n").unwrap();
+    assert!(follow.poll().unwrap());
+    assert_eq!(follow.tail(10), ["new"]);
+}
+
+#[test]
+fn only_the_last_lines_are_kept() {
+    let fixture = Fixture::new().unwrap();
+    let text: String = (0..FOLLOW_LINES + 50).map(|i| format!("{}\n", i)).collect();
+    fixture.file("big.log", &text).unwrap();
+    let follow = Follow::open(&fixture.path("big.log")).unwrap();
+    assert_eq!(follow.lines.len(), FOLLOW_LINES);
+    assert_eq!(follow.tail(1), [format!("{}", FOLLOW_LINES + 49)]);
+}
+
+#[test]
+fn log_files_are_followed_until_the_selection_moves() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.log", "started\n").unwrap();
+    fixture.file("b.txt", "text\n").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.settle().unwrap();
+    assert!(harness.app.follow.is_some());
+    assert!(harness.screen().contains("[following]"));
+
+    harness.keys("j").unwrap();
+    assert!(harness.app.follow.is_none());
+    harness.keys("F").unwrap();
+    assert!(harness
+        .app
+        .follow
+        .as_ref()
+        .is_some_and(|follow| follow.path.ends_with("b.txt")));
+    harness.keys("F").unwrap();
+    assert!(harness.app.follow.is_none());
+}
@@ -0,0 +1,98 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use termfm::harness::{Fixture, Harness};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// A repository with a committed a.txt and b.txt, both changed since
+fn repository() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "a")
+        .unwrap()
+        .file("b.txt", "b")
+        .unwrap();
+    let root = &fixture.root;
+    git(root, &["init", "-q"]);
+    git(root, &["config", "user.name", "Test"]);
+    git(root, &["config", "user.email", "test@example.com"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "Initial"]);
+    fs::write(fixture.path("a.txt"), "changed a").unwrap();
+    fs::write(fixture.path("b.txt"), "changed b").unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+fn status(harness: &Harness) -> String {
+    git(&harness.fixture.root, &["status", "--porcelain"])
+}
+
+#[test]
+fn stages_and_unstages_the_selection() {
+    let mut harness = repository();
+    harness.keys("A").unwrap();
+    assert_eq!(status(&harness), "M  a.txt\n M b.txt\n");
+    harness.keys("U").unwrap();
+    assert_eq!(status(&harness), " M a.txt\n M b.txt\n");
+}
+
+#[test]
+fn stages_the_marked_files() {
+    let mut harness = repository();
+    harness.keys("mmA").unwrap();
+    assert_eq!(status(&harness), "M  a.txt\nM  b.txt\n");
+    assert!(harness.app.state.marked.is_empty());
+}
+
+#[test]
+fn discarding_asks_first() {
+    let mut harness = repository();
+    harness.keys("Xn").unwrap();
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("a.txt")).unwrap(),
+        "changed a"
+    );
+    harness.keys("X").unwrap();
+    assert!(harness.screen().contains("Discard changes to 1 file(s)?"));
+    harness.keys("y").unwrap();
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("a.txt")).unwrap(),
+        "a"
+    );
+    assert_eq!(status(&harness), " M b.txt\n");
+}
+
+#[test]
+fn commits_from_the_prompt() {
+    let mut harness = repository();
+    harness.keys("AC").unwrap();
+    assert_eq!(
+        harness.app.state.prompt.as_ref().unwrap().input,
+        "git commit "
+    );
+    harness.keys("Change a").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert!(harness.last_message().starts_with("Committed "));
+    assert!(harness.last_message().ends_with(" Change a"));
+    assert_eq!(status(&harness), " M b.txt\n");
+}
+
+#[test]
+fn errors_outside_a_repository_are_reported() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("A").unwrap();
+    assert!(harness.last_message().contains("not a git repository"));
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::app::Notifications;
+use termfm::harness::{Fixture, Harness};
+use termfm::hooks::{HookEvent, Hooks};
+
+// Every hook appends what it was told to `hooks.log` in the fixture
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("docs/a.txt", "a").unwrap();
+    fixture.dir("backup").unwrap();
+    let log = fixture.path("hooks.log");
+    fs::write(
+        fixture.config_path(),
+        format!(
+            "[hooks]\n\
+             enter_dir = 'echo \"$TERMFM_EVENT $TERMFM_DIR\" >> {log}'\n\
+             open = 'echo \"$TERMFM_EVENT $TERMFM_FILE\" >> {log}'\n\
+             job_done = 'echo \"$TERMFM_EVENT $TERMFM_JOB: $TERMFM_MESSAGE\" >> {log}'\n",
+            log = log.display()
+        ),
+    )
+    .unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+// Hooks run in the background, so their lines turn up a little later
+fn wait_for_line(file: &Path, start: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        let lines = fs::read_to_string(file).unwrap_or_default();
+        if lines.lines().any(|line| line.starts_with(start)) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn hooks_are_read_from_the_config() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.config_path(),
+        "[hooks]\nopen = \"true\"\nenter = \"true\"\njob_done = 1\n",
+    )
+    .unwrap();
+    let mut notifications = Notifications::default();
+    let hooks = Hooks::from_config(&fixture.config_path(), &mut notifications);
+    assert_eq!(hooks.commands, [(HookEvent::Open, "true".to_string())]);
+    let errors: Vec<&str> = notifications
+        .log
+        .iter()
+        .map(|n| n.message.as_str())
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "Unknown hook hooks.enter, expected one of enter_dir, open, job_done",
+            "hooks.job_done must be a string",
+        ]
+    );
+}
+
+#[test]
+fn entering_directories_and_opening_files_run_hooks() {
+    let mut harness = harness();
+    let log = harness.fixture.path("hooks.log");
+    let root = harness.fixture.root.display().to_string();
+    assert!(wait_for_line(&log, &format!("enter_dir {}", root)));
+
+    harness.command("cd docs").unwrap();
+    let docs = harness.fixture.path("docs");
+    assert!(wait_for_line(
+        &log,
+        &format!("enter_dir {}", docs.display())
+    ));
+    harness.command("open true %f").unwrap();
+    let file = harness.fixture.path("docs/a.txt");
+    assert!(wait_for_line(&log, &format!("open {}", file.display())));
+}
+
+#[test]
+fn finished_jobs_run_a_hook() {
+    let mut harness = harness();
+    harness.command("cd docs").unwrap();
+    harness.keys("yy").unwrap();
+    harness.command("cd ../backup").unwrap();
+    harness.keys("P").unwrap();
+    harness.settle().unwrap();
+    assert!(harness.fixture.path("backup/a.txt").exists());
+    let log = harness.fixture.path("hooks.log");
+    assert!(wait_for_line(&log, "job_done paste: Pasted 1 item(s) by "));
+}
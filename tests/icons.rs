@@ -0,0 +1,88 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use termfm::app::Notifications;
+use termfm::fs_ops::Entry;
+use termfm::harness::{Fixture, Harness};
+#[cfg(unix)]
+use termfm::icons::ascii_icon;
+use termfm::icons::{nerd_icon, IconStyle};
+
+fn entry(fixture: &Fixture, name: &str) -> Entry {
+    Entry::load(&fixture.root, name.to_string())
+}
+
+#[test]
+fn nerd_icons_follow_the_kind_and_extension() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .dir("src")
+        .unwrap()
+        .file("main.rs", "")
+        .unwrap()
+        .file("photo.JPG", "")
+        .unwrap()
+        .file("backup.tar", "")
+        .unwrap()
+        .file("notes", "")
+        .unwrap();
+    let folder = nerd_icon(&entry(&fixture, "src"));
+    let rust = nerd_icon(&entry(&fixture, "main.rs"));
+    let image = nerd_icon(&entry(&fixture, "photo.JPG"));
+    let archive = nerd_icon(&entry(&fixture, "backup.tar"));
+    let plain = nerd_icon(&entry(&fixture, "notes"));
+    let all = [folder, rust, image, archive, plain];
+    for (i, icon) in all.iter().enumerate() {
+        assert!(!all[i + 1..].contains(icon), "{} is used twice", icon);
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn ascii_icons_classify_like_ls() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .dir("dir")
+        .unwrap()
+        .file("run.sh", "")
+        .unwrap()
+        .file("plain.txt", "")
+        .unwrap();
+    let script = fixture.path("run.sh");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink(fixture.path("plain.txt"), fixture.path("link")).unwrap();
+    assert_eq!(ascii_icon(&entry(&fixture, "dir")), '/');
+    assert_eq!(ascii_icon(&entry(&fixture, "run.sh")), '*');
+    assert_eq!(ascii_icon(&entry(&fixture, "plain.txt")), '-');
+    assert_eq!(ascii_icon(&entry(&fixture, "link")), '@');
+}
+
+#[test]
+fn icons_are_off_by_default() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    let harness = Harness::new(fixture).unwrap();
+    assert!(harness.app.icons == IconStyle::None);
+    assert!(harness.screen().contains("│>> a.txt"));
+}
+
+#[test]
+fn configured_icons_prefix_the_listing() {
+    let fixture = Fixture::new().unwrap();
+    fixture.dir("docs").unwrap().file("a.txt", "").unwrap();
+    fs::write(fixture.config_path(), "[icons]\nstyle = \"ascii\"\n").unwrap();
+    let harness = Harness::new(fixture).unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains(">> / docs"), "{}", screen);
+    assert!(screen.contains("- a.txt"), "{}", screen);
+}
+
+#[test]
+fn unknown_styles_are_reported() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(fixture.config_path(), "[icons]\nstyle = \"emoji\"\n").unwrap();
+    let mut notifications = Notifications::default();
+    let style = IconStyle::from_config(&fixture.config_path(), &mut notifications);
+    assert!(style == IconStyle::None);
+    assert_eq!(notifications.log.len(), 1);
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use termfm::harness::{Fixture, Harness};
+use termfm::ignored::IgnoreMode;
+
+// A repository with build output, logs and a log that is kept
+fn repository() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .dir(".git")
+        .unwrap()
+        .file(".gitignore", "target/\n*.log\n!keep.log\n")
+        .unwrap()
+        .file("target/debug/app", "")
+        .unwrap()
+        .file("main.rs", "")
+        .unwrap()
+        .file("debug.log", "")
+        .unwrap()
+        .file("keep.log", "")
+        .unwrap();
+    fixture
+}
+
+fn with_config(fixture: Fixture, config: &str) -> Harness {
+    fs::write(fixture.config_path(), config).unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+#[test]
+fn ignored_entries_are_shown_by_default() {
+    let harness = Harness::new(repository()).unwrap();
+    assert_eq!(harness.app.ignore.mode, IgnoreMode::Show);
+    assert_eq!(harness.names().len(), 4);
+}
+
+#[test]
+fn gitignored_entries_can_be_hidden() {
+    let harness = with_config(repository(), "[ignore]\nmode = \"hide\"\n");
+    let mut names = harness.names();
+    names.sort();
+    assert_eq!(names, ["keep.log", "main.rs"]);
+}
+
+#[test]
+fn dimmed_entries_stay_listed() {
+    let harness = with_config(repository(), "[ignore]\nmode = \"dim\"\n");
+    assert_eq!(harness.names().len(), 4);
+    let target = harness.fixture.path("target");
+    assert!(harness.app.ignore.is_ignored(&target, true));
+    assert!(!harness
+        .app
+        .ignore
+        .is_ignored(&harness.fixture.path("keep.log"), false));
+    assert!(harness.screen().contains("ignored: dimmed"));
+}
+
+#[test]
+fn the_key_cycles_through_the_modes() {
+    let mut harness = Harness::new(repository()).unwrap();
+    harness.keys("I").unwrap();
+    assert_eq!(harness.app.ignore.mode, IgnoreMode::Dim);
+    harness.keys("I").unwrap();
+    assert_eq!(harness.app.ignore.mode, IgnoreMode::Hide);
+    assert_eq!(harness.names().len(), 2);
+    harness.keys("I").unwrap();
+    assert_eq!(harness.names().len(), 4);
+}
+
+#[test]
+fn configured_patterns_and_ignore_files_apply_outside_repositories() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file(".ignore", "*.tmp\n")
+        .unwrap()
+        .file("notes.tmp", "")
+        .unwrap()
+        .file("a.o", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap()
+        // Without a repository .gitignore files don't count
+        .file(".gitignore", "b.txt\n")
+        .unwrap();
+    let harness = with_config(fixture, "[ignore]\nmode = \"hide\"\npatterns = [\"*.o\"]\n");
+    assert_eq!(harness.names(), ["b.txt"]);
+}
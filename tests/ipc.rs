@@ -0,0 +1,95 @@
+// Remote control goes through unix sockets
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::thread;
+use termfm::harness::{Fixture, Harness};
+use termfm::ipc::{send, socket_dir, target_socket, IpcServer, Reply};
+
+fn controlled(fixture: Fixture, name: &str) -> (Harness, PathBuf) {
+    let socket = env::temp_dir().join(format!("termfm-ipc-{}-{}.sock", std::process::id(), name));
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.app.ipc = Some(IpcServer::bind(socket.clone()).unwrap());
+    (harness, socket)
+}
+
+// Sends from another process' point of view while the interface runs
+fn remote(harness: &mut Harness, socket: &Path, command: &str) -> Reply {
+    let (socket, command) = (socket.to_path_buf(), command.to_string());
+    let client = thread::spawn(move || send(&socket, &command).unwrap());
+    while !client.is_finished() {
+        harness.app.tick();
+    }
+    harness.settle().unwrap();
+    client.join().unwrap()
+}
+
+fn tree() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("docs/a.txt", "").unwrap();
+    fixture.file("docs/b.md", "").unwrap();
+    fixture.file("docs/c.txt", "").unwrap();
+    fixture
+}
+
+#[test]
+fn commands_change_the_running_instance() {
+    let (mut harness, socket) = controlled(tree(), "cd");
+    let reply = remote(&mut harness, &socket, "cd docs");
+    assert_eq!(reply.status, "ok");
+    assert_eq!(reply.cwd, harness.fixture.path("docs"));
+    assert_eq!(harness.current_dir(), harness.fixture.path("docs"));
+
+    let reply = remote(&mut harness, &socket, "selection");
+    assert_eq!(reply.selection, [harness.fixture.path("docs/a.txt")]);
+    let reply = remote(&mut harness, &socket, ":select *.txt");
+    assert_eq!(reply.message, "Marked 2 item(s)");
+    assert_eq!(
+        reply.selection,
+        [
+            harness.fixture.path("docs/a.txt"),
+            harness.fixture.path("docs/c.txt")
+        ]
+    );
+}
+
+#[test]
+fn failures_are_answered_as_errors() {
+    let (mut harness, socket) = controlled(tree(), "errors");
+    let reply = remote(&mut harness, &socket, "cd nowhere");
+    assert_eq!(reply.status, "error");
+    assert!(reply.message.starts_with("Not a directory: "));
+    let reply = remote(&mut harness, &socket, "frobnicate");
+    assert_eq!(reply.status, "error");
+    assert_eq!(reply.cwd, harness.fixture.root);
+}
+
+#[test]
+fn programs_started_inside_reach_their_instance() {
+    let (harness, socket) = controlled(tree(), "env");
+    env::set_var("TERMFM_SOCKET", &socket);
+    assert_eq!(target_socket(), Some(socket.clone()));
+    drop(harness);
+    assert!(!socket.exists());
+}
+
+#[test]
+fn only_sockets_of_the_user_are_trusted() {
+    // Without a runtime directory the sockets go into a private one
+    env::remove_var("XDG_RUNTIME_DIR");
+    let dir = fs::metadata(socket_dir()).unwrap();
+    assert_eq!(dir.permissions().mode() & 0o777, 0o700);
+    let (_harness, socket) = controlled(tree(), "owner");
+    assert_eq!(dir.uid(), fs::metadata(&socket).unwrap().uid());
+
+    // One that another user made is left alone. Handing it over takes root.
+    if std::os::unix::fs::chown(&socket, Some(65534), None).is_err() {
+        return;
+    }
+    let error = send(&socket, "selection").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use termfm::config::Programs;
+use termfm::harness::{Fixture, Harness};
+use termfm::preview::{preview_text, LARGE_FILE, PREVIEW_LINES};
+
+// Numbered lines until the file is past the size limit
+fn large_log(fixture: &Fixture) -> std::path::PathBuf {
+    let mut text = String::new();
+    let mut line = 0;
+    while text.len() as u64 <= LARGE_FILE {
+        line += 1;
+        text.push_str(&format!("line {}\n", line));
+    }
+    fixture.file("big.txt", &text).unwrap();
+    fixture.path("big.txt")
+}
+
+#[test]
+fn the_start_is_read_with_a_marker() {
+    let fixture = Fixture::new().unwrap();
+    let preview = preview_text(
+        &large_log(&fixture),
+        &Programs::default(),
+        &AtomicBool::new(false),
+    );
+    assert!(preview.lines[0].starts_with("<Showing the first 64"));
+    assert!(preview.lines[1].ends_with("line 1"));
+    assert_eq!(preview.lines.len(), PREVIEW_LINES + 1);
+    assert_eq!(preview.encoding, Some("UTF-8"));
+}
+
+#[test]
+fn the_end_is_read_as_well() {
+    let fixture = Fixture::new().unwrap();
+    let path = large_log(&fixture);
+    let last = fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .last()
+        .unwrap()
+        .to_string();
+    let preview = preview_text(&path, &Programs::default(), &AtomicBool::new(false));
+    let tail = preview.tail.unwrap();
+    assert!(tail[0].starts_with("<Showing the last 64"));
+    assert_eq!(tail.len(), 21);
+    assert_eq!(tail[20], last);
+}
+
+#[test]
+fn large_binary_files_are_not_shown() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.path("blob.bin"),
+        [0u8, 0, 0x80].repeat(LARGE_FILE as usize / 3 + 1),
+    )
+    .unwrap();
+    let preview = preview_text(
+        &fixture.path("blob.bin"),
+        &Programs::default(),
+        &AtomicBool::new(false),
+    );
+    assert!(preview.lines[0].starts_with("<Binary file of"));
+    assert!(preview.tail.is_none());
+}
+
+#[test]
+fn t_switches_between_start_and_end() {
+    let fixture = Fixture::new().unwrap();
+    large_log(&fixture);
+    let mut harness = Harness::new(fixture).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while harness.app.preview_cache.is_none() {
+        assert!(Instant::now() < deadline, "no preview");
+        std::thread::sleep(Duration::from_millis(5));
+        harness.settle().unwrap();
+    }
+    assert!(harness.screen().contains("T shows the end"));
+    harness.keys("T").unwrap();
+    assert!(harness.screen().contains("T shows the start"));
+}
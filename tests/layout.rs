@@ -0,0 +1,48 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+
+const LONG_NAME: &str = "a-file-name-that-needs-more-room-than-the-list-has.txt";
+
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.file(LONG_NAME, "contents").unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+#[test]
+fn hiding_the_preview_gives_the_list_its_room() {
+    let mut harness = harness();
+    assert!(harness.screen().contains("File Preview"));
+    assert!(!harness.screen().contains(LONG_NAME));
+
+    harness.keys("zp").unwrap();
+    assert!(!harness.screen().contains("File Preview"));
+    assert!(!harness.screen().contains("To-Do List"));
+    assert!(harness.screen().contains(LONG_NAME));
+
+    harness.keys(":set preview!").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert!(harness.screen().contains("File Preview"));
+}
+
+#[test]
+fn the_preview_is_resized_in_steps() {
+    let mut harness = harness();
+    assert_eq!(harness.app.list_width(), 30);
+    harness.keys(">").unwrap();
+    assert_eq!(harness.app.state.preview_width, Some(75));
+    harness.keys("4<").unwrap();
+    assert_eq!(harness.app.state.preview_width, Some(55));
+    assert_eq!(harness.app.list_width(), 45);
+    harness.keys("99<").unwrap();
+    assert_eq!(harness.app.state.preview_width, Some(10));
+    assert!(harness.screen().contains(LONG_NAME));
+}
+
+#[test]
+fn resizing_shows_a_hidden_preview() {
+    let mut harness = harness();
+    harness.keys("zp>").unwrap();
+    assert!(!harness.app.state.preview_hidden);
+    assert_eq!(harness.app.state.preview_width, Some(75));
+}
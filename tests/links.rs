@@ -0,0 +1,101 @@
+use crossterm::event::KeyCode;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use termfm::app::SplitDirection;
+use termfm::cli::StartupOptions;
+use termfm::fs_ops::relative_path;
+use termfm::harness::{Fixture, Harness};
+
+fn files() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "a").unwrap();
+    fixture.file("b.txt", "b").unwrap();
+    fixture.dir("dest").unwrap();
+    fixture
+}
+
+#[test]
+fn a_single_link_asks_for_its_name() {
+    let mut harness = Harness::new(files()).unwrap();
+    harness.keys("j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("a.txt"));
+    harness.command("alink").unwrap();
+    let prompt = harness.app.state.prompt.as_ref().unwrap();
+    assert_eq!(prompt.input, "alink a.txt");
+
+    for _ in 0..3 {
+        harness.press(KeyCode::Backspace).unwrap();
+    }
+    harness.keys("md").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert_eq!(
+        fs::read_link(harness.fixture.path("a.md")).unwrap(),
+        harness.fixture.path("a.txt")
+    );
+    assert!(harness.names().contains(&"a.md".to_string()));
+
+    // Its own name is taken
+    harness.command("alink a.txt").unwrap();
+    assert!(harness.last_message().ends_with("a.txt already exists"));
+}
+
+#[test]
+fn links_go_into_the_other_pane() {
+    let fixture = files();
+    let options = StartupOptions {
+        split: Some(SplitDirection::Vertical),
+        right: Some(fixture.path("dest")),
+        ..Default::default()
+    };
+    let mut harness = Harness::with_options(fixture, options, 80, 24).unwrap();
+    harness.keys("jmm").unwrap();
+    harness.command("rlink").unwrap();
+    assert_eq!(
+        fs::read_link(harness.fixture.path("dest/a.txt")).unwrap(),
+        PathBuf::from("../a.txt")
+    );
+    assert_eq!(
+        fs::read_to_string(harness.fixture.path("dest/b.txt")).unwrap(),
+        "b"
+    );
+    assert!(harness.last_message().starts_with("Created 2 symlink(s)"));
+    let other = &harness.app.state.split.as_ref().unwrap().other;
+    assert_eq!(other.files.len(), 2);
+
+    // One name for two links
+    harness.command("link both").unwrap();
+    assert_eq!(
+        harness.last_message(),
+        "A link name only works for a single file"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn hard_links_share_the_file() {
+    let mut harness = Harness::new(files()).unwrap();
+    harness.keys("j").unwrap();
+    harness.command("link same.txt").unwrap();
+    let inode = |path: &str| fs::metadata(harness.fixture.path(path)).unwrap().ino();
+    assert_eq!(inode("same.txt"), inode("a.txt"));
+    assert!(!fs::symlink_metadata(harness.fixture.path("same.txt"))
+        .unwrap()
+        .is_symlink());
+
+    harness.keys("gg").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("dest"));
+    harness.command("link other").unwrap();
+    assert!(harness
+        .last_message()
+        .starts_with("Cannot hard link directory"));
+}
+
+#[test]
+fn relative_paths_climb_to_the_common_ancestor() {
+    let relative = |from: &str, to: &str| relative_path(Path::new(from), Path::new(to));
+    assert_eq!(relative("/a/b/c", "/a/d/e"), PathBuf::from("../../d/e"));
+    assert_eq!(relative("/a", "/a/b.txt"), PathBuf::from("b.txt"));
+    assert_eq!(relative("/a/b", "/c"), PathBuf::from("../../c"));
+}
@@ -0,0 +1,56 @@
+use termfm::harness::{Fixture, Harness};
+
+fn marked(harness: &Harness) -> Vec<String> {
+    let mut names: Vec<String> = harness
+        .app
+        .state
+        .marked
+        .iter()
+        .map(|path| {
+            path.strip_prefix(&harness.fixture.root)
+                .unwrap()
+                .display()
+                .to_string()
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn macros_replay_the_recorded_keys() {
+    let fixture = Fixture::new().unwrap();
+    for name in ["a", "b", "c", "d", "e"] {
+        fixture.file(name, "").unwrap();
+    }
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("Qa").unwrap();
+    assert!(harness.screen().contains("recording @a"));
+    // `m` moves on to the next entry
+    harness.keys("mQ").unwrap();
+    assert!(!harness.screen().contains("recording"));
+
+    harness.keys("2@a").unwrap();
+    assert_eq!(marked(&harness), ["a", "b", "c"]);
+    harness.keys("@@").unwrap();
+    assert_eq!(marked(&harness), ["a", "b", "c", "d"]);
+}
+
+#[test]
+fn replays_wait_for_listings() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("one/x", "").unwrap();
+    fixture.file("two/y", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("QblmhjQ").unwrap();
+    harness.keys("@b").unwrap();
+    assert_eq!(marked(&harness), ["one/x", "two/y"]);
+}
+
+#[test]
+fn empty_registers_are_reported() {
+    let fixture = Fixture::new().unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("@z").unwrap();
+    assert_eq!(harness.last_message(), "Nothing recorded in @z");
+}
@@ -0,0 +1,77 @@
+use crossterm::event::KeyCode;
+use std::time::{Duration, Instant};
+use termfm::harness::{Fixture, Harness};
+use termfm::markdown::render;
+use termfm::ui::{Line, Modifier};
+
+fn text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+fn texts(markdown: &str) -> Vec<String> {
+    render(markdown).iter().map(text).collect()
+}
+
+// Lets the preview worker finish the selected file's preview
+fn wait_for_preview(harness: &mut Harness) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while harness.app.preview_cache.is_none() {
+        assert!(Instant::now() < deadline, "no preview");
+        std::thread::sleep(Duration::from_millis(5));
+        harness.settle().unwrap();
+    }
+}
+
+#[test]
+fn markup_is_replaced_by_styles() {
+    let lines = render("# Title\n\nSome **bold** and *slanted* `code`.\n");
+    assert_eq!(text(&lines[0]), "Title");
+    assert!(lines[0].spans[0]
+        .style
+        .add_modifier
+        .contains(Modifier::BOLD | Modifier::UNDERLINED));
+    assert_eq!(text(&lines[2]), "Some bold and slanted code.");
+    let bold = lines[2].spans.iter().find(|s| s.content == "bold").unwrap();
+    assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+    let slanted = lines[2]
+        .spans
+        .iter()
+        .find(|s| s.content == "slanted")
+        .unwrap();
+    assert!(slanted.style.add_modifier.contains(Modifier::ITALIC));
+}
+
+#[test]
+fn lists_quotes_and_code_blocks() {
+    assert_eq!(
+        texts("- one\n- two\n  1. nested\n- [x] done\n"),
+        ["• one", "• two", "  1. nested", "• [x] done"]
+    );
+    assert_eq!(texts("> quoted\n"), ["│ quoted"]);
+    assert_eq!(
+        texts("```rust\nfn main() {}\n```\n"),
+        ["[rust]", "  fn main() {}"]
+    );
+}
+
+#[test]
+fn the_preview_toggles_between_rendered_and_source() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("README.md", "# Heading\n\nBody text\n")
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    wait_for_preview(&mut harness);
+    let screen = harness.screen();
+    assert!(screen.contains("File Preview [rendered]"), "{}", screen);
+    assert!(!screen.contains("# Heading"));
+
+    harness.keys(":set markdown!").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains("# Heading"), "{}", screen);
+    assert!(!screen.contains("[rendered]"));
+}
@@ -0,0 +1,93 @@
+use serde_json::json;
+use std::sync::atomic::AtomicBool;
+use termfm::config::Programs;
+use termfm::harness::Fixture;
+use termfm::media::{describe, format_duration, preview_media};
+
+#[test]
+fn durations_are_formatted() {
+    assert_eq!(format_duration(5.4), "0:05");
+    assert_eq!(format_duration(205.0), "3:25");
+    assert_eq!(format_duration(3725.0), "1:02:05");
+}
+
+#[test]
+fn video_streams_and_tags_are_described() {
+    let probe = json!({
+        "format": {
+            "format_long_name": "Matroska / WebM",
+            "duration": "5025.120000",
+            "bit_rate": "4500000",
+            "tags": { "TITLE": "A film" }
+        },
+        "streams": [
+            {
+                "codec_type": "video",
+                "codec_name": "h264",
+                "width": 1920,
+                "height": 1080,
+                "avg_frame_rate": "24000/1001"
+            },
+            {
+                "codec_type": "audio",
+                "codec_name": "aac",
+                "sample_rate": "48000",
+                "channel_layout": "5.1"
+            },
+            { "codec_type": "subtitle", "codec_name": "subrip" }
+        ]
+    });
+    assert_eq!(
+        describe(&probe),
+        [
+            "Format:   Matroska / WebM",
+            "Duration: 1:23:45",
+            "Bitrate:  4500 kb/s",
+            "Video:    h264, 1920x1080, 23.98 fps",
+            "Audio:    aac, 48000 Hz, 5.1",
+            "Subtitle: subrip",
+            "",
+            "Title:    A film",
+        ]
+    );
+}
+
+#[test]
+fn audio_tags_come_from_the_streams_too() {
+    let probe = json!({
+        "format": { "format_name": "ogg", "duration": "61.2" },
+        "streams": [
+            {
+                "codec_type": "audio",
+                "codec_name": "opus",
+                "channels": 2,
+                "tags": { "ARTIST": "Someone", "album": "Something" }
+            },
+            {
+                "codec_type": "video",
+                "codec_name": "mjpeg",
+                "disposition": { "attached_pic": 1 }
+            }
+        ]
+    });
+    let lines = describe(&probe);
+    assert!(lines.contains(&"Audio:    opus, 2 channels".to_string()));
+    assert!(lines.contains(&"Cover:    mjpeg".to_string()));
+    assert!(lines.contains(&"Artist:   Someone".to_string()));
+    assert!(lines.contains(&"Album:    Something".to_string()));
+}
+
+#[test]
+fn only_media_files_are_probed() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("notes.txt", "")
+        .unwrap()
+        .file("song.MP3", "")
+        .unwrap();
+    let (programs, cancel) = (Programs::default(), AtomicBool::new(false));
+    assert!(preview_media(&fixture.path("notes.txt"), &programs, &cancel).is_none());
+    // Whether ffprobe is installed or not, the preview is about the media
+    let preview = preview_media(&fixture.path("song.MP3"), &programs, &cancel).unwrap();
+    assert!(preview.lines[0].contains("ffprobe") || preview.lines[0].starts_with("Format"));
+}
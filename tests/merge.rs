@@ -0,0 +1,30 @@
+// The conflicts here are made of symlinks
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use termfm::harness::{Fixture, Harness};
+
+// A link in the target is a conflict like any file, even when it dangles
+#[test]
+fn a_dangling_link_in_the_way_is_kept_both() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("src/a.txt", "new").unwrap();
+    fixture.dir("dest").unwrap();
+    symlink("missing", fixture.path("dest/a.txt")).unwrap();
+    let dest = fixture.path("dest");
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("src"));
+
+    harness
+        .command(&format!("merge {}", dest.display()))
+        .unwrap();
+    assert!(harness.app.state.merge.is_some());
+    harness.keys("b").unwrap();
+    assert!(harness.app.state.merge.is_none());
+    let read = |path: &str| fs::read_to_string(harness.fixture.path(path)).unwrap();
+    assert_eq!(read("dest/a (1).txt"), "new");
+    assert!(fs::symlink_metadata(harness.fixture.path("dest/a.txt")).is_ok());
+    assert!(!harness.fixture.path("src").exists());
+}
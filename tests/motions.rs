@@ -0,0 +1,72 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use termfm::harness::{Fixture, Harness};
+
+// 100 files, `file000` to `file099`; the list shows 18 of them at a time
+fn many() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for i in 0..100 {
+        fixture.file(&format!("file{:03}", i), "").unwrap();
+    }
+    Harness::new(fixture).unwrap()
+}
+
+fn ctrl(harness: &mut Harness, c: char) {
+    harness.events.key(KeyCode::Char(c), KeyModifiers::CONTROL);
+    harness.run().unwrap();
+}
+
+#[test]
+fn counts_repeat_motions() {
+    let mut harness = many();
+    harness.keys("5j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file005"));
+    harness.keys("12j3k").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file014"));
+    // Past the end stops at the last entry
+    harness.keys("500j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file099"));
+}
+
+#[test]
+fn top_bottom_and_numbered_entries() {
+    let mut harness = many();
+    harness.keys("G").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file099"));
+    harness.keys("gg").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file000"));
+    harness.keys("42G").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file041"));
+    harness.keys("7gg").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("file006"));
+    assert!(harness.app.state.count.is_none());
+}
+
+#[test]
+fn screen_positions() {
+    let mut harness = many();
+    let height = harness.app.list_height;
+    harness.keys("L").unwrap();
+    assert_eq!(harness.app.cursor_position, height - 1);
+    harness.keys("H").unwrap();
+    assert_eq!(harness.app.cursor_position, 0);
+    harness.keys("M").unwrap();
+    assert_eq!(harness.app.cursor_position, (height - 1) / 2);
+    harness.keys("3H").unwrap();
+    assert_eq!(harness.app.cursor_position, 2);
+}
+
+#[test]
+fn half_and_full_pages() {
+    let mut harness = many();
+    let height = harness.app.list_height;
+    ctrl(&mut harness, 'd');
+    assert_eq!(harness.app.cursor_position, height / 2);
+    assert_eq!(harness.app.list_offset, height / 2);
+    ctrl(&mut harness, 'u');
+    assert_eq!(harness.app.cursor_position, 0);
+    assert_eq!(harness.app.list_offset, 0);
+    ctrl(&mut harness, 'f');
+    assert_eq!(harness.app.cursor_position, height);
+    harness.press(KeyCode::PageUp).unwrap();
+    assert_eq!(harness.app.cursor_position, 0);
+}
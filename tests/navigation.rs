@@ -0,0 +1,73 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("docs/readme.txt", "hello")
+        .unwrap()
+        .file("docs/notes/todo.txt", "")
+        .unwrap()
+        .file("a.txt", "a")
+        .unwrap()
+        .file("b.txt", "b")
+        .unwrap()
+        .file(".hidden", "")
+        .unwrap();
+    fixture
+}
+
+#[test]
+fn lists_directories_first_without_hidden_files() {
+    let harness = Harness::new(fixture()).unwrap();
+    assert_eq!(harness.names(), ["docs", "a.txt", "b.txt"]);
+    assert_eq!(harness.selected().as_deref(), Some("docs"));
+}
+
+#[test]
+fn moves_the_cursor_within_bounds() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("jj").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("b.txt"));
+    harness.keys("jjj").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("b.txt"));
+    harness.keys("kkkk").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("docs"));
+}
+
+#[test]
+fn enters_and_leaves_directories() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("l").unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("docs"));
+    assert_eq!(harness.names(), ["notes", "readme.txt"]);
+
+    harness.press(KeyCode::Left).unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.root);
+    assert_eq!(harness.names(), ["docs", "a.txt", "b.txt"]);
+}
+
+#[test]
+fn toggles_hidden_files() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("zh").unwrap();
+    assert!(harness.names().contains(&".hidden".to_string()));
+    harness.keys("zh").unwrap();
+    assert!(!harness.names().contains(&".hidden".to_string()));
+}
+
+#[test]
+fn draws_the_listing() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("j").unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains(">> a.txt"), "{}", screen);
+    assert!(screen.contains("docs"), "{}", screen);
+}
+
+#[test]
+fn quits_on_q() {
+    let mut harness = Harness::new(fixture()).unwrap();
+    harness.keys("q").unwrap();
+    assert!(harness.app.quit);
+}
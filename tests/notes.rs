@@ -0,0 +1,58 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use termfm::harness::{Fixture, Harness};
+
+#[test]
+fn notes_show_in_the_properties_popup() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("data.csv", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("note from the 2024 survey").unwrap();
+    harness.keys("i").unwrap();
+    assert!(harness
+        .screen()
+        .contains("Note:        from the 2024 survey"));
+
+    let saved =
+        fs::read_to_string(harness.fixture.config_path().with_file_name("notes.json")).unwrap();
+    assert!(saved.contains("from the 2024 survey"));
+    let harness = Harness::new(harness.fixture).unwrap();
+    let path = harness.fixture.path("data.csv");
+    assert_eq!(harness.app.notes.get(&path), Some("from the 2024 survey"));
+}
+
+#[test]
+fn notes_can_be_edited_and_removed() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("setup.iso", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    let path = harness.fixture.path("setup.iso");
+    harness.command("note checksum ok").unwrap();
+    harness.command("note").unwrap();
+    assert_eq!(
+        harness.app.state.prompt.as_ref().unwrap().input,
+        "note checksum ok"
+    );
+    harness
+        .keys(", burned")
+        .unwrap()
+        .press(KeyCode::Enter)
+        .unwrap();
+    assert_eq!(harness.app.notes.get(&path), Some("checksum ok, burned"));
+
+    harness.command("unnote").unwrap();
+    assert_eq!(harness.app.notes.get(&path), None);
+}
+
+#[test]
+fn notes_can_follow_the_names() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    fs::write(fixture.config_path(), "[notes]\nin_listing = true\n").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("note draft").unwrap();
+    assert!(harness.screen().contains("a.txt  draft"));
+
+    harness.command("set notes!").unwrap();
+    assert!(!harness.screen().contains("draft"));
+}
@@ -0,0 +1,58 @@
+use crossterm::event::KeyCode;
+use std::time::{Duration, Instant};
+use termfm::harness::{Fixture, Harness};
+
+// 100 numbered lines, and a directory sorted before them
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    let lines: Vec<String> = (1..=100).map(|n| format!("line {}", n)).collect();
+    fixture.file("numbers.txt", &lines.join("\n")).unwrap();
+    fixture.file("dir/inside.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("j").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while harness.app.preview_cache.is_none() {
+        assert!(Instant::now() < deadline, "no preview");
+        std::thread::sleep(Duration::from_millis(5));
+        harness.settle().unwrap();
+    }
+    harness
+}
+
+#[test]
+fn the_preview_fills_the_terminal() {
+    let mut harness = harness();
+    harness.keys("v").unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains("numbers.txt 22/100 (Esc closes)"));
+    assert!(screen.contains("line 22"));
+    assert!(!screen.contains("Current Directory"));
+
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.state.pager.is_none());
+    assert!(harness.screen().contains("Current Directory"));
+}
+
+#[test]
+fn the_pager_scrolls() {
+    let mut harness = harness();
+    harness.keys("vjj").unwrap();
+    assert!(harness.screen().contains("24/100"));
+    assert!(!harness.screen().contains("line 2\n"));
+    harness.keys("G").unwrap();
+    assert!(harness.screen().contains("100/100"));
+    assert!(harness.screen().contains("line 100"));
+    // The last page stays full
+    harness.keys(" ").unwrap();
+    assert_eq!(harness.app.state.pager.as_ref().unwrap().scroll, 78);
+    harness.keys("g").unwrap();
+    assert!(harness.screen().contains("line 1 "));
+}
+
+#[test]
+fn directories_show_their_entries() {
+    let mut harness = harness();
+    harness.keys("kv").unwrap();
+    assert!(harness.screen().contains("inside.txt"));
+    assert!(harness.screen().contains("1/1 (Esc closes)"));
+}
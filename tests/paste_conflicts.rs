@@ -0,0 +1,164 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use termfm::fs_ops::Preserve;
+use termfm::harness::{Fixture, Harness};
+use termfm::register::{PasteChoice, Register, RegisterOperation};
+
+// `a.txt` and `b.txt` in `from/`, both also in `to/`
+fn taken() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("from/a.txt", "new a").unwrap();
+    fixture.file("from/b.txt", "new b").unwrap();
+    fixture.file("to/a.txt", "old a").unwrap();
+    fixture.file("to/b.txt", "old b").unwrap();
+    fixture
+}
+
+fn register(fixture: &Fixture, operation: RegisterOperation) -> Register {
+    Register {
+        operation,
+        paths: vec![fixture.path("from/a.txt"), fixture.path("from/b.txt")],
+    }
+}
+
+fn read(fixture: &Fixture, path: &str) -> String {
+    fs::read_to_string(fixture.path(path)).unwrap()
+}
+
+#[test]
+fn each_taken_name_waits_for_a_choice() {
+    let fixture = taken();
+    fixture.file("from/c.txt", "c").unwrap();
+    let mut register = register(&fixture, RegisterOperation::Copy);
+    register.paths.push(fixture.path("from/c.txt"));
+    let mut paste = register.plan(&fixture.path("to"));
+    assert!(!paste.advance());
+    assert_eq!(
+        paste.conflict,
+        Some((fixture.path("from/a.txt"), fixture.path("to/a.txt")))
+    );
+
+    paste.resolve(PasteChoice::Overwrite, false);
+    assert!(!paste.advance());
+    paste.resolve(PasteChoice::Rename, false);
+    assert!(paste.advance());
+    let (pasted, errors, _) = paste.run(Preserve::default());
+    assert!(errors.is_empty());
+    assert_eq!(pasted.len(), 3);
+    assert_eq!(read(&fixture, "to/a.txt"), "new a");
+    assert_eq!(read(&fixture, "to/b.txt"), "old b");
+    assert_eq!(read(&fixture, "to/b (1).txt"), "new b");
+    assert_eq!(read(&fixture, "to/c.txt"), "c");
+}
+
+#[test]
+fn only_older_files_are_overwritten_if_newer() {
+    let fixture = taken();
+    let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    for path in ["to/a.txt", "from/b.txt"] {
+        let file = fs::File::options()
+            .write(true)
+            .open(fixture.path(path))
+            .unwrap();
+        file.set_modified(an_hour_ago).unwrap();
+    }
+    let mut paste = register(&fixture, RegisterOperation::Cut).plan(&fixture.path("to"));
+    assert!(!paste.advance());
+    paste.resolve(PasteChoice::OverwriteIfNewer, true);
+    assert!(paste.advance());
+    assert_eq!(paste.skipped, 1);
+    paste.run(Preserve::default());
+    assert_eq!(read(&fixture, "to/a.txt"), "new a");
+    assert_eq!(read(&fixture, "to/b.txt"), "old b");
+    // The skipped file stays where it was
+    assert!(!fixture.path("from/a.txt").exists());
+    assert!(fixture.path("from/b.txt").exists());
+}
+
+fn to_names(fixture: &Fixture) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(fixture.path("to"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn a_failed_copy_leaves_the_overwritten_file() {
+    let fixture = taken();
+    let mut paste = register(&fixture, RegisterOperation::Copy).plan(&fixture.path("to"));
+    assert!(!paste.advance());
+    paste.resolve(PasteChoice::Overwrite, true);
+    assert!(paste.advance());
+    fs::remove_file(fixture.path("from/a.txt")).unwrap();
+    let (pasted, errors, _) = paste.run(Preserve::default());
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("Failed to copy"));
+    assert_eq!(pasted.len(), 1);
+    assert_eq!(read(&fixture, "to/a.txt"), "old a");
+    assert_eq!(read(&fixture, "to/b.txt"), "new b");
+    assert_eq!(to_names(&fixture), ["a.txt", "b.txt"]);
+}
+
+#[test]
+fn directories_are_overwritten_once_the_new_one_is_in() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("from/d/new.txt", "new").unwrap();
+    fixture.file("to/d/old.txt", "old").unwrap();
+    let register = Register {
+        operation: RegisterOperation::Cut,
+        paths: vec![fixture.path("from/d")],
+    };
+    let mut paste = register.plan(&fixture.path("to"));
+    assert!(!paste.advance());
+    paste.resolve(PasteChoice::Overwrite, false);
+    assert!(paste.advance());
+    let (_, errors, _) = paste.run(Preserve::default());
+    assert!(errors.is_empty());
+    assert_eq!(read(&fixture, "to/d/new.txt"), "new");
+    assert!(!fixture.path("to/d/old.txt").exists());
+    assert!(!fixture.path("from/d").exists());
+    assert_eq!(to_names(&fixture), ["d"]);
+}
+
+// All harnesses of this binary share one HOME and so one register, keep every
+// test touching it in here
+#[test]
+fn the_dialog_applies_a_choice_to_the_rest_with_shift() {
+    let fixture = taken();
+    let register = register(&fixture, RegisterOperation::Copy);
+    let mut harness = Harness::new(fixture).unwrap();
+    register.save().unwrap();
+    harness.keys("l").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("from"));
+
+    // Pasting next to themselves needs no decision
+    harness.keys("P").unwrap();
+    harness.settle().unwrap();
+    assert!(harness.app.state.paste.is_none());
+    assert_eq!(
+        harness.names(),
+        ["a (1).txt", "a.txt", "b (1).txt", "b.txt"]
+    );
+
+    harness.keys("hjl").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("to"));
+    harness.keys("P").unwrap();
+    assert!(harness.screen().contains("File exists"));
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.state.paste.is_none());
+    assert_eq!(read(&harness.fixture, "to/a.txt"), "old a");
+
+    harness.keys("PO").unwrap();
+    harness.settle().unwrap();
+    assert!(harness.app.state.paste.is_none());
+    assert_eq!(read(&harness.fixture, "to/a.txt"), "new a");
+    assert_eq!(read(&harness.fixture, "to/b.txt"), "new b");
+    assert_eq!(harness.names(), ["a.txt", "b.txt"]);
+    // Followed by how the files were copied
+    assert!(harness.last_message().starts_with("Pasted 2 item(s) by "));
+}
@@ -0,0 +1,94 @@
+use exif::experimental::Writer;
+use exif::{Field, In, Rational, Tag, Value};
+use std::fs;
+use std::io::Cursor;
+use termfm::harness::Fixture;
+use termfm::photo::{describe, preview_photo};
+
+fn ascii(tag: Tag, value: &str) -> Field {
+    Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+    }
+}
+
+fn rationals(tag: Tag, values: &[(u32, u32)]) -> Field {
+    Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Rational(
+            values
+                .iter()
+                .map(|&(num, denom)| Rational { num, denom })
+                .collect(),
+        ),
+    }
+}
+
+// A TIFF file holding nothing but EXIF fields
+fn write_tiff(fixture: &Fixture, name: &str, fields: &[Field]) {
+    let mut writer = Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+    let mut buffer = Cursor::new(Vec::new());
+    writer.write(&mut buffer, false).unwrap();
+    fs::write(fixture.path(name), buffer.into_inner()).unwrap();
+}
+
+#[test]
+fn camera_settings_and_location_are_shown() {
+    let fixture = Fixture::new().unwrap();
+    write_tiff(
+        &fixture,
+        "photo.tif",
+        &[
+            ascii(Tag::Make, "Canon"),
+            ascii(Tag::Model, "Canon EOS R6"),
+            ascii(Tag::DateTimeOriginal, "2023:05:01 12:34:56"),
+            rationals(Tag::ExposureTime, &[(1, 250)]),
+            rationals(Tag::FNumber, &[(28, 10)]),
+            Field {
+                tag: Tag::PhotographicSensitivity,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![400]),
+            },
+            rationals(Tag::FocalLength, &[(50, 1)]),
+            ascii(Tag::GPSLatitudeRef, "N"),
+            rationals(Tag::GPSLatitude, &[(48, 1), (51, 1), (30, 1)]),
+            ascii(Tag::GPSLongitudeRef, "W"),
+            rationals(Tag::GPSLongitude, &[(2, 1), (17, 1), (24, 1)]),
+        ],
+    );
+    let lines = describe(&fixture.path("photo.tif"));
+    for expected in [
+        "Camera:     Canon EOS R6",
+        "Taken:      2023-05-01 12:34:56",
+        "Exposure:   1/250 s, f/2.8, ISO 400",
+        "Focal:      50 mm",
+        "GPS:        48.858333, -2.290000",
+    ] {
+        assert!(lines.iter().any(|line| line == expected), "{:?}", lines);
+    }
+}
+
+#[test]
+fn images_without_exif_still_show_their_size() {
+    let fixture = Fixture::new().unwrap();
+    // The signature and the header chunk of a 640x480 PNG
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&640u32.to_be_bytes());
+    png.extend_from_slice(&480u32.to_be_bytes());
+    png.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+    fs::write(fixture.path("image.png"), png).unwrap();
+    let preview = preview_photo(&fixture.path("image.png")).unwrap();
+    assert_eq!(preview.lines, ["Dimensions: 640x480", "<No EXIF data>"]);
+}
+
+#[test]
+fn only_images_are_described() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "").unwrap();
+    assert!(preview_photo(&fixture.path("notes.txt")).is_none());
+}
@@ -0,0 +1,96 @@
+use crossterm::event::KeyCode;
+use termfm::cli::StartupOptions;
+use termfm::harness::{Fixture, Harness};
+
+fn picker() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("dir/inner.txt", "")
+        .unwrap()
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap();
+    let options = StartupOptions {
+        pick: true,
+        ..Default::default()
+    };
+    Harness::with_options(fixture, options, 80, 24).unwrap()
+}
+
+#[test]
+fn enter_picks_the_selected_file() {
+    let mut harness = picker();
+    harness.keys("jj").unwrap().press(KeyCode::Enter).unwrap();
+    assert!(harness.app.quit);
+    assert_eq!(
+        harness.app.picked,
+        Some(vec![harness.fixture.path("b.txt")])
+    );
+}
+
+#[test]
+fn enter_on_a_directory_enters_it() {
+    let mut harness = picker();
+    harness.press(KeyCode::Enter).unwrap();
+    assert!(!harness.app.quit);
+    harness.keys("l").unwrap().press(KeyCode::Enter).unwrap();
+    assert_eq!(
+        harness.app.picked,
+        Some(vec![harness.fixture.path("dir/inner.txt")])
+    );
+}
+
+#[test]
+fn enter_picks_the_marked_set() {
+    let mut harness = picker();
+    harness.keys("jmm").unwrap().press(KeyCode::Enter).unwrap();
+    let mut picked = harness.app.picked.clone().unwrap();
+    picked.sort();
+    assert_eq!(
+        picked,
+        [harness.fixture.path("a.txt"), harness.fixture.path("b.txt")]
+    );
+}
+
+#[test]
+fn quitting_picks_nothing() {
+    let mut harness = picker();
+    harness.keys("q").unwrap();
+    assert!(harness.app.quit);
+    assert_eq!(harness.app.picked, None);
+}
+
+#[test]
+fn quit_and_choose_takes_directories_too() {
+    let mut harness = picker();
+    harness.keys("zq").unwrap();
+    assert!(harness.app.quit);
+    assert_eq!(harness.app.picked, Some(vec![harness.fixture.path("dir")]));
+}
+
+// Without `--pick` the file manager opens files as usual, and only `zq` or
+// `:choose` fill the `--chooser-file`
+#[test]
+fn choose_works_outside_the_picker() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness
+        .keys("mjm:choose")
+        .unwrap()
+        .press(KeyCode::Enter)
+        .unwrap();
+    assert!(harness.app.quit);
+    assert_eq!(
+        harness.app.picked,
+        Some(vec![
+            harness.fixture.path("a.txt"),
+            harness.fixture.path("b.txt")
+        ])
+    );
+}
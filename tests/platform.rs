@@ -0,0 +1,27 @@
+use termfm::command::is_drive;
+use termfm::opener::{posix_quote, shell_command, shell_quote};
+
+#[test]
+fn drives_are_recognised() {
+    assert!(is_drive("C:"));
+    assert!(is_drive("d:"));
+    assert!(!is_drive("C:\\"));
+    assert!(!is_drive("1:"));
+    assert!(!is_drive("Cd"));
+}
+
+// Whatever the platform's shell, a quoted argument arrives as it was
+#[test]
+fn quoted_arguments_survive_the_shell() {
+    let value = "it's a \"file\" $HOME; ls";
+    let output = shell_command(&format!("echo {}", shell_quote(value)))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), value);
+}
+
+#[test]
+fn posix_quoting_is_the_same_everywhere() {
+    assert_eq!(posix_quote("a b"), "'a b'");
+    assert_eq!(posix_quote("it's"), "'it'\\''s'");
+}
@@ -0,0 +1,84 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use termfm::config::{load_opener_config, OpenerConfig, Programs};
+use termfm::harness::Fixture;
+use termfm::preview::{preview_document, preview_file};
+use termfm::previewers::PreviewerRegistry;
+
+fn pdf(fixture: &Fixture) -> PathBuf {
+    // Binary content that would otherwise be shown as garbage
+    fixture.file("report.PDF", "%PDF-1.7\0\0\u{1}").unwrap();
+    fixture.path("report.PDF")
+}
+
+#[test]
+fn pdfs_are_converted_to_text() {
+    let fixture = Fixture::new().unwrap();
+    let path = pdf(&fixture);
+    // Prints its arguments
+    fixture
+        .program("pdftotext", "echo \"Page one of $4\"\necho \"args: $*\"\n")
+        .unwrap();
+    let config = OpenerConfig {
+        programs: fixture.programs(),
+        ..OpenerConfig::default()
+    };
+    let preview = preview_file(
+        &path,
+        &config,
+        &PreviewerRegistry::default(),
+        &AtomicBool::new(false),
+    );
+    assert_eq!(preview.lines[0], format!("Page one of {}", path.display()));
+    assert!(preview.lines[1].contains("-l 3 -layout"));
+}
+
+#[test]
+fn programs_are_looked_up_in_the_config() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.config_path(),
+        "[programs]\npdftotext = \"/opt/poppler/bin/pdftotext\"\nffprobe = \"~/bin/ffprobe\"\n",
+    )
+    .unwrap();
+    let programs = load_opener_config(&fixture.config_path()).unwrap().programs;
+    assert_eq!(
+        programs.path("pdftotext"),
+        Path::new("/opt/poppler/bin/pdftotext")
+    );
+    let home = PathBuf::from(env::var_os("HOME").unwrap());
+    assert_eq!(programs.path("ffprobe"), home.join("bin/ffprobe"));
+    // Everything else is found on PATH
+    assert_eq!(programs.path("batcat"), Path::new("batcat"));
+}
+
+#[test]
+fn missing_converters_are_named() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("letter.odt", "PK\u{3}\u{4}").unwrap();
+    // Neither is installed, wherever the tests run
+    let mut programs = Programs::default();
+    programs.insert("pandoc", fixture.bin().join("pandoc"));
+    programs.insert("odt2txt", fixture.bin().join("odt2txt"));
+    let preview = preview_document(
+        &fixture.path("letter.odt"),
+        &programs,
+        &AtomicBool::new(false),
+    )
+    .unwrap();
+    assert_eq!(preview.lines, ["<Install pandoc to preview .odt files>"]);
+}
+
+#[test]
+fn other_files_are_not_documents() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "text").unwrap();
+    assert!(preview_document(
+        &fixture.path("notes.txt"),
+        &Programs::default(),
+        &AtomicBool::new(false)
+    )
+    .is_none());
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use termfm::app::Notifications;
+use termfm::config::Programs;
+use termfm::harness::Fixture;
+use termfm::previewers::PreviewerRegistry;
+
+fn registry(fixture: &Fixture, config: &str) -> (PreviewerRegistry, Notifications) {
+    fs::write(fixture.config_path(), config).unwrap();
+    let mut notifications = Notifications::default();
+    let registry = PreviewerRegistry::from_config(&fixture.config_path(), &mut notifications);
+    (registry, notifications)
+}
+
+#[test]
+fn user_previewers_come_before_the_built_in_ones() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("table.csv", "a,b\n1,2\n").unwrap();
+    let (registry, notifications) = registry(
+        &fixture,
+        "[[previewers]]\nname = \"csv\"\nextensions = [\"CSV\"]\ncommand = \"echo rows of %f\"\n",
+    );
+    assert!(notifications.log.is_empty());
+    assert_eq!(
+        registry.ids(),
+        vec!["csv", "document", "media", "photo", "text"]
+    );
+
+    let path = fixture.path("table.csv");
+    let preview = registry.preview(&path, &Programs::default(), &AtomicBool::new(false));
+    assert_eq!(preview.lines, vec![format!("rows of {}", path.display())]);
+}
+
+#[test]
+fn failing_scripts_pass_the_file_on() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("notes.txt", "plain text\n").unwrap();
+    let (registry, _) = registry(
+        &fixture,
+        "[[previewers]]\nname = \"decline\"\ncommand = \"sh -c 'echo nope; exit 1'\"\n",
+    );
+    let preview = registry.preview(
+        &fixture.path("notes.txt"),
+        &Programs::default(),
+        &AtomicBool::new(false),
+    );
+    assert!(preview.lines[0].contains("plain text"));
+}
+
+#[test]
+fn mime_patterns_match_the_sniffed_type() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("LICENSE", "Permission is granted\n").unwrap();
+    fs::write(fixture.path("blob.bin"), [0x80, 0x81, 0x00, 0xc3]).unwrap();
+    let (registry, _) = registry(
+        &fixture,
+        "[[previewers]]\nname = \"text\"\nmime = \"text/*\"\ncommand = \"printf sniffed\"\n",
+    );
+    // Replaces the built-in text previewer, the binary file falls back to it
+    assert_eq!(registry.ids().iter().filter(|id| **id == "text").count(), 1);
+    let preview = registry.preview(
+        &fixture.path("LICENSE"),
+        &Programs::default(),
+        &AtomicBool::new(false),
+    );
+    assert_eq!(preview.lines, vec!["sniffed"]);
+    let preview = registry.preview(
+        &fixture.path("blob.bin"),
+        &Programs::default(),
+        &AtomicBool::new(false),
+    );
+    assert_ne!(preview.lines, vec!["sniffed"]);
+}
+
+#[test]
+fn priorities_order_user_and_built_in_previewers() {
+    let fixture = Fixture::new().unwrap();
+    let (registry, notifications) = registry(
+        &fixture,
+        "[[previewers]]\nname = \"late\"\nextensions = [\"pdf\"]\ncommand = \"echo late\"\npriority = 5\n\n\
+         [[previewers]]\nname = \"broken\"\nextensions = [\"pdf\"]\n",
+    );
+    assert_eq!(
+        registry.ids(),
+        vec!["document", "media", "photo", "late", "text"]
+    );
+    assert_eq!(
+        notifications.log.back().unwrap().message,
+        "[[previewers]] broken has no command"
+    );
+}
@@ -0,0 +1,71 @@
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::{load_todos, Todo, TODO_FILE};
+
+// A project with its own list and a directory outside of it
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.dir("project/src").unwrap();
+    fixture.dir("elsewhere").unwrap();
+    fixture
+        .file(
+            &format!("project/{}", TODO_FILE),
+            r#"[{"description":"ship it","completed":false}]"#,
+        )
+        .unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+fn descriptions(harness: &Harness) -> Vec<String> {
+    harness
+        .app
+        .todos
+        .iter()
+        .map(|todo| todo.description.clone())
+        .collect()
+}
+
+#[test]
+fn the_nearest_project_list_is_shown_inside_the_project() {
+    let mut harness = harness();
+    assert!(!descriptions(&harness).contains(&"ship it".to_string()));
+    assert!(harness.app.todo_project().is_none());
+
+    harness.command("cd project/src").unwrap();
+    assert_eq!(descriptions(&harness), ["ship it"]);
+    let project = harness.fixture.path("project");
+    assert_eq!(harness.app.todo_project(), Some(project.as_path()));
+    assert!(harness.screen().contains("To-Do List: "));
+
+    harness.command("cd ../../elsewhere").unwrap();
+    assert!(harness.app.todo_project().is_none());
+}
+
+#[test]
+fn changes_are_saved_when_leaving_the_project() {
+    let mut harness = harness();
+    harness.command("cd project").unwrap();
+    harness.app.todos.push(Todo::new("write docs"));
+    harness.command("cd ..").unwrap();
+    let saved = load_todos(&harness.fixture.path(&format!("project/{}", TODO_FILE)));
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[1].description, "write docs");
+}
+
+#[test]
+fn zt_switches_between_the_project_and_the_global_list() {
+    let mut harness = harness();
+    harness.command("cd project/src").unwrap();
+    harness.keys("zt").unwrap();
+    assert!(harness.app.todo_project().is_none());
+    assert!(!harness.app.project_todos);
+    harness.keys("zt").unwrap();
+    assert_eq!(descriptions(&harness), ["ship it"]);
+
+    // Outside of a project, a list is started in the current directory
+    harness.command("cd ../../elsewhere").unwrap();
+    harness.keys("zt").unwrap();
+    let elsewhere = harness.fixture.path("elsewhere");
+    assert_eq!(harness.app.todo_project(), Some(elsewhere.as_path()));
+    assert!(elsewhere.join(TODO_FILE).is_file());
+    assert!(harness.app.todos.is_empty());
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use termfm::harness::{Fixture, Harness};
+
+fn journal_path(pid: u32) -> PathBuf {
+    PathBuf::from(env::var("HOME").unwrap()).join(format!(".termfm_journal.{}.json", pid))
+}
+
+// A journal of `pid` moving `from` to `to`
+fn write_journal(pid: u32, from: PathBuf, to: PathBuf) {
+    let journal = serde_json::json!({
+        "operation": { "Moves": [[from, to]] },
+        "in_flight": null,
+    });
+    fs::write(journal_path(pid), journal.to_string()).unwrap();
+}
+
+// Journals live in the shared HOME, keep every test touching them in here
+#[test]
+fn only_journals_of_dead_instances_are_recovered() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("dead.txt", "").unwrap();
+    fixture.file("live.txt", "").unwrap();
+    fixture.dir("sorted").unwrap();
+    // Sets up the shared HOME
+    drop(Harness::new(Fixture::new().unwrap()).unwrap());
+
+    let mut running = Command::new("sleep").arg("30").spawn().unwrap();
+    let mut exited = Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    let live = running.id();
+    write_journal(
+        live,
+        fixture.path("live.txt"),
+        fixture.path("sorted/live.txt"),
+    );
+    write_journal(
+        exited.id(),
+        fixture.path("dead.txt"),
+        fixture.path("sorted/dead.txt"),
+    );
+
+    let mut harness = Harness::new(fixture).unwrap();
+    assert!(harness.app.state.recovery.is_some());
+    harness.keys("r").unwrap();
+    assert!(harness.app.state.recovery.is_none());
+    assert!(harness.fixture.path("sorted/dead.txt").exists());
+    assert!(!journal_path(exited.id()).exists());
+    assert!(!journal_path(std::process::id()).exists());
+    // The running instance's journal and files are left alone
+    assert!(journal_path(live).exists());
+    assert!(harness.fixture.path("live.txt").exists());
+
+    let harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    assert!(harness.app.state.recovery.is_none());
+    running.kill().unwrap();
+    running.wait().unwrap();
+    fs::remove_file(journal_path(live)).unwrap();
+}
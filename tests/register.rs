@@ -0,0 +1,107 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use termfm::fs_ops::copy_path;
+use termfm::fs_ops::Preserve;
+use termfm::harness::{Fixture, Harness};
+use termfm::register::{Register, RegisterOperation};
+
+#[test]
+fn copies_get_a_free_name_next_to_taken_ones() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "a").unwrap();
+    fixture.file("a (1).txt", "taken").unwrap();
+    let register = Register {
+        operation: RegisterOperation::Copy,
+        paths: vec![fixture.path("a.txt")],
+    };
+    let (pasted, errors, _) = register.plan(&fixture.root).run(Preserve::default());
+    assert_eq!(pasted, [(fixture.path("a.txt"), fixture.path("a (2).txt"))]);
+    assert!(errors.is_empty());
+    assert_eq!(fs::read_to_string(fixture.path("a (2).txt")).unwrap(), "a");
+}
+
+#[test]
+fn directories_are_not_pasted_into_themselves() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("dir/sub/file.txt", "").unwrap();
+    fixture.file("kept.txt", "").unwrap();
+    let register = Register {
+        operation: RegisterOperation::Cut,
+        paths: vec![
+            fixture.path("dir"),
+            fixture.path("gone.txt"),
+            fixture.path("kept.txt"),
+        ],
+    };
+    let paste = register.plan(&fixture.path("dir/sub"));
+    assert_eq!(
+        paste.files,
+        [(fixture.path("kept.txt"), fixture.path("dir/sub/kept.txt"))]
+    );
+    assert_eq!(paste.errors.len(), 2);
+    assert!(paste.errors[0].starts_with("Cannot paste"));
+    assert!(paste.errors[1].ends_with("gone.txt no longer exists"));
+
+    // Cutting into the same directory has nothing to do
+    assert!(register.plan(&fixture.root).files.is_empty());
+}
+
+#[test]
+#[cfg(unix)]
+fn trees_are_copied_with_their_links() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("src/deep/file.txt", "content").unwrap();
+    symlink("deep/file.txt", fixture.path("src/link")).unwrap();
+    copy_path(
+        &fixture.path("src"),
+        &fixture.path("dest"),
+        Preserve::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(fixture.path("dest/deep/file.txt")).unwrap(),
+        "content"
+    );
+    assert_eq!(
+        fs::read_link(fixture.path("dest/link")).unwrap(),
+        PathBuf::from("deep/file.txt")
+    );
+}
+
+// All harnesses of this binary share one HOME and so one register, keep every
+// test touching it in here
+#[test]
+fn the_register_is_shared_between_instances() {
+    let source = Fixture::new().unwrap();
+    source.file("report.txt", "data").unwrap();
+    source.file("notes.txt", "notes").unwrap();
+    let mut first = Harness::new(source).unwrap();
+    let mut second = Harness::new(Fixture::new().unwrap()).unwrap();
+
+    second.keys("P").unwrap();
+    assert!(second.last_message().starts_with("Nothing to paste"));
+
+    // Copies can be pasted again and again
+    first.keys("yy").unwrap();
+    assert_eq!(first.selected().as_deref(), Some("notes.txt"));
+    // The second time the name is taken
+    second.keys("PPr").unwrap();
+    assert_eq!(second.names(), ["notes (1).txt", "notes.txt"]);
+    assert!(first.fixture.path("notes.txt").exists());
+
+    // A cut moves the files once and empties the register
+    first.keys("mmx").unwrap();
+    assert!(first.app.state.marked.is_empty());
+    second.keys("Pr").unwrap();
+    assert_eq!(
+        second.names(),
+        ["notes (1).txt", "notes (2).txt", "notes.txt", "report.txt"]
+    );
+    assert_eq!(second.last_message(), "Moved 2 item(s)");
+    assert!(!first.fixture.path("report.txt").exists());
+    assert!(Register::load().is_none());
+}
@@ -0,0 +1,92 @@
+use termfm::harness::{Fixture, Harness};
+use termfm::repeat::RenamePattern;
+
+#[test]
+fn rename_patterns_carry_over() {
+    let prefix = RenamePattern::new("IMG_1.jpg", "2024_IMG_1.jpg");
+    assert_eq!(prefix.apply("IMG_7.jpg").as_deref(), Some("2024_IMG_7.jpg"));
+
+    let extension = RenamePattern::new("photo.jpg", "photo.jpeg");
+    assert_eq!(extension.apply("cat.jpg").as_deref(), Some("cat.jpeg"));
+    assert_eq!(extension.apply("cat.png"), None);
+
+    let insert = RenamePattern::new("a.txt", "a-old.txt");
+    assert_eq!(insert.apply("notes.txt").as_deref(), Some("notes-old.txt"));
+
+    let word = RenamePattern::new("report draft.txt", "report final.txt");
+    assert_eq!(
+        word.apply("letter draft.txt").as_deref(),
+        Some("letter final.txt")
+    );
+}
+
+#[test]
+fn dot_repeats_a_rename() {
+    let fixture = Fixture::new().unwrap();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fixture.file(name, "").unwrap();
+    }
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("rename a.md").unwrap();
+    harness.keys("j.").unwrap();
+    assert_eq!(harness.names(), ["a.md", "b.md", "c.txt"]);
+}
+
+#[test]
+fn repeated_renames_stay_in_each_files_directory() {
+    let fixture = Fixture::new().unwrap();
+    for name in ["a.txt", "c.txt", "sub/b.txt"] {
+        fixture.file(name, "").unwrap();
+    }
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("j").unwrap();
+    harness.command("rename a.md").unwrap();
+    // c.txt here and b.txt in sub are marked
+    harness.keys("Gm").unwrap();
+    harness.keys("ggl").unwrap();
+    harness.keys("m.").unwrap();
+    assert!(harness.fixture.path("sub/b.md").exists());
+    assert!(harness.fixture.path("c.md").exists());
+    assert!(!harness.fixture.path("b.md").exists());
+    assert!(!harness.fixture.path("sub/c.md").exists());
+}
+
+#[test]
+fn dot_repeats_commands_on_the_new_selection() {
+    let fixture = Fixture::new().unwrap();
+    for name in ["a", "b", "c"] {
+        fixture.file(name, "").unwrap();
+    }
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("delete").unwrap();
+    harness.keys("y").unwrap();
+    // Deleting again asks again
+    harness.keys("j.").unwrap();
+    assert_eq!(
+        harness.app.state.pending_delete,
+        Some(vec![harness.fixture.path("c")])
+    );
+    harness.keys("y").unwrap();
+    assert_eq!(harness.names(), ["b"]);
+}
+
+#[test]
+fn dot_moves_into_the_last_paste_target() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a", "").unwrap();
+    fixture.file("b", "").unwrap();
+    fixture.dir("target").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("jddklP").unwrap();
+    harness.keys("hj.").unwrap();
+    assert!(harness.fixture.path("target/a").exists());
+    assert!(harness.fixture.path("target/b").exists());
+    assert_eq!(harness.names(), ["target"]);
+}
+
+#[test]
+fn nothing_to_repeat_yet() {
+    let mut harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    harness.keys(".").unwrap();
+    assert_eq!(harness.last_message(), "Nothing to repeat");
+}
@@ -0,0 +1,48 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use termfm::harness::{Fixture, Harness};
+
+fn harness(files: usize) -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for n in 0..files {
+        fixture.file(&format!("file-{:02}.txt", n), "").unwrap();
+    }
+    Harness::new(fixture).unwrap()
+}
+
+#[test]
+fn small_terminals_get_a_placeholder() {
+    let mut harness = harness(3);
+    harness.resize(30, 8).unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains("Terminal too small (30x8)"));
+    assert!(!screen.contains("file-00.txt"));
+
+    harness.resize(80, 24).unwrap();
+    assert!(harness.screen().contains("file-00.txt"));
+}
+
+#[test]
+fn the_selection_stays_on_screen_when_it_shrinks() {
+    let mut harness = harness(40);
+    harness.keys("35j").unwrap();
+    assert!(harness.screen().contains("file-35.txt"));
+    harness.resize(80, 12).unwrap();
+    assert!(harness.screen().contains("file-35.txt"));
+    assert!(harness.app.clear_screen);
+
+    harness.keys("G").unwrap();
+    harness.resize(100, 40).unwrap();
+    assert!(harness.screen().contains("file-39.txt"));
+    assert!(harness.screen().contains("file-10.txt"));
+}
+
+#[test]
+fn ctrl_r_redraws_everything() {
+    let mut harness = harness(1);
+    harness.app.clear_screen = false;
+    harness
+        .events
+        .key(KeyCode::Char('r'), KeyModifiers::CONTROL);
+    harness.run().unwrap();
+    assert!(harness.app.clear_screen);
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::harness::{Fixture, Harness};
+
+// Stand-ins for dbus-send and xdg-open that append their arguments to
+// `bin/dbus` and `bin/opened`. There is no file manager on the bus for
+// paths with "nodbus" in them.
+fn fake_desktop(fixture: &Fixture) {
+    let bin = fixture.bin();
+    fixture
+        .program(
+            "dbus-send",
+            &format!(
+                "case \"$*\" in *nodbus*) exit 1 ;; esac\necho \"$*\" >> {}/dbus\n",
+                bin.display()
+            ),
+        )
+        .unwrap()
+        .program(
+            "xdg-open",
+            &format!("echo \"$*\" >> {}/opened\n", bin.display()),
+        )
+        .unwrap();
+}
+
+// xdg-open runs detached, so its arguments turn up a little later
+fn wait_for_line(file: &Path, expected: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        let lines = fs::read_to_string(file).unwrap_or_default();
+        if lines.lines().any(|line| line == expected) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn the_selection_is_revealed_through_the_file_manager_service() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("my report.pdf", "").unwrap();
+    fake_desktop(&fixture);
+    let bin = fixture.bin();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("reveal").unwrap();
+    let path = harness.fixture.path("my report.pdf");
+    assert_eq!(
+        harness.last_message(),
+        format!("Revealed {} in the file manager", path.display())
+    );
+    let calls = fs::read_to_string(bin.join("dbus")).unwrap();
+    let uri = format!(
+        "array:string:file://{}/my%20report.pdf",
+        harness.fixture.root.display()
+    );
+    assert!(calls
+        .lines()
+        .any(|line| line.contains("FileManager1.ShowItems") && line.contains(&uri)));
+}
+
+#[test]
+fn without_the_service_the_directory_is_opened() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("nodbus/notes.txt", "").unwrap();
+    fake_desktop(&fixture);
+    let bin = fixture.bin();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("cd nodbus").unwrap();
+    harness.command("reveal").unwrap();
+    let dir = harness.fixture.path("nodbus");
+    assert!(wait_for_line(
+        &bin.join("opened"),
+        &dir.display().to_string()
+    ));
+}
+
+#[test]
+fn the_current_directory_is_opened_with_g_o() {
+    let fixture = Fixture::new().unwrap();
+    fixture.dir("photos").unwrap();
+    fake_desktop(&fixture);
+    let bin = fixture.bin();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("cd photos").unwrap();
+    harness.keys("gO").unwrap();
+    harness.settle().unwrap();
+    let dir = harness.fixture.path("photos");
+    assert_eq!(
+        harness.last_message(),
+        format!("Opened {} in the file manager", dir.display())
+    );
+    assert!(wait_for_line(
+        &bin.join("opened"),
+        &dir.display().to_string()
+    ));
+
+    harness.command("reveal missing").unwrap();
+    assert!(harness.last_message().starts_with("Not found: "));
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::time::{Duration, Instant};
+use termfm::app::Notifications;
+use termfm::harness::{Fixture, Harness};
+use termfm::vfs::{parse_list_objects, Location, RemoteEntry, S3Config};
+
+const LISTING: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>photos</Name><Prefix>2024/</Prefix><IsTruncated>false</IsTruncated>
+  <Contents><Key>2024/</Key><Size>0</Size></Contents>
+  <Contents><Key>2024/notes &amp; plans.txt</Key><Size>11</Size></Contents>
+  <CommonPrefixes><Prefix>2024/summer/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+
+// A stand-in curl that answers every listing with `LISTING`, downloads
+// every object as "object body" and keeps its arguments in `bin/args`, the
+// config it is given on stdin in `bin/config`
+fn fake_curl(fixture: &Fixture) {
+    let bin = fixture.bin();
+    let script = format!(
+        "echo \"$*\" >> {bin}/args\nout=\n\
+         while [ $# -gt 0 ]; do\n  [ \"$1\" = -o ] && out=$2\n  \
+         [ \"$1\" = -K ] && cat >> {bin}/config\n  shift\ndone\n\
+         if [ -n \"$out\" ]; then printf 'object body' > \"$out\"\n\
+         else cat {bin}/listing.xml; fi\n",
+        bin = bin.display()
+    );
+    fixture.program("curl", &script).unwrap();
+    fs::write(bin.join("listing.xml"), LISTING).unwrap();
+}
+
+#[test]
+fn keys_are_listed_a_level_at_a_time() {
+    let (entries, next) = parse_list_objects(LISTING, "2024/");
+    assert_eq!(
+        entries,
+        [
+            RemoteEntry {
+                name: "summer".to_string(),
+                is_dir: true,
+                is_symlink: false,
+                size: 0,
+            },
+            RemoteEntry {
+                name: "notes & plans.txt".to_string(),
+                is_dir: false,
+                is_symlink: false,
+                size: 11,
+            },
+        ]
+    );
+    assert_eq!(next, None);
+
+    let truncated = "<IsTruncated>true</IsTruncated>\
+                     <NextContinuationToken>1/abc=</NextContinuationToken>";
+    assert_eq!(
+        parse_list_objects(truncated, "").1.as_deref(),
+        Some("1/abc=")
+    );
+}
+
+#[test]
+fn keys_below_the_level_are_left_out() {
+    let xml = "<Contents><Key>2024/a/../../x</Key></Contents>\
+               <Contents><Key>2024/sub/deep.txt</Key></Contents>\
+               <Contents><Key>2024/..</Key></Contents>\
+               <CommonPrefixes><Prefix>2024/../</Prefix></CommonPrefixes>\
+               <Contents><Key>2024/fine.txt</Key></Contents>";
+    let names: Vec<String> = parse_list_objects(xml, "2024/")
+        .0
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    assert_eq!(names, ["fine.txt"]);
+}
+
+#[test]
+fn the_endpoint_and_credentials_are_configured() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.config_path(),
+        "[s3]\nendpoint = \"https://minio.local\"\nregion = \"eu-west-1\"\nbucket = \"x\"\nsecret_key = 1\n",
+    )
+    .unwrap();
+    let mut notifications = Notifications::default();
+    let config = S3Config::from_config(&fixture.config_path(), &mut notifications);
+    assert_eq!(config.endpoint.as_deref(), Some("https://minio.local"));
+    assert_eq!(config.region.as_deref(), Some("eu-west-1"));
+    assert_eq!(config.secret_key, None);
+    assert_eq!(notifications.log.len(), 2);
+}
+
+#[test]
+fn buckets_are_browsed_with_signed_requests() {
+    let fixture = Fixture::new().unwrap();
+    fs::write(
+        fixture.config_path(),
+        "[s3]\nendpoint = \"https://minio.local/\"\nregion = \"eu-west-1\"\n\
+         access_key = \"KEY\"\nsecret_key = \"SECRET\"\n",
+    )
+    .unwrap();
+    fake_curl(&fixture);
+    let bin = fixture.bin();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys(":cd s3://photos/2024").unwrap();
+    harness.press(crossterm::event::KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.names(), ["summer", "notes & plans.txt"]);
+
+    // Small objects are previewed, once the preview worker has fetched them
+    harness.keys("j").unwrap();
+    let mirror = Location::parse("s3://photos/2024").unwrap().mirror();
+    let local = mirror.join("notes & plans.txt");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while harness.app.preview_cache.as_ref().map(|(path, _)| path) != Some(&local) {
+        assert!(Instant::now() < deadline, "no preview");
+        harness.settle().unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(mirror.join("notes & plans.txt")).unwrap(),
+        "object body"
+    );
+
+    let args = fs::read_to_string(bin.join("args")).unwrap();
+    let lines: Vec<&str> = args.lines().collect();
+    assert!(
+        lines[0].ends_with("https://minio.local/photos?delimiter=%2F&list-type=2&prefix=2024%2F")
+    );
+    assert!(lines[1].ends_with("https://minio.local/photos/2024/notes%20%26%20plans.txt"));
+    assert!(lines
+        .iter()
+        .all(|line| line.contains("--aws-sigv4 aws:amz:eu-west-1:s3") && line.contains("-K -")));
+    // The secret key stays out of the arguments
+    assert!(!args.contains("SECRET"));
+    let config = fs::read_to_string(bin.join("config")).unwrap();
+    assert!(config.lines().all(|line| line == "user = \"KEY:SECRET\""));
+}
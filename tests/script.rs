@@ -0,0 +1,154 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use termfm::app::Notifications;
+use termfm::config::Programs;
+use termfm::harness::{Fixture, Harness};
+use termfm::previewers::PreviewerRegistry;
+use termfm::script::{Scripts, SCRIPT_FILE};
+
+const SCRIPT: &str = r#"
+fn on_startup() { notify("started"); }
+fn on_dir_change(dir) { notify(`entered ${dir}`); }
+fn on_select(path) { notify(`selected ${path}`); }
+
+command("greet", |ctx| notify(`hello ${ctx.args} from ${ctx.dir}`));
+command("up", |ctx| run(":cd .."));
+bind("ctrl-g", "greet keys");
+previewer([".CSV"], |path| ["rows of", path]);
+previewer([], |path| ());
+status(|ctx| if ctx.selected.is_empty() { "" } else { `${ctx.selected.len()} picked` });
+"#;
+
+fn fixture(script: &str) -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "a").unwrap();
+    fixture.file("table.csv", "a,b\n").unwrap();
+    fs::write(fixture.config_path().with_file_name(SCRIPT_FILE), script).unwrap();
+    fixture
+}
+
+fn messages(harness: &Harness) -> Vec<String> {
+    harness
+        .app
+        .state
+        .notifications
+        .log
+        .iter()
+        .map(|n| n.message.clone())
+        .collect()
+}
+
+#[test]
+fn hooks_are_called_when_things_happen() {
+    let harness = Harness::new(fixture(SCRIPT)).unwrap();
+    let root = harness.fixture.root.display().to_string();
+    assert_eq!(
+        messages(&harness),
+        [
+            "started".to_string(),
+            format!("entered {}", root),
+            format!("selected {}/a.txt", root),
+        ]
+    );
+}
+
+#[test]
+fn commands_and_keys_call_the_script() {
+    let mut harness = Harness::new(fixture(SCRIPT)).unwrap();
+    let root = harness.fixture.root.clone();
+    harness.command("greet there").unwrap();
+    assert_eq!(
+        messages(&harness).last().unwrap(),
+        &format!("hello there from {}", root.display())
+    );
+
+    harness
+        .events
+        .key(KeyCode::Char('g'), KeyModifiers::CONTROL);
+    harness.run().unwrap();
+    assert_eq!(
+        messages(&harness).last().unwrap(),
+        &format!("hello keys from {}", root.display())
+    );
+
+    harness.command("up").unwrap();
+    assert_eq!(harness.current_dir(), root.parent().unwrap());
+}
+
+#[test]
+fn status_segments_show_what_the_script_returns() {
+    let mut harness = Harness::new(fixture(SCRIPT)).unwrap();
+    assert!(!harness.screen().contains("picked"));
+    harness.keys("m").unwrap();
+    assert!(harness.screen().contains("1 picked"));
+}
+
+#[test]
+fn previewers_go_before_the_built_in_ones() {
+    let fixture = fixture(SCRIPT);
+    let mut notifications = Notifications::default();
+    let scripts = Scripts::load(&fixture.config_path(), &mut notifications);
+    let mut registry = PreviewerRegistry::default();
+    scripts.register_previewers(&mut registry);
+    assert_eq!(
+        registry.ids(),
+        [
+            "init.rhai previewer 1",
+            "init.rhai previewer 2",
+            "document",
+            "media",
+            "photo",
+            "text"
+        ]
+    );
+
+    let (programs, cancel) = (Programs::default(), AtomicBool::new(false));
+    let path = fixture.path("table.csv");
+    let preview = registry.preview(&path, &programs, &cancel);
+    assert_eq!(
+        preview.lines,
+        ["rows of".to_string(), path.display().to_string()]
+    );
+    // Returning nothing passes the file on
+    let preview = registry.preview(&fixture.path("a.txt"), &programs, &cancel);
+    assert!(preview.lines[0].ends_with(" a"));
+}
+
+#[test]
+fn script_errors_are_reported() {
+    let mut notifications = Notifications::default();
+    let broken = fixture("fn on_startup( {");
+    Scripts::load(&broken.config_path(), &mut notifications);
+    let registered = fixture("command(\"cd\", |ctx| ());\nbind(\"ctrl-gg\", \"greet\");\n");
+    Scripts::load(&registered.config_path(), &mut notifications);
+    let errors: Vec<&str> = notifications
+        .log
+        .iter()
+        .map(|n| n.message.as_str())
+        .collect();
+    assert!(errors[0].starts_with("Failed to load"));
+    assert_eq!(
+        errors[1..],
+        [
+            "init.rhai: command cd is taken by the built-in :cd",
+            "init.rhai: bind ctrl-gg: keys are a character, ctrl-<c> or alt-<c>",
+        ]
+    );
+
+    // A command running itself, and a loop, are stopped
+    let mut harness = Harness::new(fixture(
+        "command(\"again\", |ctx| run(\"again\"));\ncommand(\"spin\", |ctx| loop {});\n",
+    ))
+    .unwrap();
+    harness.command("again").unwrap();
+    assert_eq!(
+        messages(&harness).last().unwrap(),
+        "Scripts run commands more than 10 deep"
+    );
+    harness.command("spin").unwrap();
+    assert!(messages(&harness)
+        .last()
+        .unwrap()
+        .starts_with(":spin failed"));
+}
@@ -0,0 +1,75 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+use termfm::ui::{Scrollbar, Style};
+
+fn harness(files: usize) -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for n in 0..files {
+        fixture.file(&format!("file-{:02}.txt", n), "").unwrap();
+    }
+    let lines: Vec<String> = (0..200).map(|n| format!("line {}", n)).collect();
+    fixture.file("long.txt", &lines.join("\n")).unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+// The rows of the screen that hold a thumb
+fn thumb_rows(harness: &Harness) -> Vec<usize> {
+    harness
+        .screen()
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains('┃'))
+        .map(|(row, _)| row)
+        .collect()
+}
+
+#[test]
+fn the_thumb_follows_the_offset() {
+    let bar = |offset| Scrollbar {
+        offset,
+        rows: 100,
+        style: Style::default(),
+    };
+    assert_eq!(bar(0).thumb(10), Some((0, 1)));
+    assert_eq!(bar(45).thumb(10), Some((5, 1)));
+    assert_eq!(bar(90).thumb(10), Some((9, 1)));
+    assert_eq!(bar(500).thumb(10), Some((9, 1)));
+    let fits = Scrollbar {
+        offset: 0,
+        rows: 10,
+        style: Style::default(),
+    };
+    assert_eq!(fits.thumb(10), None);
+}
+
+#[test]
+fn long_listings_get_a_scrollbar() {
+    let few = harness(3);
+    assert!(!few.screen().contains('┃'));
+
+    // Stopping short of long.txt, whose preview would have one too
+    let mut many = harness(60);
+    let top = thumb_rows(&many);
+    assert!(!top.is_empty());
+    many.keys("Gk").unwrap();
+    let bottom = thumb_rows(&many);
+    assert!(!bottom.is_empty());
+    assert!(bottom[0] > top[0]);
+}
+
+#[test]
+fn long_previews_and_the_pager_get_one() {
+    let mut harness = harness(3);
+    harness.keys("G").unwrap();
+    while harness.app.preview_cache.is_none() {
+        harness.settle().unwrap();
+    }
+    assert!(harness.screen().lines().any(|line| line.ends_with('┃')));
+
+    harness.keys("v").unwrap();
+    let top = thumb_rows(&harness);
+    harness.press(KeyCode::End).unwrap();
+    let bottom = thumb_rows(&harness);
+    assert!(!top.is_empty());
+    assert!(bottom[0] > top[0]);
+}
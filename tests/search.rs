@@ -0,0 +1,91 @@
+use termfm::harness::{Fixture, Harness};
+use termfm::ui::{Modifier, Style};
+
+// What `/beach` leaves behind, with the listing filtered or not
+fn harness(filtered: bool) -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for name in [
+        "beach.jpg",
+        "city.jpg",
+        "dunes.jpg",
+        "old-beach.png",
+        "sea.jpg",
+    ] {
+        fixture.file(name, "").unwrap();
+    }
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.app.search_query = "beach".to_string();
+    if filtered {
+        harness
+            .app
+            .state
+            .files
+            .retain(|entry| entry.name.contains("beach"));
+        harness.app.search_filtered = true;
+    }
+    harness.settle().unwrap();
+    harness
+}
+
+fn message(harness: &Harness) -> String {
+    harness
+        .app
+        .state
+        .notifications
+        .log
+        .back()
+        .map(|n| n.message.clone())
+        .unwrap_or_default()
+}
+
+#[test]
+fn n_and_shift_n_jump_between_matches() {
+    let mut harness = harness(false);
+    harness.keys("n").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("old-beach.png"));
+    assert_eq!(message(&harness), "Match 2 of 2 for beach");
+    harness.keys("n").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("beach.jpg"));
+    assert_eq!(
+        message(&harness),
+        "Match 1 of 2 for beach, continued at the top"
+    );
+    harness.keys("N").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("old-beach.png"));
+    harness.keys("3N").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("beach.jpg"));
+
+    harness.app.search_query = "forest".to_string();
+    harness.keys("n").unwrap();
+    assert_eq!(message(&harness), "No match for forest");
+}
+
+#[test]
+fn n_lists_a_filtered_directory_in_full() {
+    let mut harness = harness(true);
+    assert_eq!(harness.names(), ["beach.jpg", "old-beach.png"]);
+    assert!(harness.screen().contains("filter: beach"));
+    harness.keys("n").unwrap();
+    assert_eq!(harness.names().len(), 5);
+    assert_eq!(harness.selected().as_deref(), Some("old-beach.png"));
+    assert!(!harness.app.search_filtered);
+    assert!(harness.screen().contains("search: beach"));
+}
+
+#[test]
+fn matches_are_highlighted_in_the_names() {
+    let harness = harness(false);
+    let screen = harness.screen();
+    let (row, line) = screen
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.contains("old-beach.png"))
+        .unwrap();
+    let column = line.chars().position(|c| c == 'o').unwrap();
+    let buffer = harness.terminal.backend().buffer();
+    let style = |offset: usize| buffer[((column + offset) as u16, row as u16)].style();
+    let underlined = |style: Style| style.add_modifier.contains(Modifier::UNDERLINED);
+    assert!(!underlined(style(0)));
+    assert!((4..9).all(|offset| underlined(style(offset))));
+    assert!(!underlined(style(9)));
+}
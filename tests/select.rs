@@ -0,0 +1,63 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+use termfm::select::NamePattern;
+
+fn logs() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for name in [
+        "app.log",
+        "error.LOG",
+        "notes.txt",
+        "IMG_001.jpg",
+        "IMG_a.jpg",
+    ] {
+        fixture.file(name, "").unwrap();
+    }
+    Harness::new(fixture).unwrap()
+}
+
+fn marked(harness: &Harness) -> Vec<String> {
+    let mut names: Vec<String> = harness
+        .app
+        .state
+        .marked
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn globs_and_regexes() {
+    let glob = NamePattern::parse("*.log").unwrap();
+    assert!(glob.matches("error.LOG"));
+    assert!(!glob.matches("log.txt"));
+    assert!(NamePattern::parse("note").unwrap().matches("notes.txt"));
+
+    let regex = NamePattern::parse(r"/^IMG_\d+/").unwrap();
+    assert!(regex.matches("IMG_001.jpg"));
+    assert!(!regex.matches("IMG_a.jpg"));
+    assert!(NamePattern::parse("/(/").is_err());
+}
+
+#[test]
+fn star_marks_the_matching_entries() {
+    let mut harness = logs();
+    harness.keys("**.log").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert_eq!(marked(&harness), ["app.log", "error.LOG"]);
+
+    harness.keys(r":select /^IMG_\d/").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert_eq!(marked(&harness), ["IMG_001.jpg", "app.log", "error.LOG"]);
+}
+
+#[test]
+fn the_inverse_marks_the_others() {
+    let mut harness = logs();
+    harness.keys(":select! *.jpg").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert_eq!(marked(&harness), ["app.log", "error.LOG", "notes.txt"]);
+    assert_eq!(harness.last_message(), "Marked 3 item(s)");
+}
@@ -0,0 +1,105 @@
+use crossterm::event::KeyCode;
+use std::env;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::harness::{Fixture, Harness};
+
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    fixture.file("b.txt", "").unwrap();
+    fixture.file("c.txt", "").unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+// Types the command at the `!` prompt and waits for it to finish
+fn shell(harness: &mut Harness, line: &str) -> Vec<String> {
+    harness.keys(&format!("!{}", line)).unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while harness.app.shell.is_some() && Instant::now() < deadline {
+        harness.app.tick();
+        thread::sleep(Duration::from_millis(5));
+    }
+    harness.settle().unwrap();
+    harness.app.state.command_output.clone().unwrap().1
+}
+
+#[test]
+fn output_is_read_into_the_panel() {
+    let mut harness = harness();
+    let lines = shell(&mut harness, "pwd; echo oops >&2; exit 3");
+    let root = harness.fixture.root.canonicalize().unwrap();
+    // stdout and stderr are read side by side, in no set order
+    assert!(lines.contains(&root.display().to_string()));
+    assert!(lines.contains(&"oops".to_string()));
+    assert!(lines
+        .last()
+        .unwrap()
+        .ends_with("exited with exit status: 3"));
+    assert!(harness
+        .screen()
+        .contains("!pwd; echo oops >&2; exit 3 (Esc closes)"));
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.state.command_output.is_none());
+}
+
+#[test]
+fn placeholders_are_the_selected_files() {
+    let mut harness = harness();
+    harness.keys("j").unwrap();
+    assert_eq!(harness.selected().as_deref(), Some("b.txt"));
+    assert_eq!(shell(&mut harness, "basename %f"), ["b.txt"]);
+    harness.press(KeyCode::Esc).unwrap();
+
+    harness.keys("mm").unwrap();
+    let lines = shell(&mut harness, "for f in %s; do basename \"$f\"; done");
+    assert_eq!(lines, ["b.txt", "c.txt"]);
+}
+
+#[test]
+fn long_output_scrolls_and_running_commands_stop() {
+    let mut harness = harness();
+    let lines = shell(&mut harness, "seq 100");
+    assert_eq!(lines.len(), 100);
+    assert!(harness.screen().contains("100"));
+    harness.keys("g").unwrap();
+    assert_eq!(harness.app.state.output_scroll, Some(0));
+    assert!(harness.screen().contains("│1 "));
+    harness.keys("G").unwrap();
+    assert_eq!(harness.app.state.output_scroll, None);
+    harness.press(KeyCode::Esc).unwrap();
+
+    harness.keys("!echo started; sleep 10").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert!(harness.app.shell.is_some());
+    assert!(harness.screen().contains("(running, Esc stops)"));
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.shell.is_none());
+    let (_, lines) = harness.app.state.command_output.clone().unwrap();
+    assert_eq!(lines.last().unwrap(), "echo started; sleep 10 stopped");
+}
+
+// The only test here that sets SHELL, `!` runs `sh` whatever it says
+#[test]
+fn s_starts_the_shell_in_the_current_directory() {
+    let fixture = Fixture::new().unwrap();
+    fixture.dir("sub").unwrap();
+    let cwd = fixture.bin().join("cwd");
+    fixture
+        .program(
+            "shell",
+            &format!("pwd > {}\ntouch made.txt\n", cwd.display()),
+        )
+        .unwrap();
+    env::set_var("SHELL", fixture.bin().join("shell"));
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("lS").unwrap();
+    assert_eq!(
+        fs::read_to_string(&cwd).unwrap().trim_end(),
+        harness.fixture.path("sub").to_string_lossy()
+    );
+    // What the shell did is listed once it exits
+    assert_eq!(harness.names(), ["made.txt"]);
+}
@@ -0,0 +1,110 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::app::SplitDirection;
+use termfm::cli::StartupOptions;
+use termfm::harness::{Fixture, Harness};
+use termfm::sync::{plan_sync, SyncAction, SyncStep};
+
+// `src` to sync into `dest`, which has one file the same, one stale and one
+// that is gone from `src`
+fn trees() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("src/same.txt", "same").unwrap();
+    fixture.file("src/changed.txt", "new contents").unwrap();
+    fixture.file("src/sub/new.txt", "new").unwrap();
+    fixture.dir("dest").unwrap();
+    fs::copy(fixture.path("src/same.txt"), fixture.path("dest/same.txt")).unwrap();
+    let modified = fs::metadata(fixture.path("src/same.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    let file = fs::File::options()
+        .write(true)
+        .open(fixture.path("dest/same.txt"))
+        .unwrap();
+    file.set_modified(modified).unwrap();
+    fixture.file("dest/changed.txt", "old").unwrap();
+    fixture.file("dest/gone/old.txt", "old").unwrap();
+    fixture
+}
+
+fn step(action: SyncAction, path: &str, is_dir: bool, size: u64) -> SyncStep {
+    SyncStep {
+        action,
+        path: PathBuf::from(path),
+        is_dir,
+        size,
+    }
+}
+
+#[test]
+fn the_plan_lists_new_changed_and_deleted_files() {
+    let fixture = trees();
+    let plan = plan_sync(&fixture.path("src"), &fixture.path("dest")).unwrap();
+    assert_eq!(
+        plan,
+        [
+            step(SyncAction::Delete, "gone", true, 0),
+            step(SyncAction::Update, "changed.txt", false, 12),
+            step(SyncAction::Create, "sub", true, 0),
+            step(SyncAction::Create, "sub/new.txt", false, 3),
+        ]
+    );
+}
+
+#[test]
+fn the_other_pane_is_synced_after_review() {
+    let fixture = trees();
+    let options = StartupOptions {
+        split: Some(SplitDirection::Vertical),
+        right: Some(fixture.path("dest")),
+        ..Default::default()
+    };
+    let mut harness = Harness::with_options(fixture, options, 80, 24).unwrap();
+    harness.command("cd src").unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("src"));
+    harness.command("sync").unwrap();
+    assert!(harness.screen().contains("2 new, 1 changed, 1 deleted"));
+
+    harness.press(KeyCode::Enter).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while harness.app.state.sync.is_some() && Instant::now() < deadline {
+        harness.app.tick();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(harness.last_message().starts_with("Synced "));
+    let read = |path: &str| fs::read_to_string(harness.fixture.path(path)).unwrap();
+    assert_eq!(read("dest/changed.txt"), "new contents");
+    assert_eq!(read("dest/sub/new.txt"), "new");
+    assert!(!harness.fixture.path("dest/gone").exists());
+    let other = &harness.app.state.split.as_ref().unwrap().other;
+    assert_eq!(other.files.len(), 3);
+
+    // Copies keep their times, so there is nothing left to do
+    harness.command("sync").unwrap();
+    assert_eq!(harness.last_message(), "Already in sync");
+}
+
+#[test]
+fn a_plan_can_be_turned_down() {
+    let fixture = trees();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("sync").unwrap();
+    assert_eq!(harness.last_message(), "Usage: :sync <target directory>");
+
+    harness.command("cd src").unwrap();
+    harness.command("sync ../dest").unwrap();
+    assert!(harness.app.state.sync.is_some());
+    harness.press(KeyCode::Esc).unwrap();
+    assert!(harness.app.state.sync.is_none());
+    assert!(harness.fixture.path("dest/gone/old.txt").exists());
+
+    harness.command("sync sub").unwrap();
+    assert_eq!(
+        harness.last_message(),
+        "Cannot sync a directory with one inside it"
+    );
+}
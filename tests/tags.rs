@@ -0,0 +1,82 @@
+use crossterm::event::KeyCode;
+use std::fs;
+use termfm::harness::{Fixture, Harness};
+
+#[test]
+fn tags_are_saved_next_to_the_config() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    fixture.file("b.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("mm").unwrap();
+    harness.command("tag important").unwrap();
+    assert_eq!(harness.last_message(), "Tagged 2 item(s) important");
+    harness.keys("t").unwrap();
+    assert_eq!(harness.app.state.prompt.as_ref().unwrap().input, "tag ");
+    harness
+        .keys("later")
+        .unwrap()
+        .press(KeyCode::Enter)
+        .unwrap();
+    assert!(harness.screen().contains("●● b.txt"));
+
+    let a = harness.fixture.path("a.txt");
+    let b = harness.fixture.path("b.txt");
+    let saved =
+        fs::read_to_string(harness.fixture.config_path().with_file_name("tags.json")).unwrap();
+    let saved: serde_json::Value = serde_json::from_str(&saved).unwrap();
+    assert_eq!(saved[a.to_str().unwrap()], serde_json::json!(["important"]));
+    assert_eq!(
+        saved[b.to_str().unwrap()],
+        serde_json::json!(["important", "later"])
+    );
+
+    // A new session picks them up
+    let harness = Harness::new(harness.fixture).unwrap();
+    assert_eq!(
+        harness.app.tags.get(&b).collect::<Vec<_>>(),
+        ["important", "later"]
+    );
+}
+
+#[test]
+fn listings_can_be_filtered_by_tag() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("a.txt", "").unwrap();
+    fixture.file("b.txt", "").unwrap();
+    fixture.file("c.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("mjm").unwrap();
+    harness.command("tag work").unwrap();
+    harness.command("tagged work").unwrap();
+    assert_eq!(harness.names(), ["a.txt", "c.txt"]);
+    assert!(harness.screen().contains("tag: work"));
+
+    harness.command("tagged").unwrap();
+    assert_eq!(harness.names(), ["a.txt", "b.txt", "c.txt"]);
+    assert_eq!(harness.last_message(), "Tags: work (2)");
+}
+
+#[test]
+fn tags_follow_renames_and_go_with_deletes() {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("dir/inner.txt", "").unwrap();
+    fixture.file("old.txt", "").unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.command("tag keep").unwrap();
+    harness.keys("l").unwrap();
+    harness.command("tag keep").unwrap();
+    harness.keys("h").unwrap();
+    harness.command("rename moved").unwrap();
+    let inner = harness.fixture.path("moved/inner.txt");
+    assert!(harness.app.tags.has(&harness.fixture.path("moved"), "keep"));
+    assert!(harness.app.tags.has(&inner, "keep"));
+
+    assert_eq!(harness.selected().as_deref(), Some("moved"));
+    harness.command("untag keep").unwrap();
+    assert!(!harness.app.tags.has(&harness.fixture.path("moved"), "keep"));
+    assert!(harness.app.tags.has(&inner, "keep"));
+    harness.command("delete").unwrap();
+    harness.keys("y").unwrap();
+    assert!(harness.app.tags.files.is_empty());
+}
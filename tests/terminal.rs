@@ -0,0 +1,124 @@
+// Job control and the terminal modes need a unix pseudo-terminal
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+use termfm::harness::{Fixture, Pty};
+use termfm::terminal::{install_panic_hook, TerminalGuard};
+
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?1049l";
+
+// The real binary on a pseudo-terminal, once it has drawn the listing
+fn start(fixture: &Fixture) -> (Pty, Child) {
+    fixture.file("marker.txt", "").unwrap().dir("home").unwrap();
+    fs::write(fixture.config_path(), "").unwrap();
+    let pty = Pty::new().unwrap();
+    let child = pty
+        .command(env!("CARGO_BIN_EXE_termfm"))
+        .unwrap()
+        .arg("--config")
+        .arg(fixture.config_path())
+        .arg("--cwd-file")
+        .arg(fixture.path("home/cwd"))
+        .arg(&fixture.root)
+        .env("HOME", fixture.path("home"))
+        .env("TERM", "xterm-256color")
+        .spawn()
+        .unwrap();
+    assert!(pty.wait_for(|output| output.contains("marker.txt")));
+    (pty, child)
+}
+
+fn signal(child: &Child, signal: i32) {
+    unsafe { libc::kill(child.id() as i32, signal) };
+}
+
+// Whether the child stops before long
+fn stopped(child: &Child) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        let mut status = 0;
+        let pid = unsafe {
+            libc::waitpid(
+                child.id() as i32,
+                &mut status,
+                libc::WUNTRACED | libc::WNOHANG,
+            )
+        };
+        if pid > 0 && libc::WIFSTOPPED(status) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn a_stop_from_outside_hands_the_terminal_back_until_continued() {
+    let fixture = Fixture::new().unwrap();
+    let (pty, mut child) = start(&fixture);
+    assert!(pty.is_raw());
+
+    signal(&child, libc::SIGTSTP);
+    assert!(stopped(&child));
+    assert!(!pty.is_raw());
+    assert_eq!(pty.output().matches(LEAVE_ALTERNATE_SCREEN).count(), 1);
+
+    signal(&child, libc::SIGCONT);
+    assert!(pty.wait_for(|output| output.matches(ENTER_ALTERNATE_SCREEN).count() == 2));
+    assert!(pty.is_raw());
+    signal(&child, libc::SIGTERM);
+    assert!(child.wait().unwrap().success());
+}
+
+// Killed or hung up on, it still saves the to-dos and remembers the directory
+#[test]
+fn termination_signals_shut_down_cleanly() {
+    for sent in [libc::SIGTERM, libc::SIGHUP] {
+        let fixture = Fixture::new().unwrap();
+        let (pty, mut child) = start(&fixture);
+        signal(&child, sent);
+        assert!(child.wait().unwrap().success());
+        assert!(!pty.is_raw());
+        assert_eq!(
+            fs::read_to_string(fixture.path("home/cwd")).unwrap(),
+            fixture.root.to_string_lossy()
+        );
+        assert!(fixture.path("home/.termfm_todo.json").exists());
+    }
+}
+
+// Panics with the TUI up when run by `a_panic_is_reported_on_the_normal_screen`,
+// as a process of its own on the pseudo-terminal
+#[test]
+fn panic_with_the_tui_up() {
+    if env::var_os("TERMFM_PANIC_TEST").is_none() {
+        return;
+    }
+    install_panic_hook();
+    let _guard = TerminalGuard::new().unwrap();
+    panic!("on purpose");
+}
+
+#[test]
+fn a_panic_is_reported_on_the_normal_screen() {
+    let pty = Pty::new().unwrap();
+    let status = pty
+        .command(env::current_exe().unwrap())
+        .unwrap()
+        .args(["--exact", "panic_with_the_tui_up", "--nocapture"])
+        .env("TERMFM_PANIC_TEST", "1")
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    assert!(pty.wait_for(|output| output.contains("on purpose")));
+    assert!(!pty.is_raw());
+    let output = pty.output();
+    // Not drawn over by the alternate screen
+    let left = output.find(LEAVE_ALTERNATE_SCREEN).unwrap();
+    assert!(left < output.find("on purpose").unwrap());
+}
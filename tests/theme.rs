@@ -0,0 +1,165 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::{symlink, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use termfm::app::Notifications;
+use termfm::config::ColorDepth;
+use termfm::fs_ops::Entry;
+use termfm::harness::{Fixture, Harness};
+use termfm::theme::{EntryKind, LsColors, Theme};
+use termfm::ui::{Color, Modifier, Style};
+
+fn load(fixture: &Fixture, config: &str) -> (Theme, Notifications) {
+    fs::write(fixture.config_path(), config).unwrap();
+    let mut notifications = Notifications::default();
+    let theme = Theme::from_config(
+        &fixture.config_path(),
+        ColorDepth::TrueColor,
+        &mut notifications,
+    );
+    (theme, notifications)
+}
+
+#[test]
+fn presets_exist() {
+    for name in Theme::PRESETS {
+        assert!(Theme::preset(name).is_some(), "{}", name);
+    }
+    assert!(Theme::preset("neon").is_none());
+}
+
+#[test]
+fn settings_override_the_preset() {
+    let fixture = Fixture::new().unwrap();
+    let (theme, notifications) = load(
+        &fixture,
+        "[theme]\npreset = \"gruvbox\"\nhighlight = \"#ff0000\"\ndirectory = \"cyan\"\n",
+    );
+    assert_eq!(theme.highlight, Color::Rgb(255, 0, 0));
+    assert_eq!(theme.directory, Color::Cyan);
+    assert_eq!(theme.file, Theme::preset("gruvbox").unwrap().file);
+    assert!(notifications.current().is_none());
+}
+
+#[test]
+fn mistakes_are_reported() {
+    let fixture = Fixture::new().unwrap();
+    let (theme, notifications) = load(
+        &fixture,
+        "[theme]\npreset = \"neon\"\nborder = \"sparkly\"\nborders = \"red\"\n",
+    );
+    assert_eq!(theme.border, Theme::default().border);
+    assert_eq!(notifications.log.len(), 3);
+}
+
+#[test]
+fn colors_are_adapted_to_the_terminal() {
+    let theme = Theme::preset("solarized")
+        .unwrap()
+        .adapt(ColorDepth::Ansi256);
+    assert!(matches!(theme.highlight, Color::Indexed(_)));
+}
+
+#[test]
+fn symbols_are_drawn() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .file("a.txt", "")
+        .unwrap()
+        .file("b.txt", "")
+        .unwrap();
+    fs::write(
+        fixture.config_path(),
+        "[theme]\nhighlight_symbol = \"-> \"\nmark_symbol = \"+\"\n",
+    )
+    .unwrap();
+    let mut harness = Harness::new(fixture).unwrap();
+    harness.keys("m").unwrap();
+    let screen = harness.screen();
+    assert!(screen.contains("   +a.txt"), "{}", screen);
+    assert!(screen.contains("-> b.txt"), "{}", screen);
+}
+
+#[test]
+#[cfg(unix)]
+fn entries_are_told_apart_by_kind() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .dir("dir")
+        .unwrap()
+        .file("plain", "")
+        .unwrap()
+        .file("tool", "")
+        .unwrap()
+        .file("su", "")
+        .unwrap();
+    fs::set_permissions(fixture.path("tool"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::set_permissions(fixture.path("su"), fs::Permissions::from_mode(0o4755)).unwrap();
+    symlink(fixture.path("plain"), fixture.path("link")).unwrap();
+    symlink(fixture.path("gone"), fixture.path("broken")).unwrap();
+    let _socket = UnixListener::bind(fixture.path("sock")).unwrap();
+    let kind = |name: &str| EntryKind::of(&Entry::load(&fixture.root, name.to_string()));
+    assert_eq!(kind("dir"), EntryKind::Directory);
+    assert_eq!(kind("plain"), EntryKind::File);
+    assert_eq!(kind("tool"), EntryKind::Executable);
+    assert_eq!(kind("su"), EntryKind::Setuid);
+    assert_eq!(kind("link"), EntryKind::Symlink);
+    assert_eq!(kind("broken"), EntryKind::BrokenLink);
+    assert_eq!(kind("sock"), EntryKind::Socket);
+}
+
+#[test]
+fn ls_colors_are_parsed() {
+    let ls_colors = LsColors::parse("di=01;34:ln=target:*.tar=38;5;208:*.TGZ=1;31:ex=01;32:ex=33");
+    assert_eq!(
+        ls_colors.kind(EntryKind::Directory),
+        Some(
+            Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD)
+        )
+    );
+    assert_eq!(ls_colors.kind(EntryKind::Symlink), None);
+    // The last entry wins
+    assert_eq!(
+        ls_colors.kind(EntryKind::Executable),
+        Some(Style::default().fg(Color::Yellow))
+    );
+    assert_eq!(
+        ls_colors.extension("backup.tar"),
+        Some(Style::default().fg(Color::Indexed(208)))
+    );
+    assert!(ls_colors.extension("old.tgz").is_some());
+    assert_eq!(ls_colors.extension("notes.txt"), None);
+}
+
+#[test]
+fn opener_colors_layer_over_ls_colors() {
+    let fixture = Fixture::new().unwrap();
+    fixture
+        .dir("archive.tar")
+        .unwrap()
+        .file("a.tar", "")
+        .unwrap();
+    let theme = Theme {
+        ls_colors: Some(LsColors::parse("di=01;34:*.tar=31")),
+        ..Theme::default()
+    };
+    let entry = |name: &str| Entry::load(&fixture.root, name.to_string());
+    // Kinds win over names, names over the theme's file color
+    assert_eq!(
+        theme
+            .entry_style(&entry("archive.tar"), Some(Color::Green))
+            .fg,
+        Some(Color::Blue)
+    );
+    assert_eq!(
+        theme.entry_style(&entry("a.tar"), None).fg,
+        Some(Color::Red)
+    );
+    assert_eq!(
+        theme.entry_style(&entry("a.tar"), Some(Color::Green)).fg,
+        Some(Color::Green)
+    );
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::{insert_todo, Priority, Todo};
+
+fn date(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn priorities_and_due_dates_come_before_the_description() {
+    let todo = Todo::parse("!high 2024-08-01 buy disk").unwrap();
+    assert_eq!(todo.priority, Some(Priority::High));
+    assert_eq!(todo.due, Some(date("2024-08-01")));
+    assert_eq!(todo.description, "buy disk");
+
+    let todo = Todo::parse("2024-08-01 !low call 2024-09-01 back").unwrap();
+    assert_eq!(todo.priority, Some(Priority::Low));
+    assert_eq!(todo.description, "call 2024-09-01 back");
+    assert_eq!(Todo::parse("plain").unwrap(), Todo::new("plain"));
+
+    assert_eq!(
+        Todo::parse("!urgent x").unwrap_err(),
+        "Unknown priority !urgent, expected !high, !medium, !low"
+    );
+    assert_eq!(
+        Todo::parse("2024-13-01 x").unwrap_err(),
+        "Not a date: 2024-13-01"
+    );
+    assert_eq!(
+        Todo::parse("!high").unwrap_err(),
+        "The to-do has no description"
+    );
+
+    // Lists saved before priorities existed still load
+    let old: Vec<Todo> =
+        serde_json::from_str(r#"[{"description":"old","completed":true}]"#).unwrap();
+    assert_eq!(old[0].priority, None);
+}
+
+#[test]
+fn new_todos_are_sorted_in_by_priority_and_due_date() {
+    let mut todos = Vec::new();
+    for line in [
+        "no rush",
+        "!low 2024-01-01 later",
+        "!high 2024-08-01 second",
+        "2024-02-01 dated",
+        "!high 2024-07-01 first",
+        "!high undated",
+    ] {
+        insert_todo(&mut todos, Todo::parse(line).unwrap());
+    }
+    let order: Vec<&str> = todos.iter().map(|t| t.description.as_str()).collect();
+    assert_eq!(
+        order,
+        ["first", "second", "undated", "later", "dated", "no rush"]
+    );
+    assert_eq!(insert_todo(&mut todos, Todo::new("last")), 6);
+}
+
+#[test]
+fn overdue_todos_are_red() {
+    let mut harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    let mut done = Todo::parse("2000-01-01 filed").unwrap();
+    done.completed = true;
+    harness.app.todos = vec![
+        Todo::parse("!high 2000-01-01 taxes").unwrap(),
+        Todo::parse("9999-01-01 someday").unwrap(),
+        done,
+    ];
+    assert!(!Todo::parse("9999-01-01 x")
+        .unwrap()
+        .is_overdue(date("2024-01-01")));
+    harness.settle().unwrap();
+
+    let screen = harness.screen();
+    let buffer = harness.terminal.backend().buffer();
+    let color = |text: &str| {
+        let (row, line) = screen
+            .lines()
+            .enumerate()
+            .find(|(_, line)| line.contains(text))
+            .unwrap();
+        let column = line[..line.find(text).unwrap()].chars().count();
+        buffer[(column as u16, row as u16)].fg
+    };
+    let red = harness.app.theme.error;
+    assert!(screen.contains("!high taxes  due 2000-01-01"));
+    assert_eq!(color("taxes"), red);
+    assert_ne!(color("someday"), red);
+    assert_ne!(color("filed"), red);
+}
@@ -0,0 +1,57 @@
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::{archive_path, load_todos, move_todo, Todo};
+
+fn todos(descriptions: &[&str]) -> Vec<Todo> {
+    descriptions.iter().map(|d| Todo::new(d)).collect()
+}
+
+fn descriptions(todos: &[Todo]) -> Vec<&str> {
+    todos.iter().map(|t| t.description.as_str()).collect()
+}
+
+#[test]
+fn todos_move_as_far_as_the_list_goes() {
+    let mut list = todos(&["a", "b", "c", "d"]);
+    assert_eq!(move_todo(&mut list, 0, 2), 2);
+    assert_eq!(descriptions(&list), ["b", "c", "a", "d"]);
+    assert_eq!(move_todo(&mut list, 2, -5), 0);
+    assert_eq!(descriptions(&list), ["a", "b", "c", "d"]);
+    assert_eq!(move_todo(&mut list, 3, 1), 3);
+    assert_eq!(descriptions(&list), ["a", "b", "c", "d"]);
+}
+
+#[test]
+fn shift_j_and_k_move_the_selected_todo() {
+    let mut harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    harness.app.todos = todos(&["a", "b", "c"]);
+    harness.app.todo_list_state.select(Some(0));
+    harness.keys("J").unwrap();
+    assert_eq!(descriptions(&harness.app.todos), ["b", "a", "c"]);
+    assert_eq!(harness.app.todo_list_state.selected(), Some(1));
+    harness.keys("2K").unwrap();
+    assert_eq!(descriptions(&harness.app.todos), ["a", "b", "c"]);
+    assert_eq!(harness.app.todo_list_state.selected(), Some(0));
+}
+
+#[test]
+fn completed_todos_are_archived_next_to_the_list() {
+    let mut harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    let list = harness.fixture.path("list.json");
+    harness.app.todo_path = Some(list.clone());
+    harness.app.todos = todos(&["done", "open", "also done"]);
+    harness.app.todos[0].completed = true;
+    harness.app.todos[2].completed = true;
+    harness.command("todo archived").unwrap();
+    assert!(harness.app.state.command_output.is_none());
+
+    harness.command("todo archive").unwrap();
+    assert_eq!(descriptions(&harness.app.todos), ["open"]);
+    let archived = load_todos(&archive_path(&list));
+    assert_eq!(descriptions(&archived), ["done", "also done"]);
+
+    harness.command("todo archived").unwrap();
+    let (name, lines) = harness.app.state.command_output.clone().unwrap();
+    assert_eq!(name, "todo archived");
+    assert_eq!(lines, ["✓  done", "✓  also done"]);
+    assert!(harness.screen().contains(":todo archived (Esc closes)"));
+}
@@ -0,0 +1,74 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::path::PathBuf;
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::{Todo, TODO_SYMBOL};
+
+fn harness() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("docs/guide.md", "").unwrap();
+    fixture.file("docs/notes.txt", "").unwrap();
+    fixture.file("readme.txt", "").unwrap();
+    Harness::new(fixture).unwrap()
+}
+
+fn linked(description: &str, path: PathBuf) -> Todo {
+    Todo {
+        path: Some(path),
+        ..Todo::new(description)
+    }
+}
+
+#[test]
+fn a_path_can_come_before_the_description() {
+    let todo = Todo::parse("!low @docs/guide.md proofread").unwrap();
+    assert_eq!(todo.path, Some(PathBuf::from("docs/guide.md")));
+    assert_eq!(todo.description, "proofread");
+    assert_eq!(todo.label(), "☐  !low proofread  → docs/guide.md");
+    assert_eq!(Todo::parse("@ mail").unwrap().description, "@ mail");
+}
+
+#[test]
+fn files_with_open_todos_are_marked_in_the_listing() {
+    let mut harness = harness();
+    let readme = harness.fixture.path("readme.txt");
+    let docs = harness.fixture.path("docs");
+    let mut done = linked("done", docs);
+    done.completed = true;
+    harness.app.todos = vec![linked("update", readme), done];
+    harness.settle().unwrap();
+    let screen = harness.screen();
+    let line = |name: &str| {
+        screen
+            .lines()
+            .find(|l| l.contains(name))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("readme.txt").contains(&format!("{} readme.txt", TODO_SYMBOL)));
+    assert!(!line("docs").contains(TODO_SYMBOL));
+    assert!(screen.contains("update  → readme.txt"));
+}
+
+#[test]
+fn enter_on_a_todo_goes_to_its_file() {
+    let mut harness = harness();
+    let guide = harness.fixture.path("docs/notes.txt");
+    harness.app.todos = vec![Todo::new("unlinked"), linked("read", guide)];
+    harness.app.todo_list_state.select(Some(0));
+    // Ctrl+w w to the preview, Ctrl+w j to the to-dos below it
+    for key in ["w", "j"] {
+        harness
+            .events
+            .key(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        harness.keys(key).unwrap();
+    }
+    harness.press(KeyCode::Enter).unwrap();
+    let message = harness.last_message();
+    assert_eq!(message, "The to-do isn't linked to a file");
+
+    harness.keys("j").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.app.current_dir, harness.fixture.path("docs"));
+    assert_eq!(harness.selected().as_deref(), Some("notes.txt"));
+}
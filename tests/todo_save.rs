@@ -0,0 +1,50 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+use termfm::todo::{load_todos, Todo};
+
+// A list kept in the fixture rather than the home directory
+fn harness() -> Harness {
+    let mut harness = Harness::new(Fixture::new().unwrap()).unwrap();
+    harness.app.todo_path = Some(harness.fixture.path("todos.json"));
+    harness.app.todos = ["first", "second", "third"].map(Todo::new).to_vec();
+    harness.app.todo_list_state.select(Some(0));
+    harness
+}
+
+fn saved(harness: &Harness) -> Vec<String> {
+    load_todos(&harness.fixture.path("todos.json"))
+        .into_iter()
+        .map(|todo| {
+            let mark = if todo.completed { "x " } else { "" };
+            format!("{}{}", mark, todo.description)
+        })
+        .collect()
+}
+
+#[test]
+fn ticking_off_a_todo_saves_the_list() {
+    let mut harness = harness();
+    harness.keys(" ").unwrap();
+    assert_eq!(saved(&harness), ["x first", "second", "third"]);
+}
+
+#[test]
+fn moving_and_deleting_todos_save_the_list() {
+    let mut harness = harness();
+    harness.keys("J").unwrap();
+    assert_eq!(saved(&harness), ["second", "first", "third"]);
+    harness.keys("dt").unwrap();
+    assert_eq!(saved(&harness), ["second", "third"]);
+}
+
+#[test]
+fn archiving_saves_what_is_left() {
+    let mut harness = harness();
+    harness.keys("+").unwrap();
+    harness.keys(" ").unwrap();
+    assert_eq!(saved(&harness), ["first", "x second", "third"]);
+    harness.keys(":todo archive").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert_eq!(saved(&harness), ["first", "third"]);
+    assert!(!harness.app.quit);
+}
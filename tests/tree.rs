@@ -0,0 +1,89 @@
+use crossterm::event::KeyCode;
+use std::path::PathBuf;
+use termfm::harness::{Fixture, Harness};
+
+fn tree() -> Fixture {
+    let fixture = Fixture::new().unwrap();
+    fixture.file("README.md", "hello").unwrap();
+    fixture.file("src/main.rs", "fn main() {}").unwrap();
+    fixture.dir("src/big").unwrap();
+    fixture.dir("docs").unwrap();
+    fixture.dir(".config").unwrap();
+    fixture
+}
+
+// The rows below the fixture's root, relative to it
+fn rows(harness: &Harness) -> Vec<String> {
+    let root = &harness.fixture.root;
+    harness
+        .app
+        .state
+        .tree
+        .as_ref()
+        .unwrap()
+        .rows
+        .iter()
+        .filter_map(|row| row.path.strip_prefix(root).ok())
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+fn selected(harness: &Harness) -> PathBuf {
+    let tree = harness.app.state.tree.as_ref().unwrap();
+    tree.selected().unwrap().to_path_buf()
+}
+
+#[test]
+fn the_tree_shows_the_current_directory() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("Z").unwrap();
+    assert_eq!(rows(&harness), ["", "docs", "src"]);
+    assert_eq!(selected(&harness), harness.fixture.root);
+    assert!(harness.screen().contains("Tree"));
+
+    harness.keys("Z").unwrap();
+    assert!(harness.app.state.tree.is_none());
+}
+
+#[test]
+fn nodes_expand_and_collapse() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("Zjjl").unwrap();
+    assert_eq!(rows(&harness), ["", "docs", "src", "src/big"]);
+    harness.keys("l").unwrap();
+    assert_eq!(selected(&harness), harness.fixture.path("src/big"));
+
+    // Out to the parent, then closed
+    harness.keys("hzh").unwrap();
+    assert_eq!(rows(&harness), ["", "docs", "src"]);
+    harness.keys("kk ").unwrap();
+    assert_eq!(rows(&harness), [""]);
+}
+
+#[test]
+fn enter_opens_the_directory_in_the_list() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("Zjjlj").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("src/big"));
+    assert!(!harness.app.state.tree.as_ref().unwrap().focused);
+}
+
+#[test]
+fn the_tree_follows_the_list() {
+    let mut harness = Harness::new(tree()).unwrap();
+    harness.keys("Z").unwrap();
+    harness.press(KeyCode::Esc).unwrap();
+    // Keys go to the list again
+    harness.keys("jl").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(harness.current_dir(), harness.fixture.path("src"));
+    assert_eq!(rows(&harness), ["", "docs", "src", "src/big"]);
+    assert_eq!(selected(&harness), harness.fixture.path("src"));
+
+    // Shown hidden directories join
+    harness.keys("hzh").unwrap();
+    harness.settle().unwrap();
+    assert_eq!(rows(&harness), ["", ".config", "docs", "src", "src/big"]);
+}
@@ -0,0 +1,57 @@
+use crossterm::event::KeyCode;
+use termfm::harness::{Fixture, Harness};
+
+fn files() -> Harness {
+    let fixture = Fixture::new().unwrap();
+    for name in ["a", "b", "c", "d", "e"] {
+        fixture.file(name, "").unwrap();
+    }
+    Harness::new(fixture).unwrap()
+}
+
+fn marked(harness: &Harness) -> Vec<String> {
+    let mut names: Vec<String> = harness
+        .app
+        .state
+        .marked
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn the_range_follows_the_cursor() {
+    let mut harness = files();
+    harness.keys("jVjj").unwrap();
+    assert_eq!(marked(&harness), ["b", "c", "d"]);
+    assert!(harness.screen().contains("VISUAL"));
+    // Back over the anchor
+    harness.keys("kkk").unwrap();
+    assert_eq!(marked(&harness), ["a", "b"]);
+
+    // The range stays marked
+    harness.keys("Vj").unwrap();
+    assert!(harness.app.visual.is_none());
+    assert_eq!(marked(&harness), ["a", "b"]);
+}
+
+#[test]
+fn escape_unmarks_the_range() {
+    let mut harness = files();
+    harness.keys("mjjVj").unwrap();
+    assert_eq!(marked(&harness), ["a", "d", "e"]);
+    harness.press(KeyCode::Esc).unwrap();
+    assert_eq!(marked(&harness), ["a"]);
+}
+
+#[test]
+fn operations_use_the_range() {
+    let mut harness = files();
+    harness.keys("jVj").unwrap();
+    harness.command("delete").unwrap();
+    harness.press(KeyCode::Enter).unwrap();
+    assert!(harness.app.visual.is_none());
+    assert_eq!(harness.names(), ["a", "d", "e"]);
+}